@@ -0,0 +1,624 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures::Stream;
+use serde::Serialize;
+use tokio::time::Instant;
+
+use crate::{
+    app::{
+        command::{
+            change_link_status::ChangeLinkStatusRepository,
+            check_link_health::CheckLinkHealthRepository,
+            create_short_url::CreateShortUrlRepository,
+            delete_short_url::DeleteShortUrlRepository,
+            import_short_urls::ImportShortUrlsRepository,
+            purge_expired_links::PurgeExpiredLinksRepository,
+            record_visit::RecordVisitRepository,
+            report_link::ReportLinkRepository,
+            update_short_url::{LinkChanges, UpdateShortUrlRepository},
+        },
+        health::{HealthCounts, LinkHealth},
+        query::{
+            export_short_urls::ExportShortUrlsRepository,
+            get_full_url::GetFullUrlRepository,
+            get_link_health::GetLinkHealthRepository,
+            get_link_stats::{GetLinkStatsRepository, LinkStats},
+            get_stats::GetStatsRepository,
+            get_workspace_usage::GetWorkspaceUsageRepository,
+            list_reports::ListReportsRepository,
+            list_short_urls::{ListShortUrlsRepository, OwnerFilter},
+        },
+        report::AbuseReport,
+        short_url::{LinkStatus, Ownership, ShortUrl},
+        workspace::Usage,
+    },
+    clock::{SharedClock, SystemClock},
+    error::AppError,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CacheConfig {
+    /// Most entries kept; the least recently used one is evicted beyond
+    /// that. Zero turns caching off.
+    pub capacity: usize,
+    pub ttl: Duration,
+    /// How long an unknown ID is remembered as `NotFound`.
+    pub negative_ttl: Duration,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 10_000,
+            ttl: Duration::from_secs(60),
+            negative_ttl: Duration::from_secs(5),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+pub struct CacheMetrics {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+}
+
+/// Read-through cache in front of another link repository.
+///
+/// Resolution is served from a bounded LRU map; unknown IDs are cached as
+/// misses for a shorter while so probing for links does not reach the
+/// store every time. Writes go straight to the inner repository and drop
+/// the affected entries, which is why the decorator implements the command
+/// traits too: wire the same instance as command and query repository.
+#[derive(Clone)]
+pub struct CachedRepository<R> {
+    inner: R,
+    config: CacheConfig,
+    cache: Arc<Mutex<Lru>>,
+    metrics: Arc<Counters>,
+    clock: SharedClock,
+}
+
+#[derive(Default)]
+struct Counters {
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+}
+
+impl<R> CachedRepository<R> {
+    pub fn new(inner: R, config: CacheConfig) -> Self {
+        Self {
+            inner,
+            config,
+            cache: Arc::new(Mutex::new(Lru::default())),
+            metrics: Arc::new(Counters::default()),
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Where cached links learn whether they have expired; use the clock
+    /// of the inner repository.
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn metrics(&self) -> CacheMetrics {
+        CacheMetrics {
+            hits: self.metrics.hits.load(Ordering::Relaxed),
+            misses: self.metrics.misses.load(Ordering::Relaxed),
+            evictions: self.metrics.evictions.load(Ordering::Relaxed),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Lru> {
+        self.cache
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn invalidate<'a>(&self, ids: impl IntoIterator<Item = &'a str>) {
+        let mut cache = self.lock();
+        cache.generation += 1;
+        for id in ids {
+            cache.remove(id);
+        }
+    }
+}
+
+impl<R> GetFullUrlRepository for CachedRepository<R>
+where
+    R: GetFullUrlRepository + Send + Sync,
+{
+    async fn get(&self, id: &str) -> Result<ShortUrl, AppError> {
+        if self.config.capacity == 0 {
+            return self.inner.get(id).await;
+        }
+
+        let generation = {
+            let mut cache = self.lock();
+            if let Some(cached) = cache.get(id) {
+                self.metrics.hits.fetch_add(1, Ordering::Relaxed);
+                return match cached {
+                    Some(link) if !link.is_expired(self.clock.now()) => Ok(link),
+                    _ => Err(AppError::not_found(id)),
+                };
+            }
+            cache.generation
+        };
+        self.metrics.misses.fetch_add(1, Ordering::Relaxed);
+
+        let result = self.inner.get(id).await;
+
+        let (value, ttl) = match &result {
+            Ok(link) => (Some(link.clone()), self.config.ttl),
+            Err(AppError::NotFound { .. }) => (None, self.config.negative_ttl),
+            Err(_) => return result,
+        };
+
+        let mut cache = self.lock();
+        // A write during the lookup may have made `result` stale already.
+        if cache.generation == generation {
+            let evicted = cache.insert(id, value, ttl, self.config.capacity);
+            self.metrics.evictions.fetch_add(evicted, Ordering::Relaxed);
+        }
+
+        result
+    }
+}
+
+#[async_trait]
+impl<R> CreateShortUrlRepository for CachedRepository<R>
+where
+    R: CreateShortUrlRepository + Send + Sync,
+{
+    async fn save(&self, link: ShortUrl) -> Result<(), AppError> {
+        let id = link.id.clone();
+        let result = self.inner.save(link).await;
+        self.invalidate([id.as_str()]);
+
+        result
+    }
+
+    async fn find(&self, id: &str) -> Result<Option<ShortUrl>, AppError> {
+        self.inner.find(id).await
+    }
+
+    async fn workspace_usage(&self, workspace: &str, month: &str) -> Result<Usage, AppError> {
+        CreateShortUrlRepository::workspace_usage(&self.inner, workspace, month).await
+    }
+}
+
+#[async_trait]
+impl<R> DeleteShortUrlRepository for CachedRepository<R>
+where
+    R: DeleteShortUrlRepository + Send + Sync,
+{
+    async fn ownership(&self, id: &str) -> Result<Ownership, AppError> {
+        DeleteShortUrlRepository::ownership(&self.inner, id).await
+    }
+
+    async fn delete(&self, id: &str) -> Result<String, AppError> {
+        let result = self.inner.delete(id).await;
+        self.invalidate([id]);
+
+        result
+    }
+}
+
+#[async_trait]
+impl<R> UpdateShortUrlRepository for CachedRepository<R>
+where
+    R: UpdateShortUrlRepository + Send + Sync,
+{
+    async fn ownership(&self, id: &str) -> Result<Ownership, AppError> {
+        UpdateShortUrlRepository::ownership(&self.inner, id).await
+    }
+
+    async fn update(&self, id: &str, changes: &LinkChanges) -> Result<ShortUrl, AppError> {
+        let result = self.inner.update(id, changes).await;
+        self.invalidate([id]);
+
+        result
+    }
+}
+
+#[async_trait]
+impl<R> ChangeLinkStatusRepository for CachedRepository<R>
+where
+    R: ChangeLinkStatusRepository + Send + Sync,
+{
+    async fn set_status(
+        &self,
+        id: &str,
+        status: LinkStatus,
+        reason: Option<String>,
+    ) -> Result<ShortUrl, AppError> {
+        let result = self.inner.set_status(id, status, reason).await;
+        self.invalidate([id]);
+
+        result
+    }
+
+    async fn close_reports(
+        &self,
+        link_id: &str,
+        outcome: LinkStatus,
+        reviewed_at: u64,
+    ) -> Result<usize, AppError> {
+        self.inner
+            .close_reports(link_id, outcome, reviewed_at)
+            .await
+    }
+}
+
+#[async_trait]
+impl<R> ReportLinkRepository for CachedRepository<R>
+where
+    R: ReportLinkRepository + Send + Sync,
+{
+    async fn save_report(&self, report: AbuseReport) -> Result<(), AppError> {
+        self.inner.save_report(report).await
+    }
+}
+
+/// Visits change nothing a resolution returns.
+#[async_trait]
+impl<R> RecordVisitRepository for CachedRepository<R>
+where
+    R: RecordVisitRepository + Send + Sync,
+{
+    async fn record_visit(&self, id: &str, variant: Option<String>) -> Result<(), AppError> {
+        self.inner.record_visit(id, variant).await
+    }
+}
+
+#[async_trait]
+impl<R> ImportShortUrlsRepository for CachedRepository<R>
+where
+    R: ImportShortUrlsRepository + Send + Sync,
+{
+    async fn find_many(&self, ids: &[String]) -> Result<Vec<Option<ShortUrl>>, AppError> {
+        self.inner.find_many(ids).await
+    }
+
+    async fn upsert_many(&self, links: Vec<ShortUrl>) -> Result<(), AppError> {
+        let ids: Vec<String> = links.iter().map(|link| link.id.clone()).collect();
+        let result = self.inner.upsert_many(links).await;
+        self.invalidate(ids.iter().map(String::as_str));
+
+        result
+    }
+}
+
+/// Cached links are checked against their expiry on every hit, so purging
+/// needs no invalidation.
+#[async_trait]
+impl<R> PurgeExpiredLinksRepository for CachedRepository<R>
+where
+    R: PurgeExpiredLinksRepository + Send + Sync,
+{
+    async fn purge_expired(&self) -> Result<Vec<ShortUrl>, AppError> {
+        self.inner.purge_expired().await
+    }
+}
+
+#[async_trait]
+impl<R> CheckLinkHealthRepository for CachedRepository<R>
+where
+    R: CheckLinkHealthRepository + Send + Sync,
+{
+    async fn links_to_check(&self) -> Result<Vec<ShortUrl>, AppError> {
+        self.inner.links_to_check().await
+    }
+
+    async fn record_health(&self, id: &str, health: LinkHealth) -> Result<(), AppError> {
+        self.inner.record_health(id, health).await
+    }
+}
+
+impl<R> ListShortUrlsRepository for CachedRepository<R>
+where
+    R: ListShortUrlsRepository + Send + Sync,
+{
+    async fn list(
+        &self,
+        owner: OwnerFilter<'_>,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Vec<ShortUrl>, AppError> {
+        self.inner.list(owner, offset, limit).await
+    }
+}
+
+impl<R> ExportShortUrlsRepository for CachedRepository<R>
+where
+    R: ExportShortUrlsRepository + Send + Sync,
+{
+    fn export(
+        &self,
+        batch_size: usize,
+    ) -> impl Stream<Item = Result<Vec<ShortUrl>, AppError>> + std::marker::Send {
+        self.inner.export(batch_size)
+    }
+}
+
+impl<R> GetLinkStatsRepository for CachedRepository<R>
+where
+    R: GetLinkStatsRepository + Send + Sync,
+{
+    async fn ownership(&self, id: &str) -> Result<Ownership, AppError> {
+        GetLinkStatsRepository::ownership(&self.inner, id).await
+    }
+
+    async fn link_stats(&self, id: &str) -> Result<Option<LinkStats>, AppError> {
+        self.inner.link_stats(id).await
+    }
+}
+
+impl<R> GetLinkHealthRepository for CachedRepository<R>
+where
+    R: GetLinkHealthRepository + Send + Sync,
+{
+    async fn ownership(&self, id: &str) -> Result<Ownership, AppError> {
+        GetLinkHealthRepository::ownership(&self.inner, id).await
+    }
+
+    async fn link_health(&self, id: &str) -> Result<Option<LinkHealth>, AppError> {
+        self.inner.link_health(id).await
+    }
+}
+
+impl<R> GetWorkspaceUsageRepository for CachedRepository<R>
+where
+    R: GetWorkspaceUsageRepository + Send + Sync,
+{
+    async fn workspace_usage(&self, workspace: &str, month: &str) -> Result<Usage, AppError> {
+        GetWorkspaceUsageRepository::workspace_usage(&self.inner, workspace, month).await
+    }
+}
+
+impl<R> ListReportsRepository for CachedRepository<R>
+where
+    R: ListReportsRepository + Send + Sync,
+{
+    async fn reports(
+        &self,
+        open_only: bool,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Vec<AbuseReport>, AppError> {
+        self.inner.reports(open_only, offset, limit).await
+    }
+}
+
+impl<R> GetStatsRepository for CachedRepository<R>
+where
+    R: GetStatsRepository + Send + Sync,
+{
+    async fn count(&self) -> Result<usize, AppError> {
+        self.inner.count().await
+    }
+
+    async fn health_counts(&self) -> Result<HealthCounts, AppError> {
+        self.inner.health_counts().await
+    }
+}
+
+/// LRU map with per-entry deadlines. `None` values are cached misses.
+#[derive(Default)]
+struct Lru {
+    entries: HashMap<String, Slot>,
+    /// Entries by last use, oldest first.
+    recency: BTreeMap<u64, String>,
+    tick: u64,
+    /// Bumped by every invalidation.
+    generation: u64,
+}
+
+struct Slot {
+    value: Option<ShortUrl>,
+    expires_at: Instant,
+    used: u64,
+}
+
+impl Lru {
+    fn get(&mut self, id: &str) -> Option<Option<ShortUrl>> {
+        let slot = self.entries.get_mut(id)?;
+        if slot.expires_at <= Instant::now() {
+            self.remove(id);
+            return None;
+        }
+
+        self.tick += 1;
+        self.recency.remove(&slot.used);
+        slot.used = self.tick;
+        self.recency.insert(self.tick, id.to_owned());
+
+        Some(slot.value.clone())
+    }
+
+    /// Returns how many entries were evicted to make room.
+    fn insert(&mut self, id: &str, value: Option<ShortUrl>, ttl: Duration, capacity: usize) -> u64 {
+        self.remove(id);
+
+        let mut evicted = 0;
+        while self.entries.len() >= capacity {
+            let Some((_, oldest)) = self.recency.pop_first() else {
+                break;
+            };
+            self.entries.remove(&oldest);
+            evicted += 1;
+        }
+
+        self.tick += 1;
+        self.recency.insert(self.tick, id.to_owned());
+        self.entries.insert(
+            id.to_owned(),
+            Slot {
+                value,
+                expires_at: Instant::now() + ttl,
+                used: self.tick,
+            },
+        );
+
+        evicted
+    }
+
+    fn remove(&mut self, id: &str) {
+        if let Some(slot) = self.entries.remove(id) {
+            self.recency.remove(&slot.used);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use dashmap::DashMap;
+
+    use crate::adapters::inmemory::InMemoryRepository;
+
+    use super::*;
+
+    fn link(id: &str, url: &str) -> ShortUrl {
+        ShortUrl::new(id.to_owned(), url.to_owned())
+    }
+
+    fn cached(
+        store: Arc<DashMap<String, ShortUrl>>,
+        config: CacheConfig,
+    ) -> CachedRepository<InMemoryRepository> {
+        CachedRepository::new(InMemoryRepository::new(store), config)
+    }
+
+    #[tokio::test]
+    async fn second_read_is_a_hit() {
+        // Given
+        let store = Arc::new(DashMap::new());
+        store.insert("abc".to_owned(), link("abc", "https://a.com/"));
+        let repo = cached(store.clone(), CacheConfig::default());
+
+        // When
+        let first = repo.get("abc").await.map(|link| link.url);
+        // Bypasses the decorator, so only the cached copy can answer.
+        store.remove("abc");
+        let second = repo.get("abc").await.map(|link| link.url);
+
+        // Then
+        assert_eq!(first, Ok("https://a.com/".to_owned()));
+        assert_eq!(second, first);
+        assert_eq!(
+            repo.metrics(),
+            CacheMetrics {
+                hits: 1,
+                misses: 1,
+                evictions: 0
+            }
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn misses_are_cached_briefly() {
+        // Given
+        let store = Arc::new(DashMap::new());
+        let repo = cached(store.clone(), CacheConfig::default());
+        assert_eq!(repo.get("abc").await, Err(AppError::not_found("abc")));
+        store.insert("abc".to_owned(), link("abc", "https://a.com/"));
+
+        // When
+        let within_window = repo.get("abc").await;
+        tokio::time::advance(Duration::from_secs(6)).await;
+        let after_window = repo.get("abc").await.map(|link| link.url);
+
+        // Then
+        assert_eq!(within_window, Err(AppError::not_found("abc")));
+        assert_eq!(after_window, Ok("https://a.com/".to_owned()));
+        assert_eq!(repo.metrics().hits, 1);
+    }
+
+    #[tokio::test]
+    async fn writes_through_the_decorator_invalidate() {
+        // Given
+        let store = Arc::new(DashMap::new());
+        store.insert("abc".to_owned(), link("abc", "https://a.com/"));
+        let repo = cached(store, CacheConfig::default());
+        repo.get("abc").await.unwrap();
+        assert_eq!(repo.get("new").await, Err(AppError::not_found("new")));
+
+        // When
+        repo.upsert_many(vec![link("abc", "https://b.com/")])
+            .await
+            .unwrap();
+        let updated = repo.get("abc").await.map(|link| link.url);
+        repo.save(link("new", "https://new.com/")).await.unwrap();
+        let created = repo.get("new").await.map(|link| link.url);
+        repo.delete("abc").await.unwrap();
+        let deleted = repo.get("abc").await;
+
+        // Then
+        assert_eq!(updated, Ok("https://b.com/".to_owned()));
+        assert_eq!(created, Ok("https://new.com/".to_owned()));
+        assert_eq!(deleted, Err(AppError::not_found("abc")));
+    }
+
+    #[tokio::test]
+    async fn evicts_least_recently_used() {
+        // Given
+        let store = Arc::new(DashMap::new());
+        for id in ["a", "b", "c"] {
+            store.insert(id.to_owned(), link(id, "https://x.com/"));
+        }
+        let config = CacheConfig {
+            capacity: 2,
+            ..CacheConfig::default()
+        };
+        let repo = cached(store, config);
+
+        // When
+        repo.get("a").await.unwrap();
+        repo.get("b").await.unwrap();
+        repo.get("a").await.unwrap();
+        repo.get("c").await.unwrap();
+        repo.get("a").await.unwrap();
+        repo.get("b").await.unwrap();
+
+        // Then
+        assert_eq!(
+            repo.metrics(),
+            CacheMetrics {
+                hits: 2,
+                misses: 4,
+                evictions: 2
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn expired_links_are_not_served_from_cache() {
+        // Given
+        let store = Arc::new(DashMap::new());
+        let mut expiring = link("abc", "https://a.com/");
+        expiring.expires_at = Some(expiring.created_at + 1);
+        store.insert("abc".to_owned(), expiring);
+        let repo = cached(store, CacheConfig::default());
+        repo.get("abc").await.unwrap();
+
+        // When
+        repo.lock().entries.get_mut("abc").unwrap().value = Some({
+            let mut link = link("abc", "https://a.com/");
+            link.expires_at = Some(link.created_at - 1);
+            link
+        });
+        let result = repo.get("abc").await;
+
+        // Then
+        assert_eq!(result, Err(AppError::not_found("abc")));
+    }
+}
@@ -0,0 +1,144 @@
+//! Country lookup from a local range file, as distributed by DB-IP and
+//! IP2Location: CSV rows of `first_ip,last_ip,country_code` with IPv4 or
+//! IPv6 addresses and no header.
+
+use std::fs::File;
+use std::io::{self, Read};
+use std::net::{IpAddr, Ipv6Addr};
+use std::path::Path;
+
+#[derive(thiserror::Error, Debug)]
+pub enum GeoIpError {
+    #[error("cannot read GeoIP database: {0}")]
+    Io(#[from] io::Error),
+    #[error("GeoIP database line {line}: {reason}")]
+    Malformed { line: u64, reason: String },
+}
+
+/// Ranges sorted by their first address. IPv4 addresses are kept as
+/// IPv4-mapped IPv6 so both families share one table.
+pub struct GeoIpDatabase {
+    ranges: Vec<(u128, u128, [u8; 2])>,
+}
+
+impl GeoIpDatabase {
+    pub fn open(path: &Path) -> Result<Self, GeoIpError> {
+        Self::from_reader(File::open(path)?)
+    }
+
+    pub fn from_reader(reader: impl Read) -> Result<Self, GeoIpError> {
+        let mut csv = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(reader);
+
+        let mut ranges = Vec::new();
+        for record in csv.records() {
+            let record = record.map_err(|err| match err.into_kind() {
+                csv::ErrorKind::Io(err) => GeoIpError::Io(err),
+                kind => GeoIpError::Malformed {
+                    line: 0,
+                    reason: format!("{kind:?}"),
+                },
+            })?;
+            let line = record.position().map_or(0, |p| p.line());
+            let malformed = |reason: String| GeoIpError::Malformed { line, reason };
+
+            let [first, last, country] = [0, 1, 2].map(|i| record.get(i).unwrap_or("").trim());
+            let first = parse_ip(first).map_err(malformed)?;
+            let last = parse_ip(last).map_err(malformed)?;
+            if last < first {
+                return Err(malformed("range ends before it starts".to_owned()));
+            }
+            // `-` and `ZZ` mark unassigned space.
+            let country = match country.as_bytes() {
+                b"-" | b"ZZ" => continue,
+                &[a, b] if a.is_ascii_alphabetic() && b.is_ascii_alphabetic() => {
+                    [a.to_ascii_uppercase(), b.to_ascii_uppercase()]
+                }
+                _ => return Err(malformed(format!("`{country}` is not a country code"))),
+            };
+
+            ranges.push((first, last, country));
+        }
+        ranges.sort_unstable_by_key(|&(first, _, _)| first);
+
+        Ok(Self { ranges })
+    }
+
+    pub fn len(&self) -> usize {
+        self.ranges.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// The ISO 3166-1 alpha-2 code of the country `ip` belongs to.
+    pub fn country(&self, ip: IpAddr) -> Option<String> {
+        let ip = to_u128(ip);
+        let after = self.ranges.partition_point(|&(first, _, _)| first <= ip);
+        let &(_, last, country) = self.ranges.get(after.checked_sub(1)?)?;
+
+        (ip <= last).then(|| String::from_utf8_lossy(&country).into_owned())
+    }
+}
+
+fn parse_ip(text: &str) -> Result<u128, String> {
+    text.parse()
+        .map(to_u128)
+        .map_err(|_| format!("`{text}` is not an IP address"))
+}
+
+fn to_u128(ip: IpAddr) -> u128 {
+    let ip: Ipv6Addr = match ip {
+        IpAddr::V4(ip) => ip.to_ipv6_mapped(),
+        IpAddr::V6(ip) => ip,
+    };
+
+    u128::from(ip)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DATABASE: &str = "\
+1.0.0.0,1.0.0.255,AU
+1.0.1.0,1.0.3.255,cn
+5.0.0.0,5.0.0.255,-
+2001:db8::,2001:db8::ffff,DE
+";
+
+    #[test]
+    fn looks_up_both_families() {
+        // Given
+        let database = GeoIpDatabase::from_reader(DATABASE.as_bytes()).unwrap();
+
+        // Then
+        assert_eq!(database.len(), 3);
+        assert_eq!(
+            database.country("1.0.0.7".parse().unwrap()),
+            Some("AU".to_owned())
+        );
+        assert_eq!(
+            database.country("1.0.2.1".parse().unwrap()),
+            Some("CN".to_owned())
+        );
+        assert_eq!(database.country("1.0.4.0".parse().unwrap()), None);
+        assert_eq!(database.country("5.0.0.1".parse().unwrap()), None);
+        assert_eq!(database.country("0.0.0.1".parse().unwrap()), None);
+        assert_eq!(
+            database.country("2001:db8::42".parse().unwrap()),
+            Some("DE".to_owned())
+        );
+    }
+
+    #[test]
+    fn reports_the_bad_line() {
+        let result =
+            GeoIpDatabase::from_reader("1.0.0.0,1.0.0.255,AU\n1.0.1.0,nope,CN\n".as_bytes());
+
+        assert!(matches!(result, Err(GeoIpError::Malformed { line: 2, .. })));
+    }
+}
@@ -0,0 +1,481 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use async_trait::async_trait;
+use dashmap::DashMap;
+
+use crate::{
+    app::{
+        account::{Account, ApiKey},
+        command::update_workspace::WorkspaceChanges,
+        workspace::{Quota, Workspace},
+    },
+    error::AppError,
+};
+
+use super::{
+    wal::{WalRecord, WriteAheadLog},
+    write,
+};
+
+/// Accounts, their keys and workspaces. Keys are indexed by the hash of
+/// their secret. All of it is persisted like links when the storage has a
+/// snapshot or WAL; see `InMemoryStorage::accounts`.
+#[derive(Clone, Default)]
+pub struct InMemoryAccountRepository {
+    pub(super) accounts: Arc<DashMap<String, Account>>,
+    pub(super) keys: Arc<DashMap<String, ApiKey>>,
+    /// Guarded by a lock so claiming domains and storing the workspace
+    /// claiming them happen at once.
+    pub(super) workspaces: Arc<Mutex<Workspaces>>,
+    pub(super) wal: Option<Arc<WriteAheadLog>>,
+}
+
+#[derive(Default)]
+pub(super) struct Workspaces {
+    by_id: HashMap<String, Workspace>,
+    /// Workspace ID per domain.
+    domains: HashMap<String, String>,
+}
+
+impl Workspaces {
+    /// Fails if a workspace other than `id` serves one of `domains`.
+    fn check_free(&self, id: &str, domains: &[String]) -> Result<(), AppError> {
+        match domains
+            .iter()
+            .find(|domain| self.domains.get(*domain).is_some_and(|owner| owner != id))
+        {
+            Some(domain) => Err(AppError::DomainTaken {
+                domain: domain.clone(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Stores `workspace` and points its domains at it.
+    pub(super) fn put(&mut self, workspace: Workspace) {
+        self.domains.retain(|_, owner| *owner != workspace.id);
+        for domain in &workspace.domains {
+            self.domains.insert(domain.clone(), workspace.id.clone());
+        }
+        self.by_id.insert(workspace.id.clone(), workspace);
+    }
+
+    pub(super) fn all(&self) -> impl Iterator<Item = &Workspace> {
+        self.by_id.values()
+    }
+}
+
+impl InMemoryAccountRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub(super) fn workspaces(&self) -> MutexGuard<'_, Workspaces> {
+        self.workspaces
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[async_trait]
+impl crate::app::command::create_account::CreateAccountRepository for InMemoryAccountRepository {
+    async fn save_account(&self, account: Account, key: ApiKey) -> Result<(), AppError> {
+        write(self.wal.as_deref(), |journal| {
+            journal.record(WalRecord::Account {
+                account: account.clone(),
+            })?;
+            journal.record(WalRecord::Key { key: key.clone() })?;
+            self.accounts.insert(account.id.clone(), account);
+            self.keys.insert(key.hash.clone(), key);
+            Ok(())
+        })
+    }
+}
+
+#[async_trait]
+impl crate::app::command::create_api_key::CreateApiKeyRepository for InMemoryAccountRepository {
+    async fn add_api_key(&self, key: ApiKey) -> Result<(), AppError> {
+        write(self.wal.as_deref(), |journal| {
+            if !self.accounts.contains_key(&key.account_id) {
+                return Err(AppError::not_found(&key.account_id));
+            }
+            journal.record(WalRecord::Key { key: key.clone() })?;
+            self.keys.insert(key.hash.clone(), key);
+            Ok(())
+        })
+    }
+}
+
+#[async_trait]
+impl crate::app::command::revoke_api_key::RevokeApiKeyRepository for InMemoryAccountRepository {
+    async fn revoke_api_key(&self, account_id: &str, key_id: &str) -> Result<(), AppError> {
+        write(self.wal.as_deref(), |journal| {
+            let Some(hash) = self
+                .keys
+                .iter()
+                .find(|key| key.account_id == account_id && key.id == key_id)
+                .map(|key| key.hash.clone())
+            else {
+                return Err(AppError::not_found(key_id));
+            };
+            journal.record(WalRecord::Revoke { hash: hash.clone() })?;
+            self.keys.remove(&hash);
+            Ok(())
+        })
+    }
+}
+
+impl crate::app::query::authenticate::AuthenticateRepository for InMemoryAccountRepository {
+    async fn account_for_key(&self, hash: &str) -> Result<Option<Account>, AppError> {
+        Ok(self
+            .keys
+            .get(hash)
+            .and_then(|key| self.accounts.get(&key.account_id))
+            .map(|account| account.clone()))
+    }
+
+    async fn workspaces_of(&self, account_id: &str) -> Result<Vec<String>, AppError> {
+        let mut ids: Vec<String> = self
+            .workspaces()
+            .by_id
+            .values()
+            .filter(|workspace| workspace.members.iter().any(|id| id == account_id))
+            .map(|workspace| workspace.id.clone())
+            .collect();
+        ids.sort();
+
+        Ok(ids)
+    }
+}
+
+#[async_trait]
+impl crate::app::command::create_workspace::CreateWorkspaceRepository
+    for InMemoryAccountRepository
+{
+    async fn save_workspace(&self, workspace: Workspace) -> Result<(), AppError> {
+        write(self.wal.as_deref(), |journal| {
+            let mut workspaces = self.workspaces();
+            workspaces.check_free(&workspace.id, &workspace.domains)?;
+            journal.record(WalRecord::Workspace {
+                workspace: workspace.clone(),
+            })?;
+            workspaces.put(workspace);
+            Ok(())
+        })
+    }
+}
+
+#[async_trait]
+impl crate::app::command::update_workspace::UpdateWorkspaceRepository
+    for InMemoryAccountRepository
+{
+    async fn update_workspace(
+        &self,
+        id: &str,
+        changes: &WorkspaceChanges,
+    ) -> Result<Workspace, AppError> {
+        write(self.wal.as_deref(), |journal| {
+            let mut workspaces = self.workspaces();
+            let Some(mut workspace) = workspaces.by_id.get(id).cloned() else {
+                return Err(AppError::not_found(id));
+            };
+            changes.apply(&mut workspace);
+            workspaces.check_free(id, &workspace.domains)?;
+            journal.record(WalRecord::Workspace {
+                workspace: workspace.clone(),
+            })?;
+            workspaces.put(workspace.clone());
+            Ok(workspace)
+        })
+    }
+}
+
+#[async_trait]
+impl crate::app::command::change_membership::ChangeMembershipRepository
+    for InMemoryAccountRepository
+{
+    async fn set_membership(
+        &self,
+        workspace_id: &str,
+        account_id: &str,
+        member: bool,
+    ) -> Result<Workspace, AppError> {
+        if member && !self.accounts.contains_key(account_id) {
+            return Err(AppError::not_found(account_id));
+        }
+        write(self.wal.as_deref(), |journal| {
+            let mut workspaces = self.workspaces();
+            let Some(mut workspace) = workspaces.by_id.get(workspace_id).cloned() else {
+                return Err(AppError::not_found(workspace_id));
+            };
+
+            workspace.members.retain(|id| id != account_id);
+            if member {
+                workspace.members.push(account_id.to_owned());
+                workspace.members.sort();
+            }
+            journal.record(WalRecord::Workspace {
+                workspace: workspace.clone(),
+            })?;
+            workspaces.put(workspace.clone());
+            Ok(workspace)
+        })
+    }
+}
+
+#[async_trait]
+impl crate::app::command::create_short_url::WorkspaceQuotaRepository for InMemoryAccountRepository {
+    async fn quota(&self, workspace: &str) -> Result<Quota, AppError> {
+        match self.workspaces().by_id.get(workspace) {
+            Some(workspace) => Ok(workspace.quota),
+            None => Err(AppError::not_found(workspace)),
+        }
+    }
+}
+
+impl crate::app::query::get_workspace::GetWorkspaceRepository for InMemoryAccountRepository {
+    async fn workspace(&self, id: &str) -> Result<Option<Workspace>, AppError> {
+        Ok(self.workspaces().by_id.get(id).cloned())
+    }
+
+    async fn workspace_for_domain(&self, domain: &str) -> Result<Option<String>, AppError> {
+        Ok(self.workspaces().domains.get(domain).cloned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app::{
+        account::hash_secret,
+        command::{
+            change_membership::ChangeMembershipRepository, create_account::CreateAccountRepository,
+            create_api_key::CreateApiKeyRepository, create_workspace::CreateWorkspaceRepository,
+            revoke_api_key::RevokeApiKeyRepository, update_workspace::UpdateWorkspaceRepository,
+        },
+        query::{authenticate::AuthenticateRepository, get_workspace::GetWorkspaceRepository},
+    };
+
+    use crate::app::policy::Role;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn revoked_keys_stop_working() {
+        // Given
+        let repo = InMemoryAccountRepository::new();
+        let account = Account {
+            id: "alice".to_owned(),
+            name: "Alice".to_owned(),
+            role: Role::Editor,
+            created_at: 0,
+        };
+        let (first, first_issued) = ApiKey::generate("alice", 0);
+        let (second, second_issued) = ApiKey::generate("alice", 0);
+        repo.save_account(account.clone(), first).await.unwrap();
+        repo.add_api_key(second.clone()).await.unwrap();
+
+        // When
+        repo.revoke_api_key("alice", &second.id).await.unwrap();
+
+        // Then
+        let holder = |secret: String| {
+            let repo = repo.clone();
+            async move { repo.account_for_key(&hash_secret(&secret)).await.unwrap() }
+        };
+        assert_eq!(holder(first_issued.secret).await, Some(account));
+        assert_eq!(holder(second_issued.secret).await, None);
+        assert_eq!(
+            repo.revoke_api_key("alice", &second.id).await,
+            Err(AppError::not_found(&second.id))
+        );
+    }
+
+    #[tokio::test]
+    async fn accounts_and_keys_survive_a_restart() {
+        use crate::{adapters::inmemory::InMemoryStorage, config::Config};
+
+        // Given
+        let dir = tempfile::tempdir().unwrap();
+        let wal = dir.path().join("links.wal");
+        let config = Config::from_vars(|name| {
+            (name == "URLSHORTENER_WAL_PATH").then(|| wal.display().to_string())
+        })
+        .unwrap();
+        let (storage, _) = InMemoryStorage::open(&config).unwrap();
+        let repo = storage.accounts();
+        let account = Account {
+            id: "alice".to_owned(),
+            name: "Alice".to_owned(),
+            role: Role::Editor,
+            created_at: 0,
+        };
+        let (first, first_issued) = ApiKey::generate("alice", 0);
+        let (second, second_issued) = ApiKey::generate("alice", 0);
+        repo.save_account(account.clone(), first).await.unwrap();
+        repo.add_api_key(second.clone()).await.unwrap();
+        repo.revoke_api_key("alice", &second.id).await.unwrap();
+        drop((repo, storage));
+
+        // When
+        let (storage, _) = InMemoryStorage::open(&config).unwrap();
+        let repo = storage.accounts();
+
+        // Then
+        let holder = |secret: String| {
+            let repo = repo.clone();
+            async move { repo.account_for_key(&hash_secret(&secret)).await.unwrap() }
+        };
+        assert_eq!(holder(first_issued.secret).await, Some(account));
+        assert_eq!(holder(second_issued.secret).await, None);
+    }
+
+    #[tokio::test]
+    async fn keys_need_an_account() {
+        let repo = InMemoryAccountRepository::new();
+        let (key, _) = ApiKey::generate("nobody", 0);
+
+        assert_eq!(
+            repo.add_api_key(key).await,
+            Err(AppError::not_found("nobody"))
+        );
+    }
+
+    fn workspace(id: &str, domains: &[&str]) -> Workspace {
+        Workspace {
+            id: id.to_owned(),
+            name: id.to_owned(),
+            members: Vec::new(),
+            domains: domains.iter().map(|domain| (*domain).to_owned()).collect(),
+            quota: Quota::default(),
+            created_at: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn domains_belong_to_one_workspace() {
+        // Given
+        let repo = InMemoryAccountRepository::new();
+        repo.save_workspace(workspace("red", &["red.example.com"]))
+            .await
+            .unwrap();
+
+        // When
+        let taken = repo
+            .save_workspace(workspace("blue", &["red.example.com"]))
+            .await;
+        repo.save_workspace(workspace("blue", &["blue.example.com"]))
+            .await
+            .unwrap();
+        let moved = repo
+            .update_workspace(
+                "red",
+                &WorkspaceChanges {
+                    domains: Some(vec!["go.example.com".to_owned()]),
+                    ..Default::default()
+                },
+            )
+            .await;
+
+        // Then
+        assert_eq!(
+            taken,
+            Err(AppError::DomainTaken {
+                domain: "red.example.com".to_owned()
+            })
+        );
+        assert_eq!(moved.unwrap().domains, ["go.example.com"]);
+        assert_eq!(repo.workspace_for_domain("red.example.com").await, Ok(None));
+        assert_eq!(
+            repo.workspace_for_domain("go.example.com").await,
+            Ok(Some("red".to_owned()))
+        );
+        assert_eq!(
+            repo.workspace_for_domain("blue.example.com").await,
+            Ok(Some("blue".to_owned()))
+        );
+    }
+
+    #[tokio::test]
+    async fn members_need_an_account() {
+        // Given
+        let repo = InMemoryAccountRepository::new();
+        let account = Account {
+            id: "alice".to_owned(),
+            name: "Alice".to_owned(),
+            role: Role::Editor,
+            created_at: 0,
+        };
+        let (key, _) = ApiKey::generate("alice", 0);
+        repo.save_account(account, key).await.unwrap();
+        repo.save_workspace(workspace("team", &[])).await.unwrap();
+
+        // When
+        let joined = repo.set_membership("team", "alice", true).await;
+        let twice = repo.set_membership("team", "alice", true).await;
+        let nobody = repo.set_membership("team", "nobody", true).await;
+
+        // Then
+        assert_eq!(joined.unwrap().members, ["alice"]);
+        assert_eq!(twice.unwrap().members, ["alice"]);
+        assert_eq!(nobody, Err(AppError::not_found("nobody")));
+        assert_eq!(
+            repo.workspaces_of("alice").await,
+            Ok(vec!["team".to_owned()])
+        );
+        repo.set_membership("team", "alice", false).await.unwrap();
+        assert_eq!(repo.workspaces_of("alice").await, Ok(vec![]));
+    }
+
+    #[tokio::test]
+    async fn workspaces_survive_a_restart() {
+        use crate::{adapters::inmemory::InMemoryStorage, config::Config};
+
+        // Given
+        let dir = tempfile::tempdir().unwrap();
+        let wal = dir.path().join("links.wal");
+        let config = Config::from_vars(|name| {
+            (name == "URLSHORTENER_WAL_PATH").then(|| wal.display().to_string())
+        })
+        .unwrap();
+        let (storage, _) = InMemoryStorage::open(&config).unwrap();
+        let repo = storage.accounts();
+        let account = Account {
+            id: "alice".to_owned(),
+            name: "Alice".to_owned(),
+            role: Role::Editor,
+            created_at: 0,
+        };
+        let (key, _) = ApiKey::generate("alice", 0);
+        repo.save_account(account, key).await.unwrap();
+        repo.save_workspace(workspace("red", &["red.example.com"]))
+            .await
+            .unwrap();
+        repo.update_workspace(
+            "red",
+            &WorkspaceChanges {
+                domains: Some(vec!["go.example.com".to_owned()]),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        repo.set_membership("red", "alice", true).await.unwrap();
+        drop((repo, storage));
+
+        // When
+        let (storage, _) = InMemoryStorage::open(&config).unwrap();
+        let repo = storage.accounts();
+
+        // Then
+        assert_eq!(repo.workspace_for_domain("red.example.com").await, Ok(None));
+        assert_eq!(
+            repo.workspace_for_domain("go.example.com").await,
+            Ok(Some("red".to_owned()))
+        );
+        assert_eq!(
+            repo.workspaces_of("alice").await,
+            Ok(vec!["red".to_owned()])
+        );
+    }
+}
@@ -1,35 +1,745 @@
+pub mod account;
+pub mod sequence;
+pub mod snapshot;
+pub mod wal;
+pub mod webhook;
+
 use std::sync::Arc;
 
 use async_trait::async_trait;
-use dashmap::DashMap;
+use dashmap::{mapref::entry::Entry, DashMap};
+use futures::{stream, Stream, StreamExt};
+
+use crate::{
+    app::{
+        command::update_short_url::LinkChanges,
+        health::{HealthCounts, LinkHealth},
+        query::{get_link_stats::LinkStats, list_short_urls::OwnerFilter},
+        report::AbuseReport,
+        short_url::{LinkStatus, Ownership, ShortUrl},
+        workspace::{self, Usage},
+    },
+    clock::{SharedClock, SystemClock},
+    config::Config,
+    error::AppError,
+};
+
+use self::{
+    account::InMemoryAccountRepository,
+    sequence::InMemorySequence,
+    snapshot::{SnapshotError, Snapshotter},
+    wal::{WalError, WalGuard, WalRecord, WriteAheadLog},
+    webhook::InMemoryWebhookRepository,
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum PersistenceError {
+    #[error(transparent)]
+    Snapshot(#[from] SnapshotError),
+    #[error(transparent)]
+    Wal(#[from] WalError),
+    #[error("id sequence: {0}")]
+    Sequence(#[source] std::io::Error),
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct RestoreReport {
+    pub from_snapshot: usize,
+    pub from_wal: usize,
+    /// Bytes of a torn trailing WAL record that were dropped.
+    pub truncated_bytes: u64,
+}
+
+/// What snapshots and the WAL persist: the links, the webhook
+/// subscriptions with their delivery log, the accounts with their keys and
+/// workspaces, the monthly workspace clicks and the abuse reports.
+#[derive(Clone, Default)]
+pub struct State {
+    pub links: Arc<DashMap<String, ShortUrl>>,
+    pub webhooks: InMemoryWebhookRepository,
+    pub accounts: InMemoryAccountRepository,
+    /// Visits per workspace and month.
+    pub workspace_clicks: Arc<DashMap<(String, String), u64>>,
+    /// Abuse reports by ID.
+    pub reports: Arc<DashMap<String, AbuseReport>>,
+}
+
+/// The in-memory state together with whatever persistence is configured
+/// for it.
+pub struct InMemoryStorage {
+    pub state: State,
+    pub wal: Option<Arc<WriteAheadLog>>,
+    pub snapshotter: Option<Snapshotter>,
+    /// Counter for sequential IDs, kept next to the other persisted files.
+    pub sequence: InMemorySequence,
+    /// Visit counts per link. Not persisted: they start over on restart.
+    pub visits: Arc<DashMap<String, LinkStats>>,
+    /// Last destination check per link, not persisted either.
+    pub health: Arc<DashMap<String, LinkHealth>>,
+    clock: SharedClock,
+}
+
+impl InMemoryStorage {
+    /// Restores the snapshot, then replays the WAL on top of it.
+    pub fn open(config: &Config) -> Result<(Self, RestoreReport), PersistenceError> {
+        let state = State::default();
+        let mut report = RestoreReport::default();
+
+        if let Some(path) = &config.snapshot_path {
+            report.from_snapshot = Snapshotter::new(path, state.clone()).load()?;
+        }
+
+        let wal = match &config.wal_path {
+            Some(path) => {
+                let (wal, replay) = WriteAheadLog::open(path, config.wal_sync, &state)?;
+                report.from_wal = replay.applied;
+                report.truncated_bytes = replay.truncated_bytes;
+                Some(Arc::new(wal))
+            }
+            None => None,
+        };
+
+        let snapshotter = config.snapshot_path.as_ref().map(|path| match &wal {
+            Some(wal) => Snapshotter::with_wal(path, state.clone(), wal.clone()),
+            None => Snapshotter::new(path, state.clone()),
+        });
+
+        let sequence_path = config.sequence_path.clone().or_else(|| {
+            let persisted = config.snapshot_path.as_ref().or(config.wal_path.as_ref())?;
+            let mut path = persisted.clone().into_os_string();
+            path.push(".sequence");
+            Some(path.into())
+        });
+        let sequence = match sequence_path {
+            Some(path) => InMemorySequence::open(path).map_err(PersistenceError::Sequence)?,
+            None => InMemorySequence::new(),
+        };
+
+        Ok((
+            InMemoryStorage {
+                state,
+                wal,
+                snapshotter,
+                sequence,
+                visits: Arc::default(),
+                health: Arc::default(),
+                clock: Arc::new(SystemClock),
+            },
+            report,
+        ))
+    }
+
+    /// Where repositories made from now on learn the time; see
+    /// `InMemoryRepository::with_clock`.
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
 
-use crate::error::AppError;
+    pub fn repository(&self) -> InMemoryRepository {
+        let store = self.state.links.clone();
+        let repository = match &self.wal {
+            Some(wal) => InMemoryRepository::with_wal(store, wal.clone()),
+            None => InMemoryRepository::new(store),
+        };
+
+        InMemoryRepository {
+            visits: self.visits.clone(),
+            workspace_clicks: self.state.workspace_clicks.clone(),
+            reports: self.state.reports.clone(),
+            health: self.health.clone(),
+            clock: self.clock.clone(),
+            ..repository
+        }
+    }
+
+    /// Account storage whose writes go through the same WAL as links.
+    pub fn accounts(&self) -> InMemoryAccountRepository {
+        InMemoryAccountRepository {
+            wal: self.wal.clone(),
+            ..self.state.accounts.clone()
+        }
+    }
+
+    /// Webhook storage whose writes go through the same WAL as links.
+    pub fn webhooks(&self) -> InMemoryWebhookRepository {
+        InMemoryWebhookRepository {
+            wal: self.wal.clone(),
+            ..self.state.webhooks.clone()
+        }
+    }
+}
 
 #[derive(Clone)]
 pub struct InMemoryRepository {
-    store: Arc<DashMap<String, String>>,
+    store: Arc<DashMap<String, ShortUrl>>,
+    wal: Option<Arc<WriteAheadLog>>,
+    visits: Arc<DashMap<String, LinkStats>>,
+    workspace_clicks: Arc<DashMap<(String, String), u64>>,
+    reports: Arc<DashMap<String, AbuseReport>>,
+    health: Arc<DashMap<String, LinkHealth>>,
+    clock: SharedClock,
 }
 
 impl InMemoryRepository {
-    pub fn new(store: Arc<DashMap<String, String>>) -> Self {
-        Self { store }
+    pub fn new(store: Arc<DashMap<String, ShortUrl>>) -> Self {
+        Self {
+            store,
+            wal: None,
+            visits: Arc::default(),
+            workspace_clicks: Arc::default(),
+            reports: Arc::default(),
+            health: Arc::default(),
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Repository whose writes are acknowledged only after they are in `wal`.
+    pub fn with_wal(store: Arc<DashMap<String, ShortUrl>>, wal: Arc<WriteAheadLog>) -> Self {
+        Self {
+            store,
+            wal: Some(wal),
+            visits: Arc::default(),
+            workspace_clicks: Arc::default(),
+            reports: Arc::default(),
+            health: Arc::default(),
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Where the repository learns whether links have expired and which
+    /// month visits count towards.
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    fn now(&self) -> u64 {
+        self.clock.now()
+    }
+
+    fn write<T>(
+        &self,
+        apply: impl FnOnce(&mut Journal<'_>) -> Result<T, AppError>,
+    ) -> Result<T, AppError> {
+        write(self.wal.as_deref(), apply)
+    }
+
+    fn stored_ownership(&self, id: &str) -> Result<Ownership, AppError> {
+        match self.store.get(id) {
+            Some(link) => Ok(link.ownership()),
+            None => Err(AppError::not_found(id)),
+        }
+    }
+
+    fn usage(&self, workspace: &str, month: &str) -> Usage {
+        let links = self
+            .store
+            .iter()
+            .filter(|entry| {
+                !entry.is_expired(self.now()) && entry.workspace.as_deref() == Some(workspace)
+            })
+            .count();
+        let clicks = self
+            .workspace_clicks
+            .get(&(workspace.to_owned(), month.to_owned()))
+            .map(|clicks| *clicks)
+            .unwrap_or_default();
+
+        Usage {
+            month: month.to_owned(),
+            links: links as u64,
+            clicks,
+        }
+    }
+}
+
+/// Runs `apply` holding the log lock (when a WAL is configured). `apply`
+/// records each change in the journal before making it to the store.
+fn write<T>(
+    wal: Option<&WriteAheadLog>,
+    apply: impl FnOnce(&mut Journal<'_>) -> Result<T, AppError>,
+) -> Result<T, AppError> {
+    let mut journal = Journal {
+        log: wal.map(|wal| (wal, wal.lock())),
+    };
+
+    apply(&mut journal)
+}
+
+struct Journal<'a> {
+    log: Option<(&'a WriteAheadLog, WalGuard<'a>)>,
+}
+
+impl Journal<'_> {
+    fn record(&mut self, record: WalRecord) -> Result<(), AppError> {
+        let Some((wal, guard)) = &mut self.log else {
+            return Ok(());
+        };
+
+        guard.append(&record).map_err(|err| {
+            tracing::error!(%err, path = %wal.path().display(), "write-ahead log append failed");
+            AppError::StorageUnavailable
+        })
     }
 }
 
 #[async_trait]
 impl crate::app::command::create_short_url::CreateShortUrlRepository for InMemoryRepository {
-    async fn save(&self, full_url: String, id: String) -> Result<(), AppError> {
-        self.store.insert(id, full_url);
+    async fn save(&self, link: ShortUrl) -> Result<(), AppError> {
+        self.write(|journal| match self.store.entry(link.id.clone()) {
+            Entry::Occupied(_) => Err(AppError::IdTaken { id: link.id }),
+            Entry::Vacant(slot) => {
+                journal.record(WalRecord::Put {
+                    link: Box::new(link.clone()),
+                })?;
+                slot.insert(link);
+                Ok(())
+            }
+        })
+    }
+
+    async fn find(&self, id: &str) -> Result<Option<ShortUrl>, AppError> {
+        Ok(self.store.get(id).map(|link| link.clone()))
+    }
+
+    async fn workspace_usage(&self, workspace: &str, month: &str) -> Result<Usage, AppError> {
+        Ok(self.usage(workspace, month))
+    }
+}
+
+#[async_trait]
+impl crate::app::command::update_short_url::UpdateShortUrlRepository for InMemoryRepository {
+    async fn ownership(&self, id: &str) -> Result<Ownership, AppError> {
+        self.stored_ownership(id)
+    }
+
+    async fn update(&self, id: &str, changes: &LinkChanges) -> Result<ShortUrl, AppError> {
+        self.write(|journal| match self.store.get_mut(id) {
+            Some(mut stored) if !stored.is_expired(self.now()) => {
+                let mut link = stored.clone();
+                changes.apply(&mut link);
+                journal.record(WalRecord::Put {
+                    link: Box::new(link.clone()),
+                })?;
+                *stored = link.clone();
+                Ok(link)
+            }
+            _ => Err(AppError::not_found(id)),
+        })
+    }
+}
+
+#[async_trait]
+impl crate::app::command::change_link_status::ChangeLinkStatusRepository for InMemoryRepository {
+    async fn set_status(
+        &self,
+        id: &str,
+        status: LinkStatus,
+        reason: Option<String>,
+    ) -> Result<ShortUrl, AppError> {
+        self.write(|journal| match self.store.get_mut(id) {
+            Some(mut stored) if !stored.is_expired(self.now()) => {
+                let mut link = stored.clone();
+                link.status = status;
+                link.status_reason = reason;
+                journal.record(WalRecord::Put {
+                    link: Box::new(link.clone()),
+                })?;
+                *stored = link.clone();
+                Ok(link)
+            }
+            _ => Err(AppError::not_found(id)),
+        })
+    }
+
+    async fn close_reports(
+        &self,
+        link_id: &str,
+        outcome: LinkStatus,
+        reviewed_at: u64,
+    ) -> Result<usize, AppError> {
+        self.write(|journal| {
+            let mut closed = 0;
+            for mut report in self.reports.iter_mut() {
+                if report.link_id == link_id && report.is_open() {
+                    let mut reviewed = report.clone();
+                    reviewed.close(outcome, reviewed_at);
+                    journal.record(WalRecord::Report {
+                        report: reviewed.clone(),
+                    })?;
+                    *report = reviewed;
+                    closed += 1;
+                }
+            }
+            Ok(closed)
+        })
+    }
+}
+
+#[async_trait]
+impl crate::app::command::report_link::ReportLinkRepository for InMemoryRepository {
+    async fn save_report(&self, report: AbuseReport) -> Result<(), AppError> {
+        self.write(|journal| {
+            match self.store.get(&report.link_id) {
+                Some(link) if !link.is_expired(self.now()) => {}
+                _ => return Err(AppError::not_found(&report.link_id)),
+            }
+            journal.record(WalRecord::Report {
+                report: report.clone(),
+            })?;
+            self.reports.insert(report.id.clone(), report);
+            Ok(())
+        })
+    }
+}
+
+impl crate::app::query::list_reports::ListReportsRepository for InMemoryRepository {
+    async fn reports(
+        &self,
+        open_only: bool,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Vec<AbuseReport>, AppError> {
+        let mut reports: Vec<_> = self
+            .reports
+            .iter()
+            .filter(|entry| !open_only || entry.is_open())
+            .map(|entry| entry.value().clone())
+            .collect();
+        reports.sort_by(|a, b| (a.created_at, &a.id).cmp(&(b.created_at, &b.id)));
+
+        Ok(reports.into_iter().skip(offset).take(limit).collect())
+    }
+}
+
+#[async_trait]
+impl crate::app::command::record_visit::RecordVisitRepository for InMemoryRepository {
+    async fn record_visit(&self, id: &str, variant: Option<String>) -> Result<(), AppError> {
+        let Some(workspace) = self.store.get(id).map(|link| link.workspace.clone()) else {
+            return Ok(());
+        };
+        if let Some(workspace) = workspace {
+            let month = workspace::month_of(self.now());
+            self.write(|journal| {
+                journal.record(WalRecord::Click {
+                    workspace: workspace.clone(),
+                    month: month.clone(),
+                })?;
+                *self.workspace_clicks.entry((workspace, month)).or_default() += 1;
+                Ok(())
+            })?;
+        }
+
+        let mut stats = self.visits.entry(id.to_owned()).or_default();
+        stats.visits += 1;
+        if let Some(variant) = variant {
+            *stats.variants.entry(variant).or_default() += 1;
+        }
 
         Ok(())
     }
 }
 
+impl crate::app::query::get_link_stats::GetLinkStatsRepository for InMemoryRepository {
+    async fn ownership(&self, id: &str) -> Result<Ownership, AppError> {
+        self.stored_ownership(id)
+    }
+
+    async fn link_stats(&self, id: &str) -> Result<Option<LinkStats>, AppError> {
+        match self.store.get(id) {
+            Some(link) if !link.is_expired(self.now()) => Ok(Some(
+                self.visits
+                    .get(id)
+                    .map(|stats| stats.clone())
+                    .unwrap_or_default(),
+            )),
+            _ => Ok(None),
+        }
+    }
+}
+
+#[async_trait]
+impl crate::app::command::check_link_health::CheckLinkHealthRepository for InMemoryRepository {
+    async fn links_to_check(&self) -> Result<Vec<ShortUrl>, AppError> {
+        Ok(self
+            .store
+            .iter()
+            .filter(|entry| !entry.is_expired(self.now()))
+            .map(|entry| entry.value().clone())
+            .collect())
+    }
+
+    async fn record_health(&self, id: &str, health: LinkHealth) -> Result<(), AppError> {
+        if self.store.contains_key(id) {
+            self.health.insert(id.to_owned(), health);
+        }
+
+        Ok(())
+    }
+}
+
+impl crate::app::query::get_link_health::GetLinkHealthRepository for InMemoryRepository {
+    async fn ownership(&self, id: &str) -> Result<Ownership, AppError> {
+        self.stored_ownership(id)
+    }
+
+    async fn link_health(&self, id: &str) -> Result<Option<LinkHealth>, AppError> {
+        match self.store.get(id) {
+            Some(link) if !link.is_expired(self.now()) => {
+                Ok(self.health.get(id).map(|health| health.clone()))
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
 impl crate::app::query::get_full_url::GetFullUrlRepository for InMemoryRepository {
-    async fn get(&self, id: &str) -> Result<String, AppError> {
+    async fn get(&self, id: &str) -> Result<ShortUrl, AppError> {
         match self.store.get(id) {
-            Some(url) => Ok(url.clone()),
-            None => Err(AppError::NotFound),
+            Some(link) if !link.is_expired(self.now()) => Ok(link.clone()),
+            _ => Err(AppError::not_found(id)),
         }
     }
 }
+
+#[async_trait]
+impl crate::app::command::delete_short_url::DeleteShortUrlRepository for InMemoryRepository {
+    async fn ownership(&self, id: &str) -> Result<Ownership, AppError> {
+        self.stored_ownership(id)
+    }
+
+    async fn delete(&self, id: &str) -> Result<String, AppError> {
+        self.write(|journal| {
+            if !self.store.contains_key(id) {
+                return Err(AppError::not_found(id));
+            }
+
+            journal.record(WalRecord::Delete { id: id.to_owned() })?;
+
+            self.visits.remove(id);
+            self.health.remove(id);
+            match self.store.remove(id) {
+                Some((_, link)) => Ok(link.url),
+                None => Err(AppError::not_found(id)),
+            }
+        })
+    }
+}
+
+impl crate::app::query::list_short_urls::ListShortUrlsRepository for InMemoryRepository {
+    async fn list(
+        &self,
+        owner: OwnerFilter<'_>,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Vec<ShortUrl>, AppError> {
+        let mut links: Vec<_> = self
+            .store
+            .iter()
+            .filter(|entry| !entry.is_expired(self.now()) && owner.matches(entry))
+            .map(|entry| entry.value().clone())
+            .collect();
+        links.sort_by(|a, b| a.id.cmp(&b.id));
+
+        Ok(links.into_iter().skip(offset).take(limit).collect())
+    }
+}
+
+impl crate::app::query::get_workspace_usage::GetWorkspaceUsageRepository for InMemoryRepository {
+    async fn workspace_usage(&self, workspace: &str, month: &str) -> Result<Usage, AppError> {
+        Ok(self.usage(workspace, month))
+    }
+}
+
+impl crate::app::query::get_stats::GetStatsRepository for InMemoryRepository {
+    async fn count(&self) -> Result<usize, AppError> {
+        Ok(self
+            .store
+            .iter()
+            .filter(|entry| !entry.is_expired(self.now()))
+            .count())
+    }
+
+    async fn health_counts(&self) -> Result<HealthCounts, AppError> {
+        Ok(self
+            .health
+            .iter()
+            .filter(|entry| {
+                self.store
+                    .get(entry.key())
+                    .is_some_and(|link| !link.is_expired(self.now()))
+            })
+            .map(|entry| entry.state)
+            .collect())
+    }
+}
+
+#[async_trait]
+impl crate::app::command::import_short_urls::ImportShortUrlsRepository for InMemoryRepository {
+    async fn find_many(&self, ids: &[String]) -> Result<Vec<Option<ShortUrl>>, AppError> {
+        Ok(ids
+            .iter()
+            .map(|id| self.store.get(id).map(|link| link.clone()))
+            .collect())
+    }
+
+    async fn upsert_many(&self, links: Vec<ShortUrl>) -> Result<(), AppError> {
+        self.write(|journal| {
+            for link in links {
+                journal.record(WalRecord::Put {
+                    link: Box::new(link.clone()),
+                })?;
+                self.store.insert(link.id.clone(), link);
+            }
+            Ok(())
+        })
+    }
+}
+
+#[async_trait]
+impl crate::app::command::purge_expired_links::PurgeExpiredLinksRepository for InMemoryRepository {
+    async fn purge_expired(&self) -> Result<Vec<ShortUrl>, AppError> {
+        self.write(|journal| {
+            let expired: Vec<String> = self
+                .store
+                .iter()
+                .filter(|entry| entry.is_expired(self.now()))
+                .map(|entry| entry.key().clone())
+                .collect();
+
+            let mut purged = Vec::new();
+            for id in expired {
+                // Without a WAL nothing holds writers off, and a concurrent
+                // import may have replaced the link meanwhile. Only what was
+                // actually removed goes into the journal.
+                let Some((_, link)) = self
+                    .store
+                    .remove_if(&id, |_, link| link.is_expired(self.now()))
+                else {
+                    continue;
+                };
+                self.visits.remove(&id);
+                self.health.remove(&id);
+                journal.record(WalRecord::Delete { id })?;
+                purged.push(link);
+            }
+            Ok(purged)
+        })
+    }
+}
+
+impl crate::app::query::export_short_urls::ExportShortUrlsRepository for InMemoryRepository {
+    fn export(
+        &self,
+        batch_size: usize,
+    ) -> impl Stream<Item = Result<Vec<ShortUrl>, AppError>> + std::marker::Send {
+        // Only the keys are snapshotted up front; records are cloned batch by
+        // batch so exporting a large store does not double its memory.
+        let mut ids: Vec<String> = self.store.iter().map(|entry| entry.key().clone()).collect();
+        ids.sort();
+
+        let mut ids = ids.into_iter().peekable();
+        let mut batches: Vec<Vec<String>> = Vec::new();
+        while ids.peek().is_some() {
+            batches.push(ids.by_ref().take(batch_size.max(1)).collect());
+        }
+
+        let store = self.store.clone();
+        let clock = self.clock.clone();
+
+        stream::iter(batches).map(move |ids| {
+            Ok(ids
+                .iter()
+                .filter_map(|id| store.get(id).map(|link| link.clone()))
+                .filter(|link| !link.is_expired(clock.now()))
+                .collect())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app::{
+        command::{
+            change_link_status::ChangeLinkStatusRepository,
+            create_short_url::CreateShortUrlRepository, report_link::ReportLinkRepository,
+        },
+        query::list_reports::ListReportsRepository,
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn expiry_and_monthly_clicks_follow_the_injected_clock() {
+        use crate::app::command::{
+            purge_expired_links::PurgeExpiredLinksRepository, record_visit::RecordVisitRepository,
+        };
+        use crate::clock::FakeClock;
+
+        // Given
+        let clock = FakeClock::new(1_000);
+        let repo = InMemoryRepository::new(Arc::default()).with_clock(Arc::new(clock.clone()));
+        let mut link = ShortUrl::new("abc".to_owned(), "https://example.com/".to_owned());
+        link.created_at = 1_000;
+        link.expires_at = Some(1_000 + 40 * 86_400);
+        link.workspace = Some("team".to_owned());
+        repo.save(link).await.unwrap();
+
+        // When
+        repo.record_visit("abc", None).await.unwrap();
+        let january = repo.workspace_usage("team", "1970-01").await.unwrap();
+        clock.advance(40 * 86_400);
+        let purged = repo.purge_expired().await.unwrap();
+
+        // Then
+        assert_eq!((january.links, january.clicks), (1, 1));
+        assert_eq!(purged.len(), 1);
+        assert_eq!(repo.find("abc").await, Ok(None));
+    }
+
+    #[tokio::test]
+    async fn reports_survive_a_restart() {
+        // Given
+        let dir = tempfile::tempdir().unwrap();
+        let wal = dir.path().join("links.wal");
+        let config = Config::from_vars(|name| {
+            (name == "URLSHORTENER_WAL_PATH").then(|| wal.display().to_string())
+        })
+        .unwrap();
+        let (storage, _) = InMemoryStorage::open(&config).unwrap();
+        let repo = storage.repository();
+        repo.save(ShortUrl::new(
+            "abc".to_owned(),
+            "https://example.com/".to_owned(),
+        ))
+        .await
+        .unwrap();
+        let report = |id: &str| AbuseReport {
+            id: id.to_owned(),
+            link_id: "abc".to_owned(),
+            reason: "phishing".to_owned(),
+            created_at: 0,
+            reviewed_at: None,
+            outcome: None,
+        };
+        repo.save_report(report("first")).await.unwrap();
+        repo.close_reports("abc", LinkStatus::Disabled, 10)
+            .await
+            .unwrap();
+        repo.save_report(report("second")).await.unwrap();
+        drop((repo, storage));
+
+        // When
+        let (storage, _) = InMemoryStorage::open(&config).unwrap();
+        let repo = storage.repository();
+
+        // Then
+        let mut closed = report("first");
+        closed.close(LinkStatus::Disabled, 10);
+        assert_eq!(
+            repo.reports(false, 0, 10).await,
+            Ok(vec![closed, report("second")])
+        );
+    }
+}
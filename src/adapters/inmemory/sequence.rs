@@ -0,0 +1,113 @@
+//! ID counter for the in-memory store. The high-water mark of reserved
+//! values is kept in a small text file, rewritten atomically on every
+//! reservation, so a restart continues after the last handed out block.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+
+use crate::{error::AppError, id_provider::sequential::IdSequenceRepository};
+
+use super::snapshot::write_atomically;
+
+#[derive(Clone, Default)]
+pub struct InMemorySequence {
+    next: Arc<Mutex<u64>>,
+    path: Option<PathBuf>,
+}
+
+impl InMemorySequence {
+    /// Counter that is lost on restart; fine when the links are too.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Counter persisted at `path`, starting where the file says. A missing
+    /// file starts from zero.
+    pub fn open(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        let next = match fs::read_to_string(&path) {
+            Ok(text) => text.trim().parse().map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{} does not hold a counter", path.display()),
+                )
+            })?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => 0,
+            Err(err) => return Err(err),
+        };
+
+        Ok(Self {
+            next: Arc::new(Mutex::new(next)),
+            path: Some(path),
+        })
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+}
+
+#[async_trait]
+impl IdSequenceRepository for InMemorySequence {
+    async fn reserve(&self, count: u64) -> Result<u64, AppError> {
+        let mut next = self
+            .next
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let start = *next;
+        let end = start
+            .checked_add(count)
+            .ok_or(AppError::StorageUnavailable)?;
+
+        // Persist before handing anything out, or a crash could reissue it.
+        if let Some(path) = &self.path {
+            write_atomically(path, end.to_string().as_bytes()).map_err(|err| {
+                tracing::error!(%err, path = %path.display(), "failed to persist id counter");
+                AppError::StorageUnavailable
+            })?;
+        }
+        *next = end;
+
+        Ok(start)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn reopening_continues_after_reserved_blocks() {
+        // Given
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ids.sequence");
+        let sequence = InMemorySequence::open(&path).unwrap();
+        sequence.reserve(100).await.unwrap();
+        sequence.reserve(100).await.unwrap();
+
+        // When
+        let reopened = InMemorySequence::open(&path).unwrap();
+        let start = reopened.reserve(10).await;
+
+        // Then
+        assert_eq!(start, Ok(200));
+    }
+
+    #[test]
+    fn garbage_counter_file_is_an_error() {
+        // Given
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ids.sequence");
+        fs::write(&path, "not a number").unwrap();
+
+        // When
+        let result = InMemorySequence::open(&path);
+
+        // Then
+        assert_eq!(result.err().unwrap().kind(), io::ErrorKind::InvalidData);
+    }
+}
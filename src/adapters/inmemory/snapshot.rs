@@ -0,0 +1,575 @@
+//! Point-in-time snapshots of the in-memory state.
+//!
+//! File layout: a single header line
+//! `urlshortener-snapshot <version> <sha256 of payload> <payload length>`
+//! followed by the payload, a JSON object of the links, webhook
+//! subscriptions and delivery logs, accounts, API keys, workspaces,
+//! monthly workspace clicks and abuse reports. Version 1 payloads, a JSON
+//! array of links, are still read. The checksum covers the payload bytes exactly as
+//! written, so any truncation or bit flip is caught before a single record
+//! is restored.
+
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::app::{
+    account::{Account, ApiKey},
+    report::AbuseReport,
+    short_url::ShortUrl,
+    webhook::{WebhookDelivery, WebhookSubscription},
+    workspace::Workspace,
+};
+
+use super::{wal::WriteAheadLog, State};
+
+const MAGIC: &str = "urlshortener-snapshot";
+pub const SNAPSHOT_VERSION: u32 = 2;
+
+#[derive(thiserror::Error, Debug)]
+pub enum SnapshotError {
+    #[error("snapshot io error: {0}")]
+    Io(#[from] io::Error),
+    #[error("snapshot {path} has unsupported version {version}")]
+    UnsupportedVersion { path: PathBuf, version: u32 },
+    #[error("snapshot {path} is corrupted: {reason}")]
+    Corrupted { path: PathBuf, reason: String },
+}
+
+/// Everything a snapshot holds.
+#[derive(Default, Serialize, Deserialize)]
+struct Contents {
+    links: Vec<ShortUrl>,
+    #[serde(default)]
+    webhooks: Vec<WebhookSubscription>,
+    /// The delivery logs, oldest entry of each first.
+    #[serde(default)]
+    deliveries: Vec<WebhookDelivery>,
+    #[serde(default)]
+    accounts: Vec<Account>,
+    #[serde(default)]
+    api_keys: Vec<ApiKey>,
+    #[serde(default)]
+    workspaces: Vec<Workspace>,
+    #[serde(default)]
+    clicks: Vec<MonthlyClicks>,
+    #[serde(default)]
+    reports: Vec<AbuseReport>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct MonthlyClicks {
+    workspace: String,
+    month: String,
+    clicks: u64,
+}
+
+impl Contents {
+    fn of(state: &State) -> Self {
+        let webhooks = &state.webhooks;
+        Contents {
+            links: values(&state.links),
+            webhooks: values(&webhooks.subscriptions),
+            deliveries: webhooks
+                .deliveries
+                .iter()
+                .flat_map(|log| log.value().clone())
+                .collect(),
+            accounts: values(&state.accounts.accounts),
+            api_keys: values(&state.accounts.keys),
+            workspaces: state.accounts.workspaces().all().cloned().collect(),
+            clicks: state
+                .workspace_clicks
+                .iter()
+                .map(|entry| MonthlyClicks {
+                    workspace: entry.key().0.clone(),
+                    month: entry.key().1.clone(),
+                    clicks: *entry.value(),
+                })
+                .collect(),
+            reports: values(&state.reports),
+        }
+    }
+
+    /// Restores the contents into `state` and returns how many links there were.
+    fn restore(self, state: &State) -> usize {
+        let restored = self.links.len();
+        for link in self.links {
+            state.links.insert(link.id.clone(), link);
+        }
+        for subscription in self.webhooks {
+            state
+                .webhooks
+                .subscriptions
+                .insert(subscription.id.clone(), subscription);
+        }
+        for delivery in self.deliveries {
+            state.webhooks.append(delivery);
+        }
+        for account in self.accounts {
+            state.accounts.accounts.insert(account.id.clone(), account);
+        }
+        for key in self.api_keys {
+            state.accounts.keys.insert(key.hash.clone(), key);
+        }
+        let mut workspaces = state.accounts.workspaces();
+        for workspace in self.workspaces {
+            workspaces.put(workspace);
+        }
+        for MonthlyClicks {
+            workspace,
+            month,
+            clicks,
+        } in self.clicks
+        {
+            state.workspace_clicks.insert((workspace, month), clicks);
+        }
+        for report in self.reports {
+            state.reports.insert(report.id.clone(), report);
+        }
+
+        restored
+    }
+}
+
+fn values<T: Clone>(map: &DashMap<String, T>) -> Vec<T> {
+    map.iter().map(|entry| entry.value().clone()).collect()
+}
+
+#[derive(Clone)]
+pub struct Snapshotter {
+    path: PathBuf,
+    state: State,
+    wal: Option<Arc<WriteAheadLog>>,
+}
+
+impl Snapshotter {
+    pub fn new(path: impl Into<PathBuf>, state: State) -> Self {
+        Self {
+            path: path.into(),
+            state,
+            wal: None,
+        }
+    }
+
+    /// Snapshotter that also compacts `wal`: every save folds the log into
+    /// the snapshot and empties it.
+    pub fn with_wal(path: impl Into<PathBuf>, state: State, wal: Arc<WriteAheadLog>) -> Self {
+        Self {
+            path: path.into(),
+            state,
+            wal: Some(wal),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Restores the snapshot into the state and returns how many links it
+    /// held. A missing file is an empty state; a damaged one is an error,
+    /// never silently skipped.
+    pub fn load(&self) -> Result<usize, SnapshotError> {
+        let bytes = match fs::read(&self.path) {
+            Ok(bytes) => bytes,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(0),
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(decode(&self.path, &bytes)?.restore(&self.state))
+    }
+
+    /// Writes the current state next to the target and renames it into
+    /// place, so readers only ever see a complete snapshot.
+    pub fn save(&self) -> Result<usize, SnapshotError> {
+        match &self.wal {
+            Some(wal) => wal.compact(|| self.write_snapshot()),
+            None => self.write_snapshot(),
+        }
+    }
+
+    fn write_snapshot(&self) -> Result<usize, SnapshotError> {
+        let contents = Contents::of(&self.state);
+        let bytes = encode(&contents)?;
+
+        write_atomically(&self.path, &bytes)?;
+
+        Ok(contents.links.len())
+    }
+
+    /// Saves every `interval` until the task is dropped. Failures are
+    /// logged and retried on the next tick.
+    pub async fn run_periodic(self, interval: Duration) {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.tick().await;
+
+        loop {
+            ticker.tick().await;
+
+            let this = self.clone();
+            match tokio::task::spawn_blocking(move || this.save()).await {
+                Ok(Ok(saved)) => tracing::debug!(saved, "snapshot written"),
+                Ok(Err(err)) => tracing::error!(%err, "failed to write snapshot"),
+                Err(err) => tracing::error!(%err, "snapshot task panicked"),
+            }
+        }
+    }
+}
+
+fn encode(contents: &Contents) -> Result<Vec<u8>, SnapshotError> {
+    let payload = serde_json::to_vec(contents).map_err(io::Error::from)?;
+    let checksum = hex::encode(Sha256::digest(&payload));
+
+    let mut bytes =
+        format!("{MAGIC} {SNAPSHOT_VERSION} {checksum} {}\n", payload.len()).into_bytes();
+    bytes.extend_from_slice(&payload);
+
+    Ok(bytes)
+}
+
+fn decode(path: &Path, bytes: &[u8]) -> Result<Contents, SnapshotError> {
+    let corrupted = |reason: &str| SnapshotError::Corrupted {
+        path: path.to_owned(),
+        reason: reason.to_owned(),
+    };
+
+    let header_end = bytes
+        .iter()
+        .position(|b| *b == b'\n')
+        .ok_or_else(|| corrupted("missing header"))?;
+    let header = std::str::from_utf8(&bytes[..header_end]).map_err(|_| corrupted("bad header"))?;
+    let payload = &bytes[header_end + 1..];
+
+    let fields: Vec<&str> = header.split(' ').collect();
+    let [magic, version, checksum, length] = fields[..] else {
+        return Err(corrupted("bad header"));
+    };
+    if magic != MAGIC {
+        return Err(corrupted("not a snapshot file"));
+    }
+
+    let version: u32 = version.parse().map_err(|_| corrupted("bad version"))?;
+    if !(1..=SNAPSHOT_VERSION).contains(&version) {
+        return Err(SnapshotError::UnsupportedVersion {
+            path: path.to_owned(),
+            version,
+        });
+    }
+
+    let length: usize = length.parse().map_err(|_| corrupted("bad length"))?;
+    if payload.len() != length {
+        return Err(corrupted(&format!(
+            "expected {length} payload bytes, found {}",
+            payload.len()
+        )));
+    }
+    if hex::encode(Sha256::digest(payload)) != checksum {
+        return Err(corrupted("checksum mismatch"));
+    }
+
+    let contents = match version {
+        1 => serde_json::from_slice(payload).map(|links| Contents {
+            links,
+            ..Contents::default()
+        }),
+        _ => serde_json::from_slice(payload),
+    };
+    contents.map_err(|err| corrupted(&err.to_string()))
+}
+
+/// Temp file in the same directory, fsync, rename, fsync the directory.
+pub(crate) fn write_atomically(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path has no file name"))?;
+    let tmp_path = dir.join(format!(
+        ".{}.tmp-{}",
+        file_name.to_string_lossy(),
+        std::process::id()
+    ));
+
+    let result = (|| {
+        let mut file = File::create(&tmp_path)?;
+        file.write_all(bytes)?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, path)?;
+
+        #[cfg(unix)]
+        File::open(dir)?.sync_all()?;
+
+        Ok(())
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state_with(ids: &[&str]) -> State {
+        let state = State::default();
+        for id in ids {
+            state.links.insert(
+                id.to_string(),
+                ShortUrl::new(id.to_string(), format!("https://{id}.com/")),
+            );
+        }
+        state
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        // Given
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("links.snapshot");
+        let source = state_with(&["a", "b", "c"]);
+        Snapshotter::new(&path, source.clone()).save().unwrap();
+
+        // When
+        let target = State::default();
+        let restored = Snapshotter::new(&path, target.clone()).load().unwrap();
+
+        // Then
+        assert_eq!(restored, 3);
+        assert_eq!(
+            *target.links.get("b").unwrap(),
+            *source.links.get("b").unwrap()
+        );
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn missing_snapshot_is_empty() {
+        let dir = tempfile::tempdir().unwrap();
+        let snapshotter = Snapshotter::new(dir.path().join("none"), State::default());
+
+        assert_eq!(snapshotter.load().unwrap(), 0);
+    }
+
+    #[test]
+    fn flipped_byte_is_detected() {
+        // Given
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("links.snapshot");
+        Snapshotter::new(&path, state_with(&["a"])).save().unwrap();
+
+        let mut bytes = fs::read(&path).unwrap();
+        let last = bytes.len() - 3;
+        bytes[last] ^= 0x01;
+        fs::write(&path, bytes).unwrap();
+
+        // When
+        let result = Snapshotter::new(&path, State::default()).load();
+
+        // Then
+        assert!(
+            matches!(result, Err(SnapshotError::Corrupted { ref reason, .. }) if reason == "checksum mismatch")
+        );
+    }
+
+    #[test]
+    fn truncated_snapshot_is_detected() {
+        // Given
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("links.snapshot");
+        Snapshotter::new(&path, state_with(&["a", "b"]))
+            .save()
+            .unwrap();
+
+        let bytes = fs::read(&path).unwrap();
+        fs::write(&path, &bytes[..bytes.len() - 10]).unwrap();
+
+        // When
+        let target = State::default();
+        let result = Snapshotter::new(&path, target.clone()).load();
+
+        // Then
+        assert!(matches!(result, Err(SnapshotError::Corrupted { .. })));
+        assert!(target.links.is_empty());
+    }
+
+    #[test]
+    fn unknown_version_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("links.snapshot");
+        fs::write(&path, format!("{MAGIC} 99 abc 2\n[]")).unwrap();
+
+        let result = Snapshotter::new(&path, State::default()).load();
+
+        assert!(matches!(
+            result,
+            Err(SnapshotError::UnsupportedVersion { version: 99, .. })
+        ));
+    }
+
+    #[test]
+    fn webhooks_round_trip() {
+        // Given
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("links.snapshot");
+        let source = State::default();
+        source.webhooks.subscriptions.insert(
+            "sub".to_owned(),
+            WebhookSubscription {
+                id: "sub".to_owned(),
+                url: "https://example.com/hook".to_owned(),
+                secret: "secret".to_owned(),
+                events: vec![],
+                created_at: 0,
+            },
+        );
+        for attempt in 1..=2 {
+            source.webhooks.append(WebhookDelivery {
+                subscription_id: "sub".to_owned(),
+                event_id: "evt".to_owned(),
+                event_kind: crate::app::event::EventKind::LinkCreated,
+                attempt,
+                status_code: None,
+                error: Some("timed out".to_owned()),
+                succeeded: false,
+                attempted_at: 0,
+            });
+        }
+        Snapshotter::new(&path, source.clone()).save().unwrap();
+
+        // When
+        let target = State::default();
+        Snapshotter::new(&path, target.clone()).load().unwrap();
+
+        // Then
+        assert_eq!(
+            *target.webhooks.subscriptions.get("sub").unwrap(),
+            *source.webhooks.subscriptions.get("sub").unwrap()
+        );
+        assert_eq!(
+            *target.webhooks.deliveries.get("sub").unwrap(),
+            *source.webhooks.deliveries.get("sub").unwrap()
+        );
+    }
+
+    #[test]
+    fn workspaces_clicks_and_reports_round_trip() {
+        // Given
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("links.snapshot");
+        let source = State::default();
+        source.accounts.workspaces().put(Workspace {
+            id: "team".to_owned(),
+            name: "Team".to_owned(),
+            members: vec!["alice".to_owned()],
+            domains: vec!["go.example.com".to_owned()],
+            quota: Default::default(),
+            created_at: 0,
+        });
+        source
+            .workspace_clicks
+            .insert(("team".to_owned(), "2026-10".to_owned()), 7);
+        source.reports.insert(
+            "report".to_owned(),
+            AbuseReport {
+                id: "report".to_owned(),
+                link_id: "abc".to_owned(),
+                reason: "spam".to_owned(),
+                created_at: 0,
+                reviewed_at: None,
+                outcome: None,
+            },
+        );
+        Snapshotter::new(&path, source.clone()).save().unwrap();
+
+        // When
+        let target = State::default();
+        Snapshotter::new(&path, target.clone()).load().unwrap();
+
+        // Then
+        let workspaces = target.accounts.workspaces();
+        let restored: Vec<_> = workspaces.all().collect();
+        assert_eq!(restored.len(), 1);
+        assert_eq!(restored[0].domains, ["go.example.com"]);
+        assert_eq!(restored[0].members, ["alice"]);
+        assert_eq!(
+            target
+                .workspace_clicks
+                .get(&("team".to_owned(), "2026-10".to_owned()))
+                .map(|clicks| *clicks),
+            Some(7)
+        );
+        assert_eq!(
+            *target.reports.get("report").unwrap(),
+            *source.reports.get("report").unwrap()
+        );
+    }
+
+    #[test]
+    fn version_1_snapshots_still_load() {
+        // Given
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("links.snapshot");
+        let payload =
+            serde_json::to_vec(&[ShortUrl::new("a".to_owned(), "https://a.com/".to_owned())])
+                .unwrap();
+        let mut bytes = format!(
+            "{MAGIC} 1 {} {}\n",
+            hex::encode(Sha256::digest(&payload)),
+            payload.len()
+        )
+        .into_bytes();
+        bytes.extend_from_slice(&payload);
+        fs::write(&path, bytes).unwrap();
+
+        // When
+        let target = State::default();
+        let restored = Snapshotter::new(&path, target.clone()).load();
+
+        // Then
+        assert_eq!(restored.unwrap(), 1);
+        assert!(target.links.contains_key("a"));
+    }
+
+    #[tokio::test]
+    async fn save_folds_wal_into_snapshot() {
+        use crate::adapters::inmemory::{wal::WalSync, InMemoryRepository};
+        use crate::app::command::create_short_url::CreateShortUrlRepository;
+
+        // Given
+        let dir = tempfile::tempdir().unwrap();
+        let snapshot_path = dir.path().join("links.snapshot");
+        let wal_path = dir.path().join("links.wal");
+        let state = State::default();
+        let (wal, _) = WriteAheadLog::open(&wal_path, WalSync::Always, &state).unwrap();
+        let wal = Arc::new(wal);
+        let repo = InMemoryRepository::with_wal(state.links.clone(), wal.clone());
+        repo.save(ShortUrl::new("a".to_owned(), "https://a.com/".to_owned()))
+            .await
+            .unwrap();
+        assert!(fs::metadata(&wal_path).unwrap().len() > 0);
+
+        // When
+        Snapshotter::with_wal(&snapshot_path, state, wal)
+            .save()
+            .unwrap();
+
+        // Then
+        assert_eq!(fs::metadata(&wal_path).unwrap().len(), 0);
+        let restored = State::default();
+        Snapshotter::new(&snapshot_path, restored.clone())
+            .load()
+            .unwrap();
+        assert!(restored.links.contains_key("a"));
+    }
+}
@@ -0,0 +1,603 @@
+//! Append-only write-ahead log for the in-memory state.
+//!
+//! Each record is `[payload length: u32 LE][crc32 of payload: u32 LE]`
+//! followed by the JSON payload. A crash in the middle of an append leaves
+//! a last record that is short or fails its checksum; replay cuts the file
+//! back to the record before it. Damage anywhere else is corruption and
+//! replay refuses it, since the records after it were acknowledged.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Mutex, MutexGuard};
+
+use serde::{Deserialize, Serialize};
+
+use crate::app::{
+    account::{Account, ApiKey},
+    report::AbuseReport,
+    short_url::ShortUrl,
+    webhook::{WebhookDelivery, WebhookSubscription},
+    workspace::Workspace,
+};
+
+use super::State;
+
+const HEADER_LEN: usize = 8;
+/// Largest payload a record may have, so a damaged header cannot make
+/// replay allocate gigabytes.
+const MAX_RECORD_LEN: usize = 16 * 1024 * 1024;
+
+/// When appended records are forced to disk.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WalSync {
+    /// fsync before every write is acknowledged.
+    #[default]
+    Always,
+    /// Leave flushing to the OS; survives process crashes, not power loss.
+    Never,
+}
+
+impl FromStr for WalSync {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "always" => Ok(WalSync::Always),
+            "never" => Ok(WalSync::Never),
+            other => Err(format!(
+                "unknown fsync mode `{other}`, expected always or never"
+            )),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum WalRecord {
+    // Boxed so deletes stay small.
+    Put {
+        link: Box<ShortUrl>,
+    },
+    Delete {
+        id: String,
+    },
+    Subscribe {
+        subscription: WebhookSubscription,
+    },
+    Unsubscribe {
+        id: String,
+    },
+    Delivery {
+        delivery: WebhookDelivery,
+    },
+    Account {
+        account: Account,
+    },
+    Key {
+        key: ApiKey,
+    },
+    /// Revokes the API key with this hash.
+    Revoke {
+        hash: String,
+    },
+    Workspace {
+        workspace: Workspace,
+    },
+    /// One visit to a link of `workspace` during `month`.
+    Click {
+        workspace: String,
+        month: String,
+    },
+    /// A new abuse report, or one that was reviewed.
+    Report {
+        report: AbuseReport,
+    },
+}
+
+impl WalRecord {
+    fn apply(self, state: &State) {
+        match self {
+            WalRecord::Put { link } => {
+                state.links.insert(link.id.clone(), *link);
+            }
+            WalRecord::Delete { id } => {
+                state.links.remove(&id);
+            }
+            WalRecord::Subscribe { subscription } => {
+                state
+                    .webhooks
+                    .subscriptions
+                    .insert(subscription.id.clone(), subscription);
+            }
+            WalRecord::Unsubscribe { id } => {
+                state.webhooks.remove(&id);
+            }
+            WalRecord::Delivery { delivery } => state.webhooks.append(delivery),
+            WalRecord::Account { account } => {
+                state.accounts.accounts.insert(account.id.clone(), account);
+            }
+            WalRecord::Key { key } => {
+                state.accounts.keys.insert(key.hash.clone(), key);
+            }
+            WalRecord::Revoke { hash } => {
+                state.accounts.keys.remove(&hash);
+            }
+            WalRecord::Workspace { workspace } => state.accounts.workspaces().put(workspace),
+            WalRecord::Click { workspace, month } => {
+                *state
+                    .workspace_clicks
+                    .entry((workspace, month))
+                    .or_default() += 1;
+            }
+            WalRecord::Report { report } => {
+                state.reports.insert(report.id.clone(), report);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct ReplayReport {
+    pub applied: usize,
+    /// Bytes of a torn trailing record that were cut off.
+    pub truncated_bytes: u64,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum WalError {
+    #[error("write-ahead log {path}: {source}")]
+    Io { path: PathBuf, source: io::Error },
+    #[error("write-ahead log {path} is corrupted at byte {offset}: {reason}")]
+    Corrupted {
+        path: PathBuf,
+        offset: u64,
+        reason: &'static str,
+    },
+}
+
+pub struct WriteAheadLog {
+    path: PathBuf,
+    file: Mutex<File>,
+    sync: WalSync,
+}
+
+impl WriteAheadLog {
+    /// Replays the log into `state`, drops a torn tail and opens the log
+    /// for appending. Damage before the tail is an error.
+    pub fn open(
+        path: impl Into<PathBuf>,
+        sync: WalSync,
+        state: &State,
+    ) -> Result<(Self, ReplayReport), WalError> {
+        let path = path.into();
+        let io_err = |source| WalError::Io {
+            path: path.clone(),
+            source,
+        };
+
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .map_err(io_err)?;
+
+        let file_len = file.metadata().map_err(io_err)?.len();
+        let (applied, good_len) = match replay(&mut file, file_len, state) {
+            Ok(replayed) => replayed,
+            Err(Replay::Io(source)) => return Err(io_err(source)),
+            Err(Replay::Corrupted { offset, reason }) => {
+                return Err(WalError::Corrupted {
+                    path,
+                    offset,
+                    reason,
+                })
+            }
+        };
+        if good_len < file_len {
+            file.set_len(good_len).map_err(io_err)?;
+            file.sync_all().map_err(io_err)?;
+        }
+        file.seek(SeekFrom::End(0)).map_err(io_err)?;
+
+        let report = ReplayReport {
+            applied,
+            truncated_bytes: file_len - good_len,
+        };
+        let wal = WriteAheadLog {
+            path,
+            file: Mutex::new(file),
+            sync,
+        };
+
+        Ok((wal, report))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Exclusive access to the log. Callers keep the guard while they apply
+    /// the change to the store so compaction never observes a record that is
+    /// logged but not yet applied.
+    pub fn lock(&self) -> WalGuard<'_> {
+        WalGuard {
+            file: self
+                .file
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner()),
+            sync: self.sync,
+        }
+    }
+
+    /// Runs `checkpoint` (which must persist the whole store) with writers
+    /// blocked, then empties the log.
+    pub fn compact<T, E>(&self, checkpoint: impl FnOnce() -> Result<T, E>) -> Result<T, E>
+    where
+        E: From<io::Error>,
+    {
+        let mut guard = self.lock();
+
+        let checkpointed = checkpoint()?;
+
+        guard.file.set_len(0)?;
+        guard.file.seek(SeekFrom::Start(0))?;
+        guard.file.sync_all()?;
+
+        Ok(checkpointed)
+    }
+}
+
+pub struct WalGuard<'a> {
+    file: MutexGuard<'a, File>,
+    sync: WalSync,
+}
+
+impl WalGuard<'_> {
+    pub fn append(&mut self, record: &WalRecord) -> io::Result<()> {
+        let payload = serde_json::to_vec(record)?;
+        if payload.len() > MAX_RECORD_LEN {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "record too large",
+            ));
+        }
+        let len = payload.len() as u32;
+
+        let mut frame = Vec::with_capacity(HEADER_LEN + payload.len());
+        frame.extend_from_slice(&len.to_le_bytes());
+        frame.extend_from_slice(&crc32fast::hash(&payload).to_le_bytes());
+        frame.extend_from_slice(&payload);
+
+        let start = self.file.stream_position()?;
+        let result = self.file.write_all(&frame).and_then(|_| match self.sync {
+            WalSync::Always => self.file.sync_data(),
+            WalSync::Never => Ok(()),
+        });
+
+        // Never leave a half-written frame in front of later records.
+        if result.is_err() {
+            let _ = self.file.set_len(start);
+            let _ = self.file.seek(SeekFrom::Start(start));
+        }
+
+        result
+    }
+}
+
+enum Replay {
+    Io(io::Error),
+    Corrupted { offset: u64, reason: &'static str },
+}
+
+impl From<io::Error> for Replay {
+    fn from(err: io::Error) -> Self {
+        Replay::Io(err)
+    }
+}
+
+/// Applies every intact record of the `file_len` bytes and returns how many
+/// there were together with the length of the intact prefix. Only the last
+/// record may be torn.
+fn replay(file: &mut File, file_len: u64, state: &State) -> Result<(usize, u64), Replay> {
+    file.seek(SeekFrom::Start(0))?;
+    let mut reader = BufReader::new(file);
+
+    let mut applied = 0;
+    let mut good_len = 0u64;
+    let mut header = [0u8; HEADER_LEN];
+
+    loop {
+        if !read_full(&mut reader, &mut header)? {
+            break;
+        }
+        let len = u32::from_le_bytes(header[..4].try_into().unwrap()) as usize;
+        let crc = u32::from_le_bytes(header[4..].try_into().unwrap());
+        let end = good_len + (HEADER_LEN + len) as u64;
+        let corrupted = |reason| Replay::Corrupted {
+            offset: good_len,
+            reason,
+        };
+
+        if end > file_len {
+            break;
+        }
+        if len > MAX_RECORD_LEN {
+            return Err(corrupted("record length out of range"));
+        }
+        let mut payload = vec![0u8; len];
+        if !read_full(&mut reader, &mut payload)? {
+            break;
+        }
+        if crc32fast::hash(&payload) != crc {
+            if end == file_len {
+                break;
+            }
+            return Err(corrupted("checksum mismatch"));
+        }
+        // The checksum held, so this is no torn write.
+        let record = serde_json::from_slice::<WalRecord>(&payload)
+            .map_err(|_| corrupted("unreadable record"))?;
+
+        record.apply(state);
+        applied += 1;
+        good_len = end;
+    }
+
+    Ok((applied, good_len))
+}
+
+/// Like `read_exact`, but reports a short read as `false` instead of an error.
+fn read_full(reader: &mut impl Read, buf: &mut [u8]) -> io::Result<bool> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => return Ok(false),
+            Ok(n) => filled += n,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    fn put(id: &str) -> WalRecord {
+        WalRecord::Put {
+            link: Box::new(ShortUrl::new(id.to_owned(), format!("https://{id}.com/"))),
+        }
+    }
+
+    fn write_records(path: &Path, records: &[WalRecord]) {
+        let (wal, _) = WriteAheadLog::open(path, WalSync::Always, &State::default()).unwrap();
+        let mut log = wal.lock();
+        for record in records {
+            log.append(record).unwrap();
+        }
+    }
+
+    #[test]
+    fn replay_applies_puts_and_deletes_in_order() {
+        // Given
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("links.wal");
+        write_records(
+            &path,
+            &[put("a"), put("b"), WalRecord::Delete { id: "a".to_owned() }],
+        );
+
+        // When
+        let state = State::default();
+        let (_, report) = WriteAheadLog::open(&path, WalSync::Always, &state).unwrap();
+
+        // Then
+        assert_eq!(report.applied, 3);
+        assert_eq!(report.truncated_bytes, 0);
+        assert!(state.links.get("a").is_none());
+        assert!(state.links.get("b").is_some());
+    }
+
+    #[test]
+    fn replay_counts_workspace_clicks_per_month() {
+        // Given
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("links.wal");
+        let click = |month: &str| WalRecord::Click {
+            workspace: "team".to_owned(),
+            month: month.to_owned(),
+        };
+        write_records(
+            &path,
+            &[click("2026-09"), click("2026-10"), click("2026-10")],
+        );
+
+        // When
+        let state = State::default();
+        WriteAheadLog::open(&path, WalSync::Always, &state).unwrap();
+
+        // Then
+        let clicks = |month: &str| {
+            state
+                .workspace_clicks
+                .get(&("team".to_owned(), month.to_owned()))
+                .map(|clicks| *clicks)
+        };
+        assert_eq!(clicks("2026-09"), Some(1));
+        assert_eq!(clicks("2026-10"), Some(2));
+    }
+
+    #[test]
+    fn replay_restores_webhooks_and_their_deliveries() {
+        // Given
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("links.wal");
+        let subscription = |id: &str| WebhookSubscription {
+            id: id.to_owned(),
+            url: "https://example.com/hook".to_owned(),
+            secret: "secret".to_owned(),
+            events: vec![],
+            created_at: 0,
+        };
+        let delivery = WebhookDelivery {
+            subscription_id: "kept".to_owned(),
+            event_id: "evt".to_owned(),
+            event_kind: crate::app::event::EventKind::LinkCreated,
+            attempt: 1,
+            status_code: Some(200),
+            error: None,
+            succeeded: true,
+            attempted_at: 0,
+        };
+        write_records(
+            &path,
+            &[
+                WalRecord::Subscribe {
+                    subscription: subscription("kept"),
+                },
+                WalRecord::Subscribe {
+                    subscription: subscription("dropped"),
+                },
+                WalRecord::Delivery {
+                    delivery: delivery.clone(),
+                },
+                WalRecord::Unsubscribe {
+                    id: "dropped".to_owned(),
+                },
+            ],
+        );
+
+        // When
+        let state = State::default();
+        WriteAheadLog::open(&path, WalSync::Always, &state).unwrap();
+
+        // Then
+        let webhooks = &state.webhooks;
+        assert!(webhooks.subscriptions.contains_key("kept"));
+        assert!(!webhooks.subscriptions.contains_key("dropped"));
+        assert_eq!(*webhooks.deliveries.get("kept").unwrap(), [delivery]);
+    }
+
+    #[test]
+    fn torn_tail_is_cut_and_log_stays_usable() {
+        // Given
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("links.wal");
+        write_records(&path, &[put("a"), put("b")]);
+        let full_len = fs::metadata(&path).unwrap().len();
+        let file = OpenOptions::new().write(true).open(&path).unwrap();
+        file.set_len(full_len - 5).unwrap();
+
+        // When
+        let state = State::default();
+        let (wal, report) = WriteAheadLog::open(&path, WalSync::Always, &state).unwrap();
+        wal.lock().append(&put("c")).unwrap();
+        drop(wal);
+
+        // Then
+        assert_eq!(report.applied, 1);
+        assert!(report.truncated_bytes > 0);
+
+        let state = State::default();
+        let (_, report) = WriteAheadLog::open(&path, WalSync::Always, &state).unwrap();
+        assert_eq!(report.applied, 2);
+        assert!(state.links.get("a").is_some() && state.links.get("c").is_some());
+        assert!(state.links.get("b").is_none());
+    }
+
+    #[test]
+    fn checksum_mismatch_ends_replay() {
+        // Given
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("links.wal");
+        write_records(&path, &[put("a"), put("b")]);
+        let mut bytes = fs::read(&path).unwrap();
+        let last = bytes.len() - 2;
+        bytes[last] ^= 0xff;
+        fs::write(&path, bytes).unwrap();
+
+        // When
+        let state = State::default();
+        let (_, report) = WriteAheadLog::open(&path, WalSync::Never, &state).unwrap();
+
+        // Then
+        assert_eq!(report.applied, 1);
+        assert_eq!(state.links.len(), 1);
+    }
+
+    #[test]
+    fn damage_before_the_tail_is_refused() {
+        // Given
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("links.wal");
+        write_records(&path, &[put("a"), put("b")]);
+        let mut bytes = fs::read(&path).unwrap();
+        bytes[HEADER_LEN + 2] ^= 0xff;
+        fs::write(&path, &bytes).unwrap();
+
+        // When
+        let result = WriteAheadLog::open(&path, WalSync::Never, &State::default());
+
+        // Then
+        assert!(matches!(
+            result,
+            Err(WalError::Corrupted {
+                offset: 0,
+                reason: "checksum mismatch",
+                ..
+            })
+        ));
+        assert_eq!(fs::read(&path).unwrap(), bytes);
+    }
+
+    #[test]
+    fn oversized_length_is_refused_without_allocating() {
+        // Given
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("links.wal");
+        let mut bytes = ((MAX_RECORD_LEN + 1) as u32).to_le_bytes().to_vec();
+        bytes.resize(HEADER_LEN + MAX_RECORD_LEN + 1, 0);
+        fs::write(&path, bytes).unwrap();
+
+        // When
+        let result = WriteAheadLog::open(&path, WalSync::Never, &State::default());
+
+        // Then
+        assert!(matches!(
+            result,
+            Err(WalError::Corrupted {
+                reason: "record length out of range",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn compaction_empties_the_log() {
+        // Given
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("links.wal");
+        let (wal, _) = WriteAheadLog::open(&path, WalSync::Always, &State::default()).unwrap();
+        wal.lock().append(&put("a")).unwrap();
+
+        // When
+        let mut checkpointed = false;
+        wal.compact(|| {
+            checkpointed = true;
+            Ok::<_, io::Error>(())
+        })
+        .unwrap();
+
+        // Then
+        assert!(checkpointed);
+        assert_eq!(fs::metadata(&path).unwrap().len(), 0);
+    }
+}
@@ -0,0 +1,275 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use dashmap::DashMap;
+
+use crate::{
+    app::{
+        event::EventKind,
+        webhook::{dispatcher::WebhookDeliveryRepository, WebhookDelivery, WebhookSubscription},
+    },
+    error::AppError,
+};
+
+use super::{
+    wal::{WalRecord, WriteAheadLog},
+    write,
+};
+
+/// Upper bound of log entries kept per subscription; older ones are dropped.
+const MAX_DELIVERIES_PER_SUBSCRIPTION: usize = 100;
+
+/// Subscriptions and their delivery logs. Persisted like links when the
+/// storage has a snapshot or WAL; see `InMemoryStorage::webhooks`.
+#[derive(Clone, Default)]
+pub struct InMemoryWebhookRepository {
+    pub(super) subscriptions: Arc<DashMap<String, WebhookSubscription>>,
+    pub(super) deliveries: Arc<DashMap<String, Vec<WebhookDelivery>>>,
+    pub(super) wal: Option<Arc<WriteAheadLog>>,
+}
+
+impl InMemoryWebhookRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub(super) fn remove(&self, id: &str) -> bool {
+        self.deliveries.remove(id);
+        self.subscriptions.remove(id).is_some()
+    }
+
+    pub(super) fn append(&self, delivery: WebhookDelivery) {
+        let mut log = self
+            .deliveries
+            .entry(delivery.subscription_id.clone())
+            .or_default();
+        log.push(delivery);
+        if log.len() > MAX_DELIVERIES_PER_SUBSCRIPTION {
+            let overflow = log.len() - MAX_DELIVERIES_PER_SUBSCRIPTION;
+            log.drain(..overflow);
+        }
+    }
+}
+
+#[async_trait]
+impl crate::app::command::create_webhook::CreateWebhookRepository for InMemoryWebhookRepository {
+    async fn save_subscription(&self, subscription: WebhookSubscription) -> Result<(), AppError> {
+        write(self.wal.as_deref(), |journal| {
+            journal.record(WalRecord::Subscribe {
+                subscription: subscription.clone(),
+            })?;
+            self.subscriptions
+                .insert(subscription.id.clone(), subscription);
+            Ok(())
+        })
+    }
+}
+
+#[async_trait]
+impl crate::app::command::delete_webhook::DeleteWebhookRepository for InMemoryWebhookRepository {
+    async fn delete_subscription(&self, id: &str) -> Result<(), AppError> {
+        write(self.wal.as_deref(), |journal| {
+            if !self.subscriptions.contains_key(id) {
+                return Err(AppError::not_found(id));
+            }
+            journal.record(WalRecord::Unsubscribe { id: id.to_owned() })?;
+            self.remove(id);
+            Ok(())
+        })
+    }
+}
+
+impl crate::app::query::list_webhooks::ListWebhooksRepository for InMemoryWebhookRepository {
+    async fn list_subscriptions(&self) -> Result<Vec<WebhookSubscription>, AppError> {
+        let mut subscriptions: Vec<_> = self
+            .subscriptions
+            .iter()
+            .map(|entry| entry.value().clone())
+            .collect();
+        subscriptions.sort_by(|a, b| a.created_at.cmp(&b.created_at).then(a.id.cmp(&b.id)));
+
+        Ok(subscriptions)
+    }
+}
+
+impl crate::app::query::list_webhook_deliveries::ListWebhookDeliveriesRepository
+    for InMemoryWebhookRepository
+{
+    async fn list_deliveries(
+        &self,
+        subscription_id: &str,
+    ) -> Result<Vec<WebhookDelivery>, AppError> {
+        if !self.subscriptions.contains_key(subscription_id) {
+            return Err(AppError::not_found(subscription_id));
+        }
+
+        Ok(self
+            .deliveries
+            .get(subscription_id)
+            .map(|log| log.clone())
+            .unwrap_or_default())
+    }
+}
+
+#[async_trait]
+impl WebhookDeliveryRepository for InMemoryWebhookRepository {
+    async fn subscriptions_for(
+        &self,
+        kind: EventKind,
+    ) -> Result<Vec<WebhookSubscription>, AppError> {
+        Ok(self
+            .subscriptions
+            .iter()
+            .filter(|entry| entry.accepts(kind))
+            .map(|entry| entry.value().clone())
+            .collect())
+    }
+
+    async fn record_delivery(&self, delivery: WebhookDelivery) -> Result<(), AppError> {
+        write(self.wal.as_deref(), |journal| {
+            // The subscription may have been deleted while a delivery was in flight.
+            if !self.subscriptions.contains_key(&delivery.subscription_id) {
+                return Ok(());
+            }
+            journal.record(WalRecord::Delivery {
+                delivery: delivery.clone(),
+            })?;
+            self.append(delivery);
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app::{
+        command::create_webhook::CreateWebhookRepository,
+        query::list_webhook_deliveries::ListWebhookDeliveriesRepository,
+    };
+
+    use super::*;
+
+    fn subscription(id: &str, events: Vec<EventKind>) -> WebhookSubscription {
+        WebhookSubscription {
+            id: id.to_owned(),
+            url: "https://example.com/hook".to_owned(),
+            secret: "secret".to_owned(),
+            events,
+            created_at: 0,
+        }
+    }
+
+    fn delivery(subscription_id: &str, attempt: u32) -> WebhookDelivery {
+        WebhookDelivery {
+            subscription_id: subscription_id.to_owned(),
+            event_id: "evt".to_owned(),
+            event_kind: EventKind::LinkCreated,
+            attempt,
+            status_code: Some(200),
+            error: None,
+            succeeded: true,
+            attempted_at: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn subscriptions_are_filtered_by_event_kind() {
+        // Given
+        let repo = InMemoryWebhookRepository::new();
+        repo.save_subscription(subscription("all", vec![]))
+            .await
+            .unwrap();
+        repo.save_subscription(subscription("deletes", vec![EventKind::LinkDeleted]))
+            .await
+            .unwrap();
+
+        // When
+        let result = repo
+            .subscriptions_for(EventKind::LinkCreated)
+            .await
+            .unwrap();
+
+        // Then
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].id, "all");
+    }
+
+    #[tokio::test]
+    async fn delivery_log_is_bounded() {
+        // Given
+        let repo = InMemoryWebhookRepository::new();
+        repo.save_subscription(subscription("sub", vec![]))
+            .await
+            .unwrap();
+
+        // When
+        for attempt in 0..(MAX_DELIVERIES_PER_SUBSCRIPTION as u32 + 5) {
+            repo.record_delivery(delivery("sub", attempt))
+                .await
+                .unwrap();
+        }
+
+        // Then
+        let log = repo.list_deliveries("sub").await.unwrap();
+        assert_eq!(log.len(), MAX_DELIVERIES_PER_SUBSCRIPTION);
+        assert_eq!(log[0].attempt, 5);
+    }
+
+    #[tokio::test]
+    async fn subscriptions_survive_a_restart() {
+        use crate::{
+            adapters::inmemory::InMemoryStorage,
+            app::{
+                command::delete_webhook::DeleteWebhookRepository,
+                query::list_webhooks::ListWebhooksRepository,
+            },
+            config::Config,
+        };
+
+        // Given
+        let dir = tempfile::tempdir().unwrap();
+        let wal = dir.path().join("links.wal");
+        let config = Config::from_vars(|name| {
+            (name == "URLSHORTENER_WAL_PATH").then(|| wal.display().to_string())
+        })
+        .unwrap();
+        let (storage, _) = InMemoryStorage::open(&config).unwrap();
+        let repo = storage.webhooks();
+        repo.save_subscription(subscription("kept", vec![]))
+            .await
+            .unwrap();
+        repo.save_subscription(subscription("dropped", vec![]))
+            .await
+            .unwrap();
+        repo.record_delivery(delivery("kept", 1)).await.unwrap();
+        repo.delete_subscription("dropped").await.unwrap();
+        drop((repo, storage));
+
+        // When
+        let (storage, _) = InMemoryStorage::open(&config).unwrap();
+        let repo = storage.webhooks();
+
+        // Then
+        let ids: Vec<String> = repo
+            .list_subscriptions()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|subscription| subscription.id)
+            .collect();
+        assert_eq!(ids, ["kept"]);
+        assert_eq!(
+            repo.list_deliveries("kept").await,
+            Ok(vec![delivery("kept", 1)])
+        );
+    }
+
+    #[tokio::test]
+    async fn deliveries_of_unknown_subscription() {
+        let repo = InMemoryWebhookRepository::new();
+
+        let result = repo.list_deliveries("missing").await;
+
+        assert_eq!(result, Err(AppError::not_found("missing")));
+    }
+}
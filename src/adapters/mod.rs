@@ -1 +1,7 @@
+pub mod cache;
+pub mod geoip;
 pub mod inmemory;
+pub mod prober;
+pub mod redis;
+pub mod threatlist;
+pub mod webhook;
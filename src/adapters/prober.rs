@@ -0,0 +1,369 @@
+//! Checks destinations over HTTP. Redirects are followed by hand so each
+//! hop is recorded.
+//!
+//! Any editor picks the URLs and reads back what came of them, so only
+//! public addresses are ever contacted: every host is resolved through a
+//! resolver that drops loopback, private, link-local and other internal
+//! addresses, and hosts given as addresses are checked on each hop.
+
+use std::error::Error;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::{header::LOCATION, redirect, StatusCode};
+
+use crate::app::health::{LinkProber, Probe, MAX_REDIRECTS};
+
+#[derive(Clone)]
+pub struct HttpLinkProber {
+    client: reqwest::Client,
+    allowed: Arc<[IpAddr]>,
+}
+
+impl HttpLinkProber {
+    /// `timeout` applies to each request, not to a whole redirect chain.
+    pub fn new(timeout: Duration) -> Self {
+        Self::allowing(timeout, Vec::new())
+    }
+
+    /// Prober that may also reach the `allowed` addresses, public or not.
+    /// Only tests have a reason to probe local servers.
+    fn allowing(timeout: Duration, allowed: Vec<IpAddr>) -> Self {
+        let allowed: Arc<[IpAddr]> = allowed.into();
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .redirect(redirect::Policy::none())
+            .no_proxy()
+            .dns_resolver(Arc::new(PublicResolver {
+                allowed: allowed.clone(),
+            }))
+            .user_agent(concat!(
+                "urlshortener-linkcheck/",
+                env!("CARGO_PKG_VERSION")
+            ))
+            .build()
+            .expect("failed to build link check http client");
+
+        Self { client, allowed }
+    }
+
+    /// Whether `url` names its host by an address we may not contact.
+    /// Names are left to the resolver.
+    fn refuses(&self, url: &url::Url) -> bool {
+        let ip = match url.host() {
+            Some(url::Host::Ipv4(ip)) => IpAddr::V4(ip),
+            Some(url::Host::Ipv6(ip)) => IpAddr::V6(ip),
+            _ => return false,
+        };
+        !permitted(ip, &self.allowed)
+    }
+
+    /// HEAD saves fetching the body; servers that refuse it get a GET.
+    async fn request(&self, url: url::Url) -> reqwest::Result<reqwest::Response> {
+        let response = self.client.head(url.clone()).send().await?;
+        match response.status() {
+            StatusCode::METHOD_NOT_ALLOWED | StatusCode::NOT_IMPLEMENTED => {
+                self.client.get(url).send().await
+            }
+            _ => Ok(response),
+        }
+    }
+}
+
+#[async_trait]
+impl LinkProber for HttpLinkProber {
+    async fn probe(&self, url: &str) -> Probe {
+        let started = Instant::now();
+        let mut probe = Probe::default();
+
+        let mut current = match url::Url::parse(url) {
+            Ok(url) if matches!(url.scheme(), "http" | "https") => url,
+            _ => {
+                probe.error = Some("not an http(s) URL".to_owned());
+                return probe;
+            }
+        };
+        loop {
+            if self.refuses(&current) {
+                probe.error = Some(NOT_PUBLIC.to_owned());
+                break;
+            }
+            let response = match self.request(current.clone()).await {
+                Ok(response) => response,
+                Err(err) => {
+                    probe.error = Some(describe(&err));
+                    break;
+                }
+            };
+            let status = response.status();
+            probe.status = Some(status.as_u16());
+            if !status.is_redirection() {
+                break;
+            }
+
+            // A redirect without a usable `Location` is an answer like any other.
+            let Some(next) = response
+                .headers()
+                .get(LOCATION)
+                .and_then(|location| location.to_str().ok())
+                .and_then(|location| current.join(location).ok())
+            else {
+                break;
+            };
+            if probe.redirects.len() == MAX_REDIRECTS {
+                probe.error = Some("too many redirects".to_owned());
+                break;
+            }
+            probe.redirects.push(next.to_string());
+            current = next;
+        }
+
+        probe.latency = started.elapsed();
+        probe
+    }
+}
+
+const NOT_PUBLIC: &str = "refused to contact a non-public address";
+
+/// The system resolver, minus every address that is not public.
+struct PublicResolver {
+    allowed: Arc<[IpAddr]>,
+}
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let host = name.as_str().to_owned();
+        let allowed = self.allowed.clone();
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), 0))
+                .await?
+                .filter(|addr| permitted(addr.ip(), &allowed))
+                .collect();
+            if addrs.is_empty() {
+                return Err(NOT_PUBLIC.into());
+            }
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+fn permitted(ip: IpAddr, allowed: &[IpAddr]) -> bool {
+    allowed.contains(&ip) || is_public(ip)
+}
+
+/// Whether `ip` is reachable on the public internet, as opposed to this
+/// host, a private network or a cloud metadata service.
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_v4(ip),
+        IpAddr::V6(ip) => {
+            if let Some(v4) = ip.to_ipv4_mapped() {
+                return is_public_v4(v4);
+            }
+            let segments = ip.segments();
+            // NAT64 embeds an IPv4 address in the last 32 bits.
+            if segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0] {
+                let [.., a, b, c, d] = ip.octets();
+                return is_public_v4(Ipv4Addr::new(a, b, c, d));
+            }
+            !(ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_multicast()
+                // Unique local, fc00::/7.
+                || segments[0] & 0xfe00 == 0xfc00
+                // Link local, fe80::/10, and the old site local, fec0::/10.
+                || segments[0] & 0xffc0 == 0xfe80
+                || segments[0] & 0xffc0 == 0xfec0
+                // Documentation, 2001:db8::/32.
+                || segments[..2] == [0x2001, 0xdb8])
+        }
+    }
+}
+
+fn is_public_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, c, _] = ip.octets();
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_multicast()
+        // "This network", 0.0.0.0/8.
+        || a == 0
+        // Carrier-grade NAT, 100.64.0.0/10.
+        || (a == 100 && b & 0xc0 == 64)
+        // IETF protocol assignments, 192.0.0.0/24.
+        || (a == 192 && b == 0 && c == 0)
+        // Benchmarking, 198.18.0.0/15.
+        || (a == 198 && b & 0xfe == 18)
+        // Reserved, 240.0.0.0/4.
+        || a >= 240)
+}
+
+/// The error and its causes, which say whether DNS, the connection or TLS
+/// failed.
+fn describe(err: &reqwest::Error) -> String {
+    if err.is_timeout() {
+        return "timed out".to_owned();
+    }
+
+    let mut text = err.to_string();
+    let mut source = err.source();
+    while let Some(cause) = source {
+        text.push_str(": ");
+        text.push_str(&cause.to_string());
+        source = cause.source();
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        http::{header, Method},
+        response::{IntoResponse, Redirect},
+        routing::get,
+        Router,
+    };
+
+    use super::*;
+
+    /// Serves a few canned answers on a free local port.
+    async fn stub_server() -> String {
+        let app = Router::new()
+            .route("/ok", get(|| async { "fine" }))
+            .route("/gone", get(|| async { StatusCode::NOT_FOUND }))
+            .route(
+                "/failing",
+                get(|| async { StatusCode::SERVICE_UNAVAILABLE }),
+            )
+            .route("/moved", get(|| async { Redirect::permanent("/again") }))
+            .route("/again", get(|| async { Redirect::temporary("/ok") }))
+            .route("/loop", get(|| async { Redirect::temporary("/loop") }))
+            .route(
+                "/internal",
+                get(|| async { Redirect::temporary("http://169.254.169.254/") }),
+            )
+            .route(
+                "/no-head",
+                get(|method: Method| async move {
+                    match method {
+                        Method::HEAD => StatusCode::METHOD_NOT_ALLOWED.into_response(),
+                        _ => ([(header::CONTENT_TYPE, "text/plain")], "fine").into_response(),
+                    }
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        format!("http://{addr}")
+    }
+
+    fn local_prober() -> HttpLinkProber {
+        HttpLinkProber::allowing(
+            Duration::from_secs(5),
+            vec![IpAddr::V4(Ipv4Addr::LOCALHOST)],
+        )
+    }
+
+    #[tokio::test]
+    async fn reports_the_final_status_and_every_redirect() {
+        // Given
+        let base = stub_server().await;
+        let prober = local_prober();
+
+        // When
+        let ok = prober.probe(&format!("{base}/ok")).await;
+        let gone = prober.probe(&format!("{base}/gone")).await;
+        let failing = prober.probe(&format!("{base}/failing")).await;
+        let moved = prober.probe(&format!("{base}/moved")).await;
+        let no_head = prober.probe(&format!("{base}/no-head")).await;
+
+        // Then
+        assert_eq!((ok.status, ok.error), (Some(200), None));
+        assert_eq!(gone.status, Some(404));
+        assert_eq!(failing.status, Some(503));
+        assert_eq!(moved.status, Some(200));
+        assert_eq!(
+            moved.redirects,
+            [format!("{base}/again"), format!("{base}/ok")]
+        );
+        assert_eq!(no_head.status, Some(200));
+    }
+
+    #[tokio::test]
+    async fn gives_up_on_redirect_loops_and_dead_hosts() {
+        // Given
+        let base = stub_server().await;
+        let closed = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let dead = format!("http://{}/", closed.local_addr().unwrap());
+        drop(closed);
+        let prober = local_prober();
+
+        // When
+        let looping = prober.probe(&format!("{base}/loop")).await;
+        let refused = prober.probe(&dead).await;
+        let unsupported = prober.probe("ftp://files.example/").await;
+
+        // Then
+        assert_eq!(looping.redirects.len(), MAX_REDIRECTS);
+        assert_eq!(looping.error.as_deref(), Some("too many redirects"));
+        assert_eq!(refused.status, None);
+        assert!(refused.error.is_some());
+        assert!(unsupported.error.is_some());
+    }
+
+    #[tokio::test]
+    async fn refuses_local_addresses_unless_allowed() {
+        // Given
+        let base = stub_server().await;
+        let port = base.rsplit(':').next().unwrap();
+        let prober = HttpLinkProber::new(Duration::from_secs(5));
+
+        // When
+        let literal = prober.probe(&format!("{base}/ok")).await;
+        let named = prober.probe(&format!("http://localhost:{port}/ok")).await;
+        let metadata = prober
+            .probe("http://169.254.169.254/latest/meta-data/")
+            .await;
+        let allowed = local_prober().probe(&format!("{base}/ok")).await;
+        let hop = local_prober().probe(&format!("{base}/internal")).await;
+
+        // Then
+        for refused in [literal, named, metadata] {
+            assert_eq!(refused.status, None);
+            assert!(refused.error.unwrap().contains(NOT_PUBLIC));
+        }
+        assert_eq!(allowed.status, Some(200));
+        assert_eq!(hop.redirects, ["http://169.254.169.254/"]);
+        assert_eq!(hop.error.as_deref(), Some(NOT_PUBLIC));
+    }
+
+    #[test]
+    fn tells_public_addresses_from_internal_ones() {
+        for internal in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+            "64:ff9b::a9fe:a9fe",
+        ] {
+            assert!(!is_public(internal.parse().unwrap()), "{internal}");
+        }
+        for public in ["93.184.216.34", "2606:4700::1111", "::ffff:8.8.8.8"] {
+            assert!(is_public(public.parse().unwrap()), "{public}");
+        }
+    }
+}
@@ -0,0 +1,466 @@
+use std::sync::LazyLock;
+
+use async_trait::async_trait;
+use redis::Script;
+
+use crate::{
+    app::{
+        account::{Account, ApiKey},
+        command::update_workspace::WorkspaceChanges,
+        workspace::{Quota, Workspace},
+    },
+    error::AppError,
+};
+
+use super::{decode_json, encode_json, unavailable, RedisRepository};
+
+/// Adds a key to an account, unless the account does not exist.
+/// KEYS: accounts, api keys, account keys. ARGV: account id, key id, hash, json.
+static ADD_KEY_SCRIPT: LazyLock<Script> = LazyLock::new(|| {
+    Script::new(
+        r"
+        if redis.call('HEXISTS', KEYS[1], ARGV[1]) == 0 then
+            return 0
+        end
+        redis.call('HSET', KEYS[2], ARGV[3], ARGV[4])
+        redis.call('HSET', KEYS[3], ARGV[2], ARGV[3])
+        return 1
+        ",
+    )
+});
+
+/// Drops a key of an account.
+/// KEYS: api keys, account keys. ARGV: key id.
+static REVOKE_KEY_SCRIPT: LazyLock<Script> = LazyLock::new(|| {
+    Script::new(
+        r"
+        local hash = redis.call('HGET', KEYS[2], ARGV[1])
+        if not hash then
+            return 0
+        end
+        redis.call('HDEL', KEYS[2], ARGV[1])
+        redis.call('HDEL', KEYS[1], hash)
+        return 1
+        ",
+    )
+});
+
+/// Stores a workspace and points its domains at it, unless another
+/// workspace serves one of them; that domain is returned then. Domains the
+/// workspace no longer has are released.
+/// KEYS: workspaces, domains. ARGV: id, json, domains...
+static SAVE_WORKSPACE_SCRIPT: LazyLock<Script> = LazyLock::new(|| {
+    Script::new(
+        r"
+        for i = 3, #ARGV do
+            local owner = redis.call('HGET', KEYS[2], ARGV[i])
+            if owner and owner ~= ARGV[1] then
+                return ARGV[i]
+            end
+        end
+        local old = redis.call('HGET', KEYS[1], ARGV[1])
+        if old then
+            for _, domain in ipairs(cjson.decode(old).domains or {}) do
+                if redis.call('HGET', KEYS[2], domain) == ARGV[1] then
+                    redis.call('HDEL', KEYS[2], domain)
+                end
+            end
+        end
+        for i = 3, #ARGV do
+            redis.call('HSET', KEYS[2], ARGV[i], ARGV[1])
+        end
+        redis.call('HSET', KEYS[1], ARGV[1], ARGV[2])
+        return false
+        ",
+    )
+});
+
+/// Account storage next to the links. Accounts, API keys (by the hash of
+/// their secret) and workspaces are JSON values in one hash each; another
+/// hash per account maps its key IDs to hashes, and one more maps each
+/// workspace domain to its workspace.
+#[derive(Clone)]
+pub struct RedisAccountRepository {
+    redis: RedisRepository,
+}
+
+impl RedisAccountRepository {
+    /// Shares the connection and key prefix of `redis`.
+    pub fn new(redis: RedisRepository) -> Self {
+        Self { redis }
+    }
+
+    fn accounts_key(&self) -> String {
+        format!("{}:accounts", self.redis.prefix)
+    }
+
+    fn api_keys_key(&self) -> String {
+        format!("{}:api-keys", self.redis.prefix)
+    }
+
+    fn account_keys_key(&self, account_id: &str) -> String {
+        format!("{}:account-keys:{account_id}", self.redis.prefix)
+    }
+
+    fn workspaces_key(&self) -> String {
+        format!("{}:workspaces", self.redis.prefix)
+    }
+
+    fn domains_key(&self) -> String {
+        format!("{}:domains", self.redis.prefix)
+    }
+
+    async fn hget<T: serde::de::DeserializeOwned>(
+        &self,
+        key: String,
+        field: &str,
+        what: &str,
+    ) -> Result<Option<T>, AppError> {
+        let value: Option<String> = redis::cmd("HGET")
+            .arg(key)
+            .arg(field)
+            .query_async(&mut self.redis.conn.clone())
+            .await
+            .map_err(unavailable)?;
+
+        value.map(|value| decode_json(&value, what)).transpose()
+    }
+
+    async fn account_exists(&self, id: &str) -> Result<bool, AppError> {
+        redis::cmd("HEXISTS")
+            .arg(self.accounts_key())
+            .arg(id)
+            .query_async(&mut self.redis.conn.clone())
+            .await
+            .map_err(unavailable)
+    }
+
+    async fn fetch_workspace(&self, id: &str) -> Result<Option<Workspace>, AppError> {
+        self.hget(self.workspaces_key(), id, "workspace").await
+    }
+
+    async fn store_workspace(&self, workspace: &Workspace) -> Result<(), AppError> {
+        let taken: Option<String> = SAVE_WORKSPACE_SCRIPT
+            .key(self.workspaces_key())
+            .key(self.domains_key())
+            .arg(&workspace.id)
+            .arg(encode_json(workspace, "workspace")?)
+            .arg(&workspace.domains)
+            .invoke_async(&mut self.redis.conn.clone())
+            .await
+            .map_err(unavailable)?;
+
+        match taken {
+            Some(domain) => Err(AppError::DomainTaken { domain }),
+            None => Ok(()),
+        }
+    }
+}
+
+#[async_trait]
+impl crate::app::command::create_account::CreateAccountRepository for RedisAccountRepository {
+    async fn save_account(&self, account: Account, key: ApiKey) -> Result<(), AppError> {
+        redis::pipe()
+            .atomic()
+            .cmd("HSET")
+            .arg(self.accounts_key())
+            .arg(&account.id)
+            .arg(encode_json(&account, "account")?)
+            .ignore()
+            .cmd("HSET")
+            .arg(self.api_keys_key())
+            .arg(&key.hash)
+            .arg(encode_json(&key, "api key")?)
+            .ignore()
+            .cmd("HSET")
+            .arg(self.account_keys_key(&account.id))
+            .arg(&key.id)
+            .arg(&key.hash)
+            .ignore()
+            .query_async(&mut self.redis.conn.clone())
+            .await
+            .map_err(unavailable)
+    }
+}
+
+#[async_trait]
+impl crate::app::command::create_api_key::CreateApiKeyRepository for RedisAccountRepository {
+    async fn add_api_key(&self, key: ApiKey) -> Result<(), AppError> {
+        let added: bool = ADD_KEY_SCRIPT
+            .key(self.accounts_key())
+            .key(self.api_keys_key())
+            .key(self.account_keys_key(&key.account_id))
+            .arg(&key.account_id)
+            .arg(&key.id)
+            .arg(&key.hash)
+            .arg(encode_json(&key, "api key")?)
+            .invoke_async(&mut self.redis.conn.clone())
+            .await
+            .map_err(unavailable)?;
+
+        match added {
+            true => Ok(()),
+            false => Err(AppError::not_found(&key.account_id)),
+        }
+    }
+}
+
+#[async_trait]
+impl crate::app::command::revoke_api_key::RevokeApiKeyRepository for RedisAccountRepository {
+    async fn revoke_api_key(&self, account_id: &str, key_id: &str) -> Result<(), AppError> {
+        let revoked: bool = REVOKE_KEY_SCRIPT
+            .key(self.api_keys_key())
+            .key(self.account_keys_key(account_id))
+            .arg(key_id)
+            .invoke_async(&mut self.redis.conn.clone())
+            .await
+            .map_err(unavailable)?;
+
+        match revoked {
+            true => Ok(()),
+            false => Err(AppError::not_found(key_id)),
+        }
+    }
+}
+
+impl crate::app::query::authenticate::AuthenticateRepository for RedisAccountRepository {
+    async fn account_for_key(&self, hash: &str) -> Result<Option<Account>, AppError> {
+        let Some(key) = self
+            .hget::<ApiKey>(self.api_keys_key(), hash, "api key")
+            .await?
+        else {
+            return Ok(None);
+        };
+
+        self.hget(self.accounts_key(), &key.account_id, "account")
+            .await
+    }
+
+    async fn workspaces_of(&self, account_id: &str) -> Result<Vec<String>, AppError> {
+        let values: Vec<String> = redis::cmd("HVALS")
+            .arg(self.workspaces_key())
+            .query_async(&mut self.redis.conn.clone())
+            .await
+            .map_err(unavailable)?;
+
+        let mut ids = Vec::new();
+        for value in values {
+            let workspace: Workspace = decode_json(&value, "workspace")?;
+            if workspace.members.iter().any(|id| id == account_id) {
+                ids.push(workspace.id);
+            }
+        }
+        ids.sort();
+
+        Ok(ids)
+    }
+}
+
+#[async_trait]
+impl crate::app::command::create_workspace::CreateWorkspaceRepository for RedisAccountRepository {
+    async fn save_workspace(&self, workspace: Workspace) -> Result<(), AppError> {
+        self.store_workspace(&workspace).await
+    }
+}
+
+/// Like link updates, workspace changes racing other writes are last write
+/// wins; domain claims are checked atomically all the same.
+#[async_trait]
+impl crate::app::command::update_workspace::UpdateWorkspaceRepository for RedisAccountRepository {
+    async fn update_workspace(
+        &self,
+        id: &str,
+        changes: &WorkspaceChanges,
+    ) -> Result<Workspace, AppError> {
+        let Some(mut workspace) = self.fetch_workspace(id).await? else {
+            return Err(AppError::not_found(id));
+        };
+        changes.apply(&mut workspace);
+        self.store_workspace(&workspace).await?;
+
+        Ok(workspace)
+    }
+}
+
+#[async_trait]
+impl crate::app::command::change_membership::ChangeMembershipRepository for RedisAccountRepository {
+    async fn set_membership(
+        &self,
+        workspace_id: &str,
+        account_id: &str,
+        member: bool,
+    ) -> Result<Workspace, AppError> {
+        if member && !self.account_exists(account_id).await? {
+            return Err(AppError::not_found(account_id));
+        }
+        let Some(mut workspace) = self.fetch_workspace(workspace_id).await? else {
+            return Err(AppError::not_found(workspace_id));
+        };
+
+        workspace.members.retain(|id| id != account_id);
+        if member {
+            workspace.members.push(account_id.to_owned());
+            workspace.members.sort();
+        }
+        self.store_workspace(&workspace).await?;
+
+        Ok(workspace)
+    }
+}
+
+#[async_trait]
+impl crate::app::command::create_short_url::WorkspaceQuotaRepository for RedisAccountRepository {
+    async fn quota(&self, workspace: &str) -> Result<Quota, AppError> {
+        match self.fetch_workspace(workspace).await? {
+            Some(workspace) => Ok(workspace.quota),
+            None => Err(AppError::not_found(workspace)),
+        }
+    }
+}
+
+impl crate::app::query::get_workspace::GetWorkspaceRepository for RedisAccountRepository {
+    async fn workspace(&self, id: &str) -> Result<Option<Workspace>, AppError> {
+        self.fetch_workspace(id).await
+    }
+
+    async fn workspace_for_domain(&self, domain: &str) -> Result<Option<String>, AppError> {
+        redis::cmd("HGET")
+            .arg(self.domains_key())
+            .arg(domain)
+            .query_async(&mut self.redis.conn.clone())
+            .await
+            .map_err(unavailable)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app::{
+        account::hash_secret,
+        command::{
+            change_membership::ChangeMembershipRepository, create_account::CreateAccountRepository,
+            create_api_key::CreateApiKeyRepository, create_workspace::CreateWorkspaceRepository,
+            revoke_api_key::RevokeApiKeyRepository, update_workspace::UpdateWorkspaceRepository,
+        },
+        policy::Role,
+        query::{authenticate::AuthenticateRepository, get_workspace::GetWorkspaceRepository},
+    };
+
+    use super::*;
+
+    async fn repository() -> RedisAccountRepository {
+        let url = std::env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1/".to_owned());
+        let prefix = format!("urlshortener-test-{}", nanoid::nanoid!());
+
+        RedisAccountRepository::new(
+            RedisRepository::connect_with_prefix(&url, &prefix)
+                .await
+                .unwrap(),
+        )
+    }
+
+    fn alice() -> Account {
+        Account {
+            id: "alice".to_owned(),
+            name: "Alice".to_owned(),
+            role: Role::Editor,
+            created_at: 0,
+        }
+    }
+
+    fn workspace(id: &str, domains: &[&str]) -> Workspace {
+        Workspace {
+            id: id.to_owned(),
+            name: id.to_owned(),
+            members: Vec::new(),
+            domains: domains.iter().map(|domain| (*domain).to_owned()).collect(),
+            quota: Quota::default(),
+            created_at: 0,
+        }
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a redis server, see REDIS_URL"]
+    async fn keys_work_until_revoked() {
+        // Given
+        let repo = repository().await;
+        let (first, first_issued) = ApiKey::generate("alice", 0);
+        let (second, second_issued) = ApiKey::generate("alice", 0);
+        let (stray, _) = ApiKey::generate("nobody", 0);
+        repo.save_account(alice(), first).await.unwrap();
+
+        // When
+        repo.add_api_key(second.clone()).await.unwrap();
+        let orphan = repo.add_api_key(stray).await;
+        repo.revoke_api_key("alice", &second.id).await.unwrap();
+
+        // Then
+        assert_eq!(orphan, Err(AppError::not_found("nobody")));
+        assert_eq!(
+            repo.account_for_key(&hash_secret(&first_issued.secret))
+                .await,
+            Ok(Some(alice()))
+        );
+        assert_eq!(
+            repo.account_for_key(&hash_secret(&second_issued.secret))
+                .await,
+            Ok(None)
+        );
+        assert_eq!(
+            repo.revoke_api_key("alice", &second.id).await,
+            Err(AppError::not_found(&second.id))
+        );
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a redis server, see REDIS_URL"]
+    async fn workspaces_claim_domains_and_members() {
+        // Given
+        let repo = repository().await;
+        let (key, _) = ApiKey::generate("alice", 0);
+        repo.save_account(alice(), key).await.unwrap();
+        repo.save_workspace(workspace("red", &["red.example.com"]))
+            .await
+            .unwrap();
+
+        // When
+        let taken = repo
+            .save_workspace(workspace("blue", &["red.example.com"]))
+            .await;
+        let moved = repo
+            .update_workspace(
+                "red",
+                &WorkspaceChanges {
+                    domains: Some(vec!["go.example.com".to_owned()]),
+                    ..Default::default()
+                },
+            )
+            .await;
+        let joined = repo.set_membership("red", "alice", true).await;
+        let nobody = repo.set_membership("red", "nobody", true).await;
+
+        // Then
+        assert_eq!(
+            taken,
+            Err(AppError::DomainTaken {
+                domain: "red.example.com".to_owned()
+            })
+        );
+        assert_eq!(moved.unwrap().domains, ["go.example.com"]);
+        assert_eq!(repo.workspace_for_domain("red.example.com").await, Ok(None));
+        assert_eq!(
+            repo.workspace_for_domain("go.example.com").await,
+            Ok(Some("red".to_owned()))
+        );
+        assert_eq!(joined.unwrap().members, ["alice"]);
+        assert_eq!(nobody, Err(AppError::not_found("nobody")));
+        assert_eq!(
+            repo.workspaces_of("alice").await,
+            Ok(vec!["red".to_owned()])
+        );
+        assert_eq!(
+            repo.workspace("red").await.unwrap().unwrap().domains,
+            ["go.example.com"]
+        );
+    }
+}
@@ -0,0 +1,1262 @@
+pub mod account;
+pub mod webhook;
+
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures::{stream, Stream};
+use redis::aio::{ConnectionManager, ConnectionManagerConfig};
+use redis::{RedisError, Script};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    app::{
+        command::update_short_url::LinkChanges,
+        health::{HealthCounts, LinkHealth},
+        query::{get_link_stats::LinkStats, list_short_urls::OwnerFilter},
+        report::AbuseReport,
+        short_url::{LinkStatus, Ownership, ShortUrl},
+        workspace::{self, Usage},
+    },
+    clock::{SharedClock, SystemClock},
+    error::AppError,
+};
+
+const DEFAULT_PREFIX: &str = "urlshortener";
+const VISITS_TOTAL: &str = "total";
+const VISITS_VARIANT_PREFIX: &str = "variant:";
+/// Links loaded per round trip when all of them are needed.
+const LOAD_BATCH: usize = 1_000;
+/// How long Redis keeps a link past its expiry, so the expiry sweep can
+/// still read it for the `link.expired` event. Reads ignore expired links
+/// anyway; the sweep normally drops them long before.
+const EXPIRED_RETENTION_SECS: u64 = 7 * 24 * 60 * 60;
+
+/// Stores a link unless its ID is taken and indexes it.
+/// KEYS: link, ids, expiry, owner, and workspace if the link has one.
+/// ARGV: id, json, ttl (0 for none), expires_at.
+static SAVE_SCRIPT: LazyLock<Script> = LazyLock::new(|| {
+    Script::new(
+        r"
+        local stored
+        if ARGV[3] == '0' then
+            stored = redis.call('SET', KEYS[1], ARGV[2], 'NX')
+        else
+            stored = redis.call('SET', KEYS[1], ARGV[2], 'NX', 'EX', ARGV[3])
+        end
+        if not stored then
+            return 0
+        end
+        redis.call('ZADD', KEYS[2], 0, ARGV[1])
+        redis.call('ZADD', KEYS[4], 0, ARGV[1])
+        if KEYS[5] then
+            redis.call('ZADD', KEYS[5], 0, ARGV[1])
+        end
+        if ARGV[3] == '0' then
+            redis.call('ZREM', KEYS[3], ARGV[1])
+        else
+            redis.call('ZADD', KEYS[3], ARGV[4], ARGV[1])
+        end
+        return 1
+        ",
+    )
+});
+
+/// Drops links whose expiry has passed, with their index entries, visit
+/// counts and health checks, and returns the links still stored.
+/// KEYS: ids, expiry, health. ARGV: now, link key prefix, visits key prefix.
+static PURGE_SCRIPT: LazyLock<Script> = LazyLock::new(|| {
+    Script::new(
+        r"
+        local expired = redis.call('ZRANGEBYSCORE', KEYS[2], '-inf', ARGV[1])
+        local purged = {}
+        for _, id in ipairs(expired) do
+            local value = redis.call('GET', ARGV[2] .. id)
+            if value then
+                table.insert(purged, value)
+            end
+            redis.call('DEL', ARGV[2] .. id, ARGV[3] .. id)
+            redis.call('ZREM', KEYS[1], id)
+            redis.call('ZREM', KEYS[2], id)
+            redis.call('HDEL', KEYS[3], id)
+        end
+        return purged
+        ",
+    )
+});
+
+/// Link storage backed by Redis.
+///
+/// Every link is a JSON value under its own key. Reads treat a link as gone
+/// once its expiry passes, but the key itself lives on for
+/// `EXPIRED_RETENTION_SECS` more: the expiry sweep needs the stored link to
+/// publish `link.expired`, and Redis drops the key natively only if no
+/// sweep got to it within that week. Sorted sets sit next to them: `ids`
+/// (all scores 0, hence ordered by ID) for listing and export, one like it
+/// per owner and per workspace for listing their links, and `expiry`
+/// (scored by expiry time) for counting live links and purging the index.
+/// Abuse reports are JSON values in one hash, queued in sets scored by
+/// filing time. The last health check of each link is a JSON value in
+/// another.
+#[derive(Clone)]
+pub struct RedisRepository {
+    conn: ConnectionManager,
+    prefix: String,
+    clock: SharedClock,
+}
+
+impl RedisRepository {
+    pub async fn connect(url: &str) -> Result<Self, AppError> {
+        Self::connect_with_prefix(url, DEFAULT_PREFIX).await
+    }
+
+    /// Keys are namespaced under `prefix` so several deployments can share
+    /// one server.
+    pub async fn connect_with_prefix(url: &str, prefix: &str) -> Result<Self, AppError> {
+        let client = redis::Client::open(url).map_err(unavailable)?;
+        let config = ConnectionManagerConfig::new()
+            .set_number_of_retries(2)
+            .set_factor(2)
+            .set_max_delay(1000)
+            .set_connection_timeout(Duration::from_secs(2))
+            .set_response_timeout(Duration::from_secs(2));
+        let conn = ConnectionManager::new_with_config(client, config)
+            .await
+            .map_err(unavailable)?;
+
+        Ok(Self {
+            conn,
+            prefix: prefix.to_owned(),
+            clock: Arc::new(SystemClock),
+        })
+    }
+
+    /// Where the repository learns whether links have expired and which
+    /// month visits count towards.
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    fn now(&self) -> u64 {
+        self.clock.now()
+    }
+
+    fn live(&self, links: Vec<Option<ShortUrl>>) -> Vec<ShortUrl> {
+        let now = self.now();
+        links
+            .into_iter()
+            .flatten()
+            .filter(|link| !link.is_expired(now))
+            .collect()
+    }
+
+    fn link_key(&self, id: &str) -> String {
+        format!("{}:link:{id}", self.prefix)
+    }
+
+    /// Hash of visit counts: `total`, and `variant:<name>` per variant.
+    fn visits_key(&self, id: &str) -> String {
+        format!("{}:visits:{id}", self.prefix)
+    }
+
+    fn ids_key(&self) -> String {
+        format!("{}:ids", self.prefix)
+    }
+
+    /// IDs of the links of `owner`, or of those without one. Entries of
+    /// expired and replaced links are dropped when a listing runs into them.
+    fn owner_key(&self, owner: Option<&str>) -> String {
+        match owner {
+            Some(owner) => format!("{}:owner:{owner}", self.prefix),
+            None => format!("{}:unowned", self.prefix),
+        }
+    }
+
+    /// IDs of the links of a workspace, dropped as lazily as those of owners.
+    fn workspace_key(&self, workspace: &str) -> String {
+        format!("{}:workspace:{workspace}:links", self.prefix)
+    }
+
+    /// Hash of visits to a workspace's links per month.
+    fn workspace_clicks_key(&self, workspace: &str) -> String {
+        format!("{}:workspace:{workspace}:clicks", self.prefix)
+    }
+
+    fn expiry_key(&self) -> String {
+        format!("{}:expiry", self.prefix)
+    }
+
+    /// Hash of every abuse report, by report ID.
+    fn reports_key(&self) -> String {
+        format!("{}:reports", self.prefix)
+    }
+
+    /// IDs of all reports, or of the open ones, scored by filing time.
+    fn report_queue_key(&self, open_only: bool) -> String {
+        match open_only {
+            true => format!("{}:reports:open", self.prefix),
+            false => format!("{}:reports:all", self.prefix),
+        }
+    }
+
+    /// IDs of the open reports about a link.
+    fn link_reports_key(&self, link_id: &str) -> String {
+        format!("{}:link-reports:{link_id}", self.prefix)
+    }
+
+    /// Hash of the last destination check per link ID.
+    fn health_key(&self) -> String {
+        format!("{}:health", self.prefix)
+    }
+
+    fn sequence_key(&self) -> String {
+        format!("{}:id-sequence", self.prefix)
+    }
+
+    async fn fetch(&self, id: &str) -> Result<Option<ShortUrl>, AppError> {
+        let value: Option<String> = redis::cmd("GET")
+            .arg(self.link_key(id))
+            .query_async(&mut self.conn.clone())
+            .await
+            .map_err(unavailable)?;
+
+        value.as_deref().map(decode).transpose()
+    }
+
+    async fn stored_ownership(&self, id: &str) -> Result<Ownership, AppError> {
+        match self.fetch(id).await? {
+            Some(link) => Ok(link.ownership()),
+            None => Err(AppError::not_found(id)),
+        }
+    }
+
+    /// Counts the live links in the workspace index, dropping stale entries.
+    async fn usage(&self, workspace: &str, month: &str) -> Result<Usage, AppError> {
+        let index = self.workspace_key(workspace);
+        let (ids, clicks): (Vec<String>, Option<u64>) = redis::pipe()
+            .cmd("ZRANGE")
+            .arg(&index)
+            .arg(0)
+            .arg(-1)
+            .cmd("HGET")
+            .arg(self.workspace_clicks_key(workspace))
+            .arg(month)
+            .query_async(&mut self.conn.clone())
+            .await
+            .map_err(unavailable)?;
+        let links = self.load(&ids).await?;
+
+        let filter = OwnerFilter::Workspace(workspace);
+        let stale: Vec<&String> = ids
+            .iter()
+            .zip(&links)
+            .filter(|(_, link)| !link.as_ref().is_some_and(|link| filter.matches(link)))
+            .map(|(id, _)| id)
+            .collect();
+        if !stale.is_empty() {
+            let _: () = redis::cmd("ZREM")
+                .arg(&index)
+                .arg(stale)
+                .query_async(&mut self.conn.clone())
+                .await
+                .map_err(unavailable)?;
+        }
+
+        Ok(Usage {
+            month: month.to_owned(),
+            links: self.live(links).len() as u64,
+            clicks: clicks.unwrap_or_default(),
+        })
+    }
+
+    async fn load_reports(&self, ids: &[String]) -> Result<Vec<AbuseReport>, AppError> {
+        if ids.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let values: Vec<Option<String>> = redis::cmd("HMGET")
+            .arg(self.reports_key())
+            .arg(ids)
+            .query_async(&mut self.conn.clone())
+            .await
+            .map_err(unavailable)?;
+
+        values
+            .into_iter()
+            .flatten()
+            .map(|value| {
+                serde_json::from_str(&value).map_err(|err| {
+                    tracing::error!(%err, "unreadable report record in redis");
+                    AppError::StorageUnavailable
+                })
+            })
+            .collect()
+    }
+
+    /// Loads the given links in one round trip, `None` for missing ones.
+    async fn load(&self, ids: &[String]) -> Result<Vec<Option<ShortUrl>>, AppError> {
+        if ids.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let keys: Vec<String> = ids.iter().map(|id| self.link_key(id)).collect();
+        let values: Vec<Option<String>> = redis::cmd("MGET")
+            .arg(keys)
+            .query_async(&mut self.conn.clone())
+            .await
+            .map_err(unavailable)?;
+
+        values
+            .into_iter()
+            .map(|value| value.as_deref().map(decode).transpose())
+            .collect()
+    }
+}
+
+#[async_trait]
+impl crate::app::command::create_short_url::CreateShortUrlRepository for RedisRepository {
+    async fn save(&self, link: ShortUrl) -> Result<(), AppError> {
+        // Links already expired are stored all the same, as in memory: they
+        // answer 404 and take their ID until the sweep drops them.
+        let ttl = link
+            .remaining_ttl(self.now())
+            .map_or(0, |ttl| ttl + EXPIRED_RETENTION_SECS);
+
+        let mut script = SAVE_SCRIPT.prepare_invoke();
+        script
+            .key(self.link_key(&link.id))
+            .key(self.ids_key())
+            .key(self.expiry_key())
+            .key(self.owner_key(link.owner.as_deref()));
+        if let Some(workspace) = &link.workspace {
+            script.key(self.workspace_key(workspace));
+        }
+        let stored: bool = script
+            .arg(&link.id)
+            .arg(encode(&link)?)
+            .arg(ttl)
+            .arg(link.expires_at.unwrap_or(0))
+            .invoke_async(&mut self.conn.clone())
+            .await
+            .map_err(unavailable)?;
+
+        if stored {
+            Ok(())
+        } else {
+            Err(AppError::IdTaken { id: link.id })
+        }
+    }
+
+    async fn find(&self, id: &str) -> Result<Option<ShortUrl>, AppError> {
+        self.fetch(id).await
+    }
+
+    async fn workspace_usage(&self, workspace: &str, month: &str) -> Result<Usage, AppError> {
+        self.usage(workspace, month).await
+    }
+}
+
+/// Concurrent updates of one link are last write wins.
+#[async_trait]
+impl crate::app::command::update_short_url::UpdateShortUrlRepository for RedisRepository {
+    async fn ownership(&self, id: &str) -> Result<Ownership, AppError> {
+        self.stored_ownership(id).await
+    }
+
+    async fn update(&self, id: &str, changes: &LinkChanges) -> Result<ShortUrl, AppError> {
+        let mut link = match self.fetch(id).await? {
+            Some(link) if !link.is_expired(self.now()) => link,
+            _ => return Err(AppError::not_found(id)),
+        };
+        changes.apply(&mut link);
+
+        // XX so a link deleted meanwhile stays deleted.
+        let stored: Option<String> = redis::cmd("SET")
+            .arg(self.link_key(id))
+            .arg(encode(&link)?)
+            .arg("XX")
+            .arg("KEEPTTL")
+            .query_async(&mut self.conn.clone())
+            .await
+            .map_err(unavailable)?;
+
+        match stored {
+            Some(_) => Ok(link),
+            None => Err(AppError::not_found(id)),
+        }
+    }
+}
+
+/// Like updates, status changes racing other writes are last write wins.
+#[async_trait]
+impl crate::app::command::change_link_status::ChangeLinkStatusRepository for RedisRepository {
+    async fn set_status(
+        &self,
+        id: &str,
+        status: LinkStatus,
+        reason: Option<String>,
+    ) -> Result<ShortUrl, AppError> {
+        let mut link = match self.fetch(id).await? {
+            Some(link) if !link.is_expired(self.now()) => link,
+            _ => return Err(AppError::not_found(id)),
+        };
+        link.status = status;
+        link.status_reason = reason;
+
+        let stored: Option<String> = redis::cmd("SET")
+            .arg(self.link_key(id))
+            .arg(encode(&link)?)
+            .arg("XX")
+            .arg("KEEPTTL")
+            .query_async(&mut self.conn.clone())
+            .await
+            .map_err(unavailable)?;
+
+        match stored {
+            Some(_) => Ok(link),
+            None => Err(AppError::not_found(id)),
+        }
+    }
+
+    async fn close_reports(
+        &self,
+        link_id: &str,
+        outcome: LinkStatus,
+        reviewed_at: u64,
+    ) -> Result<usize, AppError> {
+        let ids: Vec<String> = redis::cmd("SMEMBERS")
+            .arg(self.link_reports_key(link_id))
+            .query_async(&mut self.conn.clone())
+            .await
+            .map_err(unavailable)?;
+        let reports = self.load_reports(&ids).await?;
+
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        for mut report in reports.iter().cloned() {
+            report.close(outcome, reviewed_at);
+            pipe.cmd("HSET")
+                .arg(self.reports_key())
+                .arg(&report.id)
+                .arg(encode_report(&report)?)
+                .ignore();
+        }
+        if !ids.is_empty() {
+            pipe.cmd("ZREM")
+                .arg(self.report_queue_key(true))
+                .arg(&ids)
+                .ignore();
+            pipe.cmd("SREM")
+                .arg(self.link_reports_key(link_id))
+                .arg(&ids)
+                .ignore();
+        }
+        pipe.query_async::<()>(&mut self.conn.clone())
+            .await
+            .map_err(unavailable)?;
+
+        Ok(reports.len())
+    }
+}
+
+#[async_trait]
+impl crate::app::command::report_link::ReportLinkRepository for RedisRepository {
+    async fn save_report(&self, report: AbuseReport) -> Result<(), AppError> {
+        match self.fetch(&report.link_id).await? {
+            Some(link) if !link.is_expired(self.now()) => {}
+            _ => return Err(AppError::not_found(&report.link_id)),
+        }
+
+        redis::pipe()
+            .atomic()
+            .cmd("HSET")
+            .arg(self.reports_key())
+            .arg(&report.id)
+            .arg(encode_report(&report)?)
+            .ignore()
+            .cmd("ZADD")
+            .arg(self.report_queue_key(false))
+            .arg(report.created_at)
+            .arg(&report.id)
+            .ignore()
+            .cmd("ZADD")
+            .arg(self.report_queue_key(true))
+            .arg(report.created_at)
+            .arg(&report.id)
+            .ignore()
+            .cmd("SADD")
+            .arg(self.link_reports_key(&report.link_id))
+            .arg(&report.id)
+            .ignore()
+            .query_async(&mut self.conn.clone())
+            .await
+            .map_err(unavailable)
+    }
+}
+
+impl crate::app::query::list_reports::ListReportsRepository for RedisRepository {
+    async fn reports(
+        &self,
+        open_only: bool,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Vec<AbuseReport>, AppError> {
+        if limit == 0 {
+            return Ok(vec![]);
+        }
+
+        let ids: Vec<String> = redis::cmd("ZRANGE")
+            .arg(self.report_queue_key(open_only))
+            .arg(offset)
+            .arg(offset + limit - 1)
+            .query_async(&mut self.conn.clone())
+            .await
+            .map_err(unavailable)?;
+
+        self.load_reports(&ids).await
+    }
+}
+
+#[async_trait]
+impl crate::app::command::record_visit::RecordVisitRepository for RedisRepository {
+    async fn record_visit(&self, id: &str, variant: Option<String>) -> Result<(), AppError> {
+        // Checking first keeps counts of deleted links from piling up; a
+        // delete racing the increment may still leave one behind.
+        let Some(link) = self.fetch(id).await? else {
+            return Ok(());
+        };
+
+        let mut pipe = redis::pipe();
+        pipe.cmd("HINCRBY")
+            .arg(self.visits_key(id))
+            .arg(VISITS_TOTAL)
+            .arg(1)
+            .ignore();
+        if let Some(variant) = variant {
+            pipe.cmd("HINCRBY")
+                .arg(self.visits_key(id))
+                .arg(format!("{VISITS_VARIANT_PREFIX}{variant}"))
+                .arg(1)
+                .ignore();
+        }
+        if let Some(workspace) = &link.workspace {
+            pipe.cmd("HINCRBY")
+                .arg(self.workspace_clicks_key(workspace))
+                .arg(workspace::month_of(self.now()))
+                .arg(1)
+                .ignore();
+        }
+
+        pipe.query_async(&mut self.conn.clone())
+            .await
+            .map_err(unavailable)
+    }
+}
+
+impl crate::app::query::get_link_stats::GetLinkStatsRepository for RedisRepository {
+    async fn ownership(&self, id: &str) -> Result<Ownership, AppError> {
+        self.stored_ownership(id).await
+    }
+
+    async fn link_stats(&self, id: &str) -> Result<Option<LinkStats>, AppError> {
+        match self.fetch(id).await? {
+            Some(link) if !link.is_expired(self.now()) => {}
+            _ => return Ok(None),
+        }
+
+        let counts: HashMap<String, u64> = redis::cmd("HGETALL")
+            .arg(self.visits_key(id))
+            .query_async(&mut self.conn.clone())
+            .await
+            .map_err(unavailable)?;
+
+        let mut stats = LinkStats::default();
+        for (field, count) in counts {
+            if field == VISITS_TOTAL {
+                stats.visits = count;
+            } else if let Some(variant) = field.strip_prefix(VISITS_VARIANT_PREFIX) {
+                stats.variants.insert(variant.to_owned(), count);
+            }
+        }
+
+        Ok(Some(stats))
+    }
+}
+
+#[async_trait]
+impl crate::app::command::check_link_health::CheckLinkHealthRepository for RedisRepository {
+    async fn links_to_check(&self) -> Result<Vec<ShortUrl>, AppError> {
+        let ids: Vec<String> = redis::cmd("ZRANGE")
+            .arg(self.ids_key())
+            .arg(0)
+            .arg(-1)
+            .query_async(&mut self.conn.clone())
+            .await
+            .map_err(unavailable)?;
+
+        let mut links = Vec::with_capacity(ids.len());
+        for batch in ids.chunks(LOAD_BATCH) {
+            links.extend(self.live(self.load(batch).await?));
+        }
+
+        Ok(links)
+    }
+
+    async fn record_health(&self, id: &str, health: LinkHealth) -> Result<(), AppError> {
+        // A delete racing the check may still leave one behind, as with
+        // visit counts.
+        if self.fetch(id).await?.is_none() {
+            return Ok(());
+        }
+
+        let value = serde_json::to_string(&health).map_err(|err| {
+            tracing::error!(%err, id, "failed to serialize health check");
+            AppError::StorageUnavailable
+        })?;
+        redis::cmd("HSET")
+            .arg(self.health_key())
+            .arg(id)
+            .arg(value)
+            .query_async(&mut self.conn.clone())
+            .await
+            .map_err(unavailable)
+    }
+}
+
+impl crate::app::query::get_link_health::GetLinkHealthRepository for RedisRepository {
+    async fn ownership(&self, id: &str) -> Result<Ownership, AppError> {
+        self.stored_ownership(id).await
+    }
+
+    async fn link_health(&self, id: &str) -> Result<Option<LinkHealth>, AppError> {
+        match self.fetch(id).await? {
+            Some(link) if !link.is_expired(self.now()) => {}
+            _ => return Ok(None),
+        }
+
+        let value: Option<String> = redis::cmd("HGET")
+            .arg(self.health_key())
+            .arg(id)
+            .query_async(&mut self.conn.clone())
+            .await
+            .map_err(unavailable)?;
+
+        value.as_deref().map(decode_health).transpose()
+    }
+}
+
+impl crate::app::query::get_full_url::GetFullUrlRepository for RedisRepository {
+    async fn get(&self, id: &str) -> Result<ShortUrl, AppError> {
+        match self.fetch(id).await? {
+            Some(link) if !link.is_expired(self.now()) => Ok(link),
+            _ => Err(AppError::not_found(id)),
+        }
+    }
+}
+
+#[async_trait]
+impl crate::app::command::delete_short_url::DeleteShortUrlRepository for RedisRepository {
+    async fn ownership(&self, id: &str) -> Result<Ownership, AppError> {
+        self.stored_ownership(id).await
+    }
+
+    async fn delete(&self, id: &str) -> Result<String, AppError> {
+        let (value,): (Option<String>,) = redis::pipe()
+            .atomic()
+            .cmd("GETDEL")
+            .arg(self.link_key(id))
+            .cmd("ZREM")
+            .arg(self.ids_key())
+            .arg(id)
+            .ignore()
+            .cmd("ZREM")
+            .arg(self.expiry_key())
+            .arg(id)
+            .ignore()
+            .cmd("DEL")
+            .arg(self.visits_key(id))
+            .ignore()
+            .cmd("HDEL")
+            .arg(self.health_key())
+            .arg(id)
+            .ignore()
+            .query_async(&mut self.conn.clone())
+            .await
+            .map_err(unavailable)?;
+
+        let link = match value {
+            Some(value) => decode(&value)?,
+            None => return Err(AppError::not_found(id)),
+        };
+        let mut pipe = redis::pipe();
+        pipe.cmd("ZREM")
+            .arg(self.owner_key(link.owner.as_deref()))
+            .arg(id)
+            .ignore();
+        if let Some(workspace) = &link.workspace {
+            pipe.cmd("ZREM")
+                .arg(self.workspace_key(workspace))
+                .arg(id)
+                .ignore();
+        }
+        pipe.query_async::<()>(&mut self.conn.clone())
+            .await
+            .map_err(unavailable)?;
+
+        Ok(link.url)
+    }
+}
+
+/// Links that expired since the last purge still hold their place in the
+/// index, so such a page may come back short.
+impl crate::app::query::list_short_urls::ListShortUrlsRepository for RedisRepository {
+    async fn list(
+        &self,
+        owner: OwnerFilter<'_>,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Vec<ShortUrl>, AppError> {
+        if limit == 0 {
+            return Ok(vec![]);
+        }
+
+        let index = match owner {
+            OwnerFilter::Anyone => self.ids_key(),
+            OwnerFilter::Only(owner) => self.owner_key(owner),
+            OwnerFilter::Workspace(workspace) => self.workspace_key(workspace),
+        };
+        let ids: Vec<String> = redis::cmd("ZRANGE")
+            .arg(&index)
+            .arg(offset)
+            .arg(offset + limit - 1)
+            .query_async(&mut self.conn.clone())
+            .await
+            .map_err(unavailable)?;
+        let links = self.load(&ids).await?;
+
+        let stale: Vec<&String> = ids
+            .iter()
+            .zip(&links)
+            .filter(|(_, link)| !link.as_ref().is_some_and(|link| owner.matches(link)))
+            .map(|(id, _)| id)
+            .collect();
+        if owner != OwnerFilter::Anyone && !stale.is_empty() {
+            let _: () = redis::cmd("ZREM")
+                .arg(&index)
+                .arg(stale)
+                .query_async(&mut self.conn.clone())
+                .await
+                .map_err(unavailable)?;
+        }
+
+        Ok(self
+            .live(links)
+            .into_iter()
+            .filter(|link| owner.matches(link))
+            .collect())
+    }
+}
+
+impl crate::app::query::get_workspace_usage::GetWorkspaceUsageRepository for RedisRepository {
+    async fn workspace_usage(&self, workspace: &str, month: &str) -> Result<Usage, AppError> {
+        self.usage(workspace, month).await
+    }
+}
+
+impl crate::app::query::get_stats::GetStatsRepository for RedisRepository {
+    async fn count(&self) -> Result<usize, AppError> {
+        let (indexed, expired): (usize, usize) = redis::pipe()
+            .cmd("ZCARD")
+            .arg(self.ids_key())
+            .cmd("ZCOUNT")
+            .arg(self.expiry_key())
+            .arg("-inf")
+            .arg(self.now())
+            .query_async(&mut self.conn.clone())
+            .await
+            .map_err(unavailable)?;
+
+        Ok(indexed.saturating_sub(expired))
+    }
+
+    /// Links that expired since the last purge are still counted.
+    async fn health_counts(&self) -> Result<HealthCounts, AppError> {
+        let values: Vec<String> = redis::cmd("HVALS")
+            .arg(self.health_key())
+            .query_async(&mut self.conn.clone())
+            .await
+            .map_err(unavailable)?;
+
+        values
+            .iter()
+            .map(|value| decode_health(value).map(|health| health.state))
+            .collect()
+    }
+}
+
+#[async_trait]
+impl crate::app::command::import_short_urls::ImportShortUrlsRepository for RedisRepository {
+    async fn find_many(&self, ids: &[String]) -> Result<Vec<Option<ShortUrl>>, AppError> {
+        self.load(ids).await
+    }
+
+    async fn upsert_many(&self, links: Vec<ShortUrl>) -> Result<(), AppError> {
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+
+        for link in &links {
+            let key = self.link_key(&link.id);
+            let owner_key = self.owner_key(link.owner.as_deref());
+            if let Some(workspace) = &link.workspace {
+                if link.is_expired(self.now()) {
+                    pipe.cmd("ZREM")
+                        .arg(self.workspace_key(workspace))
+                        .arg(&link.id)
+                        .ignore();
+                } else {
+                    pipe.cmd("ZADD")
+                        .arg(self.workspace_key(workspace))
+                        .arg(0)
+                        .arg(&link.id)
+                        .ignore();
+                }
+            }
+            match link.remaining_ttl(self.now()) {
+                Some(0) => {
+                    pipe.cmd("DEL").arg(key).ignore();
+                    pipe.cmd("ZREM").arg(self.ids_key()).arg(&link.id).ignore();
+                    pipe.cmd("ZREM").arg(owner_key).arg(&link.id).ignore();
+                }
+                Some(ttl) => {
+                    pipe.cmd("SET")
+                        .arg(key)
+                        .arg(encode(link)?)
+                        .arg("EX")
+                        .arg(ttl + EXPIRED_RETENTION_SECS)
+                        .ignore();
+                    pipe.cmd("ZADD")
+                        .arg(self.ids_key())
+                        .arg(0)
+                        .arg(&link.id)
+                        .ignore();
+                    pipe.cmd("ZADD")
+                        .arg(owner_key)
+                        .arg(0)
+                        .arg(&link.id)
+                        .ignore();
+                }
+                None => {
+                    pipe.cmd("SET").arg(key).arg(encode(link)?).ignore();
+                    pipe.cmd("ZADD")
+                        .arg(self.ids_key())
+                        .arg(0)
+                        .arg(&link.id)
+                        .ignore();
+                    pipe.cmd("ZADD")
+                        .arg(owner_key)
+                        .arg(0)
+                        .arg(&link.id)
+                        .ignore();
+                }
+            }
+
+            match link.expires_at {
+                Some(expires_at) if !link.is_expired(self.now()) => {
+                    pipe.cmd("ZADD")
+                        .arg(self.expiry_key())
+                        .arg(expires_at)
+                        .arg(&link.id)
+                        .ignore();
+                }
+                _ => {
+                    pipe.cmd("ZREM")
+                        .arg(self.expiry_key())
+                        .arg(&link.id)
+                        .ignore();
+                }
+            }
+        }
+
+        pipe.query_async::<()>(&mut self.conn.clone())
+            .await
+            .map_err(unavailable)
+    }
+}
+
+#[async_trait]
+impl crate::app::command::purge_expired_links::PurgeExpiredLinksRepository for RedisRepository {
+    async fn purge_expired(&self) -> Result<Vec<ShortUrl>, AppError> {
+        let values: Vec<String> = PURGE_SCRIPT
+            .key(self.ids_key())
+            .key(self.expiry_key())
+            .key(self.health_key())
+            .arg(self.now())
+            .arg(self.link_key(""))
+            .arg(self.visits_key(""))
+            .invoke_async(&mut self.conn.clone())
+            .await
+            .map_err(unavailable)?;
+
+        values.iter().map(|value| decode(value)).collect()
+    }
+}
+
+impl crate::app::query::export_short_urls::ExportShortUrlsRepository for RedisRepository {
+    fn export(
+        &self,
+        batch_size: usize,
+    ) -> impl Stream<Item = Result<Vec<ShortUrl>, AppError>> + std::marker::Send {
+        let batch_size = batch_size.max(1);
+        let repo = self.clone();
+
+        // Walks the index by ID rather than by position, so links created or
+        // deleted during the export do not shift later batches.
+        stream::unfold(Some("-".to_owned()), move |from| {
+            let repo = repo.clone();
+            async move {
+                let from = from?;
+                let ids: Vec<String> = match redis::cmd("ZRANGEBYLEX")
+                    .arg(repo.ids_key())
+                    .arg(&from)
+                    .arg("+")
+                    .arg("LIMIT")
+                    .arg(0)
+                    .arg(batch_size)
+                    .query_async(&mut repo.conn.clone())
+                    .await
+                {
+                    Ok(ids) => ids,
+                    Err(err) => return Some((Err(unavailable(err)), None)),
+                };
+
+                let next = match ids.last() {
+                    Some(last) if ids.len() == batch_size => Some(format!("({last}")),
+                    _ => None,
+                };
+                if ids.is_empty() {
+                    return None;
+                }
+
+                Some((repo.load(&ids).await.map(|links| repo.live(links)), next))
+            }
+        })
+    }
+}
+
+#[async_trait]
+impl crate::id_provider::sequential::IdSequenceRepository for RedisRepository {
+    async fn reserve(&self, count: u64) -> Result<u64, AppError> {
+        let end: u64 = redis::cmd("INCRBY")
+            .arg(self.sequence_key())
+            .arg(count)
+            .query_async(&mut self.conn.clone())
+            .await
+            .map_err(unavailable)?;
+
+        Ok(end - count)
+    }
+}
+
+fn encode(link: &ShortUrl) -> Result<String, AppError> {
+    serde_json::to_string(link).map_err(|err| {
+        tracing::error!(%err, id = %link.id, "failed to serialize link");
+        AppError::StorageUnavailable
+    })
+}
+
+fn decode(value: &str) -> Result<ShortUrl, AppError> {
+    serde_json::from_str(value).map_err(|err| {
+        tracing::error!(%err, "unreadable link record in redis");
+        AppError::StorageUnavailable
+    })
+}
+
+fn encode_report(report: &AbuseReport) -> Result<String, AppError> {
+    serde_json::to_string(report).map_err(|err| {
+        tracing::error!(%err, id = %report.id, "failed to serialize report");
+        AppError::StorageUnavailable
+    })
+}
+
+fn decode_health(value: &str) -> Result<LinkHealth, AppError> {
+    serde_json::from_str(value).map_err(|err| {
+        tracing::error!(%err, "unreadable health record in redis");
+        AppError::StorageUnavailable
+    })
+}
+
+fn encode_json<T: Serialize>(value: &T, what: &str) -> Result<String, AppError> {
+    serde_json::to_string(value).map_err(|err| {
+        tracing::error!(%err, "failed to serialize {what}");
+        AppError::StorageUnavailable
+    })
+}
+
+fn decode_json<T: DeserializeOwned>(value: &str, what: &str) -> Result<T, AppError> {
+    serde_json::from_str(value).map_err(|err| {
+        tracing::error!(%err, "unreadable {what} record in redis");
+        AppError::StorageUnavailable
+    })
+}
+
+fn unavailable(err: RedisError) -> AppError {
+    tracing::error!(%err, "redis request failed");
+    AppError::StorageUnavailable
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+
+    use crate::id_provider::sequential::IdSequenceRepository;
+
+    use crate::app::{
+        command::{
+            check_link_health::CheckLinkHealthRepository,
+            create_short_url::CreateShortUrlRepository, delete_short_url::DeleteShortUrlRepository,
+            import_short_urls::ImportShortUrlsRepository,
+            purge_expired_links::PurgeExpiredLinksRepository,
+        },
+        query::{
+            export_short_urls::ExportShortUrlsRepository, get_full_url::GetFullUrlRepository,
+            get_link_health::GetLinkHealthRepository, get_stats::GetStatsRepository,
+            list_short_urls::ListShortUrlsRepository,
+        },
+    };
+
+    use super::*;
+
+    /// A repository under a fresh prefix on the server named by `REDIS_URL`.
+    async fn repository() -> RedisRepository {
+        let url = std::env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1/".to_owned());
+        let prefix = format!("urlshortener-test-{}", nanoid::nanoid!());
+
+        RedisRepository::connect_with_prefix(&url, &prefix)
+            .await
+            .unwrap()
+    }
+
+    fn link(id: &str, url: &str) -> ShortUrl {
+        ShortUrl::new(id.to_owned(), url.to_owned())
+    }
+
+    fn expired(id: &str, url: &str) -> ShortUrl {
+        let mut link = link(id, url);
+        link.expires_at = Some(link.created_at - 1);
+        link
+    }
+
+    #[tokio::test]
+    async fn unreachable_server_is_unavailable() {
+        // When
+        let result = RedisRepository::connect("redis://127.0.0.1:1/").await;
+
+        // Then
+        assert!(matches!(result, Err(AppError::StorageUnavailable)));
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a redis server, see REDIS_URL"]
+    async fn save_get_and_delete() {
+        // Given
+        let repo = repository().await;
+
+        // When
+        repo.save(link("abc", "https://a.com/")).await.unwrap();
+        let fetched = repo.get("abc").await.map(|link| link.url);
+        let collision = repo.save(link("abc", "https://b.com/")).await;
+        let deleted = repo.delete("abc").await;
+
+        // Then
+        assert_eq!(fetched, Ok("https://a.com/".to_owned()));
+        assert_eq!(
+            collision,
+            Err(AppError::IdTaken {
+                id: "abc".to_owned()
+            })
+        );
+        assert_eq!(deleted, Ok("https://a.com/".to_owned()));
+        assert_eq!(repo.get("abc").await, Err(AppError::not_found("abc")));
+        assert_eq!(repo.delete("abc").await, Err(AppError::not_found("abc")));
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a redis server, see REDIS_URL"]
+    async fn ttl_is_native_key_expiry_after_retention() {
+        // Given
+        let repo = repository().await;
+        let link = link("abc", "https://a.com/").with_ttl(Duration::from_secs(100));
+
+        // When
+        repo.save(link).await.unwrap();
+
+        // Then
+        let ttl: i64 = redis::cmd("TTL")
+            .arg(repo.link_key("abc"))
+            .query_async(&mut repo.conn.clone())
+            .await
+            .unwrap();
+        let ttl = ttl - EXPIRED_RETENTION_SECS as i64;
+        assert!((1..=100).contains(&ttl));
+        assert_eq!(repo.count().await, Ok(1));
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a redis server, see REDIS_URL"]
+    async fn expired_links_are_stored_until_purged() {
+        // Given
+        let repo = repository().await;
+
+        // When
+        let saved = repo.save(expired("old", "https://old.com/")).await;
+
+        // Then
+        assert_eq!(saved, Ok(()));
+        assert_eq!(repo.get("old").await, Err(AppError::not_found("old")));
+        assert!(matches!(
+            repo.save(link("old", "https://new.com/")).await,
+            Err(AppError::IdTaken { .. })
+        ));
+        let purged = repo.purge_expired().await.unwrap();
+        assert_eq!(purged.len(), 1);
+        assert_eq!(purged[0].url, "https://old.com/");
+        assert_eq!(repo.save(link("old", "https://new.com/")).await, Ok(()));
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a redis server, see REDIS_URL"]
+    async fn list_export_and_import_in_id_order() {
+        // Given
+        let repo = repository().await;
+        repo.upsert_many(vec![
+            link("c", "https://c.com/"),
+            link("a", "https://a.com/"),
+            link("b", "https://b.com/"),
+            expired("d", "https://d.com/"),
+        ])
+        .await
+        .unwrap();
+
+        // When
+        let page = repo.list(OwnerFilter::Anyone, 1, 10).await.unwrap();
+        let found = repo
+            .find_many(&["a".to_owned(), "zzz".to_owned()])
+            .await
+            .unwrap();
+        let batches: Vec<_> = repo.export(2).collect().await;
+
+        // Then
+        let ids = |links: &[ShortUrl]| links.iter().map(|l| l.id.clone()).collect::<Vec<_>>();
+        assert_eq!(ids(&page), ["b", "c"]);
+        assert_eq!(
+            found[0].as_ref().map(|l| l.url.as_str()),
+            Some("https://a.com/")
+        );
+        assert_eq!(found[1], None);
+        let batches: Vec<Vec<ShortUrl>> = batches.into_iter().map(Result::unwrap).collect();
+        assert_eq!(batches.len(), 2);
+        assert_eq!(ids(&batches.concat()), ["a", "b", "c"]);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a redis server, see REDIS_URL"]
+    async fn sequence_blocks_do_not_overlap() {
+        // Given
+        let repo = repository().await;
+        let other = repo.clone();
+
+        // When
+        let first = repo.reserve(100).await;
+        let second = other.reserve(100).await;
+
+        // Then
+        assert_eq!(first, Ok(0));
+        assert_eq!(second, Ok(100));
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a redis server, see REDIS_URL"]
+    async fn purge_drops_expired_index_entries() {
+        // Given
+        let repo = repository().await;
+        repo.save(link("live", "https://live.com/")).await.unwrap();
+        let mut old = link("old", "https://old.com/");
+        old.expires_at = Some(repo.now() - 1);
+        // Simulates a link whose key Redis has already expired, and one
+        // still kept for the retention period.
+        let _: () = redis::pipe()
+            .cmd("ZADD")
+            .arg(repo.ids_key())
+            .arg(0)
+            .arg("gone")
+            .ignore()
+            .cmd("ZADD")
+            .arg(repo.expiry_key())
+            .arg(repo.now() - 1)
+            .arg("gone")
+            .ignore()
+            .cmd("SET")
+            .arg(repo.link_key("old"))
+            .arg(encode(&old).unwrap())
+            .ignore()
+            .cmd("ZADD")
+            .arg(repo.ids_key())
+            .arg(0)
+            .arg("old")
+            .ignore()
+            .cmd("ZADD")
+            .arg(repo.expiry_key())
+            .arg(repo.now() - 1)
+            .arg("old")
+            .ignore()
+            .query_async(&mut repo.conn.clone())
+            .await
+            .unwrap();
+
+        // When
+        let counted_before = repo.count().await;
+        let purged = repo.purge_expired().await;
+
+        // Then
+        assert_eq!(counted_before, Ok(1));
+        assert_eq!(purged, Ok(vec![old]));
+        assert_eq!(
+            repo.list(OwnerFilter::Anyone, 0, 10).await.unwrap().len(),
+            1
+        );
+        assert_eq!(repo.count().await, Ok(1));
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a redis server, see REDIS_URL"]
+    async fn health_checks_go_with_their_link() {
+        // Given
+        let repo = repository().await;
+        repo.save(link("a", "https://a.com/")).await.unwrap();
+        let health = LinkHealth::new(
+            "https://a.com/".to_owned(),
+            crate::app::health::Probe {
+                status: Some(404),
+                ..Default::default()
+            },
+            repo.now(),
+        );
+
+        // When
+        let checked = repo.links_to_check().await.unwrap();
+        repo.record_health("a", health.clone()).await.unwrap();
+        repo.record_health("deleted", health.clone()).await.unwrap();
+        let stored = repo.link_health("a").await;
+        let counts = repo.health_counts().await;
+        repo.delete("a").await.unwrap();
+
+        // Then
+        assert_eq!(checked.len(), 1);
+        assert_eq!(stored, Ok(Some(health)));
+        assert_eq!(counts.map(|counts| counts.broken), Ok(1));
+        assert_eq!(repo.health_counts().await, Ok(HealthCounts::default()));
+    }
+}
@@ -0,0 +1,258 @@
+use std::sync::LazyLock;
+
+use async_trait::async_trait;
+use redis::Script;
+
+use crate::{
+    app::{
+        event::EventKind,
+        webhook::{dispatcher::WebhookDeliveryRepository, WebhookDelivery, WebhookSubscription},
+    },
+    error::AppError,
+};
+
+use super::{decode_json, encode_json, unavailable, RedisRepository};
+
+/// Upper bound of log entries kept per subscription; older ones are dropped.
+const MAX_DELIVERIES_PER_SUBSCRIPTION: usize = 100;
+
+/// Appends a delivery to the log of its subscription, unless the
+/// subscription was deleted while the delivery was in flight.
+/// KEYS: subscriptions, deliveries. ARGV: subscription id, json, log size.
+static RECORD_DELIVERY_SCRIPT: LazyLock<Script> = LazyLock::new(|| {
+    Script::new(
+        r"
+        if redis.call('HEXISTS', KEYS[1], ARGV[1]) == 0 then
+            return 0
+        end
+        redis.call('RPUSH', KEYS[2], ARGV[2])
+        redis.call('LTRIM', KEYS[2], -tonumber(ARGV[3]), -1)
+        return 1
+        ",
+    )
+});
+
+/// Webhook storage next to the links: subscriptions are JSON values in one
+/// hash by ID, and each delivery log is a list of JSON values, oldest first.
+#[derive(Clone)]
+pub struct RedisWebhookRepository {
+    redis: RedisRepository,
+}
+
+impl RedisWebhookRepository {
+    /// Shares the connection and key prefix of `redis`.
+    pub fn new(redis: RedisRepository) -> Self {
+        Self { redis }
+    }
+
+    fn subscriptions_key(&self) -> String {
+        format!("{}:webhooks", self.redis.prefix)
+    }
+
+    fn deliveries_key(&self, subscription_id: &str) -> String {
+        format!("{}:webhook-deliveries:{subscription_id}", self.redis.prefix)
+    }
+
+    async fn subscriptions(&self) -> Result<Vec<WebhookSubscription>, AppError> {
+        let values: Vec<String> = redis::cmd("HVALS")
+            .arg(self.subscriptions_key())
+            .query_async(&mut self.redis.conn.clone())
+            .await
+            .map_err(unavailable)?;
+
+        values
+            .iter()
+            .map(|value| decode_json(value, "webhook subscription"))
+            .collect()
+    }
+}
+
+#[async_trait]
+impl crate::app::command::create_webhook::CreateWebhookRepository for RedisWebhookRepository {
+    async fn save_subscription(&self, subscription: WebhookSubscription) -> Result<(), AppError> {
+        redis::cmd("HSET")
+            .arg(self.subscriptions_key())
+            .arg(&subscription.id)
+            .arg(encode_json(&subscription, "webhook subscription")?)
+            .query_async(&mut self.redis.conn.clone())
+            .await
+            .map_err(unavailable)
+    }
+}
+
+#[async_trait]
+impl crate::app::command::delete_webhook::DeleteWebhookRepository for RedisWebhookRepository {
+    async fn delete_subscription(&self, id: &str) -> Result<(), AppError> {
+        let (removed,): (u64,) = redis::pipe()
+            .atomic()
+            .cmd("HDEL")
+            .arg(self.subscriptions_key())
+            .arg(id)
+            .cmd("DEL")
+            .arg(self.deliveries_key(id))
+            .ignore()
+            .query_async(&mut self.redis.conn.clone())
+            .await
+            .map_err(unavailable)?;
+
+        match removed {
+            0 => Err(AppError::not_found(id)),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl crate::app::query::list_webhooks::ListWebhooksRepository for RedisWebhookRepository {
+    async fn list_subscriptions(&self) -> Result<Vec<WebhookSubscription>, AppError> {
+        let mut subscriptions = self.subscriptions().await?;
+        subscriptions.sort_by(|a, b| a.created_at.cmp(&b.created_at).then(a.id.cmp(&b.id)));
+
+        Ok(subscriptions)
+    }
+}
+
+impl crate::app::query::list_webhook_deliveries::ListWebhookDeliveriesRepository
+    for RedisWebhookRepository
+{
+    async fn list_deliveries(
+        &self,
+        subscription_id: &str,
+    ) -> Result<Vec<WebhookDelivery>, AppError> {
+        let (exists, values): (bool, Vec<String>) = redis::pipe()
+            .cmd("HEXISTS")
+            .arg(self.subscriptions_key())
+            .arg(subscription_id)
+            .cmd("LRANGE")
+            .arg(self.deliveries_key(subscription_id))
+            .arg(0)
+            .arg(-1)
+            .query_async(&mut self.redis.conn.clone())
+            .await
+            .map_err(unavailable)?;
+        if !exists {
+            return Err(AppError::not_found(subscription_id));
+        }
+
+        values
+            .iter()
+            .map(|value| decode_json(value, "webhook delivery"))
+            .collect()
+    }
+}
+
+#[async_trait]
+impl WebhookDeliveryRepository for RedisWebhookRepository {
+    async fn subscriptions_for(
+        &self,
+        kind: EventKind,
+    ) -> Result<Vec<WebhookSubscription>, AppError> {
+        let mut subscriptions = self.subscriptions().await?;
+        subscriptions.retain(|subscription| subscription.accepts(kind));
+
+        Ok(subscriptions)
+    }
+
+    async fn record_delivery(&self, delivery: WebhookDelivery) -> Result<(), AppError> {
+        RECORD_DELIVERY_SCRIPT
+            .key(self.subscriptions_key())
+            .key(self.deliveries_key(&delivery.subscription_id))
+            .arg(&delivery.subscription_id)
+            .arg(encode_json(&delivery, "webhook delivery")?)
+            .arg(MAX_DELIVERIES_PER_SUBSCRIPTION)
+            .invoke_async::<()>(&mut self.redis.conn.clone())
+            .await
+            .map_err(unavailable)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app::{
+        command::{
+            create_webhook::CreateWebhookRepository, delete_webhook::DeleteWebhookRepository,
+        },
+        query::{
+            list_webhook_deliveries::ListWebhookDeliveriesRepository,
+            list_webhooks::ListWebhooksRepository,
+        },
+    };
+
+    use super::*;
+
+    async fn repository() -> RedisWebhookRepository {
+        let url = std::env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1/".to_owned());
+        let prefix = format!("urlshortener-test-{}", nanoid::nanoid!());
+
+        RedisWebhookRepository::new(
+            RedisRepository::connect_with_prefix(&url, &prefix)
+                .await
+                .unwrap(),
+        )
+    }
+
+    fn subscription(id: &str, events: Vec<EventKind>) -> WebhookSubscription {
+        WebhookSubscription {
+            id: id.to_owned(),
+            url: "https://example.com/hook".to_owned(),
+            secret: "secret".to_owned(),
+            events,
+            created_at: 0,
+        }
+    }
+
+    fn delivery(subscription_id: &str, attempt: u32) -> WebhookDelivery {
+        WebhookDelivery {
+            subscription_id: subscription_id.to_owned(),
+            event_id: "evt".to_owned(),
+            event_kind: EventKind::LinkCreated,
+            attempt,
+            status_code: Some(200),
+            error: None,
+            succeeded: true,
+            attempted_at: 0,
+        }
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a redis server, see REDIS_URL"]
+    async fn subscriptions_and_bounded_delivery_logs() {
+        // Given
+        let repo = repository().await;
+        repo.save_subscription(subscription("all", vec![]))
+            .await
+            .unwrap();
+        repo.save_subscription(subscription("deletes", vec![EventKind::LinkDeleted]))
+            .await
+            .unwrap();
+
+        // When
+        for attempt in 0..(MAX_DELIVERIES_PER_SUBSCRIPTION as u32 + 5) {
+            repo.record_delivery(delivery("all", attempt))
+                .await
+                .unwrap();
+        }
+        repo.record_delivery(delivery("gone", 1)).await.unwrap();
+        let created = repo.subscriptions_for(EventKind::LinkCreated).await;
+        repo.delete_subscription("deletes").await.unwrap();
+
+        // Then
+        assert_eq!(created, Ok(vec![subscription("all", vec![])]));
+        let log = repo.list_deliveries("all").await.unwrap();
+        assert_eq!(log.len(), MAX_DELIVERIES_PER_SUBSCRIPTION);
+        assert_eq!(log[0].attempt, 5);
+        assert_eq!(
+            repo.list_deliveries("gone").await,
+            Err(AppError::not_found("gone"))
+        );
+        assert_eq!(
+            repo.list_subscriptions()
+                .await
+                .map(|subscriptions| subscriptions.len()),
+            Ok(1)
+        );
+        assert_eq!(
+            repo.delete_subscription("deletes").await,
+            Err(AppError::not_found("deletes"))
+        );
+    }
+}
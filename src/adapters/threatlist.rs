@@ -0,0 +1,346 @@
+//! Local lists of phishing and malware sites. A file may mix two formats,
+//! one entry per line and `#` starting a comment:
+//!
+//! - hosts-file entries such as `0.0.0.0 evil.example`, or a bare
+//!   `evil.example`, listing a host and its subdomains;
+//! - Safe Browsing style hash prefixes: the first 4 to 32 bytes, hex
+//!   encoded, of the SHA-256 of a `host/path` expression.
+
+use std::collections::{BTreeSet, HashSet};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime};
+
+use sha2::{Digest, Sha256};
+
+use crate::app::screening::UrlScreener;
+
+/// Host suffixes and path prefixes tried per URL, as Safe Browsing does.
+const MAX_HOST_SUFFIXES: usize = 5;
+const MAX_PATH_PREFIXES: usize = 4;
+
+#[derive(thiserror::Error, Debug)]
+pub enum ThreatListError {
+    #[error("cannot read threat list: {0}")]
+    Io(#[from] io::Error),
+    #[error("threat list line {line}: {reason}")]
+    Malformed { line: u64, reason: String },
+}
+
+#[derive(Debug, Default)]
+pub struct ThreatList {
+    domains: HashSet<String>,
+    prefixes: HashSet<Vec<u8>>,
+    /// The lengths found in `prefixes`, so a hash is cut only to those.
+    prefix_lengths: BTreeSet<usize>,
+}
+
+impl ThreatList {
+    pub fn open(path: &Path) -> Result<Self, ThreatListError> {
+        Self::from_reader(File::open(path)?)
+    }
+
+    pub fn from_reader(reader: impl Read) -> Result<Self, ThreatListError> {
+        let mut list = Self::default();
+        for (number, line) in BufReader::new(reader).lines().enumerate() {
+            let line = line?;
+            let malformed = |reason: String| ThreatListError::Malformed {
+                line: number as u64 + 1,
+                reason,
+            };
+
+            let entry = line.split('#').next().unwrap_or_default();
+            let mut tokens = entry.split_whitespace();
+            let Some(first) = tokens.next() else {
+                continue;
+            };
+
+            if first.parse::<IpAddr>().is_ok() {
+                // Hosts files also name the machine itself; those have no dot.
+                for host in tokens.filter(|host| host.contains('.')) {
+                    list.domains.insert(parse_domain(host).map_err(malformed)?);
+                }
+                continue;
+            }
+            if let Some(extra) = tokens.next() {
+                return Err(malformed(format!("unexpected `{extra}`")));
+            }
+
+            match parse_prefix(first) {
+                Some(prefix) => {
+                    list.prefix_lengths.insert(prefix.len());
+                    list.prefixes.insert(prefix);
+                }
+                None => {
+                    list.domains.insert(parse_domain(first).map_err(malformed)?);
+                }
+            }
+        }
+
+        Ok(list)
+    }
+
+    pub fn len(&self) -> usize {
+        self.domains.len() + self.prefixes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Why `url` is listed, or `None` when it is not.
+    pub fn lookup(&self, url: &str) -> Option<String> {
+        let url = url::Url::parse(url).ok()?;
+        let host = url.host_str()?.trim_end_matches('.').to_ascii_lowercase();
+
+        let listed = std::iter::successors(Some(host.as_str()), |host| {
+            host.split_once('.').map(|(_, parent)| parent)
+        })
+        .find(|host| self.domains.contains(*host));
+        if let Some(domain) = listed {
+            return Some(format!("`{domain}` is on the threat list"));
+        }
+
+        if self.prefixes.is_empty() {
+            return None;
+        }
+        expressions(&host, &url).into_iter().find_map(|expression| {
+            let hash = Sha256::digest(expression.as_bytes());
+            self.prefix_lengths
+                .iter()
+                .any(|&len| self.prefixes.contains(&hash[..len]))
+                .then(|| format!("`{expression}` matches a listed hash prefix"))
+        })
+    }
+}
+
+impl UrlScreener for ThreatList {
+    fn screen(&self, url: &str) -> Option<String> {
+        self.lookup(url)
+    }
+}
+
+/// A `ThreatList` that follows changes to its file.
+pub struct WatchedThreatList {
+    path: PathBuf,
+    current: RwLock<Arc<ThreatList>>,
+    modified: Mutex<Option<SystemTime>>,
+}
+
+impl WatchedThreatList {
+    pub fn open(path: &Path) -> Result<Self, ThreatListError> {
+        let modified = modified(path)?;
+        let list = ThreatList::open(path)?;
+
+        Ok(Self {
+            path: path.to_owned(),
+            current: RwLock::new(Arc::new(list)),
+            modified: Mutex::new(modified),
+        })
+    }
+
+    pub fn list(&self) -> Arc<ThreatList> {
+        self.current
+            .read()
+            .expect("threat list lock poisoned")
+            .clone()
+    }
+
+    /// Loads the file again if it was modified since the last load and
+    /// returns whether it did. A file that fails to load leaves the
+    /// current list in place.
+    pub fn reload_if_changed(&self) -> Result<bool, ThreatListError> {
+        let mut last = self.modified.lock().expect("threat list lock poisoned");
+        let modified = modified(&self.path)?;
+        if modified.is_some() && modified == *last {
+            return Ok(false);
+        }
+
+        let list = ThreatList::open(&self.path)?;
+        *self.current.write().expect("threat list lock poisoned") = Arc::new(list);
+        *last = modified;
+
+        Ok(true)
+    }
+
+    /// Checks the file every `interval` until the task is dropped.
+    /// Failures are logged and retried on the next tick.
+    pub async fn run_periodic(self: Arc<Self>, interval: Duration) {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.tick().await;
+
+        loop {
+            ticker.tick().await;
+
+            let this = self.clone();
+            match tokio::task::spawn_blocking(move || this.reload_if_changed()).await {
+                Ok(Ok(true)) => tracing::info!(entries = self.list().len(), "threat list reloaded"),
+                Ok(Ok(false)) => {}
+                Ok(Err(err)) => tracing::error!(%err, "failed to reload threat list"),
+                Err(err) => tracing::error!(%err, "threat list task panicked"),
+            }
+        }
+    }
+}
+
+impl UrlScreener for WatchedThreatList {
+    fn screen(&self, url: &str) -> Option<String> {
+        self.list().lookup(url)
+    }
+}
+
+/// `None` where the platform keeps no modification times, which makes
+/// every check reload.
+fn modified(path: &Path) -> Result<Option<SystemTime>, ThreatListError> {
+    Ok(std::fs::metadata(path)?.modified().ok())
+}
+
+fn parse_prefix(token: &str) -> Option<Vec<u8>> {
+    let looks_hashed = (8..=64).contains(&token.len())
+        && token.len().is_multiple_of(2)
+        && token.bytes().all(|b| b.is_ascii_hexdigit());
+
+    looks_hashed.then(|| hex::decode(token).ok()).flatten()
+}
+
+fn parse_domain(token: &str) -> Result<String, String> {
+    let domain = token.trim_end_matches('.').to_ascii_lowercase();
+    let valid = domain.contains('.')
+        && domain
+            .split('.')
+            .all(|label| !label.is_empty() && label.bytes().all(is_label_byte));
+
+    if valid {
+        Ok(domain)
+    } else {
+        Err(format!("`{token}` is neither a domain nor a hash prefix"))
+    }
+}
+
+fn is_label_byte(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'-' || b == b'_'
+}
+
+/// The `host/path` expressions Safe Browsing hashes for `url`: the exact
+/// host and up to four of its parent domains, each with the exact path,
+/// with and without the query, and up to four of its directories.
+fn expressions(host: &str, url: &url::Url) -> Vec<String> {
+    let mut hosts = vec![host.to_owned()];
+    if host.parse::<IpAddr>().is_err() {
+        let labels: Vec<&str> = host.split('.').collect();
+        let shortest = labels.len().saturating_sub(MAX_HOST_SUFFIXES);
+        for start in shortest.max(1)..labels.len().saturating_sub(1) {
+            hosts.push(labels[start..].join("."));
+        }
+    }
+
+    let path = url.path();
+    let mut paths = Vec::new();
+    if let Some(query) = url.query() {
+        paths.push(format!("{path}?{query}"));
+    }
+    paths.push(path.to_owned());
+    let mut directory = String::from("/");
+    let segments: Vec<&str> = path.trim_start_matches('/').split('/').collect();
+    for segment in std::iter::once("").chain(segments[..segments.len() - 1].iter().copied()) {
+        if !segment.is_empty() {
+            directory.push_str(segment);
+            directory.push('/');
+        }
+        if paths.len() == MAX_PATH_PREFIXES + 2 {
+            break;
+        }
+        if !paths.contains(&directory) {
+            paths.push(directory.clone());
+        }
+    }
+
+    hosts
+        .iter()
+        .flat_map(|host| paths.iter().map(move |path| format!("{host}{path}")))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prefix_of(expression: &str) -> String {
+        hex::encode(&Sha256::digest(expression.as_bytes())[..4])
+    }
+
+    #[test]
+    fn matches_hosts_their_subdomains_and_hash_prefixes() {
+        // Given
+        let file = format!(
+            "# blocked\n\
+             0.0.0.0 evil.example login.bank.example # phishing\n\
+             127.0.0.1 localhost\n\
+             malware.example.\n\
+             {}\n",
+            prefix_of("shop.example/checkout/")
+        );
+        let list = ThreatList::from_reader(file.as_bytes()).unwrap();
+
+        // Then
+        assert_eq!(list.len(), 4);
+        assert!(list.lookup("https://cdn.evil.example/x").is_some());
+        assert!(list.lookup("http://MALWARE.example/").is_some());
+        assert!(list.lookup("https://bank.example/").is_none());
+        assert_eq!(
+            list.lookup("https://www.shop.example/checkout/pay?card=1")
+                .as_deref(),
+            Some("`shop.example/checkout/` matches a listed hash prefix")
+        );
+        assert!(list.lookup("https://shop.example/cart/").is_none());
+    }
+
+    #[test]
+    fn reports_the_bad_line() {
+        let result = ThreatList::from_reader("evil.example\nnot a domain\n".as_bytes());
+
+        assert!(matches!(
+            result,
+            Err(ThreatListError::Malformed { line: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn tries_parent_domains_and_directories() {
+        let url = url::Url::parse("https://a.b.c.d.e.f.example/1/2/3.html?q=1").unwrap();
+
+        let expressions = expressions("a.b.c.d.e.f.example", &url);
+
+        assert!(expressions.contains(&"a.b.c.d.e.f.example/1/2/3.html?q=1".to_owned()));
+        assert!(expressions.contains(&"d.e.f.example/1/".to_owned()));
+        assert!(expressions.contains(&"f.example/".to_owned()));
+        assert!(!expressions.contains(&"example/".to_owned()));
+        assert!(expressions.contains(&"c.d.e.f.example/".to_owned()));
+        assert!(!expressions.contains(&"b.c.d.e.f.example/".to_owned()));
+    }
+
+    #[test]
+    fn reloads_when_the_file_changes() {
+        // Given
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("threats.txt");
+        std::fs::write(&path, "evil.example\n").unwrap();
+        let watched = WatchedThreatList::open(&path).unwrap();
+
+        // When
+        let unchanged = watched.reload_if_changed().unwrap();
+        std::fs::write(&path, "evil.example\nworse.example\n").unwrap();
+        let file = File::options().write(true).open(&path).unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(5))
+            .unwrap();
+        let reloaded = watched.reload_if_changed().unwrap();
+
+        // Then
+        assert!(!unchanged);
+        assert!(reloaded);
+        assert!(watched.screen("https://worse.example/").is_some());
+    }
+}
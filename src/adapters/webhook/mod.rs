@@ -0,0 +1,42 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+
+use crate::app::webhook::dispatcher::WebhookSender;
+
+#[derive(Clone)]
+pub struct HttpWebhookSender {
+    client: reqwest::Client,
+}
+
+impl HttpWebhookSender {
+    pub fn new(timeout: Duration) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .build()
+            .expect("failed to build webhook http client");
+
+        Self { client }
+    }
+}
+
+#[async_trait]
+impl WebhookSender for HttpWebhookSender {
+    async fn send(
+        &self,
+        url: &str,
+        headers: Vec<(String, String)>,
+        body: Vec<u8>,
+    ) -> Result<u16, String> {
+        let mut request = self.client.post(url).body(body);
+        for (name, value) in headers {
+            request = request.header(name, value);
+        }
+
+        request
+            .send()
+            .await
+            .map(|response| response.status().as_u16())
+            .map_err(|err| err.to_string())
+    }
+}
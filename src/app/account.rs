@@ -0,0 +1,115 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::app::policy::Role;
+
+/// Lets keys be recognized in logs and by secret scanners.
+pub const API_KEY_PREFIX: &str = "usk_";
+
+/// Someone who owns links and calls the API with their keys.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct Account {
+    pub id: String,
+    pub name: String,
+    pub role: Role,
+    pub created_at: u64,
+}
+
+/// A key an account authenticates with. Only a hash of the secret is kept;
+/// the secret itself is shown once, when the key is created.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ApiKey {
+    pub id: String,
+    pub account_id: String,
+    /// Hex encoded SHA-256 of the secret.
+    pub hash: String,
+    pub created_at: u64,
+}
+
+impl ApiKey {
+    /// A fresh key for `account_id`, and the same key as handed out.
+    pub fn generate(account_id: &str, created_at: u64) -> (Self, IssuedKey) {
+        let secret = format!("{API_KEY_PREFIX}{}", nanoid::nanoid!(32));
+        let key = ApiKey {
+            id: nanoid::nanoid!(12),
+            account_id: account_id.to_owned(),
+            hash: hash_secret(&secret),
+            created_at,
+        };
+        let issued = IssuedKey {
+            id: key.id.clone(),
+            account_id: key.account_id.clone(),
+            secret,
+        };
+
+        (key, issued)
+    }
+}
+
+/// A key as handed out on creation, the only time its secret is known.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct IssuedKey {
+    pub id: String,
+    pub account_id: String,
+    /// Sent as `Authorization: Bearer <secret>`.
+    pub secret: String,
+}
+
+/// Keys are looked up by this rather than by the secret.
+pub fn hash_secret(secret: &str) -> String {
+    hex::encode(Sha256::digest(secret.as_bytes()))
+}
+
+/// Who a command runs for.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Principal {
+    /// `None` for the operator, who acts outside of any account.
+    pub account_id: Option<String>,
+    pub role: Role,
+    /// IDs of the workspaces the account is a member of.
+    pub workspaces: Vec<String>,
+}
+
+impl Principal {
+    /// The admin CLI and holders of the bootstrap key, with the admin
+    /// role. Links the operator creates have no owner.
+    pub fn operator() -> Self {
+        Principal {
+            account_id: None,
+            role: Role::Admin,
+            workspaces: Vec::new(),
+        }
+    }
+
+    pub fn account(account: &Account, workspaces: Vec<String>) -> Self {
+        Principal {
+            account_id: Some(account.id.clone()),
+            role: account.role,
+            workspaces,
+        }
+    }
+
+    pub fn is_member_of(&self, workspace: &str) -> bool {
+        self.workspaces.iter().any(|id| id == workspace)
+    }
+
+    /// The owner recorded on links this principal creates.
+    pub fn owner(&self) -> Option<String> {
+        self.account_id.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generated_keys_store_only_a_hash() {
+        let (key, issued) = ApiKey::generate("alice", 0);
+
+        assert!(issued.secret.starts_with(API_KEY_PREFIX));
+        assert_eq!(key.id, issued.id);
+        assert_eq!(key.hash, hash_secret(&issued.secret));
+        assert!(!key.hash.contains(&issued.secret));
+    }
+}
@@ -0,0 +1,150 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use crate::{
+    app::{
+        account::Principal,
+        event::{DomainEvent, EventBus, EventKind},
+        policy::{self, Action},
+        short_url::{LinkStatus, ShortUrl},
+    },
+    clock::{SharedClock, SystemClock},
+    error::AppError,
+};
+
+#[mockall::automock]
+#[async_trait]
+pub trait ChangeLinkStatusRepository {
+    /// Gives a live link `status` and returns it. Fails with
+    /// `AppError::NotFound` when there is none.
+    async fn set_status(
+        &self,
+        id: &str,
+        status: LinkStatus,
+        reason: Option<String>,
+    ) -> Result<ShortUrl, AppError>;
+
+    /// Closes the open reports of `link_id` with `outcome` and returns how
+    /// many there were.
+    async fn close_reports(
+        &self,
+        link_id: &str,
+        outcome: LinkStatus,
+        reviewed_at: u64,
+    ) -> Result<usize, AppError>;
+}
+
+pub struct ChangeLinkStatusCommand<R>
+where
+    R: ChangeLinkStatusRepository,
+{
+    repo: R,
+    events: EventBus,
+    clock: SharedClock,
+}
+
+impl<R> ChangeLinkStatusCommand<R>
+where
+    R: ChangeLinkStatusRepository,
+{
+    pub fn new(repo: R, events: EventBus) -> Self {
+        Self {
+            repo,
+            events,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Where reviews and the events they publish take their time from.
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Disables, quarantines or restores a link, and closes its open
+    /// reports with that outcome. Only admins may. Restored links drop
+    /// their reason.
+    pub async fn execute(
+        &self,
+        principal: &Principal,
+        id: &str,
+        status: LinkStatus,
+        reason: Option<String>,
+    ) -> Result<ShortUrl, AppError> {
+        policy::authorize(principal, Action::ModerateLinks)?;
+        let reason = reason.filter(|_| !status.is_active());
+
+        let link = self.repo.set_status(id, status, reason).await?;
+        let reviewed_at = self.clock.now();
+        let closed = self.repo.close_reports(id, status, reviewed_at).await?;
+        tracing::info!(id, ?status, closed, "link status changed");
+
+        self.events.publish(DomainEvent::new(
+            EventKind::LinkUpdated,
+            link.id.clone(),
+            link.url.clone(),
+            reviewed_at,
+        ));
+        if status == LinkStatus::Quarantined {
+            self.events.publish(DomainEvent::new(
+                EventKind::LinkQuarantined,
+                link.id.clone(),
+                link.url.clone(),
+                reviewed_at,
+            ));
+        }
+
+        Ok(link)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app::policy::Role;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn only_admins_change_the_status() {
+        // Given
+        let mut repo = MockChangeLinkStatusRepository::new();
+        repo.expect_set_status()
+            .withf(|id, status, reason| {
+                id == "abc" && *status == LinkStatus::Active && reason.is_none()
+            })
+            .returning(|id, _, _| {
+                Ok(ShortUrl::new(
+                    id.to_owned(),
+                    "https://example.com/".to_owned(),
+                ))
+            })
+            .times(1);
+        repo.expect_close_reports()
+            .returning(|_, _, _| Ok(2))
+            .times(1);
+        let command = ChangeLinkStatusCommand::new(repo, EventBus::new());
+        let alice = Principal {
+            account_id: Some("alice".to_owned()),
+            role: Role::Editor,
+            workspaces: Vec::new(),
+        };
+
+        // When
+        let denied = command
+            .execute(&alice, "abc", LinkStatus::Disabled, None)
+            .await;
+        let restored = command
+            .execute(
+                &Principal::operator(),
+                "abc",
+                LinkStatus::Active,
+                Some("false alarm".to_owned()),
+            )
+            .await;
+
+        // Then
+        assert!(matches!(denied, Err(AppError::Forbidden { .. })));
+        assert!(restored.is_ok());
+    }
+}
@@ -0,0 +1,68 @@
+use async_trait::async_trait;
+
+use crate::{
+    app::{
+        account::Principal,
+        policy::{self, Action},
+        workspace::Workspace,
+    },
+    error::AppError,
+};
+
+#[mockall::automock]
+#[async_trait]
+pub trait ChangeMembershipRepository {
+    /// Makes `account_id` a member of the workspace or drops it, and returns
+    /// the workspace. Fails with `AppError::NotFound` when either does not
+    /// exist.
+    async fn set_membership(
+        &self,
+        workspace_id: &str,
+        account_id: &str,
+        member: bool,
+    ) -> Result<Workspace, AppError>;
+}
+
+/// Adds accounts to workspaces and removes them. Changes apply from the
+/// member's next request on.
+pub struct ChangeMembershipCommand<A>
+where
+    A: ChangeMembershipRepository,
+{
+    repo: A,
+}
+
+impl<A> ChangeMembershipCommand<A>
+where
+    A: ChangeMembershipRepository,
+{
+    pub fn new(repo: A) -> Self {
+        Self { repo }
+    }
+
+    /// Adding a member twice changes nothing.
+    pub async fn add(
+        &self,
+        principal: &Principal,
+        workspace_id: &str,
+        account_id: &str,
+    ) -> Result<Workspace, AppError> {
+        policy::authorize(principal, Action::ManageWorkspaces)?;
+        self.repo
+            .set_membership(workspace_id, account_id, true)
+            .await
+    }
+
+    /// The links the account created in the workspace stay there.
+    pub async fn remove(
+        &self,
+        principal: &Principal,
+        workspace_id: &str,
+        account_id: &str,
+    ) -> Result<Workspace, AppError> {
+        policy::authorize(principal, Action::ManageWorkspaces)?;
+        self.repo
+            .set_membership(workspace_id, account_id, false)
+            .await
+    }
+}
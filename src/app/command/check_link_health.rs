@@ -0,0 +1,251 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures::{stream, StreamExt};
+
+use crate::{
+    app::{
+        health::{HealthCounts, HealthState, LinkHealth, LinkProber},
+        short_url::ShortUrl,
+    },
+    clock::{SharedClock, SystemClock},
+    error::AppError,
+};
+
+#[mockall::automock]
+#[async_trait]
+pub trait CheckLinkHealthRepository {
+    /// Every live link.
+    async fn links_to_check(&self) -> Result<Vec<ShortUrl>, AppError>;
+
+    /// Replaces the last check of a link. Links deleted meanwhile are
+    /// skipped.
+    async fn record_health(&self, id: &str, health: LinkHealth) -> Result<(), AppError>;
+}
+
+#[derive(Clone, Debug)]
+pub struct HealthCheckPolicy {
+    /// Hosts checked at once, hence requests in flight.
+    pub concurrency: usize,
+    /// Pause between two requests to the same host.
+    pub host_delay: Duration,
+}
+
+impl Default for HealthCheckPolicy {
+    fn default() -> Self {
+        Self {
+            concurrency: 8,
+            host_delay: Duration::from_secs(1),
+        }
+    }
+}
+
+/// Requests the destination of every active link and stores what came
+/// back. Disabled and quarantined links are left alone, and so are
+/// templated targets, which only a visitor can fill.
+pub struct CheckLinkHealthCommand<R, P>
+where
+    R: CheckLinkHealthRepository,
+    P: LinkProber,
+{
+    repo: R,
+    prober: P,
+    policy: HealthCheckPolicy,
+    clock: SharedClock,
+}
+
+impl<R, P> CheckLinkHealthCommand<R, P>
+where
+    R: CheckLinkHealthRepository,
+    P: LinkProber,
+{
+    pub fn new(repo: R, prober: P, policy: HealthCheckPolicy) -> Self {
+        Self {
+            repo,
+            prober,
+            policy,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Where checks take their timestamps from.
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Checks each link once. Hosts are checked side by side, the links of
+    /// one host one after the other, so no site gets more than a request
+    /// per `host_delay`.
+    pub async fn execute(&self) -> Result<HealthCounts, AppError> {
+        let mut by_host: BTreeMap<String, Vec<(String, String)>> = BTreeMap::new();
+        for link in self.repo.links_to_check().await? {
+            if !link.status.is_active() || link.url.contains('{') {
+                continue;
+            }
+            let Some(host) = url::Url::parse(&link.url)
+                .ok()
+                .and_then(|url| url.host_str().map(str::to_owned))
+            else {
+                continue;
+            };
+            by_host.entry(host).or_default().push((link.id, link.url));
+        }
+
+        let checked: Vec<Result<Vec<HealthState>, AppError>> = stream::iter(by_host.into_values())
+            .map(|links| self.check_host(links))
+            .buffer_unordered(self.policy.concurrency.max(1))
+            .collect()
+            .await;
+
+        let mut counts = HealthCounts::default();
+        for states in checked {
+            states?.into_iter().for_each(|state| counts.add(state));
+        }
+
+        Ok(counts)
+    }
+
+    async fn check_host(&self, links: Vec<(String, String)>) -> Result<Vec<HealthState>, AppError> {
+        let mut states = Vec::with_capacity(links.len());
+        for (i, (id, url)) in links.into_iter().enumerate() {
+            if i > 0 {
+                tokio::time::sleep(self.policy.host_delay).await;
+            }
+
+            let probe = self.prober.probe(&url).await;
+            let health = LinkHealth::new(url, probe, self.clock.now());
+            if health.state != HealthState::Healthy {
+                tracing::warn!(
+                    id,
+                    url = health.url,
+                    status = health.status,
+                    error = health.error,
+                    "link destination is {:?}",
+                    health.state
+                );
+            }
+            states.push(health.state);
+            self.repo.record_health(&id, health).await?;
+        }
+
+        Ok(states)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use dashmap::DashMap;
+    use tokio::time::Instant;
+
+    use crate::{
+        adapters::inmemory::InMemoryRepository,
+        app::{
+            health::{MockLinkProber, Probe},
+            query::get_link_health::GetLinkHealthRepository,
+            short_url::LinkStatus,
+        },
+    };
+
+    use super::*;
+
+    fn answering(status: u16) -> Probe {
+        Probe {
+            status: Some(status),
+            latency: Duration::from_millis(20),
+            ..Probe::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn records_the_state_of_active_links() {
+        // Given
+        let store = Arc::new(DashMap::new());
+        let mut disabled = ShortUrl::new("off".to_owned(), "https://c.example/".to_owned());
+        disabled.status = LinkStatus::Disabled;
+        for link in [
+            ShortUrl::new("ok".to_owned(), "https://a.example/".to_owned()),
+            ShortUrl::new("gone".to_owned(), "https://b.example/gone".to_owned()),
+            ShortUrl::new("tpl".to_owned(), "https://d.example/{path}".to_owned()),
+            disabled,
+        ] {
+            store.insert(link.id.clone(), link);
+        }
+        let repo = InMemoryRepository::new(store);
+        let mut prober = MockLinkProber::new();
+        prober
+            .expect_probe()
+            .times(2)
+            .returning(|url| answering(if url.ends_with("/gone") { 404 } else { 200 }));
+        let command =
+            CheckLinkHealthCommand::new(repo.clone(), prober, HealthCheckPolicy::default());
+
+        // When
+        let counts = command.execute().await.unwrap();
+
+        // Then
+        assert_eq!(
+            counts,
+            HealthCounts {
+                healthy: 1,
+                broken: 1,
+                unreachable: 0
+            }
+        );
+        let gone = repo.link_health("gone").await.unwrap().unwrap();
+        assert_eq!(gone.state, HealthState::Broken);
+        assert_eq!(gone.status, Some(404));
+        assert_eq!(gone.latency_ms, 20);
+        assert_eq!(repo.link_health("off").await.unwrap(), None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn paces_requests_to_the_same_host() {
+        // Given
+        let store = Arc::new(DashMap::new());
+        for (id, url) in [
+            ("a1", "https://a.example/1"),
+            ("a2", "https://a.example/2"),
+            ("a3", "https://a.example/3"),
+            ("b1", "https://b.example/1"),
+        ] {
+            store.insert(id.to_owned(), ShortUrl::new(id.to_owned(), url.to_owned()));
+        }
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let mut prober = MockLinkProber::new();
+        let log = requests.clone();
+        prober.expect_probe().returning(move |url| {
+            log.lock().unwrap().push((url.to_owned(), Instant::now()));
+            answering(200)
+        });
+        let policy = HealthCheckPolicy {
+            concurrency: 4,
+            host_delay: Duration::from_secs(2),
+        };
+        let command = CheckLinkHealthCommand::new(InMemoryRepository::new(store), prober, policy);
+        let start = Instant::now();
+
+        // When
+        command.execute().await.unwrap();
+
+        // Then
+        let requests = requests.lock().unwrap();
+        let at = |host: &str| -> Vec<Duration> {
+            requests
+                .iter()
+                .filter(|(url, _)| url.contains(host))
+                .map(|(_, at)| at.duration_since(start))
+                .collect()
+        };
+        let on_a = at("a.example");
+        assert_eq!(on_a.len(), 3);
+        assert!(on_a
+            .windows(2)
+            .all(|w| w[1] - w[0] >= Duration::from_secs(2)));
+        assert_eq!(at("b.example"), [Duration::ZERO]);
+    }
+}
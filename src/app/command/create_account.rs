@@ -0,0 +1,116 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use crate::{
+    app::{
+        account::{Account, ApiKey, IssuedKey, Principal},
+        policy::{self, Action, Role},
+    },
+    clock::{SharedClock, SystemClock},
+    error::AppError,
+};
+
+#[mockall::automock]
+#[async_trait]
+pub trait CreateAccountRepository {
+    /// Stores a new account together with its first key.
+    async fn save_account(&self, account: Account, key: ApiKey) -> Result<(), AppError>;
+}
+
+pub struct CreateAccountCommand<A>
+where
+    A: CreateAccountRepository,
+{
+    repo: A,
+    clock: SharedClock,
+}
+
+impl<A> CreateAccountCommand<A>
+where
+    A: CreateAccountRepository,
+{
+    pub fn new(repo: A) -> Self {
+        Self {
+            repo,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Where new accounts and their first keys take their creation time from.
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Opens an account with `role` and hands out its first API key.
+    pub async fn execute(
+        &self,
+        principal: &Principal,
+        name: &str,
+        role: Role,
+    ) -> Result<(Account, IssuedKey), AppError> {
+        policy::authorize(principal, Action::CreateAccount)?;
+
+        let created_at = self.clock.now();
+        let account = Account {
+            id: nanoid::nanoid!(),
+            name: name.to_owned(),
+            role,
+            created_at,
+        };
+        let (key, issued) = ApiKey::generate(&account.id, created_at);
+
+        self.repo.save_account(account.clone(), key).await?;
+
+        Ok((account, issued))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app::account::hash_secret;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn admins_open_accounts_with_a_first_key() {
+        // Given
+        let mut repo = MockCreateAccountRepository::new();
+        repo.expect_save_account()
+            .withf(|account, key| account.name == "alice" && key.account_id == account.id)
+            .returning(|_, _| Ok(()))
+            .times(1);
+        let command = CreateAccountCommand::new(repo);
+
+        // When
+        let (account, issued) = command
+            .execute(&Principal::operator(), "alice", Role::Editor)
+            .await
+            .unwrap();
+
+        // Then
+        assert_eq!(account.role, Role::Editor);
+        assert_eq!(issued.account_id, account.id);
+        assert_ne!(hash_secret(&issued.secret), issued.secret);
+    }
+
+    #[tokio::test]
+    async fn others_may_not_open_accounts() {
+        // Given
+        let mut repo = MockCreateAccountRepository::new();
+        repo.expect_save_account().never();
+        let command = CreateAccountCommand::new(repo);
+        let alice = Principal {
+            account_id: Some("alice".to_owned()),
+            role: Role::Editor,
+            workspaces: Vec::new(),
+        };
+
+        // When
+        let result = command.execute(&alice, "mallory", Role::Admin).await;
+
+        // Then
+        assert!(matches!(result, Err(AppError::Forbidden { .. })));
+    }
+}
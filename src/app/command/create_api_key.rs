@@ -0,0 +1,94 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use crate::{
+    app::{
+        account::{ApiKey, IssuedKey, Principal},
+        policy::{self, Action},
+    },
+    clock::{SharedClock, SystemClock},
+    error::AppError,
+};
+
+#[mockall::automock]
+#[async_trait]
+pub trait CreateApiKeyRepository {
+    /// Adds a key to its account. Fails with `AppError::NotFound` when the
+    /// account does not exist.
+    async fn add_api_key(&self, key: ApiKey) -> Result<(), AppError>;
+}
+
+pub struct CreateApiKeyCommand<A>
+where
+    A: CreateApiKeyRepository,
+{
+    repo: A,
+    clock: SharedClock,
+}
+
+impl<A> CreateApiKeyCommand<A>
+where
+    A: CreateApiKeyRepository,
+{
+    pub fn new(repo: A) -> Self {
+        Self {
+            repo,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Where new keys take their creation time from.
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Hands out another key of `account_id`, e.g. to rotate keys without
+    /// downtime.
+    pub async fn execute(
+        &self,
+        principal: &Principal,
+        account_id: &str,
+    ) -> Result<IssuedKey, AppError> {
+        policy::authorize(principal, Action::ManageKeys { account_id })?;
+
+        let created_at = self.clock.now();
+        let (key, issued) = ApiKey::generate(account_id, created_at);
+
+        self.repo.add_api_key(key).await?;
+
+        Ok(issued)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app::policy::Role;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn accounts_create_only_their_own_keys() {
+        // Given
+        let mut repo = MockCreateApiKeyRepository::new();
+        repo.expect_add_api_key()
+            .withf(|key| key.account_id == "alice")
+            .returning(|_| Ok(()))
+            .times(1);
+        let command = CreateApiKeyCommand::new(repo);
+        let alice = Principal {
+            account_id: Some("alice".to_owned()),
+            role: Role::Editor,
+            workspaces: Vec::new(),
+        };
+
+        // When
+        let own = command.execute(&alice, "alice").await;
+        let other = command.execute(&alice, "bob").await;
+
+        // Then
+        assert_eq!(own.unwrap().account_id, "alice");
+        assert!(matches!(other, Err(AppError::Forbidden { .. })));
+    }
+}
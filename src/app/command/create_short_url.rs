@@ -1,37 +1,230 @@
+use std::sync::Arc;
+use std::time::Duration;
+
 use async_trait::async_trait;
 
-use crate::{error::AppError, id_provider::IDProvider};
+use crate::{
+    app::{
+        account::Principal,
+        event::{DomainEvent, EventBus, EventKind},
+        policy::{self, Action},
+        redirect_rule::{self, RedirectRule},
+        screening::{self, SharedScreener},
+        short_url::{OpenGraph, ShortUrl},
+        target::{self, QueryPassthrough},
+        variant::{self, Variant},
+        workspace::{self, Quota, Usage},
+    },
+    clock::{SharedClock, SystemClock},
+    error::AppError,
+    id_provider::{IDProvider, IdRequest},
+};
+
+/// How many freshly generated IDs to try before giving up on collisions.
+const MAX_ID_ATTEMPTS: usize = 3;
 
 #[mockall::automock]
 #[async_trait]
 pub trait CreateShortUrlRepository {
-    async fn save(&self, full_url: String, id: String) -> Result<(), AppError>;
+    /// Stores a new link. Fails with `AppError::IdTaken` instead of
+    /// replacing a link that already uses the ID.
+    async fn save(&self, link: ShortUrl) -> Result<(), AppError>;
+
+    /// The link stored under `id`, expired or not.
+    async fn find(&self, id: &str) -> Result<Option<ShortUrl>, AppError>;
+
+    /// The live links of `workspace` and their visits in `month`.
+    async fn workspace_usage(&self, workspace: &str, month: &str) -> Result<Usage, AppError>;
+}
+
+#[mockall::automock]
+#[async_trait]
+pub trait WorkspaceQuotaRepository {
+    /// The quota of `workspace`. Fails with `AppError::NotFound` when there
+    /// is no such workspace.
+    async fn quota(&self, workspace: &str) -> Result<Quota, AppError>;
 }
 
-pub struct CreateShortUrlCommand<I, R>
+#[derive(Clone, Debug, Default)]
+pub struct CreateOptions {
+    /// The link stops resolving once this has passed.
+    pub ttl: Option<Duration>,
+    /// Scopes hash derived IDs: the same URL gets the same ID within a
+    /// namespace and different ones across namespaces.
+    pub namespace: Option<String>,
+    /// Show the preview page on every visit instead of redirecting.
+    pub always_preview: bool,
+    pub open_graph: OpenGraph,
+    /// Redirect rules, checked in order.
+    pub rules: Vec<RedirectRule>,
+    /// Weighted targets for an A/B split.
+    pub variants: Vec<Variant>,
+    pub query_passthrough: QueryPassthrough,
+    /// The workspace to own the link jointly; the creator must be a member.
+    pub workspace: Option<String>,
+    /// Unix time the link goes live at; before that it is not found.
+    pub activates_at: Option<u64>,
+    /// Show early visitors a coming soon page instead of a 404.
+    pub coming_soon_page: bool,
+}
+
+pub struct CreateShortUrlCommand<I, R, S>
 where
     I: IDProvider,
     R: CreateShortUrlRepository,
+    S: WorkspaceQuotaRepository,
 {
     id_provider: I,
     repo: R,
+    quotas: S,
+    events: EventBus,
+    screener: Option<SharedScreener>,
+    clock: SharedClock,
 }
 
-impl<I, R> CreateShortUrlCommand<I, R>
+impl<I, R, S> CreateShortUrlCommand<I, R, S>
 where
     I: IDProvider,
     R: CreateShortUrlRepository,
+    S: WorkspaceQuotaRepository,
 {
-    pub fn new(id_provider: I, repo: R) -> Self {
-        Self { id_provider, repo }
+    pub fn new(id_provider: I, repo: R, quotas: S, events: EventBus) -> Self {
+        Self {
+            id_provider,
+            repo,
+            quotas,
+            events,
+            screener: None,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Where new links take their creation time from, which their TTL and
+    /// schedule count against.
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
     }
 
+    /// Quarantines new links whose destinations `screener` flags.
+    pub fn with_screener(mut self, screener: SharedScreener) -> Self {
+        self.screener = Some(screener);
+        self
+    }
+
+    /// Creates a link without an owner, as the operator.
     pub async fn execute(&self, full_url: &str) -> Result<String, AppError> {
-        let parsed_url = url::Url::parse(full_url).map_err(|_| AppError::URLParseError)?;
+        self.execute_with(&Principal::operator(), full_url, CreateOptions::default())
+            .await
+    }
+
+    /// Like `execute`, with per-link options, for a link owned by
+    /// `principal`. `full_url` may be a template such as
+    /// `https://shop.example/{path}?ref={ref}`.
+    ///
+    /// When the generated ID already holds a live link of the same owner to
+    /// the same URL, that link's ID is returned and nothing is stored. With
+    /// hash derived IDs this makes creation idempotent.
+    ///
+    /// Links of a workspace that reached its quota are refused with
+    /// `AppError::QuotaExceeded`. Concurrent requests may each pass the
+    /// check, so the limit can be overshot by a few links.
+    ///
+    /// Links to listed phishing or malware sites are still created, but
+    /// quarantined.
+    pub async fn execute_with(
+        &self,
+        principal: &Principal,
+        full_url: &str,
+        options: CreateOptions,
+    ) -> Result<String, AppError> {
+        policy::authorize(
+            principal,
+            Action::CreateLink {
+                workspace: options.workspace.as_deref(),
+            },
+        )?;
+        let url = target::validate(full_url).map_err(|err| AppError::invalid_url(full_url, err))?;
+        options.open_graph.validate()?;
+        let rules = redirect_rule::validate(options.rules)?;
+        let variants = variant::validate(options.variants)?;
+        if let Some(workspace) = &options.workspace {
+            let quota = self.quotas.quota(workspace).await?;
+            let usage = self
+                .repo
+                .workspace_usage(workspace, &workspace::month_of(self.clock.now()))
+                .await?;
+            quota.check(workspace, &usage)?;
+        }
 
-        let id = self.id_provider.provide();
+        let mut request = IdRequest {
+            url,
+            namespace: options.namespace,
+            attempt: 0,
+        };
+        let now = self.clock.now();
+        let (id, quarantine) = loop {
+            let id = self.id_provider.provide(&request).await?;
+            let mut link = ShortUrl::new(id.clone(), request.url.clone());
+            link.created_at = now;
+            if let Some(ttl) = options.ttl {
+                link = link.with_ttl(ttl);
+            }
+            link.activates_at = options.activates_at;
+            link.coming_soon_page = options.coming_soon_page;
+            link.validate_schedule()?;
+            link.always_preview = options.always_preview;
+            link.open_graph = options.open_graph.clone();
+            link.rules = rules.clone();
+            link.variants = variants.clone();
+            link.query_passthrough = options.query_passthrough;
+            link.owner = principal.owner();
+            link.workspace = options.workspace.clone();
+            let quarantine = self
+                .screener
+                .as_deref()
+                .and_then(|screener| screening::screen_link(screener, &link));
+            if let Some(reason) = &quarantine {
+                link.quarantine(reason.clone());
+            }
 
-        self.repo.save(parsed_url.to_string(), id.clone()).await?;
+            match self.repo.save(link).await {
+                Ok(()) => break (id, quarantine),
+                Err(AppError::IdTaken { .. }) => {
+                    if let Some(existing) = self.repo.find(&id).await? {
+                        if existing.url == request.url
+                            && existing.owner == principal.owner()
+                            && existing.workspace == options.workspace
+                            && !existing.is_expired(now)
+                        {
+                            return Ok(id);
+                        }
+                    }
+
+                    request.attempt += 1;
+                    if request.attempt == MAX_ID_ATTEMPTS {
+                        return Err(AppError::IdGenerationFailed);
+                    }
+                }
+                Err(err) => return Err(err),
+            }
+        };
+
+        self.events.publish(DomainEvent::new(
+            EventKind::LinkCreated,
+            id.clone(),
+            request.url.clone(),
+            now,
+        ));
+        if let Some(reason) = quarantine {
+            tracing::warn!(id, reason, "quarantined new link");
+            self.events.publish(DomainEvent::new(
+                EventKind::LinkQuarantined,
+                id.clone(),
+                request.url,
+                now,
+            ));
+        }
 
         Ok(id)
     }
@@ -44,7 +237,13 @@ mod tests {
 
     use dashmap::DashMap;
 
-    use crate::{adapters::inmemory::InMemoryRepository, id_provider::MockIDProvider};
+    use crate::{
+        adapters::inmemory::InMemoryRepository,
+        clock::FakeClock,
+        id_provider::{alphabet::Alphabet, hash::HashIDProvider, MockIDProvider},
+    };
+
+    use crate::app::{policy::Role, short_url::LinkStatus};
 
     use super::*;
 
@@ -54,13 +253,18 @@ mod tests {
         let mut stub_id_provider = MockIDProvider::new();
         stub_id_provider
             .expect_provide()
-            .returning(|| "123".to_owned())
+            .returning(|_| Ok("123".to_owned()))
             .times(1);
 
         let mut mock_repo = MockCreateShortUrlRepository::new();
-        mock_repo.expect_save().returning(|_, _| Ok(())).times(1);
+        mock_repo.expect_save().returning(|_| Ok(())).times(1);
 
-        let sut = CreateShortUrlCommand::new(stub_id_provider, mock_repo);
+        let sut = CreateShortUrlCommand::new(
+            stub_id_provider,
+            mock_repo,
+            MockWorkspaceQuotaRepository::new(),
+            EventBus::new(),
+        );
 
         // When
         let result = sut.execute("https://www.google.com").await;
@@ -75,7 +279,12 @@ mod tests {
         let id_provider = crate::id_provider::FakeIDProvider::new("123".to_owned());
         let store = Arc::new(DashMap::new());
         let repo = InMemoryRepository::new(store);
-        let command = CreateShortUrlCommand::new(id_provider, repo);
+        let command = CreateShortUrlCommand::new(
+            id_provider,
+            repo,
+            MockWorkspaceQuotaRepository::new(),
+            EventBus::new(),
+        );
 
         // When
         let result = command.execute("https://www.google.com").await;
@@ -87,10 +296,15 @@ mod tests {
     #[tokio::test]
     async fn get_two_different_short_url() {
         // Given
-        let idp = crate::id_provider::NanoIDProvider;
+        let idp = crate::id_provider::NanoIDProvider::default();
         let store = Arc::new(DashMap::new());
         let repo = InMemoryRepository::new(store);
-        let command = CreateShortUrlCommand::new(idp, repo);
+        let command = CreateShortUrlCommand::new(
+            idp,
+            repo,
+            MockWorkspaceQuotaRepository::new(),
+            EventBus::new(),
+        );
 
         // When
         let result = command.execute("https://www.google.com").await;
@@ -104,33 +318,394 @@ mod tests {
     #[tokio::test]
     async fn after_save_store_should_have_one_item() {
         // Given
-        let idp = crate::id_provider::NanoIDProvider;
+        let idp = crate::id_provider::NanoIDProvider::default();
         let store = Arc::new(DashMap::new());
         let repo = InMemoryRepository::new(store.clone());
-        let command = CreateShortUrlCommand::new(idp, repo);
+        let command = CreateShortUrlCommand::new(
+            idp,
+            repo,
+            MockWorkspaceQuotaRepository::new(),
+            EventBus::new(),
+        );
 
         // When
         let id = command.execute("https://www.google.com").await.unwrap();
 
         // Then
         assert_eq!(store.len(), 1);
-        let full_url = store.get(&id).unwrap();
-        assert_eq!(full_url.value(), "https://www.google.com/");
+        let link = store.get(&id).unwrap();
+        assert_eq!(link.url, "https://www.google.com/");
     }
 
     #[tokio::test]
     async fn test_for_invalid_url() {
         // Given
-        let idp = crate::id_provider::NanoIDProvider;
+        let idp = crate::id_provider::NanoIDProvider::default();
         let store = Arc::new(DashMap::new());
         let repo = InMemoryRepository::new(store);
-        let command = CreateShortUrlCommand::new(idp, repo);
+        let command = CreateShortUrlCommand::new(
+            idp,
+            repo,
+            MockWorkspaceQuotaRepository::new(),
+            EventBus::new(),
+        );
 
         // When
         let result = command.execute("google").await;
 
         // Then
-        assert!(result.is_err());
-        assert_eq!(result, Err(AppError::URLParseError));
+        assert!(matches!(result, Err(AppError::InvalidUrl { url, .. }) if url == "google"));
+    }
+
+    #[tokio::test]
+    async fn retries_with_a_new_id_on_collision() {
+        // Given
+        let mut ids = vec!["taken".to_owned(), "free".to_owned()].into_iter();
+        let mut stub_id_provider = MockIDProvider::new();
+        stub_id_provider
+            .expect_provide()
+            .returning(move |_| Ok(ids.next().unwrap()))
+            .times(2);
+
+        let store = Arc::new(DashMap::new());
+        store.insert(
+            "taken".to_owned(),
+            ShortUrl::new("taken".to_owned(), "https://old.com/".to_owned()),
+        );
+        let repo = InMemoryRepository::new(store.clone());
+        let command = CreateShortUrlCommand::new(
+            stub_id_provider,
+            repo,
+            MockWorkspaceQuotaRepository::new(),
+            EventBus::new(),
+        );
+
+        // When
+        let result = command.execute("https://www.google.com").await;
+
+        // Then
+        assert_eq!(result, Ok("free".to_owned()));
+        assert_eq!(store.get("taken").unwrap().url, "https://old.com/");
+    }
+
+    #[tokio::test]
+    async fn gives_up_when_ids_keep_colliding() {
+        // Given
+        let id_provider = crate::id_provider::FakeIDProvider::new("taken".to_owned());
+        let mut mock_repo = MockCreateShortUrlRepository::new();
+        mock_repo
+            .expect_save()
+            .returning(|link| Err(AppError::IdTaken { id: link.id }))
+            .times(MAX_ID_ATTEMPTS);
+        mock_repo
+            .expect_find()
+            .returning(|id| {
+                Ok(Some(ShortUrl::new(
+                    id.to_owned(),
+                    "https://old.com/".to_owned(),
+                )))
+            })
+            .times(MAX_ID_ATTEMPTS);
+        let command = CreateShortUrlCommand::new(
+            id_provider,
+            mock_repo,
+            MockWorkspaceQuotaRepository::new(),
+            EventBus::new(),
+        );
+
+        // When
+        let result = command.execute("https://www.google.com").await;
+
+        // Then
+        assert_eq!(result, Err(AppError::IdGenerationFailed));
+    }
+
+    #[tokio::test]
+    async fn ttl_sets_expiry() {
+        // Given
+        let id_provider = crate::id_provider::FakeIDProvider::new("123".to_owned());
+        let store = Arc::new(DashMap::new());
+        let repo = InMemoryRepository::new(store.clone());
+        let command = CreateShortUrlCommand::new(
+            id_provider,
+            repo,
+            MockWorkspaceQuotaRepository::new(),
+            EventBus::new(),
+        )
+        .with_clock(Arc::new(FakeClock::new(1_000)));
+
+        // When
+        command
+            .execute_with(
+                &Principal::operator(),
+                "https://www.google.com",
+                CreateOptions {
+                    ttl: Some(Duration::from_secs(60)),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+
+        // Then
+        let link = store.get("123").unwrap();
+        assert_eq!(link.created_at, 1_000);
+        assert_eq!(link.expires_at, Some(1_060));
+        assert!(!link.is_expired(1_059));
+        assert!(link.is_expired(1_060));
+    }
+
+    #[tokio::test]
+    async fn same_url_and_namespace_returns_the_same_link() {
+        // Given
+        let id_provider = HashIDProvider::new("secret", Alphabet::base62(), 7);
+        let store = Arc::new(DashMap::new());
+        let repo = InMemoryRepository::new(store.clone());
+        let events = EventBus::new();
+        let mut rx = events.subscribe();
+        let command = CreateShortUrlCommand::new(
+            id_provider,
+            repo,
+            MockWorkspaceQuotaRepository::new(),
+            events,
+        );
+        let options = CreateOptions {
+            namespace: Some("ci".to_owned()),
+            ..Default::default()
+        };
+        let alice = Principal {
+            account_id: Some("alice".to_owned()),
+            role: Role::Editor,
+            workspaces: Vec::new(),
+        };
+
+        // When
+        let first = command
+            .execute_with(&alice, "https://www.google.com", options.clone())
+            .await;
+        let second = command
+            .execute_with(&alice, "https://www.google.com", options)
+            .await;
+
+        // Then
+        assert_eq!(first, second);
+        assert_eq!(store.len(), 1);
+        let link = store.get(&first.unwrap()).unwrap().clone();
+        assert_eq!(link.owner.as_deref(), Some("alice"));
+        rx.recv().await.unwrap();
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn same_url_of_another_owner_gets_its_own_link() {
+        // Given
+        let id_provider = HashIDProvider::new("secret", Alphabet::base62(), 7);
+        let store = Arc::new(DashMap::new());
+        let repo = InMemoryRepository::new(store.clone());
+        let command = CreateShortUrlCommand::new(
+            id_provider,
+            repo,
+            MockWorkspaceQuotaRepository::new(),
+            EventBus::new(),
+        );
+        let alice = Principal {
+            account_id: Some("alice".to_owned()),
+            role: Role::Editor,
+            workspaces: Vec::new(),
+        };
+
+        // When
+        let first = command.execute("https://www.google.com").await.unwrap();
+        let second = command
+            .execute_with(&alice, "https://www.google.com", CreateOptions::default())
+            .await
+            .unwrap();
+
+        // Then
+        assert_ne!(first, second);
+        assert_eq!(store.get(&first).unwrap().owner, None);
+        assert_eq!(store.get(&second).unwrap().owner.as_deref(), Some("alice"));
+    }
+
+    #[tokio::test]
+    async fn hash_collision_extends_the_id() {
+        // Given
+        let id_provider = HashIDProvider::new("secret", Alphabet::base62(), 7);
+        let url = "https://www.google.com/";
+        let taken = id_provider
+            .provide(&IdRequest {
+                url: url.to_owned(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        let store = Arc::new(DashMap::new());
+        store.insert(
+            taken.clone(),
+            ShortUrl::new(taken.clone(), "https://other.com/".to_owned()),
+        );
+        let command = CreateShortUrlCommand::new(
+            id_provider,
+            InMemoryRepository::new(store.clone()),
+            MockWorkspaceQuotaRepository::new(),
+            EventBus::new(),
+        );
+
+        // When
+        let id = command.execute(url).await.unwrap();
+
+        // Then
+        assert_eq!(id.len(), 8);
+        assert!(id.starts_with(&taken));
+        assert_eq!(store.get(&id).unwrap().url, url);
+    }
+
+    #[tokio::test]
+    async fn publishes_link_created_event() {
+        // Given
+        let id_provider = crate::id_provider::FakeIDProvider::new("123".to_owned());
+        let store = Arc::new(DashMap::new());
+        let repo = InMemoryRepository::new(store);
+        let events = EventBus::new();
+        let mut rx = events.subscribe();
+        let command = CreateShortUrlCommand::new(
+            id_provider,
+            repo,
+            MockWorkspaceQuotaRepository::new(),
+            events,
+        );
+
+        // When
+        command.execute("https://www.google.com").await.unwrap();
+
+        // Then
+        let event = rx.recv().await.unwrap();
+        assert_eq!(event.kind, EventKind::LinkCreated);
+        assert_eq!(event.data.id, "123");
+        assert_eq!(event.data.url, "https://www.google.com/");
+    }
+
+    #[tokio::test]
+    async fn workspace_links_stop_at_the_quota() {
+        // Given
+        let store = Arc::new(DashMap::new());
+        let mut quotas = MockWorkspaceQuotaRepository::new();
+        quotas.expect_quota().returning(|_| {
+            Ok(Quota {
+                max_links: Some(1),
+                ..Default::default()
+            })
+        });
+        let command = CreateShortUrlCommand::new(
+            crate::id_provider::NanoIDProvider::default(),
+            InMemoryRepository::new(store.clone()),
+            quotas,
+            EventBus::new(),
+        );
+        let alice = Principal {
+            account_id: Some("alice".to_owned()),
+            role: Role::Editor,
+            workspaces: vec!["team".to_owned()],
+        };
+        let in_team = || CreateOptions {
+            workspace: Some("team".to_owned()),
+            ..Default::default()
+        };
+
+        // When
+        let first = command
+            .execute_with(&alice, "https://a.com", in_team())
+            .await;
+        let second = command
+            .execute_with(&alice, "https://b.com", in_team())
+            .await;
+        let own = command
+            .execute_with(&alice, "https://b.com", CreateOptions::default())
+            .await;
+
+        // Then
+        let first = first.unwrap();
+        assert_eq!(
+            store.get(&first).unwrap().workspace.as_deref(),
+            Some("team")
+        );
+        assert!(matches!(
+            second,
+            Err(AppError::QuotaExceeded { workspace, .. }) if workspace == "team"
+        ));
+        assert!(own.is_ok());
+    }
+
+    #[tokio::test]
+    async fn only_members_create_workspace_links() {
+        // Given
+        let mut quotas = MockWorkspaceQuotaRepository::new();
+        quotas.expect_quota().never();
+        let command = CreateShortUrlCommand::new(
+            crate::id_provider::NanoIDProvider::default(),
+            MockCreateShortUrlRepository::new(),
+            quotas,
+            EventBus::new(),
+        );
+        let bob = Principal {
+            account_id: Some("bob".to_owned()),
+            role: Role::Editor,
+            workspaces: Vec::new(),
+        };
+
+        // When
+        let result = command
+            .execute_with(
+                &bob,
+                "https://a.com",
+                CreateOptions {
+                    workspace: Some("team".to_owned()),
+                    ..Default::default()
+                },
+            )
+            .await;
+
+        // Then
+        assert!(matches!(result, Err(AppError::Forbidden { .. })));
+    }
+
+    #[tokio::test]
+    async fn quarantines_links_to_listed_sites() {
+        // Given
+        let store = Arc::new(DashMap::new());
+        let events = EventBus::new();
+        let mut received = events.subscribe();
+        let mut screener = crate::app::screening::MockUrlScreener::new();
+        screener
+            .expect_screen()
+            .returning(|url| url.contains("phish").then(|| "phishing".to_owned()));
+        let command = CreateShortUrlCommand::new(
+            crate::id_provider::NanoIDProvider::default(),
+            InMemoryRepository::new(store.clone()),
+            MockWorkspaceQuotaRepository::new(),
+            events,
+        )
+        .with_screener(Arc::new(screener));
+
+        // When
+        let clean = command.execute("https://example.com").await.unwrap();
+        let listed = command.execute("https://phish.example").await.unwrap();
+
+        // Then
+        assert_eq!(store.get(&clean).unwrap().status, LinkStatus::Active);
+        let listed = store.get(&listed).unwrap();
+        assert_eq!(listed.status, LinkStatus::Quarantined);
+        assert_eq!(listed.status_reason.as_deref(), Some("phishing"));
+        let kinds: Vec<EventKind> = std::iter::from_fn(|| received.try_recv().ok())
+            .map(|event| event.kind)
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                EventKind::LinkCreated,
+                EventKind::LinkCreated,
+                EventKind::LinkQuarantined
+            ]
+        );
     }
 }
@@ -0,0 +1,127 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use crate::{
+    app::{
+        account::Principal,
+        event::EventKind,
+        policy::{self, Action},
+        webhook::WebhookSubscription,
+    },
+    clock::{SharedClock, SystemClock},
+    error::AppError,
+};
+
+#[mockall::automock]
+#[async_trait]
+pub trait CreateWebhookRepository {
+    async fn save_subscription(&self, subscription: WebhookSubscription) -> Result<(), AppError>;
+}
+
+pub struct CreateWebhookCommand<W>
+where
+    W: CreateWebhookRepository,
+{
+    repo: W,
+    clock: SharedClock,
+}
+
+impl<W> CreateWebhookCommand<W>
+where
+    W: CreateWebhookRepository,
+{
+    pub fn new(repo: W) -> Self {
+        Self {
+            repo,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Where new subscriptions take their creation time from.
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Registers a new subscription. A random secret is generated when the
+    /// caller does not provide one.
+    pub async fn execute(
+        &self,
+        principal: &Principal,
+        url: &str,
+        events: Vec<EventKind>,
+        secret: Option<String>,
+    ) -> Result<WebhookSubscription, AppError> {
+        policy::authorize(principal, Action::ManageWebhooks)?;
+        let parsed_url = url::Url::parse(url).map_err(|err| AppError::invalid_url(url, err))?;
+        if !matches!(parsed_url.scheme(), "http" | "https") {
+            return Err(AppError::invalid_url(
+                url,
+                "only http and https are supported",
+            ));
+        }
+
+        let created_at = self.clock.now();
+
+        let subscription = WebhookSubscription {
+            id: nanoid::nanoid!(),
+            url: parsed_url.to_string(),
+            secret: secret.unwrap_or_else(|| nanoid::nanoid!(32)),
+            events,
+            created_at,
+        };
+
+        self.repo.save_subscription(subscription.clone()).await?;
+
+        Ok(subscription)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn create_subscription_with_generated_secret() {
+        // Given
+        let mut mock_repo = MockCreateWebhookRepository::new();
+        mock_repo
+            .expect_save_subscription()
+            .returning(|_| Ok(()))
+            .times(1);
+        let command = CreateWebhookCommand::new(mock_repo);
+
+        // When
+        let result = command
+            .execute(
+                &Principal::operator(),
+                "https://example.com/hook",
+                vec![EventKind::LinkCreated],
+                None,
+            )
+            .await
+            .unwrap();
+
+        // Then
+        assert_eq!(result.url, "https://example.com/hook");
+        assert_eq!(result.events, vec![EventKind::LinkCreated]);
+        assert_eq!(result.secret.len(), 32);
+    }
+
+    #[tokio::test]
+    async fn reject_non_http_url() {
+        // Given
+        let mut mock_repo = MockCreateWebhookRepository::new();
+        mock_repo.expect_save_subscription().times(0);
+        let command = CreateWebhookCommand::new(mock_repo);
+
+        // When
+        let result = command
+            .execute(&Principal::operator(), "ftp://example.com", vec![], None)
+            .await;
+
+        // Then
+        assert!(matches!(result, Err(AppError::InvalidUrl { .. })));
+    }
+}
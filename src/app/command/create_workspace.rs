@@ -0,0 +1,122 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use crate::{
+    app::{
+        account::Principal,
+        policy::{self, Action},
+        workspace::{self, Quota, Workspace},
+    },
+    clock::{SharedClock, SystemClock},
+    error::AppError,
+};
+
+#[mockall::automock]
+#[async_trait]
+pub trait CreateWorkspaceRepository {
+    /// Stores a new workspace. Fails with `AppError::DomainTaken` when
+    /// another workspace serves one of its domains.
+    async fn save_workspace(&self, workspace: Workspace) -> Result<(), AppError>;
+}
+
+pub struct CreateWorkspaceCommand<A>
+where
+    A: CreateWorkspaceRepository,
+{
+    repo: A,
+    clock: SharedClock,
+}
+
+impl<A> CreateWorkspaceCommand<A>
+where
+    A: CreateWorkspaceRepository,
+{
+    pub fn new(repo: A) -> Self {
+        Self {
+            repo,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Where new workspaces take their creation time from.
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Opens a workspace without members.
+    pub async fn execute(
+        &self,
+        principal: &Principal,
+        name: &str,
+        domains: Vec<String>,
+        quota: Quota,
+    ) -> Result<Workspace, AppError> {
+        policy::authorize(principal, Action::ManageWorkspaces)?;
+
+        let created_at = self.clock.now();
+        let workspace = Workspace {
+            id: nanoid::nanoid!(),
+            name: name.to_owned(),
+            members: Vec::new(),
+            domains: normalize_domains(domains)?,
+            quota,
+            created_at,
+        };
+
+        self.repo.save_workspace(workspace.clone()).await?;
+
+        Ok(workspace)
+    }
+}
+
+/// `domains` normalized, sorted and without duplicates.
+pub(crate) fn normalize_domains(domains: Vec<String>) -> Result<Vec<String>, AppError> {
+    let mut domains = domains
+        .iter()
+        .map(|domain| workspace::normalize_domain(domain))
+        .collect::<Result<Vec<_>, _>>()?;
+    domains.sort();
+    domains.dedup();
+
+    Ok(domains)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app::policy::Role;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn admins_open_workspaces_with_normalized_domains() {
+        // Given
+        let mut repo = MockCreateWorkspaceRepository::new();
+        repo.expect_save_workspace()
+            .withf(|workspace| workspace.domains == ["go.example.com"])
+            .returning(|_| Ok(()))
+            .times(1);
+        let command = CreateWorkspaceCommand::new(repo);
+        let alice = Principal {
+            account_id: Some("alice".to_owned()),
+            role: Role::Editor,
+            workspaces: Vec::new(),
+        };
+        let domains = || vec!["Go.Example.com".to_owned(), "go.example.com.".to_owned()];
+
+        // When
+        let created = command
+            .execute(&Principal::operator(), "Team", domains(), Quota::default())
+            .await;
+        let denied = command
+            .execute(&alice, "Team", domains(), Quota::default())
+            .await;
+
+        // Then
+        let created = created.unwrap();
+        assert_eq!(created.name, "Team");
+        assert!(created.members.is_empty());
+        assert!(matches!(denied, Err(AppError::Forbidden { .. })));
+    }
+}
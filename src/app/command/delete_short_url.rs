@@ -0,0 +1,155 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use crate::{
+    app::{
+        account::Principal,
+        event::{DomainEvent, EventBus, EventKind},
+        policy::{self, Action},
+        short_url::Ownership,
+    },
+    clock::{SharedClock, SystemClock},
+    error::AppError,
+};
+
+#[mockall::automock]
+#[async_trait]
+pub trait DeleteShortUrlRepository {
+    /// Whom the link stored under `id` belongs to. Fails with
+    /// `AppError::NotFound` when there is none.
+    async fn ownership(&self, id: &str) -> Result<Ownership, AppError>;
+
+    /// Removes the link and returns the URL it pointed to.
+    async fn delete(&self, id: &str) -> Result<String, AppError>;
+}
+
+pub struct DeleteShortUrlCommand<R>
+where
+    R: DeleteShortUrlRepository,
+{
+    repo: R,
+    events: EventBus,
+    clock: SharedClock,
+}
+
+impl<R> DeleteShortUrlCommand<R>
+where
+    R: DeleteShortUrlRepository,
+{
+    pub fn new(repo: R, events: EventBus) -> Self {
+        Self {
+            repo,
+            events,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Where published events take their time from.
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Deletes a link, if the policy lets `principal` edit it.
+    pub async fn execute(&self, principal: &Principal, id: &str) -> Result<(), AppError> {
+        let ownership = self.repo.ownership(id).await?;
+        policy::authorize(
+            principal,
+            Action::EditLink {
+                owner: ownership.owner.as_deref(),
+                workspace: ownership.workspace.as_deref(),
+            },
+        )?;
+        let url = self.repo.delete(id).await?;
+
+        self.events.publish(DomainEvent::new(
+            EventKind::LinkDeleted,
+            id.to_owned(),
+            url,
+            self.clock.now(),
+        ));
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use dashmap::DashMap;
+
+    use crate::{adapters::inmemory::InMemoryRepository, app::short_url::ShortUrl};
+
+    use crate::app::policy::Role;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn delete_removes_link_and_publishes_event() {
+        // Given
+        let store = Arc::new(DashMap::new());
+        store.insert(
+            "123".to_owned(),
+            ShortUrl::new("123".to_owned(), "https://www.google.com/".to_owned()),
+        );
+        let repo = InMemoryRepository::new(store.clone());
+        let events = EventBus::new();
+        let mut rx = events.subscribe();
+        let command = DeleteShortUrlCommand::new(repo, events);
+
+        // When
+        let result = command.execute(&Principal::operator(), "123").await;
+
+        // Then
+        assert_eq!(result, Ok(()));
+        assert!(store.is_empty());
+        let event = rx.recv().await.unwrap();
+        assert_eq!(event.kind, EventKind::LinkDeleted);
+        assert_eq!(event.data.url, "https://www.google.com/");
+    }
+
+    #[tokio::test]
+    async fn delete_missing_link() {
+        // Given
+        let mut mock_repo = MockDeleteShortUrlRepository::new();
+        mock_repo
+            .expect_ownership()
+            .returning(|id| Err(AppError::not_found(id)))
+            .times(1);
+        mock_repo.expect_delete().never();
+        let command = DeleteShortUrlCommand::new(mock_repo, EventBus::new());
+
+        // When
+        let result = command.execute(&Principal::operator(), "missing").await;
+
+        // Then
+        assert_eq!(result, Err(AppError::not_found("missing")));
+    }
+
+    #[tokio::test]
+    async fn others_may_not_delete() {
+        // Given
+        let mut mock_repo = MockDeleteShortUrlRepository::new();
+        mock_repo.expect_ownership().returning(|_| {
+            Ok(Ownership {
+                owner: Some("alice".to_owned()),
+                workspace: None,
+            })
+        });
+        mock_repo.expect_delete().never();
+        let command = DeleteShortUrlCommand::new(mock_repo, EventBus::new());
+        let bob = Principal {
+            account_id: Some("bob".to_owned()),
+            role: Role::Editor,
+            workspaces: Vec::new(),
+        };
+
+        // When
+        let result = command.execute(&bob, "123").await;
+
+        // Then
+        assert!(matches!(result, Err(AppError::Forbidden { .. })));
+    }
+}
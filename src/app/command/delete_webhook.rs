@@ -0,0 +1,58 @@
+use async_trait::async_trait;
+
+use crate::{
+    app::{
+        account::Principal,
+        policy::{self, Action},
+    },
+    error::AppError,
+};
+
+#[mockall::automock]
+#[async_trait]
+pub trait DeleteWebhookRepository {
+    async fn delete_subscription(&self, id: &str) -> Result<(), AppError>;
+}
+
+pub struct DeleteWebhookCommand<W>
+where
+    W: DeleteWebhookRepository,
+{
+    repo: W,
+}
+
+impl<W> DeleteWebhookCommand<W>
+where
+    W: DeleteWebhookRepository,
+{
+    pub fn new(repo: W) -> Self {
+        Self { repo }
+    }
+
+    pub async fn execute(&self, principal: &Principal, id: &str) -> Result<(), AppError> {
+        policy::authorize(principal, Action::ManageWebhooks)?;
+        self.repo.delete_subscription(id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn delete_missing_subscription() {
+        // Given
+        let mut mock_repo = MockDeleteWebhookRepository::new();
+        mock_repo
+            .expect_delete_subscription()
+            .returning(|id| Err(AppError::not_found(id)))
+            .times(1);
+        let command = DeleteWebhookCommand::new(mock_repo);
+
+        // When
+        let result = command.execute(&Principal::operator(), "missing").await;
+
+        // Then
+        assert_eq!(result, Err(AppError::not_found("missing")));
+    }
+}
@@ -0,0 +1,373 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app::{
+        account::Principal,
+        policy::{self, Action},
+        short_url::ShortUrl,
+        target,
+    },
+    error::AppError,
+};
+
+/// Number of per-record errors kept in the report; the rest are only counted.
+const MAX_REPORTED_ERRORS: usize = 100;
+
+#[mockall::automock]
+#[async_trait]
+pub trait ImportShortUrlsRepository {
+    /// Looks up every ID in one go; the result lines up with `ids`.
+    async fn find_many(&self, ids: &[String]) -> Result<Vec<Option<ShortUrl>>, AppError>;
+    /// Stores all links, replacing existing ones with the same ID.
+    async fn upsert_many(&self, links: Vec<ShortUrl>) -> Result<(), AppError>;
+}
+
+/// What to do when an imported ID already exists with different content.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictPolicy {
+    #[default]
+    Skip,
+    Overwrite,
+    Fail,
+}
+
+impl FromStr for ConflictPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "skip" => Ok(ConflictPolicy::Skip),
+            "overwrite" => Ok(ConflictPolicy::Overwrite),
+            "fail" => Ok(ConflictPolicy::Fail),
+            other => Err(format!(
+                "unknown conflict policy `{other}`, expected skip, overwrite or fail"
+            )),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct ImportReport {
+    pub created: usize,
+    pub overwritten: usize,
+    pub skipped: usize,
+    /// Records identical to what is already stored. Re-running an import
+    /// only ever increases this counter.
+    pub unchanged: usize,
+    pub invalid: usize,
+    pub errors: Vec<String>,
+    /// Set when the `fail` policy stopped the import on this ID.
+    pub conflict: Option<String>,
+}
+
+impl ImportReport {
+    pub fn is_aborted(&self) -> bool {
+        self.conflict.is_some()
+    }
+
+    pub fn record_invalid(&mut self, error: String) {
+        self.invalid += 1;
+        if self.errors.len() < MAX_REPORTED_ERRORS {
+            self.errors.push(error);
+        }
+    }
+}
+
+/// Bulk load of links that keeps their IDs. Unlike `CreateShortUrlCommand`
+/// it does not publish domain events, so a migration does not flood
+/// webhook subscribers.
+pub struct ImportShortUrlsCommand<R>
+where
+    R: ImportShortUrlsRepository,
+{
+    repo: R,
+}
+
+impl<R> ImportShortUrlsCommand<R>
+where
+    R: ImportShortUrlsRepository,
+{
+    pub fn new(repo: R) -> Self {
+        Self { repo }
+    }
+
+    /// Imports one batch, accumulating into `report`. Callers stream batches
+    /// through the same report and stop once it is aborted.
+    ///
+    /// The batch costs one lookup and one write against the repository, so
+    /// remote stores can serve it in two round trips.
+    pub async fn execute(
+        &self,
+        principal: &Principal,
+        links: Vec<ShortUrl>,
+        policy: ConflictPolicy,
+        report: &mut ImportReport,
+    ) -> Result<(), AppError> {
+        policy::authorize(principal, Action::TransferLinks)?;
+        let mut valid = Vec::with_capacity(links.len());
+        for mut link in links {
+            match target::validate(&link.url) {
+                Ok(url) => link.url = url,
+                Err(err) => {
+                    report.record_invalid(format!("{}: invalid url: {err}", link.id));
+                    continue;
+                }
+            }
+            if link.id.is_empty() {
+                report.record_invalid(format!("{}: empty id", link.url));
+                continue;
+            }
+            valid.push(link);
+        }
+
+        let ids: Vec<String> = valid.iter().map(|link| link.id.clone()).collect();
+        let found = self.repo.find_many(&ids).await?;
+
+        // Tracks what the store will hold once `writes` are applied, so
+        // repeated IDs within a batch are judged against each other.
+        let mut current: HashMap<String, Option<ShortUrl>> = ids.into_iter().zip(found).collect();
+        let mut writes = Vec::new();
+
+        for link in valid {
+            if report.is_aborted() {
+                break;
+            }
+
+            let existing = current.get(&link.id).cloned().flatten();
+            match existing {
+                None => report.created += 1,
+                Some(existing) if existing == link => {
+                    report.unchanged += 1;
+                    continue;
+                }
+                Some(_) => match policy {
+                    ConflictPolicy::Skip => {
+                        report.skipped += 1;
+                        continue;
+                    }
+                    ConflictPolicy::Overwrite => report.overwritten += 1,
+                    ConflictPolicy::Fail => {
+                        report.conflict = Some(link.id);
+                        continue;
+                    }
+                },
+            }
+
+            current.insert(link.id.clone(), Some(link.clone()));
+            writes.push(link);
+        }
+
+        if !writes.is_empty() {
+            self.repo.upsert_many(writes).await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use dashmap::DashMap;
+
+    use crate::adapters::inmemory::InMemoryRepository;
+
+    use super::*;
+
+    fn link(id: &str, url: &str) -> ShortUrl {
+        ShortUrl {
+            created_at: 1,
+            ..ShortUrl::new(id.to_owned(), url.to_owned())
+        }
+    }
+
+    #[tokio::test]
+    async fn import_keeps_ids() {
+        // Given
+        let store = Arc::new(DashMap::new());
+        let command = ImportShortUrlsCommand::new(InMemoryRepository::new(store.clone()));
+        let mut report = ImportReport::default();
+
+        // When
+        let result = command
+            .execute(
+                &Principal::operator(),
+                vec![
+                    link("abc", "https://www.google.com"),
+                    link("def", "https://www.github.com"),
+                ],
+                ConflictPolicy::Skip,
+                &mut report,
+            )
+            .await;
+
+        // Then
+        assert_eq!(result, Ok(()));
+        assert_eq!(report.created, 2);
+        assert_eq!(store.get("abc").unwrap().url, "https://www.google.com/");
+        assert_eq!(store.get("def").unwrap().url, "https://www.github.com/");
+    }
+
+    #[tokio::test]
+    async fn reimport_is_idempotent() {
+        // Given
+        let store = Arc::new(DashMap::new());
+        let command = ImportShortUrlsCommand::new(InMemoryRepository::new(store.clone()));
+        let links = vec![link("abc", "https://www.google.com/")];
+        command
+            .execute(
+                &Principal::operator(),
+                links.clone(),
+                ConflictPolicy::Fail,
+                &mut ImportReport::default(),
+            )
+            .await
+            .unwrap();
+
+        // When
+        let mut report = ImportReport::default();
+        command
+            .execute(
+                &Principal::operator(),
+                links,
+                ConflictPolicy::Fail,
+                &mut report,
+            )
+            .await
+            .unwrap();
+
+        // Then
+        assert_eq!(report.unchanged, 1);
+        assert!(!report.is_aborted());
+        assert_eq!(store.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn conflict_policies() {
+        // Given
+        let store = Arc::new(DashMap::new());
+        store.insert("abc".to_owned(), link("abc", "https://old.com/"));
+        let command = ImportShortUrlsCommand::new(InMemoryRepository::new(store.clone()));
+        let incoming = || {
+            vec![
+                link("abc", "https://new.com/"),
+                link("xyz", "https://x.com/"),
+            ]
+        };
+
+        // When
+        let mut skipped = ImportReport::default();
+        command
+            .execute(
+                &Principal::operator(),
+                incoming(),
+                ConflictPolicy::Skip,
+                &mut skipped,
+            )
+            .await
+            .unwrap();
+        let url_after_skip = store.get("abc").unwrap().url.clone();
+
+        store.remove("xyz");
+        let mut failed = ImportReport::default();
+        command
+            .execute(
+                &Principal::operator(),
+                incoming(),
+                ConflictPolicy::Fail,
+                &mut failed,
+            )
+            .await
+            .unwrap();
+
+        let mut overwritten = ImportReport::default();
+        command
+            .execute(
+                &Principal::operator(),
+                incoming(),
+                ConflictPolicy::Overwrite,
+                &mut overwritten,
+            )
+            .await
+            .unwrap();
+
+        // Then
+        assert_eq!((skipped.skipped, skipped.created), (1, 1));
+        assert_eq!(url_after_skip, "https://old.com/");
+
+        assert_eq!(failed.conflict.as_deref(), Some("abc"));
+        assert_eq!(failed.created, 0);
+
+        assert_eq!((overwritten.overwritten, overwritten.created), (1, 1));
+        assert_eq!(store.get("abc").unwrap().url, "https://new.com/");
+    }
+
+    #[tokio::test]
+    async fn repeated_ids_within_a_batch() {
+        // Given
+        let store = Arc::new(DashMap::new());
+        let command = ImportShortUrlsCommand::new(InMemoryRepository::new(store.clone()));
+        let mut report = ImportReport::default();
+
+        // When
+        command
+            .execute(
+                &Principal::operator(),
+                vec![
+                    link("abc", "https://first.com/"),
+                    link("abc", "https://first.com/"),
+                    link("abc", "https://second.com/"),
+                ],
+                ConflictPolicy::Skip,
+                &mut report,
+            )
+            .await
+            .unwrap();
+
+        // Then
+        assert_eq!(
+            (report.created, report.unchanged, report.skipped),
+            (1, 1, 1)
+        );
+        assert_eq!(store.get("abc").unwrap().url, "https://first.com/");
+    }
+
+    #[tokio::test]
+    async fn invalid_records_are_reported_not_fatal() {
+        // Given
+        let mut mock_repo = MockImportShortUrlsRepository::new();
+        mock_repo
+            .expect_find_many()
+            .returning(|ids| Ok(vec![None; ids.len()]))
+            .times(1);
+        mock_repo
+            .expect_upsert_many()
+            .withf(|links| links.len() == 1)
+            .returning(|_| Ok(()))
+            .times(1);
+        let command = ImportShortUrlsCommand::new(mock_repo);
+        let mut report = ImportReport::default();
+
+        // When
+        command
+            .execute(
+                &Principal::operator(),
+                vec![link("bad", "not a url"), link("ok", "https://ok.com")],
+                ConflictPolicy::Skip,
+                &mut report,
+            )
+            .await
+            .unwrap();
+
+        // Then
+        assert_eq!(report.invalid, 1);
+        assert_eq!(report.created, 1);
+        assert!(report.errors[0].starts_with("bad:"));
+    }
+}
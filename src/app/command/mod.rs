@@ -1 +1,17 @@
+pub mod change_link_status;
+pub mod change_membership;
+pub mod check_link_health;
+pub mod create_account;
+pub mod create_api_key;
 pub mod create_short_url;
+pub mod create_webhook;
+pub mod create_workspace;
+pub mod delete_short_url;
+pub mod delete_webhook;
+pub mod import_short_urls;
+pub mod purge_expired_links;
+pub mod record_visit;
+pub mod report_link;
+pub mod revoke_api_key;
+pub mod update_short_url;
+pub mod update_workspace;
@@ -0,0 +1,192 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use crate::{
+    app::{
+        event::{DomainEvent, EventBus, EventKind},
+        short_url::ShortUrl,
+    },
+    clock::{SharedClock, SystemClock},
+    error::AppError,
+};
+
+#[mockall::automock]
+#[async_trait]
+pub trait PurgeExpiredLinksRepository {
+    /// Drops links whose expiry has passed and returns them.
+    async fn purge_expired(&self) -> Result<Vec<ShortUrl>, AppError>;
+}
+
+/// Reclaims space held by expired links and announces each one with a
+/// `link.expired` event. Expired links already stop resolving on their own,
+/// so the sweep only decides when the event goes out.
+pub struct PurgeExpiredLinksCommand<R>
+where
+    R: PurgeExpiredLinksRepository,
+{
+    repo: R,
+    events: EventBus,
+    clock: SharedClock,
+}
+
+impl<R> PurgeExpiredLinksCommand<R>
+where
+    R: PurgeExpiredLinksRepository,
+{
+    pub fn new(repo: R, events: EventBus) -> Self {
+        Self {
+            repo,
+            events,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Where published events take their time from.
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Returns how many links went away.
+    pub async fn execute(&self) -> Result<usize, AppError> {
+        let purged = self.repo.purge_expired().await?;
+        let now = self.clock.now();
+        for link in &purged {
+            self.events.publish(DomainEvent::new(
+                EventKind::LinkExpired,
+                link.id.clone(),
+                link.url.clone(),
+                now,
+            ));
+        }
+
+        Ok(purged.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use dashmap::DashMap;
+    use tokio::sync::mpsc;
+
+    use crate::{
+        adapters::inmemory::{webhook::InMemoryWebhookRepository, InMemoryRepository},
+        app::{
+            command::create_webhook::CreateWebhookRepository,
+            webhook::{
+                dispatcher::{MockWebhookSender, RetryPolicy, WebhookDispatcher},
+                WebhookSubscription, EVENT_HEADER,
+            },
+        },
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn removes_only_expired_links() {
+        // Given
+        let store = Arc::new(DashMap::new());
+        let mut expired = ShortUrl::new("old".to_owned(), "https://old.com/".to_owned());
+        expired.expires_at = Some(expired.created_at - 1);
+        let fresh = ShortUrl::new("new".to_owned(), "https://new.com/".to_owned())
+            .with_ttl(Duration::from_secs(3600));
+        let forever = ShortUrl::new("forever".to_owned(), "https://forever.com/".to_owned());
+        for link in [expired, fresh, forever] {
+            store.insert(link.id.clone(), link);
+        }
+        let events = EventBus::new();
+        let mut published = events.subscribe();
+        let command = PurgeExpiredLinksCommand::new(InMemoryRepository::new(store.clone()), events);
+
+        // When
+        let purged = command.execute().await;
+
+        // Then
+        assert_eq!(purged, Ok(1));
+        assert!(!store.contains_key("old"));
+        assert!(store.contains_key("new"));
+        assert!(store.contains_key("forever"));
+        let event = published.try_recv().unwrap();
+        assert_eq!(event.kind, EventKind::LinkExpired);
+        assert_eq!(event.data.id, "old");
+        assert_eq!(event.data.url, "https://old.com/");
+        assert!(published.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn replaying_the_wal_drops_only_purged_links() {
+        use crate::adapters::inmemory::{
+            wal::{WalSync, WriteAheadLog},
+            State,
+        };
+        use crate::app::command::create_short_url::CreateShortUrlRepository;
+
+        // Given
+        let dir = tempfile::tempdir().unwrap();
+        let wal_path = dir.path().join("links.wal");
+        let state = State::default();
+        let (wal, _) = WriteAheadLog::open(&wal_path, WalSync::Always, &state).unwrap();
+        let repo = InMemoryRepository::with_wal(state.links, Arc::new(wal));
+        let mut expired = ShortUrl::new("old".to_owned(), "https://old.com/".to_owned());
+        expired.expires_at = Some(expired.created_at - 1);
+        let fresh = ShortUrl::new("new".to_owned(), "https://new.com/".to_owned());
+        for link in [expired, fresh] {
+            repo.save(link).await.unwrap();
+        }
+        let command = PurgeExpiredLinksCommand::new(repo, EventBus::new());
+
+        // When
+        command.execute().await.unwrap();
+
+        // Then
+        let restored = State::default();
+        WriteAheadLog::open(&wal_path, WalSync::Always, &restored).unwrap();
+        assert!(!restored.links.contains_key("old"));
+        assert!(restored.links.contains_key("new"));
+    }
+
+    #[tokio::test]
+    async fn expired_links_reach_webhook_subscribers() {
+        // Given
+        let store = Arc::new(DashMap::new());
+        let mut expired = ShortUrl::new("old".to_owned(), "https://old.com/".to_owned());
+        expired.expires_at = Some(expired.created_at - 1);
+        store.insert(expired.id.clone(), expired);
+        let webhooks = InMemoryWebhookRepository::new();
+        webhooks
+            .save_subscription(WebhookSubscription {
+                id: "hook".to_owned(),
+                url: "https://example.com/hook".to_owned(),
+                secret: "secret".to_owned(),
+                events: vec![EventKind::LinkExpired],
+                created_at: 0,
+            })
+            .await
+            .unwrap();
+        let (delivered, mut deliveries) = mpsc::unbounded_channel();
+        let mut sender = MockWebhookSender::new();
+        sender.expect_send().returning(move |_, headers, body| {
+            delivered.send((headers, body)).unwrap();
+            Ok(204)
+        });
+        let events = EventBus::new();
+        let dispatcher = WebhookDispatcher::new(webhooks, sender, RetryPolicy::default());
+        tokio::spawn(dispatcher.run(events.subscribe()));
+        let command = PurgeExpiredLinksCommand::new(InMemoryRepository::new(store), events);
+
+        // When
+        command.execute().await.unwrap();
+
+        // Then
+        let (headers, body) = deliveries.recv().await.unwrap();
+        assert!(headers
+            .iter()
+            .any(|(name, value)| name == EVENT_HEADER && value == "link.expired"));
+        let event: DomainEvent = serde_json::from_slice(&body).unwrap();
+        assert_eq!(event.data.id, "old");
+    }
+}
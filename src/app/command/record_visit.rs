@@ -0,0 +1,87 @@
+use async_trait::async_trait;
+
+use crate::error::AppError;
+
+#[mockall::automock]
+#[async_trait]
+pub trait RecordVisitRepository {
+    /// Counts a visit of the link and, for split links, of the variant
+    /// served. Visits of links that are gone are dropped.
+    async fn record_visit(&self, id: &str, variant: Option<String>) -> Result<(), AppError>;
+}
+
+pub struct RecordVisitCommand<R>
+where
+    R: RecordVisitRepository,
+{
+    repo: R,
+}
+
+impl<R> RecordVisitCommand<R>
+where
+    R: RecordVisitRepository,
+{
+    pub fn new(repo: R) -> Self {
+        Self { repo }
+    }
+
+    pub async fn execute(&self, id: &str, variant: Option<String>) -> Result<(), AppError> {
+        self.repo.record_visit(id, variant).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use dashmap::DashMap;
+
+    use crate::{
+        adapters::inmemory::InMemoryRepository,
+        app::account::Principal,
+        app::query::get_link_stats::{GetLinkStatsQuery, LinkStats},
+        app::short_url::ShortUrl,
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn visits_are_counted_per_variant() {
+        // Given
+        let store = Arc::new(DashMap::new());
+        store.insert(
+            "123".to_owned(),
+            ShortUrl::new("123".to_owned(), "https://example.com/".to_owned()),
+        );
+        let repo = InMemoryRepository::new(store);
+        let command = RecordVisitCommand::new(repo.clone());
+
+        // When
+        for variant in ["a", "b", "a"] {
+            command
+                .execute("123", Some(variant.to_owned()))
+                .await
+                .unwrap();
+        }
+        command.execute("123", None).await.unwrap();
+        command.execute("gone", None).await.unwrap();
+
+        // Then
+        let stats = GetLinkStatsQuery::new(repo.clone())
+            .execute(&Principal::operator(), "123")
+            .await;
+        assert_eq!(
+            stats,
+            Ok(LinkStats {
+                visits: 4,
+                variants: [("a".to_owned(), 2), ("b".to_owned(), 1)].into(),
+            })
+        );
+        assert_eq!(
+            GetLinkStatsQuery::new(repo)
+                .execute(&Principal::operator(), "gone")
+                .await,
+            Err(AppError::not_found("gone"))
+        );
+    }
+}
@@ -0,0 +1,85 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use crate::{
+    app::report::{self, AbuseReport},
+    clock::{SharedClock, SystemClock},
+    error::AppError,
+};
+
+#[mockall::automock]
+#[async_trait]
+pub trait ReportLinkRepository {
+    /// Stores a report. Fails with `AppError::NotFound` unless the link it
+    /// is about exists and has not expired.
+    async fn save_report(&self, report: AbuseReport) -> Result<(), AppError>;
+}
+
+pub struct ReportLinkCommand<R>
+where
+    R: ReportLinkRepository,
+{
+    repo: R,
+    clock: SharedClock,
+}
+
+impl<R> ReportLinkCommand<R>
+where
+    R: ReportLinkRepository,
+{
+    pub fn new(repo: R) -> Self {
+        Self {
+            repo,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Where new reports take their filing time from.
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Files a report against a link. Visitors have no account, so this
+    /// needs none either; the link works on until an admin reviews it.
+    pub async fn execute(&self, link_id: &str, reason: &str) -> Result<AbuseReport, AppError> {
+        let created_at = self.clock.now();
+        let report = AbuseReport {
+            id: nanoid::nanoid!(),
+            link_id: link_id.to_owned(),
+            reason: report::validate_reason(reason)?,
+            created_at,
+            reviewed_at: None,
+            outcome: None,
+        };
+
+        self.repo.save_report(report.clone()).await?;
+
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn stores_open_reports_with_a_reason() {
+        // Given
+        let mut repo = MockReportLinkRepository::new();
+        repo.expect_save_report()
+            .withf(|report| report.link_id == "abc" && report.is_open())
+            .returning(|_| Ok(()))
+            .times(1);
+        let command = ReportLinkCommand::new(repo);
+
+        // When
+        let filed = command.execute("abc", " phishing ").await;
+        let empty = command.execute("abc", "").await;
+
+        // Then
+        assert_eq!(filed.unwrap().reason, "phishing");
+        assert!(matches!(empty, Err(AppError::InvalidReport { .. })));
+    }
+}
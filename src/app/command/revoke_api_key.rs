@@ -0,0 +1,66 @@
+use async_trait::async_trait;
+
+use crate::{
+    app::{
+        account::Principal,
+        policy::{self, Action},
+    },
+    error::AppError,
+};
+
+#[mockall::automock]
+#[async_trait]
+pub trait RevokeApiKeyRepository {
+    /// Removes the key `key_id` of `account_id`. Fails with
+    /// `AppError::NotFound` when the account has no such key.
+    async fn revoke_api_key(&self, account_id: &str, key_id: &str) -> Result<(), AppError>;
+}
+
+pub struct RevokeApiKeyCommand<A>
+where
+    A: RevokeApiKeyRepository,
+{
+    repo: A,
+}
+
+impl<A> RevokeApiKeyCommand<A>
+where
+    A: RevokeApiKeyRepository,
+{
+    pub fn new(repo: A) -> Self {
+        Self { repo }
+    }
+
+    pub async fn execute(
+        &self,
+        principal: &Principal,
+        account_id: &str,
+        key_id: &str,
+    ) -> Result<(), AppError> {
+        policy::authorize(principal, Action::ManageKeys { account_id })?;
+
+        self.repo.revoke_api_key(account_id, key_id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn admins_revoke_keys_of_any_account() {
+        // Given
+        let mut repo = MockRevokeApiKeyRepository::new();
+        repo.expect_revoke_api_key()
+            .withf(|account_id, key_id| account_id == "alice" && key_id == "k1")
+            .returning(|_, _| Ok(()))
+            .times(1);
+        let command = RevokeApiKeyCommand::new(repo);
+
+        // When
+        let result = command.execute(&Principal::operator(), "alice", "k1").await;
+
+        // Then
+        assert_eq!(result, Ok(()));
+    }
+}
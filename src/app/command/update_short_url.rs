@@ -0,0 +1,258 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use crate::{
+    app::{
+        account::Principal,
+        event::{DomainEvent, EventBus, EventKind},
+        policy::{self, Action},
+        redirect_rule::{self, RedirectRule},
+        short_url::{OpenGraph, Ownership, ShortUrl},
+        target::QueryPassthrough,
+        variant::{self, Variant},
+    },
+    clock::{SharedClock, SystemClock},
+    error::AppError,
+};
+
+/// The settings to change on a link; `None` leaves a setting as it is.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LinkChanges {
+    pub always_preview: Option<bool>,
+    /// Replaces all `og:` values at once.
+    pub open_graph: Option<OpenGraph>,
+    /// Replaces all redirect rules at once.
+    pub rules: Option<Vec<RedirectRule>>,
+    /// Replaces the A/B split at once.
+    pub variants: Option<Vec<Variant>>,
+    pub query_passthrough: Option<QueryPassthrough>,
+    /// Reschedules the link; a time that has passed makes it live.
+    pub activates_at: Option<u64>,
+    pub coming_soon_page: Option<bool>,
+}
+
+impl LinkChanges {
+    pub fn apply(&self, link: &mut ShortUrl) {
+        if let Some(always_preview) = self.always_preview {
+            link.always_preview = always_preview;
+        }
+        if let Some(open_graph) = &self.open_graph {
+            link.open_graph = open_graph.clone();
+        }
+        if let Some(rules) = &self.rules {
+            link.rules = rules.clone();
+        }
+        if let Some(variants) = &self.variants {
+            link.variants = variants.clone();
+        }
+        if let Some(query_passthrough) = self.query_passthrough {
+            link.query_passthrough = query_passthrough;
+        }
+        if let Some(activates_at) = self.activates_at {
+            link.activates_at = Some(activates_at);
+        }
+        if let Some(coming_soon_page) = self.coming_soon_page {
+            link.coming_soon_page = coming_soon_page;
+        }
+    }
+}
+
+#[mockall::automock]
+#[async_trait]
+pub trait UpdateShortUrlRepository {
+    /// Whom the link stored under `id` belongs to. Fails with
+    /// `AppError::NotFound` when there is none.
+    async fn ownership(&self, id: &str) -> Result<Ownership, AppError>;
+
+    /// Applies `changes` to a live link and returns the result. Fails with
+    /// `AppError::NotFound` rather than bringing back a deleted or expired
+    /// link.
+    async fn update(&self, id: &str, changes: &LinkChanges) -> Result<ShortUrl, AppError>;
+}
+
+pub struct UpdateShortUrlCommand<R>
+where
+    R: UpdateShortUrlRepository,
+{
+    repo: R,
+    events: EventBus,
+    clock: SharedClock,
+}
+
+impl<R> UpdateShortUrlCommand<R>
+where
+    R: UpdateShortUrlRepository,
+{
+    pub fn new(repo: R, events: EventBus) -> Self {
+        Self {
+            repo,
+            events,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Where published events take their time from.
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Applies `changes` to a link, if the policy lets `principal` edit it.
+    pub async fn execute(
+        &self,
+        principal: &Principal,
+        id: &str,
+        mut changes: LinkChanges,
+    ) -> Result<ShortUrl, AppError> {
+        let ownership = self.repo.ownership(id).await?;
+        policy::authorize(
+            principal,
+            Action::EditLink {
+                owner: ownership.owner.as_deref(),
+                workspace: ownership.workspace.as_deref(),
+            },
+        )?;
+        if let Some(open_graph) = &changes.open_graph {
+            open_graph.validate()?;
+        }
+        changes.rules = changes.rules.map(redirect_rule::validate).transpose()?;
+        changes.variants = changes.variants.map(variant::validate).transpose()?;
+
+        let link = self.repo.update(id, &changes).await?;
+
+        self.events.publish(DomainEvent::new(
+            EventKind::LinkUpdated,
+            link.id.clone(),
+            link.url.clone(),
+            self.clock.now(),
+        ));
+
+        Ok(link)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use dashmap::DashMap;
+
+    use crate::adapters::inmemory::InMemoryRepository;
+
+    use crate::app::policy::Role;
+
+    use super::*;
+
+    fn open_graph(title: &str) -> OpenGraph {
+        OpenGraph {
+            title: Some(title.to_owned()),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn update_changes_only_given_settings() {
+        // Given
+        let store = Arc::new(DashMap::new());
+        let mut link = ShortUrl::new("123".to_owned(), "https://www.google.com/".to_owned());
+        link.always_preview = true;
+        store.insert("123".to_owned(), link);
+        let events = EventBus::new();
+        let mut received = events.subscribe();
+        let command = UpdateShortUrlCommand::new(InMemoryRepository::new(store.clone()), events);
+
+        // When
+        let result = command
+            .execute(
+                &Principal::operator(),
+                "123",
+                LinkChanges {
+                    open_graph: Some(open_graph("Search")),
+                    ..Default::default()
+                },
+            )
+            .await;
+
+        // Then
+        let updated = result.unwrap();
+        assert_eq!(updated.open_graph, open_graph("Search"));
+        assert!(updated.always_preview);
+        assert_eq!(store.get("123").unwrap().open_graph, open_graph("Search"));
+        assert_eq!(received.try_recv().unwrap().kind, EventKind::LinkUpdated);
+    }
+
+    #[tokio::test]
+    async fn update_of_missing_link_is_not_found() {
+        // Given
+        let mut repo = MockUpdateShortUrlRepository::new();
+        repo.expect_ownership()
+            .returning(|id| Err(AppError::not_found(id)));
+        repo.expect_update().never();
+        let events = EventBus::new();
+        let mut received = events.subscribe();
+        let command = UpdateShortUrlCommand::new(repo, events);
+
+        // When
+        let result = command
+            .execute(&Principal::operator(), "nope", LinkChanges::default())
+            .await;
+
+        // Then
+        assert_eq!(result, Err(AppError::not_found("nope")));
+        assert!(received.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn rejects_non_web_images() {
+        // Given
+        let mut repo = MockUpdateShortUrlRepository::new();
+        repo.expect_ownership()
+            .returning(|_| Ok(Ownership::default()));
+        repo.expect_update().never();
+        let command = UpdateShortUrlCommand::new(repo, EventBus::new());
+
+        // When
+        let result = command
+            .execute(
+                &Principal::operator(),
+                "123",
+                LinkChanges {
+                    open_graph: Some(OpenGraph {
+                        image: Some("javascript:alert(1)".to_owned()),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                },
+            )
+            .await;
+
+        // Then
+        assert!(matches!(result, Err(AppError::InvalidUrl { .. })));
+    }
+
+    #[tokio::test]
+    async fn only_owners_and_admins_update() {
+        // Given
+        let mut repo = MockUpdateShortUrlRepository::new();
+        repo.expect_ownership().returning(|_| {
+            Ok(Ownership {
+                owner: Some("alice".to_owned()),
+                workspace: None,
+            })
+        });
+        repo.expect_update().never();
+        let command = UpdateShortUrlCommand::new(repo, EventBus::new());
+        let bob = Principal {
+            account_id: Some("bob".to_owned()),
+            role: Role::Editor,
+            workspaces: Vec::new(),
+        };
+
+        // When
+        let result = command.execute(&bob, "123", LinkChanges::default()).await;
+
+        // Then
+        assert!(matches!(result, Err(AppError::Forbidden { .. })));
+    }
+}
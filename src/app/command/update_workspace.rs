@@ -0,0 +1,106 @@
+use async_trait::async_trait;
+
+use crate::{
+    app::{
+        account::Principal,
+        command::create_workspace::normalize_domains,
+        policy::{self, Action},
+        workspace::{Quota, Workspace},
+    },
+    error::AppError,
+};
+
+/// The settings to change on a workspace; `None` leaves a setting as it is.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WorkspaceChanges {
+    pub name: Option<String>,
+    /// Replaces all domains at once.
+    pub domains: Option<Vec<String>>,
+    /// Replaces both limits at once.
+    pub quota: Option<Quota>,
+}
+
+impl WorkspaceChanges {
+    pub fn apply(&self, workspace: &mut Workspace) {
+        if let Some(name) = &self.name {
+            workspace.name = name.clone();
+        }
+        if let Some(domains) = &self.domains {
+            workspace.domains = domains.clone();
+        }
+        if let Some(quota) = self.quota {
+            workspace.quota = quota;
+        }
+    }
+}
+
+#[mockall::automock]
+#[async_trait]
+pub trait UpdateWorkspaceRepository {
+    /// Applies `changes` and returns the result. Fails with
+    /// `AppError::NotFound` for unknown workspaces and with
+    /// `AppError::DomainTaken` when another one serves a new domain.
+    async fn update_workspace(
+        &self,
+        id: &str,
+        changes: &WorkspaceChanges,
+    ) -> Result<Workspace, AppError>;
+}
+
+pub struct UpdateWorkspaceCommand<A>
+where
+    A: UpdateWorkspaceRepository,
+{
+    repo: A,
+}
+
+impl<A> UpdateWorkspaceCommand<A>
+where
+    A: UpdateWorkspaceRepository,
+{
+    pub fn new(repo: A) -> Self {
+        Self { repo }
+    }
+
+    /// Renames a workspace or changes its domains or quota. A lowered quota
+    /// only stops new links; existing ones stay.
+    pub async fn execute(
+        &self,
+        principal: &Principal,
+        id: &str,
+        mut changes: WorkspaceChanges,
+    ) -> Result<Workspace, AppError> {
+        policy::authorize(principal, Action::ManageWorkspaces)?;
+        changes.domains = changes.domains.map(normalize_domains).transpose()?;
+
+        self.repo.update_workspace(id, &changes).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn rejects_invalid_domains_before_storing() {
+        // Given
+        let mut repo = MockUpdateWorkspaceRepository::new();
+        repo.expect_update_workspace().never();
+        let command = UpdateWorkspaceCommand::new(repo);
+
+        // When
+        let result = command
+            .execute(
+                &Principal::operator(),
+                "team",
+                WorkspaceChanges {
+                    domains: Some(vec!["https://go.example.com/".to_owned()]),
+                    ..Default::default()
+                },
+            )
+            .await;
+
+        // Then
+        assert!(matches!(result, Err(AppError::InvalidUrl { .. })));
+    }
+}
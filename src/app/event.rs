@@ -0,0 +1,106 @@
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+
+const EVENT_BUS_CAPACITY: usize = 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, utoipa::ToSchema)]
+pub enum EventKind {
+    #[serde(rename = "link.created")]
+    LinkCreated,
+    #[serde(rename = "link.updated")]
+    LinkUpdated,
+    #[serde(rename = "link.deleted")]
+    LinkDeleted,
+    #[serde(rename = "link.expired")]
+    LinkExpired,
+    #[serde(rename = "link.quarantined")]
+    LinkQuarantined,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LinkData {
+    pub id: String,
+    pub url: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DomainEvent {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub kind: EventKind,
+    pub occurred_at: u64,
+    pub data: LinkData,
+}
+
+impl DomainEvent {
+    pub fn new(kind: EventKind, link_id: String, url: String, occurred_at: u64) -> Self {
+        Self {
+            id: nanoid::nanoid!(),
+            kind,
+            occurred_at,
+            data: LinkData { id: link_id, url },
+        }
+    }
+}
+
+/// In-process fan-out of domain events. Commands publish, background
+/// consumers (e.g. the webhook dispatcher) subscribe.
+#[derive(Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<DomainEvent>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(EVENT_BUS_CAPACITY);
+
+        Self { sender }
+    }
+
+    pub fn publish(&self, event: DomainEvent) {
+        // Having no subscribers is fine, the event is simply dropped.
+        let _ = self.sender.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<DomainEvent> {
+        self.sender.subscribe()
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn subscriber_receives_published_event() {
+        // Given
+        let bus = EventBus::new();
+        let mut rx = bus.subscribe();
+
+        // When
+        bus.publish(DomainEvent::new(
+            EventKind::LinkCreated,
+            "123".to_owned(),
+            "https://www.google.com/".to_owned(),
+            0,
+        ));
+
+        // Then
+        let event = rx.recv().await.unwrap();
+        assert_eq!(event.kind, EventKind::LinkCreated);
+        assert_eq!(event.data.id, "123");
+    }
+
+    #[test]
+    fn event_kind_uses_dotted_names() {
+        let json = serde_json::to_string(&EventKind::LinkDeleted).unwrap();
+
+        assert_eq!(json, "\"link.deleted\"");
+    }
+}
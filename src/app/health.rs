@@ -0,0 +1,140 @@
+//! Checking that link destinations still answer. Links whose target went
+//! away or stopped resolving are only reported; they keep redirecting.
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+/// Redirects followed before a destination counts as unreachable.
+pub const MAX_REDIRECTS: usize = 10;
+
+/// What one request to a destination found.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Probe {
+    /// Status of the last response; `None` when there was none.
+    pub status: Option<u16>,
+    pub latency: Duration,
+    /// The URLs redirected to, in order.
+    pub redirects: Vec<String>,
+    /// Why no final response came: the host did not resolve, the
+    /// connection failed or timed out, or the redirects went on too long.
+    pub error: Option<String>,
+}
+
+#[mockall::automock]
+#[async_trait]
+pub trait LinkProber {
+    /// Requests `url`, following up to `MAX_REDIRECTS` redirects.
+    async fn probe(&self, url: &str) -> Probe;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthState {
+    Healthy,
+    /// The destination answers 404, 410 or a server error.
+    Broken,
+    /// No answer at all.
+    Unreachable,
+}
+
+impl HealthState {
+    /// Any other answer means the destination is still there, even a 401
+    /// or a 429.
+    pub fn of(probe: &Probe) -> Self {
+        match probe.status {
+            _ if probe.error.is_some() => Self::Unreachable,
+            None => Self::Unreachable,
+            Some(404 | 410 | 500..) => Self::Broken,
+            Some(_) => Self::Healthy,
+        }
+    }
+}
+
+/// The last check of a link's destination.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct LinkHealth {
+    pub state: HealthState,
+    /// The destination that was checked.
+    pub url: String,
+    pub checked_at: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    pub latency_ms: u64,
+    /// The URLs redirected to, in order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub redirects: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl LinkHealth {
+    pub fn new(url: String, probe: Probe, checked_at: u64) -> Self {
+        Self {
+            state: HealthState::of(&probe),
+            url,
+            checked_at,
+            status: probe.status,
+            latency_ms: probe.latency.as_millis().try_into().unwrap_or(u64::MAX),
+            redirects: probe.redirects,
+            error: probe.error,
+        }
+    }
+}
+
+/// Links by the state of their last check.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct HealthCounts {
+    pub healthy: usize,
+    pub broken: usize,
+    pub unreachable: usize,
+}
+
+impl HealthCounts {
+    pub fn add(&mut self, state: HealthState) {
+        match state {
+            HealthState::Healthy => self.healthy += 1,
+            HealthState::Broken => self.broken += 1,
+            HealthState::Unreachable => self.unreachable += 1,
+        }
+    }
+}
+
+impl FromIterator<HealthState> for HealthCounts {
+    fn from_iter<T: IntoIterator<Item = HealthState>>(states: T) -> Self {
+        let mut counts = Self::default();
+        for state in states {
+            counts.add(state);
+        }
+        counts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_gone_and_failing_destinations_are_broken() {
+        let answered = |status| Probe {
+            status: Some(status),
+            ..Probe::default()
+        };
+
+        assert_eq!(HealthState::of(&answered(200)), HealthState::Healthy);
+        assert_eq!(HealthState::of(&answered(403)), HealthState::Healthy);
+        assert_eq!(HealthState::of(&answered(404)), HealthState::Broken);
+        assert_eq!(HealthState::of(&answered(410)), HealthState::Broken);
+        assert_eq!(HealthState::of(&answered(503)), HealthState::Broken);
+        assert_eq!(
+            HealthState::of(&Probe {
+                status: Some(301),
+                error: Some("too many redirects".to_owned()),
+                ..Probe::default()
+            }),
+            HealthState::Unreachable
+        );
+        assert_eq!(HealthState::of(&Probe::default()), HealthState::Unreachable);
+    }
+}
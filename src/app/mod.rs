@@ -1,5 +1,17 @@
+pub mod account;
 pub mod command;
+pub mod event;
+pub mod health;
+pub mod policy;
 pub mod query;
+pub mod redirect_rule;
+pub mod report;
+pub mod screening;
+pub mod short_url;
+pub mod target;
+pub mod variant;
+pub mod webhook;
+pub mod workspace;
 
 #[cfg(test)]
 mod tests {
@@ -15,8 +27,10 @@ mod tests {
         let repo = InMemoryRepository::new(store.clone());
 
         let create_command = crate::app::command::create_short_url::CreateShortUrlCommand::new(
-            crate::id_provider::NanoIDProvider,
+            crate::id_provider::NanoIDProvider::default(),
             repo.clone(),
+            crate::app::command::create_short_url::MockWorkspaceQuotaRepository::new(),
+            crate::app::event::EventBus::new(),
         );
 
         let get_query = crate::app::query::get_full_url::GetFullUrlQuery::new(repo);
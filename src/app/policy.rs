@@ -0,0 +1,229 @@
+//! Who may do what. Commands and queries ask `authorize` before they touch
+//! storage, so the rules hold for every port alike.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{app::account::Principal, error::AppError};
+
+/// What an account may do.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// Everything, including other accounts' links, accounts, workspaces and
+    /// webhooks.
+    Admin,
+    /// Creates links and changes the ones it or its workspaces own.
+    #[default]
+    Editor,
+    /// Reads links and their stats, and changes nothing.
+    Viewer,
+}
+
+/// Something a principal asks to do.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action<'a> {
+    /// Creating a link, of `workspace` if given.
+    CreateLink {
+        workspace: Option<&'a str>,
+    },
+    /// Changing or deleting a link of `owner` and `workspace`.
+    EditLink {
+        owner: Option<&'a str>,
+        workspace: Option<&'a str>,
+    },
+    /// Reading links and their destinations.
+    ReadLinks,
+    /// Reading what is recorded about a link of `owner` and `workspace`,
+    /// such as its stats.
+    ReadLink {
+        owner: Option<&'a str>,
+        workspace: Option<&'a str>,
+    },
+    /// Listing the links of `account_id`, or of every account when `None`.
+    ListLinks {
+        account_id: Option<&'a str>,
+    },
+    CreateAccount,
+    /// Issuing and revoking keys of `account_id`.
+    ManageKeys {
+        account_id: &'a str,
+    },
+    ManageWebhooks,
+    /// Bulk import and export.
+    TransferLinks,
+    /// Creating workspaces and changing their members, domains and quotas.
+    ManageWorkspaces,
+    /// Reading a workspace and its usage.
+    ReadWorkspace {
+        workspace_id: &'a str,
+    },
+    /// Reviewing abuse reports and disabling, quarantining or restoring
+    /// links.
+    ModerateLinks,
+}
+
+/// Fails with `AppError::Forbidden` unless `principal` may do `action`.
+pub fn authorize(principal: &Principal, action: Action<'_>) -> Result<(), AppError> {
+    let own = |account_id: Option<&str>| principal.account_id.as_deref() == account_id;
+    let member = |workspace: Option<&str>| workspace.is_some_and(|id| principal.is_member_of(id));
+    let allowed = match (principal.role, action) {
+        (Role::Admin, _) => true,
+        (_, Action::ReadLinks) => true,
+        (_, Action::ReadLink { owner, workspace }) => own(owner) || member(workspace),
+        (_, Action::ListLinks { account_id }) => account_id.is_some() && own(account_id),
+        (_, Action::ManageKeys { account_id }) => own(Some(account_id)),
+        (_, Action::ReadWorkspace { workspace_id }) => member(Some(workspace_id)),
+        (Role::Editor, Action::CreateLink { workspace }) => {
+            workspace.is_none() || member(workspace)
+        }
+        (Role::Editor, Action::EditLink { owner, workspace }) => own(owner) || member(workspace),
+        _ => false,
+    };
+
+    if allowed {
+        Ok(())
+    } else {
+        Err(AppError::forbidden(denial(principal.role, action)))
+    }
+}
+
+fn denial(role: Role, action: Action<'_>) -> &'static str {
+    match (role, action) {
+        (Role::Viewer, Action::CreateLink { .. } | Action::EditLink { .. }) => {
+            "viewers may not change links"
+        }
+        (_, Action::CreateLink { .. } | Action::ReadWorkspace { .. }) => {
+            "this is not a workspace of the account"
+        }
+        (_, Action::EditLink { .. } | Action::ReadLink { .. }) => {
+            "the link belongs to another account"
+        }
+        (
+            _,
+            Action::ManageKeys { .. }
+            | Action::ListLinks {
+                account_id: Some(_),
+            },
+        ) => "this is another account",
+        _ => "only admins may do this",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn principal(account_id: &str, role: Role) -> Principal {
+        Principal {
+            account_id: Some(account_id.to_owned()),
+            role,
+            workspaces: vec!["team".to_owned()],
+        }
+    }
+
+    fn edit<'a>(owner: Option<&'a str>, workspace: Option<&'a str>) -> Action<'a> {
+        Action::EditLink { owner, workspace }
+    }
+
+    #[test]
+    fn editors_change_only_their_own_links() {
+        let alice = principal("alice", Role::Editor);
+
+        assert!(authorize(&alice, Action::CreateLink { workspace: None }).is_ok());
+        assert!(authorize(&alice, edit(Some("alice"), None)).is_ok());
+        assert!(matches!(
+            authorize(&alice, edit(Some("bob"), None)),
+            Err(AppError::Forbidden { .. })
+        ));
+        assert!(authorize(&alice, edit(None, None)).is_err());
+        assert!(authorize(&alice, Action::ReadLinks).is_ok());
+        assert!(authorize(&alice, Action::ManageWebhooks).is_err());
+    }
+
+    #[test]
+    fn accounts_read_the_details_of_their_own_links() {
+        let vic = principal("vic", Role::Viewer);
+        let read = |owner, workspace| Action::ReadLink { owner, workspace };
+        let list = |account_id| Action::ListLinks { account_id };
+
+        assert!(authorize(&vic, read(Some("vic"), None)).is_ok());
+        assert!(authorize(&vic, read(Some("bob"), Some("team"))).is_ok());
+        assert!(matches!(
+            authorize(&vic, read(Some("bob"), None)),
+            Err(AppError::Forbidden { .. })
+        ));
+        assert!(authorize(&vic, list(Some("vic"))).is_ok());
+        assert!(authorize(&vic, list(Some("bob"))).is_err());
+        assert!(authorize(&vic, list(None)).is_err());
+        assert!(authorize(&Principal::operator(), read(Some("bob"), None)).is_ok());
+        assert!(authorize(&Principal::operator(), list(None)).is_ok());
+    }
+
+    #[test]
+    fn viewers_only_read() {
+        let viewer = principal("vic", Role::Viewer);
+
+        assert!(authorize(&viewer, Action::ReadLinks).is_ok());
+        assert!(authorize(&viewer, Action::ManageKeys { account_id: "vic" }).is_ok());
+        assert!(authorize(&viewer, Action::CreateLink { workspace: None }).is_err());
+        assert!(authorize(&viewer, edit(Some("vic"), Some("team"))).is_err());
+        assert!(authorize(
+            &viewer,
+            Action::ReadWorkspace {
+                workspace_id: "team"
+            }
+        )
+        .is_ok());
+        assert!(authorize(&viewer, Action::TransferLinks).is_err());
+    }
+
+    #[test]
+    fn admins_do_everything() {
+        for admin in [principal("root", Role::Admin), Principal::operator()] {
+            assert!(authorize(&admin, edit(Some("bob"), Some("other"))).is_ok());
+            assert!(authorize(&admin, Action::ManageKeys { account_id: "bob" }).is_ok());
+            assert!(authorize(&admin, Action::CreateAccount).is_ok());
+            assert!(authorize(&admin, Action::TransferLinks).is_ok());
+            assert!(authorize(&admin, Action::ManageWorkspaces).is_ok());
+            assert!(authorize(&admin, Action::ModerateLinks).is_ok());
+        }
+    }
+
+    #[test]
+    fn members_share_the_links_of_their_workspaces() {
+        let alice = principal("alice", Role::Editor);
+
+        assert!(authorize(
+            &alice,
+            Action::CreateLink {
+                workspace: Some("team")
+            }
+        )
+        .is_ok());
+        assert!(authorize(&alice, edit(Some("bob"), Some("team"))).is_ok());
+        assert!(authorize(
+            &alice,
+            Action::ReadWorkspace {
+                workspace_id: "team"
+            }
+        )
+        .is_ok());
+        assert!(authorize(
+            &alice,
+            Action::CreateLink {
+                workspace: Some("other")
+            }
+        )
+        .is_err());
+        assert!(authorize(&alice, edit(Some("bob"), Some("other"))).is_err());
+        assert!(authorize(
+            &alice,
+            Action::ReadWorkspace {
+                workspace_id: "other"
+            }
+        )
+        .is_err());
+        assert!(authorize(&alice, Action::ManageWorkspaces).is_err());
+        assert!(authorize(&alice, Action::ModerateLinks).is_err());
+    }
+}
@@ -0,0 +1,105 @@
+use crate::{
+    app::account::{self, Account, Principal},
+    error::AppError,
+};
+
+pub trait AuthenticateRepository {
+    /// The account owning the key with this hash, if any.
+    fn account_for_key(
+        &self,
+        hash: &str,
+    ) -> impl std::future::Future<Output = Result<Option<Account>, AppError>> + std::marker::Send;
+
+    /// IDs of the workspaces `account_id` is a member of.
+    fn workspaces_of(
+        &self,
+        account_id: &str,
+    ) -> impl std::future::Future<Output = Result<Vec<String>, AppError>> + std::marker::Send;
+}
+
+pub struct AuthenticateQuery<A>
+where
+    A: AuthenticateRepository,
+{
+    repo: A,
+    operator_key_hash: Option<String>,
+}
+
+impl<A> AuthenticateQuery<A>
+where
+    A: AuthenticateRepository,
+{
+    pub fn new(repo: A) -> Self {
+        Self {
+            repo,
+            operator_key_hash: None,
+        }
+    }
+
+    /// Also accept `secret` as the operator's key, so the first accounts
+    /// can be created over the API.
+    pub fn with_operator_key(mut self, secret: &str) -> Self {
+        self.operator_key_hash = Some(account::hash_secret(secret));
+        self
+    }
+
+    /// Who holds the API key `secret`.
+    pub async fn execute(&self, secret: &str) -> Result<Principal, AppError> {
+        let hash = account::hash_secret(secret);
+        if self.operator_key_hash.as_deref() == Some(hash.as_str()) {
+            return Ok(Principal::operator());
+        }
+
+        let Some(account) = self.repo.account_for_key(&hash).await? else {
+            return Err(AppError::Unauthenticated);
+        };
+        let workspaces = self.repo.workspaces_of(&account.id).await?;
+
+        Ok(Principal::account(&account, workspaces))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app::policy::Role;
+
+    use super::*;
+
+    struct FakeRepository;
+
+    impl AuthenticateRepository for FakeRepository {
+        async fn account_for_key(&self, hash: &str) -> Result<Option<Account>, AppError> {
+            Ok(
+                (hash == account::hash_secret("usk_alice")).then(|| Account {
+                    id: "alice".to_owned(),
+                    name: "Alice".to_owned(),
+                    role: Role::Editor,
+                    created_at: 0,
+                }),
+            )
+        }
+
+        async fn workspaces_of(&self, account_id: &str) -> Result<Vec<String>, AppError> {
+            Ok(vec![format!("{account_id}-team")])
+        }
+    }
+
+    #[tokio::test]
+    async fn keys_identify_their_holder() {
+        // Given
+        let query = AuthenticateQuery::new(FakeRepository).with_operator_key("usk_operator");
+
+        // Then
+        let alice = query.execute("usk_alice").await.unwrap();
+        assert_eq!(alice.account_id.as_deref(), Some("alice"));
+        assert_eq!(alice.workspaces, ["alice-team"]);
+        assert_eq!(
+            query.execute("usk_operator").await,
+            Ok(Principal::operator())
+        );
+        assert_eq!(
+            query.execute("usk_nobody").await,
+            Err(AppError::Unauthenticated)
+        );
+    }
+}
@@ -0,0 +1,86 @@
+use futures::Stream;
+
+use crate::{
+    app::{
+        account::Principal,
+        policy::{self, Action},
+        short_url::ShortUrl,
+    },
+    error::AppError,
+};
+
+pub trait ExportShortUrlsRepository {
+    /// Every stored link, in batches of at most `batch_size`. The order is
+    /// up to the adapter.
+    fn export(
+        &self,
+        batch_size: usize,
+    ) -> impl Stream<Item = Result<Vec<ShortUrl>, AppError>> + std::marker::Send;
+}
+
+pub struct ExportShortUrlsQuery<R>
+where
+    R: ExportShortUrlsRepository,
+{
+    repo: R,
+}
+
+impl<R> ExportShortUrlsQuery<R>
+where
+    R: ExportShortUrlsRepository,
+{
+    pub fn new(repo: R) -> Self {
+        Self { repo }
+    }
+
+    /// Every link, if `principal` may export them.
+    pub fn execute(
+        &self,
+        principal: &Principal,
+        batch_size: usize,
+    ) -> Result<
+        impl Stream<Item = Result<Vec<ShortUrl>, AppError>> + std::marker::Send + '_,
+        AppError,
+    > {
+        policy::authorize(principal, Action::TransferLinks)?;
+
+        Ok(self.repo.export(batch_size))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use dashmap::DashMap;
+    use futures::TryStreamExt;
+
+    use crate::adapters::inmemory::InMemoryRepository;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn export_everything_in_batches() {
+        // Given
+        let store = Arc::new(DashMap::new());
+        for id in ["a", "b", "c", "d", "e"] {
+            store.insert(
+                id.to_owned(),
+                ShortUrl::new(id.to_owned(), format!("https://{id}.com/")),
+            );
+        }
+        let query = ExportShortUrlsQuery::new(InMemoryRepository::new(store));
+
+        // When
+        let batches: Vec<Vec<ShortUrl>> = query
+            .execute(&Principal::operator(), 2)
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+
+        // Then
+        let sizes: Vec<_> = batches.iter().map(Vec::len).collect();
+        assert_eq!(sizes, vec![2, 2, 1]);
+    }
+}
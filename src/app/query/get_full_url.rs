@@ -1,10 +1,32 @@
-use crate::error::AppError;
+use std::sync::Arc;
+
+use crate::{
+    app::{
+        redirect_rule::{self, Visitor},
+        screening::SharedScreener,
+        short_url::{LinkStatus, ShortUrl},
+        target, variant,
+    },
+    clock::{SharedClock, SystemClock},
+    error::AppError,
+};
 
 pub trait GetFullUrlRepository {
+    /// Returns the link if it exists and has not expired.
     fn get(
         &self,
         id: &str,
-    ) -> impl std::future::Future<Output = Result<String, AppError>> + std::marker::Send;
+    ) -> impl std::future::Future<Output = Result<ShortUrl, AppError>> + std::marker::Send;
+}
+
+/// A link and where it sends one particular visitor.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Resolution {
+    pub link: ShortUrl,
+    pub target: String,
+    /// The A/B variant the visitor was assigned, if the link splits
+    /// traffic and no rule matched first.
+    pub variant: Option<String>,
 }
 
 pub struct GetFullUrlQuery<R>
@@ -12,6 +34,8 @@ where
     R: GetFullUrlRepository,
 {
     repo: R,
+    screener: Option<SharedScreener>,
+    clock: SharedClock,
 }
 
 impl<R> GetFullUrlQuery<R>
@@ -19,11 +43,123 @@ where
     R: GetFullUrlRepository,
 {
     pub fn new(repo: R) -> Self {
-        Self { repo }
+        Self {
+            repo,
+            screener: None,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Where links learn whether they are live yet and whether they have
+    /// expired.
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
     }
 
+    /// Screens targets again on every `resolve`, so links created before
+    /// a site was listed are caught too. The stored link is not changed.
+    pub fn with_screener(mut self, screener: SharedScreener) -> Self {
+        self.screener = Some(screener);
+        self
+    }
+
+    /// Following a link needs no account, so neither does this.
     pub async fn execute(&self, id: &str) -> Result<String, AppError> {
-        self.repo.get(id).await
+        self.link(id).await.map(|link| link.url)
+    }
+
+    /// The whole link, for callers that need more than the destination.
+    /// Disabled links fail with `AppError::LinkDisabled`; quarantined ones
+    /// are for the caller to warn about. Links that are not live yet fail
+    /// as `check_live` says.
+    pub async fn link(&self, id: &str) -> Result<ShortUrl, AppError> {
+        let link = self.stored(id).await?;
+        self.check_live(&link)?;
+        Ok(link)
+    }
+
+    /// Fails for links that are not live yet: with
+    /// `AppError::LinkNotYetActive` if they have a coming soon page, else
+    /// as if they did not exist.
+    pub fn check_live(&self, link: &ShortUrl) -> Result<(), AppError> {
+        match link.activates_at {
+            Some(activates_at) if link.is_pending(self.clock.now()) => {
+                if link.coming_soon_page {
+                    Err(AppError::LinkNotYetActive {
+                        id: link.id.clone(),
+                        activates_at,
+                    })
+                } else {
+                    Err(AppError::not_found(&link.id))
+                }
+            }
+            _ => Ok(()),
+        }
+    }
+
+    async fn stored(&self, id: &str) -> Result<ShortUrl, AppError> {
+        let link = self.repo.get(id).await?;
+        if link.is_expired(self.clock.now()) {
+            return Err(AppError::not_found(id));
+        }
+        if link.status == LinkStatus::Disabled {
+            return Err(AppError::LinkDisabled { id: link.id });
+        }
+        Ok(link)
+    }
+
+    /// Where `visitor` goes: the target of the link's first matching
+    /// redirect rule, else that of their A/B variant, else its URL.
+    /// Visitors keep the variant they name as long as it exists. Templated
+    /// targets are filled and the visitor's query passed through as the
+    /// link asks.
+    ///
+    /// The link comes back quarantined when the target is screened and
+    /// found on a threat list. Links that are not live yet resolve all the
+    /// same, so the caller can check where they are served first; it then
+    /// asks `check_live` before sending anyone on.
+    pub async fn resolve(&self, id: &str, visitor: &Visitor) -> Result<Resolution, AppError> {
+        let link = self.stored(id).await?;
+
+        if let Some(target) = redirect_rule::first_match(&link.rules, visitor) {
+            let target = target::build(target, link.query_passthrough, visitor);
+            return Ok(self.screen(Resolution {
+                link,
+                target,
+                variant: None,
+            }));
+        }
+
+        let assigned = visitor
+            .variant
+            .as_deref()
+            .and_then(|name| variant::find(&link.variants, name))
+            .or_else(|| variant::assign(&link.variants, &link.id, &visitor.fingerprint));
+        let (target, variant) = match assigned {
+            Some(assigned) => (assigned.target.as_str(), Some(assigned.name.clone())),
+            None => (link.url.as_str(), None),
+        };
+        let target = target::build(target, link.query_passthrough, visitor);
+
+        Ok(self.screen(Resolution {
+            link,
+            target,
+            variant,
+        }))
+    }
+
+    fn screen(&self, mut resolution: Resolution) -> Resolution {
+        let Some(screener) = &self.screener else {
+            return resolution;
+        };
+        if resolution.link.status.is_active() {
+            if let Some(reason) = screener.screen(&resolution.target) {
+                resolution.link.quarantine(reason);
+            }
+        }
+
+        resolution
     }
 }
 
@@ -33,7 +169,14 @@ mod test {
 
     use dashmap::DashMap;
 
-    use crate::adapters::inmemory::InMemoryRepository;
+    use crate::{
+        adapters::inmemory::InMemoryRepository,
+        app::{
+            redirect_rule::{Platform, RedirectRule},
+            short_url::ShortUrl,
+        },
+        clock::FakeClock,
+    };
 
     use super::*;
 
@@ -42,8 +185,11 @@ mod test {
         // Given
         struct FakeRepository;
         impl GetFullUrlRepository for FakeRepository {
-            async fn get(&self, _id: &str) -> Result<String, AppError> {
-                Ok("https://www.google.com".to_owned())
+            async fn get(&self, id: &str) -> Result<ShortUrl, AppError> {
+                Ok(ShortUrl::new(
+                    id.to_owned(),
+                    "https://www.google.com".to_owned(),
+                ))
             }
         }
 
@@ -61,7 +207,10 @@ mod test {
     async fn get_from_inmemory_repo() {
         // Given
         let store = Arc::new(DashMap::new());
-        store.insert("123".to_owned(), "https://www.google.com".to_owned());
+        store.insert(
+            "123".to_owned(),
+            ShortUrl::new("123".to_owned(), "https://www.google.com".to_owned()),
+        );
         let repo = InMemoryRepository::new(store);
         let query = GetFullUrlQuery::new(repo);
 
@@ -72,12 +221,167 @@ mod test {
         assert_eq!(result, Ok("https://www.google.com".to_owned()));
     }
 
+    #[tokio::test]
+    async fn resolve_follows_the_first_matching_rule() {
+        // Given
+        let store = Arc::new(DashMap::new());
+        let mut link = ShortUrl::new("123".to_owned(), "https://example.com/".to_owned());
+        link.rules = vec![RedirectRule {
+            target: "https://apps.apple.com/app/id1".to_owned(),
+            platforms: vec![Platform::Ios],
+            ..Default::default()
+        }];
+        store.insert("123".to_owned(), link);
+        let query = GetFullUrlQuery::new(InMemoryRepository::new(store));
+        let on = |platform| Visitor {
+            platform,
+            ..Default::default()
+        };
+
+        // When
+        let iphone = query.resolve("123", &on(Platform::Ios)).await.unwrap();
+        let desktop = query.resolve("123", &on(Platform::Linux)).await.unwrap();
+
+        // Then
+        assert_eq!(iphone.target, "https://apps.apple.com/app/id1");
+        assert_eq!(desktop.target, "https://example.com/");
+        assert_eq!(desktop.link.id, "123");
+    }
+
+    #[tokio::test]
+    async fn resolve_screens_the_target_when_asked() {
+        // Given
+        let store = Arc::new(DashMap::new());
+        let mut link = ShortUrl::new("123".to_owned(), "https://example.com/".to_owned());
+        link.rules = vec![RedirectRule {
+            target: "https://phish.example/".to_owned(),
+            platforms: vec![Platform::Ios],
+            ..Default::default()
+        }];
+        store.insert("123".to_owned(), link);
+        let mut screener = crate::app::screening::MockUrlScreener::new();
+        screener
+            .expect_screen()
+            .returning(|url| url.contains("phish").then(|| "phishing".to_owned()));
+        let query =
+            GetFullUrlQuery::new(InMemoryRepository::new(store)).with_screener(Arc::new(screener));
+        let on = |platform| Visitor {
+            platform,
+            ..Default::default()
+        };
+
+        // When
+        let iphone = query.resolve("123", &on(Platform::Ios)).await.unwrap();
+        let desktop = query.resolve("123", &on(Platform::Linux)).await.unwrap();
+
+        // Then
+        assert_eq!(iphone.link.status, LinkStatus::Quarantined);
+        assert_eq!(iphone.link.status_reason.as_deref(), Some("phishing"));
+        assert_eq!(desktop.link.status, LinkStatus::Active);
+    }
+
+    #[tokio::test]
+    async fn expired_link_is_not_found() {
+        // Given
+        let store = Arc::new(DashMap::new());
+        let mut link = ShortUrl::new("123".to_owned(), "https://www.google.com".to_owned());
+        link.expires_at = Some(link.created_at - 1);
+        store.insert("123".to_owned(), link);
+        let query = GetFullUrlQuery::new(InMemoryRepository::new(store));
+
+        // When
+        let result = query.execute("123").await;
+
+        // Then
+        assert_eq!(result, Err(AppError::not_found("123")));
+    }
+
+    #[tokio::test]
+    async fn link_expires_by_the_query_clock() {
+        // Given
+        let store = Arc::new(DashMap::new());
+        let mut link = ShortUrl::new("123".to_owned(), "https://www.google.com".to_owned());
+        link.expires_at = Some(link.created_at + 60);
+        let clock = FakeClock::new(link.created_at);
+        store.insert("123".to_owned(), link);
+        let query = GetFullUrlQuery::new(InMemoryRepository::new(store))
+            .with_clock(Arc::new(clock.clone()));
+
+        // When
+        let live = query.execute("123").await;
+        clock.advance(60);
+        let expired = query.execute("123").await;
+
+        // Then
+        assert_eq!(live, Ok("https://www.google.com".to_owned()));
+        assert_eq!(expired, Err(AppError::not_found("123")));
+    }
+
+    #[tokio::test]
+    async fn disabled_link_is_unavailable() {
+        // Given
+        let store = Arc::new(DashMap::new());
+        let mut link = ShortUrl::new("123".to_owned(), "https://www.google.com".to_owned());
+        link.status = LinkStatus::Disabled;
+        store.insert("123".to_owned(), link);
+        let query = GetFullUrlQuery::new(InMemoryRepository::new(store));
+
+        // When
+        let result = query.execute("123").await;
+
+        // Then
+        assert_eq!(
+            result,
+            Err(AppError::LinkDisabled {
+                id: "123".to_owned()
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn scheduled_link_goes_live_at_its_time() {
+        // Given
+        let store = Arc::new(DashMap::new());
+        for (id, coming_soon_page) in [("launch", false), ("teaser", true)] {
+            let mut link = ShortUrl::new(id.to_owned(), "https://www.google.com".to_owned());
+            link.activates_at = Some(1_000);
+            link.coming_soon_page = coming_soon_page;
+            store.insert(id.to_owned(), link);
+        }
+        let clock = FakeClock::new(999);
+        let query = GetFullUrlQuery::new(InMemoryRepository::new(store))
+            .with_clock(Arc::new(clock.clone()));
+
+        // When
+        let early = query.execute("launch").await;
+        let teased = query.execute("teaser").await;
+        clock.advance(1);
+        let live = query.execute("launch").await;
+
+        // Then
+        assert_eq!(early, Err(AppError::not_found("launch")));
+        assert_eq!(
+            teased,
+            Err(AppError::LinkNotYetActive {
+                id: "teaser".to_owned(),
+                activates_at: 1_000
+            })
+        );
+        assert_eq!(live, Ok("https://www.google.com".to_owned()));
+    }
+
     #[tokio::test]
     async fn get_two_different_full_url() {
         // Given
         let store = Arc::new(DashMap::new());
-        store.insert("123".to_owned(), "https://www.google.com".to_owned());
-        store.insert("456".to_owned(), "https://www.github.com".to_owned());
+        store.insert(
+            "123".to_owned(),
+            ShortUrl::new("123".to_owned(), "https://www.google.com".to_owned()),
+        );
+        store.insert(
+            "456".to_owned(),
+            ShortUrl::new("456".to_owned(), "https://www.github.com".to_owned()),
+        );
         let repo = InMemoryRepository::new(store);
         let query = GetFullUrlQuery::new(repo);
 
@@ -0,0 +1,120 @@
+use crate::{
+    app::{
+        account::Principal,
+        health::LinkHealth,
+        policy::{self, Action},
+        short_url::Ownership,
+    },
+    error::AppError,
+};
+
+pub trait GetLinkHealthRepository {
+    /// Whom the link stored under `id` belongs to. Fails with
+    /// `AppError::NotFound` when there is none.
+    fn ownership(
+        &self,
+        id: &str,
+    ) -> impl std::future::Future<Output = Result<Ownership, AppError>> + std::marker::Send;
+
+    /// The last check of a live link; `None` when there is no such link or
+    /// it was not checked yet.
+    fn link_health(
+        &self,
+        id: &str,
+    ) -> impl std::future::Future<Output = Result<Option<LinkHealth>, AppError>> + std::marker::Send;
+}
+
+pub struct GetLinkHealthQuery<R>
+where
+    R: GetLinkHealthRepository,
+{
+    repo: R,
+}
+
+impl<R> GetLinkHealthQuery<R>
+where
+    R: GetLinkHealthRepository,
+{
+    pub fn new(repo: R) -> Self {
+        Self { repo }
+    }
+
+    /// The last check of a link the policy lets `principal` read.
+    pub async fn execute(&self, principal: &Principal, id: &str) -> Result<LinkHealth, AppError> {
+        let ownership = self.repo.ownership(id).await?;
+        policy::authorize(
+            principal,
+            Action::ReadLink {
+                owner: ownership.owner.as_deref(),
+                workspace: ownership.workspace.as_deref(),
+            },
+        )?;
+        self.repo
+            .link_health(id)
+            .await?
+            .ok_or_else(|| AppError::not_found(id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use dashmap::DashMap;
+
+    use crate::{
+        adapters::inmemory::InMemoryRepository,
+        app::{
+            command::check_link_health::CheckLinkHealthRepository, health::Probe, policy::Role,
+            short_url::ShortUrl,
+        },
+    };
+
+    use super::*;
+
+    fn account(id: &str, workspaces: &[&str]) -> Principal {
+        Principal {
+            account_id: Some(id.to_owned()),
+            role: Role::Viewer,
+            workspaces: workspaces.iter().map(|w| (*w).to_owned()).collect(),
+        }
+    }
+
+    #[tokio::test]
+    async fn only_owners_members_and_admins_see_checks() {
+        // Given
+        let store = Arc::new(DashMap::new());
+        let mut link = ShortUrl::new("abc".to_owned(), "https://a.example/".to_owned());
+        link.owner = Some("alice".to_owned());
+        link.workspace = Some("team".to_owned());
+        store.insert(link.id.clone(), link);
+        let repo = InMemoryRepository::new(store);
+        let probe = Probe {
+            status: Some(200),
+            latency: Duration::from_millis(5),
+            ..Probe::default()
+        };
+        repo.record_health(
+            "abc",
+            LinkHealth::new("https://a.example/".to_owned(), probe, 0),
+        )
+        .await
+        .unwrap();
+        let query = GetLinkHealthQuery::new(repo);
+
+        // When
+        let owner = query.execute(&account("alice", &[]), "abc").await;
+        let member = query.execute(&account("bob", &["team"]), "abc").await;
+        let admin = query.execute(&Principal::operator(), "abc").await;
+        let outsider = query.execute(&account("eve", &["other"]), "abc").await;
+        let unknown = query.execute(&Principal::operator(), "nope").await;
+
+        // Then
+        assert_eq!(owner.map(|health| health.status), Ok(Some(200)));
+        assert!(member.is_ok());
+        assert!(admin.is_ok());
+        assert!(matches!(outsider, Err(AppError::Forbidden { .. })));
+        assert_eq!(unknown, Err(AppError::not_found("nope")));
+    }
+}
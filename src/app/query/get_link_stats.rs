@@ -0,0 +1,67 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app::{
+        account::Principal,
+        policy::{self, Action},
+        short_url::Ownership,
+    },
+    error::AppError,
+};
+
+pub trait GetLinkStatsRepository {
+    /// Whom the link stored under `id` belongs to. Fails with
+    /// `AppError::NotFound` when there is none.
+    fn ownership(
+        &self,
+        id: &str,
+    ) -> impl std::future::Future<Output = Result<Ownership, AppError>> + std::marker::Send;
+
+    /// Visit counts of a live link; `None` when there is no such link.
+    fn link_stats(
+        &self,
+        id: &str,
+    ) -> impl std::future::Future<Output = Result<Option<LinkStats>, AppError>> + std::marker::Send;
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct LinkStats {
+    pub visits: u64,
+    /// Visits per A/B variant served.
+    #[serde(default)]
+    pub variants: BTreeMap<String, u64>,
+}
+
+pub struct GetLinkStatsQuery<R>
+where
+    R: GetLinkStatsRepository,
+{
+    repo: R,
+}
+
+impl<R> GetLinkStatsQuery<R>
+where
+    R: GetLinkStatsRepository,
+{
+    pub fn new(repo: R) -> Self {
+        Self { repo }
+    }
+
+    /// Visits to a link the policy lets `principal` read.
+    pub async fn execute(&self, principal: &Principal, id: &str) -> Result<LinkStats, AppError> {
+        let ownership = self.repo.ownership(id).await?;
+        policy::authorize(
+            principal,
+            Action::ReadLink {
+                owner: ownership.owner.as_deref(),
+                workspace: ownership.workspace.as_deref(),
+            },
+        )?;
+        self.repo
+            .link_stats(id)
+            .await?
+            .ok_or_else(|| AppError::not_found(id))
+    }
+}
@@ -0,0 +1,55 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app::{
+        account::Principal,
+        health::HealthCounts,
+        policy::{self, Action},
+    },
+    error::AppError,
+};
+
+pub trait GetStatsRepository {
+    fn count(
+        &self,
+    ) -> impl std::future::Future<Output = Result<usize, AppError>> + std::marker::Send;
+
+    /// Checked links by the state their destination was last found in.
+    fn health_counts(
+        &self,
+    ) -> impl std::future::Future<Output = Result<HealthCounts, AppError>> + std::marker::Send;
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Stats {
+    pub total_links: usize,
+    #[serde(default)]
+    pub link_health: HealthCounts,
+}
+
+pub struct GetStatsQuery<R>
+where
+    R: GetStatsRepository,
+{
+    repo: R,
+}
+
+impl<R> GetStatsQuery<R>
+where
+    R: GetStatsRepository,
+{
+    pub fn new(repo: R) -> Self {
+        Self { repo }
+    }
+
+    pub async fn execute(&self, principal: &Principal) -> Result<Stats, AppError> {
+        policy::authorize(principal, Action::ReadLinks)?;
+        let total_links = self.repo.count().await?;
+        let link_health = self.repo.health_counts().await?;
+
+        Ok(Stats {
+            total_links,
+            link_health,
+        })
+    }
+}
@@ -0,0 +1,91 @@
+use crate::{
+    app::{
+        account::Principal,
+        policy::{self, Action},
+        workspace::{self, Workspace},
+    },
+    error::AppError,
+};
+
+pub trait GetWorkspaceRepository {
+    fn workspace(
+        &self,
+        id: &str,
+    ) -> impl std::future::Future<Output = Result<Option<Workspace>, AppError>> + std::marker::Send;
+
+    /// The ID of the workspace serving `domain`, a normalized host.
+    fn workspace_for_domain(
+        &self,
+        domain: &str,
+    ) -> impl std::future::Future<Output = Result<Option<String>, AppError>> + std::marker::Send;
+}
+
+pub struct GetWorkspaceQuery<A>
+where
+    A: GetWorkspaceRepository,
+{
+    repo: A,
+}
+
+impl<A> GetWorkspaceQuery<A>
+where
+    A: GetWorkspaceRepository,
+{
+    pub fn new(repo: A) -> Self {
+        Self { repo }
+    }
+
+    /// A workspace, for its members and admins.
+    pub async fn execute(&self, principal: &Principal, id: &str) -> Result<Workspace, AppError> {
+        policy::authorize(principal, Action::ReadWorkspace { workspace_id: id })?;
+        self.repo
+            .workspace(id)
+            .await?
+            .ok_or_else(|| AppError::not_found(id))
+    }
+
+    /// The workspace a request's `Host` belongs to, if any. Visitors have no
+    /// account, so this needs none either.
+    pub async fn for_host(&self, host: &str) -> Result<Option<String>, AppError> {
+        let host = host.rsplit_once(':').map_or(host, |(host, _port)| host);
+        match workspace::normalize_domain(host) {
+            Ok(domain) => self.repo.workspace_for_domain(&domain).await,
+            Err(_) => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FakeRepository;
+
+    impl GetWorkspaceRepository for FakeRepository {
+        async fn workspace(&self, _id: &str) -> Result<Option<Workspace>, AppError> {
+            Ok(None)
+        }
+
+        async fn workspace_for_domain(&self, domain: &str) -> Result<Option<String>, AppError> {
+            Ok((domain == "go.example.com").then(|| "team".to_owned()))
+        }
+    }
+
+    #[tokio::test]
+    async fn hosts_match_domains_whatever_the_port_and_case() {
+        // Given
+        let query = GetWorkspaceQuery::new(FakeRepository);
+
+        // Then
+        assert_eq!(
+            query.for_host("Go.Example.com:8080").await,
+            Ok(Some("team".to_owned()))
+        );
+        assert_eq!(
+            query.for_host("go.example.com").await,
+            Ok(Some("team".to_owned()))
+        );
+        assert_eq!(query.for_host("example.com").await, Ok(None));
+        assert_eq!(query.for_host("127.0.0.1:3000").await, Ok(None));
+    }
+}
@@ -0,0 +1,59 @@
+use std::sync::Arc;
+
+use crate::{
+    app::{
+        account::Principal,
+        policy::{self, Action},
+        workspace::{self, Usage},
+    },
+    clock::{SharedClock, SystemClock},
+    error::AppError,
+};
+
+pub trait GetWorkspaceUsageRepository {
+    /// The live links of `workspace` and their visits in `month`; zero for
+    /// workspaces without any.
+    fn workspace_usage(
+        &self,
+        workspace: &str,
+        month: &str,
+    ) -> impl std::future::Future<Output = Result<Usage, AppError>> + std::marker::Send;
+}
+
+pub struct GetWorkspaceUsageQuery<R>
+where
+    R: GetWorkspaceUsageRepository,
+{
+    repo: R,
+    clock: SharedClock,
+}
+
+impl<R> GetWorkspaceUsageQuery<R>
+where
+    R: GetWorkspaceUsageRepository,
+{
+    pub fn new(repo: R) -> Self {
+        Self {
+            repo,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Where the query learns which month it is.
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// What `workspace_id` used this month, for its members and admins.
+    pub async fn execute(
+        &self,
+        principal: &Principal,
+        workspace_id: &str,
+    ) -> Result<Usage, AppError> {
+        policy::authorize(principal, Action::ReadWorkspace { workspace_id })?;
+        self.repo
+            .workspace_usage(workspace_id, &workspace::month_of(self.clock.now()))
+            .await
+    }
+}
@@ -0,0 +1,46 @@
+use crate::{
+    app::{
+        account::Principal,
+        policy::{self, Action},
+        report::AbuseReport,
+    },
+    error::AppError,
+};
+
+pub trait ListReportsRepository {
+    /// Page of reports, oldest first; only the open ones when `open_only`.
+    fn reports(
+        &self,
+        open_only: bool,
+        offset: usize,
+        limit: usize,
+    ) -> impl std::future::Future<Output = Result<Vec<AbuseReport>, AppError>> + std::marker::Send;
+}
+
+pub struct ListReportsQuery<R>
+where
+    R: ListReportsRepository,
+{
+    repo: R,
+}
+
+impl<R> ListReportsQuery<R>
+where
+    R: ListReportsRepository,
+{
+    pub fn new(repo: R) -> Self {
+        Self { repo }
+    }
+
+    /// The review queue, for admins.
+    pub async fn execute(
+        &self,
+        principal: &Principal,
+        open_only: bool,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Vec<AbuseReport>, AppError> {
+        policy::authorize(principal, Action::ModerateLinks)?;
+        self.repo.reports(open_only, offset, limit).await
+    }
+}
@@ -0,0 +1,216 @@
+use crate::{
+    app::{
+        account::Principal,
+        policy::{self, Action},
+        short_url::ShortUrl,
+    },
+    error::AppError,
+};
+
+/// Whose links a page holds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OwnerFilter<'a> {
+    Anyone,
+    /// Links of this owner; `None` for links without one.
+    Only(Option<&'a str>),
+    /// Links of this workspace, whoever created them.
+    Workspace(&'a str),
+}
+
+impl OwnerFilter<'_> {
+    pub fn matches(&self, link: &ShortUrl) -> bool {
+        match self {
+            OwnerFilter::Anyone => true,
+            OwnerFilter::Only(owner) => link.owner.as_deref() == *owner,
+            OwnerFilter::Workspace(workspace) => link.workspace.as_deref() == Some(*workspace),
+        }
+    }
+}
+
+/// Which links a caller asks to list.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum ListScope {
+    /// The caller's own links.
+    #[default]
+    Mine,
+    /// Every link; admins only.
+    All,
+    /// The links of one account; admins only, unless it is the caller's.
+    Account(String),
+    /// The links of one workspace; members and admins only.
+    Workspace(String),
+}
+
+pub trait ListShortUrlsRepository {
+    /// Page of the links `owner` matches, ordered by ID.
+    fn list(
+        &self,
+        owner: OwnerFilter<'_>,
+        offset: usize,
+        limit: usize,
+    ) -> impl std::future::Future<Output = Result<Vec<ShortUrl>, AppError>> + std::marker::Send;
+}
+
+pub struct ListShortUrlsQuery<R>
+where
+    R: ListShortUrlsRepository,
+{
+    repo: R,
+}
+
+impl<R> ListShortUrlsQuery<R>
+where
+    R: ListShortUrlsRepository,
+{
+    pub fn new(repo: R) -> Self {
+        Self { repo }
+    }
+
+    pub async fn execute(
+        &self,
+        principal: &Principal,
+        scope: &ListScope,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Vec<ShortUrl>, AppError> {
+        policy::authorize(principal, Action::ReadLinks)?;
+        let owner = match scope {
+            ListScope::Mine => OwnerFilter::Only(principal.account_id.as_deref()),
+            ListScope::All => {
+                policy::authorize(principal, Action::ListLinks { account_id: None })?;
+                OwnerFilter::Anyone
+            }
+            ListScope::Account(account_id) => {
+                policy::authorize(
+                    principal,
+                    Action::ListLinks {
+                        account_id: Some(account_id),
+                    },
+                )?;
+                OwnerFilter::Only(Some(account_id))
+            }
+            ListScope::Workspace(workspace) => {
+                policy::authorize(
+                    principal,
+                    Action::ReadWorkspace {
+                        workspace_id: workspace,
+                    },
+                )?;
+                OwnerFilter::Workspace(workspace)
+            }
+        };
+
+        self.repo.list(owner, offset, limit).await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use dashmap::DashMap;
+
+    use crate::adapters::inmemory::InMemoryRepository;
+
+    use crate::app::policy::Role;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn list_pages_in_id_order() {
+        // Given
+        let store = Arc::new(DashMap::new());
+        store.insert(
+            "c".to_owned(),
+            ShortUrl::new("c".to_owned(), "https://c.com".to_owned()),
+        );
+        store.insert(
+            "a".to_owned(),
+            ShortUrl::new("a".to_owned(), "https://a.com".to_owned()),
+        );
+        store.insert(
+            "b".to_owned(),
+            ShortUrl::new("b".to_owned(), "https://b.com".to_owned()),
+        );
+        let query = ListShortUrlsQuery::new(InMemoryRepository::new(store));
+        let operator = Principal::operator();
+
+        // When
+        let first = query
+            .execute(&operator, &ListScope::All, 0, 2)
+            .await
+            .unwrap();
+        let second = query
+            .execute(&operator, &ListScope::All, 2, 2)
+            .await
+            .unwrap();
+
+        // Then
+        let ids: Vec<_> = first.iter().chain(&second).map(|l| l.id.as_str()).collect();
+        assert_eq!(ids, vec!["a", "b", "c"]);
+        assert_eq!(second.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn accounts_list_their_own_links() {
+        // Given
+        let store = Arc::new(DashMap::new());
+        for (id, owner) in [("a", Some("alice")), ("b", Some("bob")), ("c", None)] {
+            let mut link = ShortUrl::new(id.to_owned(), format!("https://{id}.com"));
+            link.owner = owner.map(str::to_owned);
+            link.workspace = (id != "a").then(|| "team".to_owned());
+            store.insert(id.to_owned(), link);
+        }
+        let query = ListShortUrlsQuery::new(InMemoryRepository::new(store));
+        let alice = Principal {
+            account_id: Some("alice".to_owned()),
+            role: Role::Editor,
+            workspaces: vec!["team".to_owned()],
+        };
+
+        // When
+        let mine = query
+            .execute(&alice, &ListScope::Mine, 0, 10)
+            .await
+            .unwrap();
+        let all = query.execute(&alice, &ListScope::All, 0, 10).await;
+        let bobs = query
+            .execute(&alice, &ListScope::Account("bob".to_owned()), 0, 10)
+            .await;
+        let teams = query
+            .execute(&alice, &ListScope::Workspace("team".to_owned()), 0, 10)
+            .await
+            .unwrap();
+
+        // Then
+        assert_eq!(mine.len(), 1);
+        assert_eq!(mine[0].id, "a");
+        assert!(matches!(all, Err(AppError::Forbidden { .. })));
+        assert!(matches!(bobs, Err(AppError::Forbidden { .. })));
+        assert_eq!(teams.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn only_members_list_a_workspace() {
+        // Given
+        let store = Arc::new(DashMap::new());
+        let mut link = ShortUrl::new("a".to_owned(), "https://a.com".to_owned());
+        link.workspace = Some("team".to_owned());
+        store.insert(link.id.clone(), link);
+        let query = ListShortUrlsQuery::new(InMemoryRepository::new(store));
+        let outsider = Principal {
+            account_id: Some("bob".to_owned()),
+            role: Role::Editor,
+            workspaces: vec!["other".to_owned()],
+        };
+        let team = ListScope::Workspace("team".to_owned());
+
+        // When
+        let refused = query.execute(&outsider, &team, 0, 10).await;
+        let listed = query.execute(&Principal::operator(), &team, 0, 10).await;
+
+        // Then
+        assert!(matches!(refused, Err(AppError::Forbidden { .. })));
+        assert_eq!(listed.map(|links| links.len()), Ok(1));
+    }
+}
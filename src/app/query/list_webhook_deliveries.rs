@@ -0,0 +1,41 @@
+use crate::{
+    app::{
+        account::Principal,
+        policy::{self, Action},
+        webhook::WebhookDelivery,
+    },
+    error::AppError,
+};
+
+pub trait ListWebhookDeliveriesRepository {
+    /// Delivery log of a subscription, oldest attempt first.
+    fn list_deliveries(
+        &self,
+        subscription_id: &str,
+    ) -> impl std::future::Future<Output = Result<Vec<WebhookDelivery>, AppError>> + std::marker::Send;
+}
+
+pub struct ListWebhookDeliveriesQuery<W>
+where
+    W: ListWebhookDeliveriesRepository,
+{
+    repo: W,
+}
+
+impl<W> ListWebhookDeliveriesQuery<W>
+where
+    W: ListWebhookDeliveriesRepository,
+{
+    pub fn new(repo: W) -> Self {
+        Self { repo }
+    }
+
+    pub async fn execute(
+        &self,
+        principal: &Principal,
+        subscription_id: &str,
+    ) -> Result<Vec<WebhookDelivery>, AppError> {
+        policy::authorize(principal, Action::ManageWebhooks)?;
+        self.repo.list_deliveries(subscription_id).await
+    }
+}
@@ -0,0 +1,38 @@
+use crate::{
+    app::{
+        account::Principal,
+        policy::{self, Action},
+        webhook::WebhookSubscription,
+    },
+    error::AppError,
+};
+
+pub trait ListWebhooksRepository {
+    fn list_subscriptions(
+        &self,
+    ) -> impl std::future::Future<Output = Result<Vec<WebhookSubscription>, AppError>> + std::marker::Send;
+}
+
+pub struct ListWebhooksQuery<W>
+where
+    W: ListWebhooksRepository,
+{
+    repo: W,
+}
+
+impl<W> ListWebhooksQuery<W>
+where
+    W: ListWebhooksRepository,
+{
+    pub fn new(repo: W) -> Self {
+        Self { repo }
+    }
+
+    pub async fn execute(
+        &self,
+        principal: &Principal,
+    ) -> Result<Vec<WebhookSubscription>, AppError> {
+        policy::authorize(principal, Action::ManageWebhooks)?;
+        self.repo.list_subscriptions().await
+    }
+}
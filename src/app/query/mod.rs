@@ -1 +1,12 @@
+pub mod authenticate;
+pub mod export_short_urls;
 pub mod get_full_url;
+pub mod get_link_health;
+pub mod get_link_stats;
+pub mod get_stats;
+pub mod get_workspace;
+pub mod get_workspace_usage;
+pub mod list_reports;
+pub mod list_short_urls;
+pub mod list_webhook_deliveries;
+pub mod list_webhooks;
@@ -0,0 +1,294 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{app::target, error::AppError};
+
+/// Most rules a link may carry; they are checked on every visit.
+pub const MAX_RULES: usize = 32;
+
+/// Operating system family, as far as the user agent tells.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Platform {
+    Ios,
+    Android,
+    Windows,
+    Macos,
+    Linux,
+    Other,
+}
+
+impl Platform {
+    pub fn from_user_agent(user_agent: &str) -> Self {
+        let user_agent = user_agent.to_ascii_lowercase();
+        let has = |needle: &str| user_agent.contains(needle);
+
+        // Order matters: iOS agents mention Mac OS X, Android ones Linux.
+        if has("iphone") || has("ipad") || has("ipod") {
+            Platform::Ios
+        } else if has("android") {
+            Platform::Android
+        } else if has("windows") {
+            Platform::Windows
+        } else if has("macintosh") || has("mac os x") {
+            Platform::Macos
+        } else if has("linux") {
+            Platform::Linux
+        } else {
+            Platform::Other
+        }
+    }
+}
+
+/// What is known about whoever follows a link.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Visitor {
+    pub platform: Platform,
+    /// The most preferred language tag, e.g. `de-AT`.
+    pub language: Option<String>,
+    /// ISO 3166-1 alpha-2 code.
+    pub country: Option<String>,
+    /// Unix time of the visit.
+    pub now: u64,
+    /// Stable for one visitor as far as the request tells; splits traffic
+    /// when no variant is remembered.
+    pub fingerprint: String,
+    /// The variant this visitor was served before, if they say so.
+    pub variant: Option<String>,
+    /// Path segments after the link ID, e.g. `extra/path` for
+    /// `/abc/extra/path`.
+    pub path: String,
+    /// Query parameters the visitor arrived with, in order.
+    pub query: Vec<(String, String)>,
+}
+
+impl Default for Visitor {
+    fn default() -> Self {
+        Self {
+            platform: Platform::Other,
+            language: None,
+            country: None,
+            now: 0,
+            fingerprint: String::new(),
+            variant: None,
+            path: String::new(),
+            query: Vec::new(),
+        }
+    }
+}
+
+/// Sends visitors matching every given condition to `target`. Within a
+/// condition any listed value matches; conditions left empty match all.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct RedirectRule {
+    pub target: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub platforms: Vec<Platform>,
+    /// Language tags; `de` also matches `de-AT`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub languages: Vec<String>,
+    /// ISO 3166-1 alpha-2 codes.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub countries: Vec<String>,
+    /// Unix time from which the rule applies.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub starts_at: Option<u64>,
+    /// Unix time from which the rule no longer applies.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ends_at: Option<u64>,
+}
+
+impl RedirectRule {
+    pub fn matches(&self, visitor: &Visitor) -> bool {
+        let platform = self.platforms.is_empty() || self.platforms.contains(&visitor.platform);
+        let language = self.languages.is_empty()
+            || visitor.language.as_deref().is_some_and(|language| {
+                self.languages
+                    .iter()
+                    .any(|wanted| language_matches(wanted, language))
+            });
+        let country = self.countries.is_empty()
+            || visitor.country.as_deref().is_some_and(|country| {
+                self.countries
+                    .iter()
+                    .any(|wanted| wanted.eq_ignore_ascii_case(country))
+            });
+        let started = self.starts_at.is_none_or(|at| visitor.now >= at);
+        let ended = self.ends_at.is_some_and(|at| visitor.now >= at);
+
+        platform && language && country && started && !ended
+    }
+}
+
+/// The target of the first rule `visitor` matches.
+pub fn first_match<'a>(rules: &'a [RedirectRule], visitor: &Visitor) -> Option<&'a str> {
+    rules
+        .iter()
+        .find(|rule| rule.matches(visitor))
+        .map(|rule| rule.target.as_str())
+}
+
+/// Checks rules before they are stored and normalizes their targets and
+/// country codes.
+pub fn validate(rules: Vec<RedirectRule>) -> Result<Vec<RedirectRule>, AppError> {
+    if rules.len() > MAX_RULES {
+        return Err(AppError::InvalidRule {
+            index: MAX_RULES,
+            reason: format!("a link has at most {MAX_RULES} rules"),
+        });
+    }
+
+    rules
+        .into_iter()
+        .enumerate()
+        .map(|(index, mut rule)| {
+            let invalid = |reason: String| AppError::InvalidRule { index, reason };
+
+            rule.target = target::validate(&rule.target)
+                .map_err(|err| invalid(format!("invalid target `{}`: {err}", rule.target)))?;
+            for country in &mut rule.countries {
+                if country.len() != 2 || !country.bytes().all(|b| b.is_ascii_alphabetic()) {
+                    return Err(invalid(format!(
+                        "`{country}` is not a two letter country code"
+                    )));
+                }
+                country.make_ascii_uppercase();
+            }
+            if let Some(language) = rule.languages.iter().find(|l| !is_language_tag(l)) {
+                return Err(invalid(format!("`{language}` is not a language tag")));
+            }
+            if let (Some(starts_at), Some(ends_at)) = (rule.starts_at, rule.ends_at) {
+                if starts_at >= ends_at {
+                    return Err(invalid("`starts_at` must be before `ends_at`".to_owned()));
+                }
+            }
+
+            Ok(rule)
+        })
+        .collect()
+}
+
+/// Whether `wanted` is `tag` or one of its prefixes, by whole subtags.
+fn language_matches(wanted: &str, tag: &str) -> bool {
+    tag.len() >= wanted.len()
+        && tag[..wanted.len()].eq_ignore_ascii_case(wanted)
+        && matches!(tag.as_bytes().get(wanted.len()), None | Some(b'-'))
+}
+
+fn is_language_tag(tag: &str) -> bool {
+    !tag.is_empty()
+        && tag.split('-').all(|subtag| {
+            (1..=8).contains(&subtag.len()) && subtag.bytes().all(|b| b.is_ascii_alphanumeric())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(target: &str) -> RedirectRule {
+        RedirectRule {
+            target: target.to_owned(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn detects_platforms() {
+        let iphone = "Mozilla/5.0 (iPhone; CPU iPhone OS 17_5 like Mac OS X) AppleWebKit/605.1.15";
+        let pixel = "Mozilla/5.0 (Linux; Android 14; Pixel 8) AppleWebKit/537.36";
+        let mac = "Mozilla/5.0 (Macintosh; Intel Mac OS X 14_5) AppleWebKit/605.1.15";
+
+        assert_eq!(Platform::from_user_agent(iphone), Platform::Ios);
+        assert_eq!(Platform::from_user_agent(pixel), Platform::Android);
+        assert_eq!(Platform::from_user_agent(mac), Platform::Macos);
+        assert_eq!(Platform::from_user_agent("curl/8.5.0"), Platform::Other);
+    }
+
+    #[test]
+    fn first_matching_rule_wins() {
+        // Given
+        let rules = vec![
+            RedirectRule {
+                platforms: vec![Platform::Ios],
+                ..rule("https://apps.apple.com/app/id1")
+            },
+            RedirectRule {
+                platforms: vec![Platform::Android],
+                ..rule("https://play.google.com/store/apps/details?id=app")
+            },
+            RedirectRule {
+                languages: vec!["de".to_owned()],
+                countries: vec!["AT".to_owned(), "DE".to_owned()],
+                ..rule("https://example.com/de")
+            },
+        ];
+        let visitor = |platform, language: &str, country: &str| Visitor {
+            platform,
+            language: Some(language.to_owned()),
+            country: Some(country.to_owned()),
+            ..Default::default()
+        };
+
+        // Then
+        assert_eq!(
+            first_match(&rules, &visitor(Platform::Ios, "de-AT", "AT")),
+            Some("https://apps.apple.com/app/id1")
+        );
+        assert_eq!(
+            first_match(&rules, &visitor(Platform::Windows, "de-AT", "at")),
+            Some("https://example.com/de")
+        );
+        assert_eq!(
+            first_match(&rules, &visitor(Platform::Windows, "den", "AT")),
+            None
+        );
+        assert_eq!(
+            first_match(&rules, &visitor(Platform::Windows, "de", "CH")),
+            None
+        );
+    }
+
+    #[test]
+    fn time_windows_include_start_and_exclude_end() {
+        let campaign = RedirectRule {
+            starts_at: Some(100),
+            ends_at: Some(200),
+            ..rule("https://example.com/sale")
+        };
+        let at = |now| Visitor {
+            now,
+            ..Default::default()
+        };
+
+        assert!(!campaign.matches(&at(99)));
+        assert!(campaign.matches(&at(100)));
+        assert!(!campaign.matches(&at(200)));
+    }
+
+    #[test]
+    fn validation_normalizes_and_rejects() {
+        let valid = validate(vec![RedirectRule {
+            countries: vec!["us".to_owned()],
+            ..rule("https://example.com")
+        }])
+        .unwrap();
+        assert_eq!(valid[0].countries, ["US"]);
+        assert_eq!(valid[0].target, "https://example.com/");
+
+        let bad_country = RedirectRule {
+            countries: vec!["USA".to_owned()],
+            ..rule("https://example.com")
+        };
+        assert!(matches!(
+            validate(vec![rule("https://example.com"), bad_country]),
+            Err(AppError::InvalidRule { index: 1, .. })
+        ));
+        assert!(validate(vec![rule("not a url")]).is_err());
+        assert!(validate(vec![RedirectRule {
+            starts_at: Some(2),
+            ends_at: Some(1),
+            ..rule("https://example.com")
+        }])
+        .is_err());
+    }
+}
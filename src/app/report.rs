@@ -0,0 +1,71 @@
+//! Abuse reports visitors file against links, for admins to review.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{app::short_url::LinkStatus, error::AppError};
+
+/// Longest reason a report may give, in characters.
+pub const MAX_REASON_CHARS: usize = 1_000;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct AbuseReport {
+    pub id: String,
+    pub link_id: String,
+    pub reason: String,
+    pub created_at: u64,
+    /// When an admin reviewed the report; open until then.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reviewed_at: Option<u64>,
+    /// The status the link was given on review.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub outcome: Option<LinkStatus>,
+}
+
+impl AbuseReport {
+    pub fn is_open(&self) -> bool {
+        self.reviewed_at.is_none()
+    }
+
+    pub fn close(&mut self, outcome: LinkStatus, reviewed_at: u64) {
+        self.outcome = Some(outcome);
+        self.reviewed_at = Some(reviewed_at);
+    }
+}
+
+/// `reason` without surrounding whitespace, if it is fit to store.
+pub fn validate_reason(reason: &str) -> Result<String, AppError> {
+    let reason = reason.trim();
+    let invalid = |reason: &str| {
+        Err(AppError::InvalidReport {
+            reason: reason.to_owned(),
+        })
+    };
+
+    if reason.is_empty() {
+        return invalid("a reason is required");
+    }
+    if reason.chars().count() > MAX_REASON_CHARS {
+        return invalid("the reason is too long");
+    }
+    if reason.chars().any(|c| c.is_control() && c != '\n') {
+        return invalid("the reason contains control characters");
+    }
+
+    Ok(reason.to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reasons_are_trimmed_and_bounded() {
+        assert_eq!(
+            validate_reason("  phishing page\n"),
+            Ok("phishing page".to_owned())
+        );
+        assert!(validate_reason(" \n ").is_err());
+        assert!(validate_reason(&"x".repeat(MAX_REASON_CHARS + 1)).is_err());
+        assert!(validate_reason("bell\u{7}").is_err());
+    }
+}
@@ -0,0 +1,57 @@
+//! Screening destinations against lists of known phishing and malware
+//! sites. Links that fail are kept but quarantined: visitors get a warning
+//! page instead of the redirect.
+
+use std::sync::Arc;
+
+use crate::app::short_url::ShortUrl;
+
+/// Says whether a destination is known to be harmful. Lookups are local,
+/// so nothing leaves the server while links are created or followed.
+#[mockall::automock]
+pub trait UrlScreener {
+    /// Why `url` is unsafe to send visitors to, or `None` when no list
+    /// has it.
+    fn screen(&self, url: &str) -> Option<String>;
+}
+
+pub type SharedScreener = Arc<dyn UrlScreener + Send + Sync>;
+
+/// Screens every destination of `link`: its URL and the targets of its
+/// rules and variants. The first hit is the reason.
+pub fn screen_link(screener: &dyn UrlScreener, link: &ShortUrl) -> Option<String> {
+    std::iter::once(&link.url)
+        .chain(link.rules.iter().map(|rule| &rule.target))
+        .chain(link.variants.iter().map(|variant| &variant.target))
+        .find_map(|target| screener.screen(target))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app::variant::Variant;
+
+    use super::*;
+
+    #[test]
+    fn screens_every_destination() {
+        // Given
+        let mut screener = MockUrlScreener::new();
+        screener
+            .expect_screen()
+            .returning(|url| url.contains("evil").then(|| format!("{url} is listed")));
+        let mut link = ShortUrl::new("abc".to_owned(), "https://example.com/".to_owned());
+
+        // When
+        let clean = screen_link(&screener, &link);
+        link.variants = vec![Variant {
+            name: "b".to_owned(),
+            target: "https://evil.example/".to_owned(),
+            weight: 50,
+        }];
+        let flagged = screen_link(&screener, &link);
+
+        // Then
+        assert_eq!(clean, None);
+        assert_eq!(flagged.as_deref(), Some("https://evil.example/ is listed"));
+    }
+}
@@ -0,0 +1,190 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app::{redirect_rule::RedirectRule, target::QueryPassthrough, variant::Variant},
+    clock::{Clock, SystemClock},
+    error::AppError,
+};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ShortUrl {
+    pub id: String,
+    pub url: String,
+    pub created_at: u64,
+    /// Free-form attributes carried over from imports or set by clients.
+    #[serde(default)]
+    pub metadata: BTreeMap<String, String>,
+    /// Unix time after which the link no longer resolves. Never when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<u64>,
+    /// Unix time before which the link does not resolve yet. Live from the
+    /// start when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub activates_at: Option<u64>,
+    /// Visitors who come before `activates_at` get a coming soon page
+    /// rather than a 404.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub coming_soon_page: bool,
+    /// Visitors see the preview page instead of being redirected.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub always_preview: bool,
+    /// What chat apps and social sites show when the link is shared.
+    #[serde(default, skip_serializing_if = "OpenGraph::is_empty")]
+    pub open_graph: OpenGraph,
+    /// Checked in order on every visit; the first match replaces `url`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<RedirectRule>,
+    /// Weighted targets splitting the visitors no rule matched.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub variants: Vec<Variant>,
+    /// Whether the query a visitor arrives with reaches the target.
+    #[serde(default, skip_serializing_if = "QueryPassthrough::is_off")]
+    pub query_passthrough: QueryPassthrough,
+    /// The account that created the link; `None` for links made by the
+    /// operator or before there were accounts.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    /// The workspace owning the link jointly with `owner`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub workspace: Option<String>,
+    #[serde(default, skip_serializing_if = "LinkStatus::is_active")]
+    pub status: LinkStatus,
+    /// Why the link is disabled or quarantined.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status_reason: Option<String>,
+}
+
+/// Whether a link redirects. Disabled and quarantined links keep their
+/// stats and history so they can be restored.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkStatus {
+    #[default]
+    Active,
+    /// Taken down, e.g. after an abuse report; visitors get a 451.
+    Disabled,
+    /// Suspected to be harmful; visitors get a warning page instead.
+    Quarantined,
+}
+
+impl LinkStatus {
+    pub fn is_active(&self) -> bool {
+        *self == LinkStatus::Active
+    }
+}
+
+/// Whom a link belongs to, which decides who besides admins may change it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Ownership {
+    pub owner: Option<String>,
+    pub workspace: Option<String>,
+}
+
+/// `og:` values served to unfurl bots in place of the destination's.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct OpenGraph {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Absolute http or https URL of the card image.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+}
+
+impl OpenGraph {
+    pub fn is_empty(&self) -> bool {
+        self.title.is_none() && self.description.is_none() && self.image.is_none()
+    }
+
+    pub fn validate(&self) -> Result<(), AppError> {
+        let Some(image) = &self.image else {
+            return Ok(());
+        };
+
+        let parsed = url::Url::parse(image).map_err(|err| AppError::invalid_url(image, err))?;
+        match parsed.scheme() {
+            "http" | "https" => Ok(()),
+            _ => Err(AppError::invalid_url(
+                image,
+                "only http and https are supported",
+            )),
+        }
+    }
+}
+
+impl ShortUrl {
+    /// A plain link created now by the system clock; commands set
+    /// `created_at` from their own clock.
+    pub fn new(id: String, url: String) -> Self {
+        Self {
+            id,
+            url,
+            created_at: SystemClock.now(),
+            metadata: BTreeMap::new(),
+            expires_at: None,
+            activates_at: None,
+            coming_soon_page: false,
+            always_preview: false,
+            open_graph: OpenGraph::default(),
+            rules: Vec::new(),
+            variants: Vec::new(),
+            query_passthrough: QueryPassthrough::Off,
+            owner: None,
+            workspace: None,
+            status: LinkStatus::Active,
+            status_reason: None,
+        }
+    }
+
+    pub fn ownership(&self) -> Ownership {
+        Ownership {
+            owner: self.owner.clone(),
+            workspace: self.workspace.clone(),
+        }
+    }
+
+    /// Quarantines the link for `reason`.
+    pub fn quarantine(&mut self, reason: String) {
+        self.status = LinkStatus::Quarantined;
+        self.status_reason = Some(reason);
+    }
+
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.expires_at = Some(self.created_at.saturating_add(ttl.as_secs()));
+        self
+    }
+
+    /// Seconds the link has left to live at `now`: `None` for links that
+    /// never expire, `Some(0)` for links that already have.
+    pub fn remaining_ttl(&self, now: u64) -> Option<u64> {
+        self.expires_at
+            .map(|expires_at| expires_at.saturating_sub(now))
+    }
+
+    /// Refuses links that would expire before they go live.
+    pub fn validate_schedule(&self) -> Result<(), AppError> {
+        match (self.activates_at, self.expires_at) {
+            (Some(activates_at), Some(expires_at)) if activates_at >= expires_at => {
+                Err(AppError::InvalidSchedule {
+                    reason: "the link would expire before it goes live".to_owned(),
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Whether the link is scheduled and not live yet at `now`.
+    pub fn is_pending(&self, now: u64) -> bool {
+        self.activates_at
+            .is_some_and(|activates_at| now < activates_at)
+    }
+
+    /// Whether the link has expired at `now`.
+    pub fn is_expired(&self, now: u64) -> bool {
+        self.remaining_ttl(now) == Some(0)
+    }
+}
@@ -0,0 +1,296 @@
+//! Turning a stored target into the URL one visitor is sent to: templated
+//! targets such as `https://shop.example/{path}?ref={ref}` are filled from
+//! the request, and the request's query can be passed through.
+
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use serde::{Deserialize, Serialize};
+
+use crate::app::redirect_rule::Visitor;
+
+/// Everything but the URL unreserved characters.
+const COMPONENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+/// Filled with the path segments after the link ID; any other placeholder
+/// takes the query value of that name.
+const PATH: &str = "path";
+
+/// Whether and how the query a visitor arrives with reaches the target.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryPassthrough {
+    /// The target is used as stored.
+    #[default]
+    Off,
+    /// Incoming parameters are appended unless the target already sets them.
+    TargetWins,
+    /// Incoming parameters are appended and replace those the target sets.
+    VisitorWins,
+}
+
+impl QueryPassthrough {
+    pub fn is_off(&self) -> bool {
+        *self == QueryPassthrough::Off
+    }
+}
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum TargetError {
+    #[error(transparent)]
+    Url(#[from] url::ParseError),
+    #[error("placeholders may only follow the host")]
+    PlaceholderInHost,
+    #[error("`{{` is never closed")]
+    Unclosed,
+    #[error("`{0}` is not a placeholder name")]
+    BadPlaceholder(String),
+    #[error("`{0}` targets are not allowed, only http and https")]
+    Scheme(String),
+}
+
+enum Part<'a> {
+    Literal(&'a str),
+    Placeholder(&'a str),
+}
+
+/// Checks a target before it is stored. Plain URLs come back normalized;
+/// templates come back as given, since normalizing would encode their
+/// braces. Only web targets pass: pages we render link to them, and a
+/// `javascript:` or `data:` one would run there.
+pub fn validate(target: &str) -> Result<String, TargetError> {
+    let Some(start) = target.find('{') else {
+        return Ok(web_url(target)?.to_string());
+    };
+
+    // A visitor picks the values, so they must not pick the host.
+    let host_is_fixed = target[..start]
+        .split_once("://")
+        .is_some_and(|(_, rest)| rest.contains(['/', '?', '#']));
+    if !host_is_fixed {
+        return Err(TargetError::PlaceholderInHost);
+    }
+    let filled: String = parse(target)?
+        .into_iter()
+        .map(|part| match part {
+            Part::Literal(text) => text,
+            Part::Placeholder(_) => "x",
+        })
+        .collect();
+    web_url(&filled)?;
+
+    Ok(target.to_owned())
+}
+
+fn web_url(target: &str) -> Result<url::Url, TargetError> {
+    let url = url::Url::parse(target)?;
+    match url.scheme() {
+        "http" | "https" => Ok(url),
+        scheme => Err(TargetError::Scheme(scheme.to_owned())),
+    }
+}
+
+/// Where `visitor` goes for the stored `target`.
+pub fn build(target: &str, passthrough: QueryPassthrough, visitor: &Visitor) -> String {
+    let (filled, used) = fill(target, visitor);
+    let incoming: Vec<&(String, String)> = visitor
+        .query
+        .iter()
+        .filter(|(name, _)| !used.contains(&name.as_str()))
+        .collect();
+    if passthrough.is_off() || incoming.is_empty() {
+        return filled;
+    }
+    let Ok(mut url) = url::Url::parse(&filled) else {
+        return filled;
+    };
+
+    let own: Vec<(String, String)> = url.query_pairs().into_owned().collect();
+    let kept: Vec<&(String, String)> = own
+        .iter()
+        .filter(|(name, _)| {
+            passthrough == QueryPassthrough::TargetWins || !incoming.iter().any(|(n, _)| n == name)
+        })
+        .collect();
+    let added: Vec<&(String, String)> = incoming
+        .iter()
+        .copied()
+        .filter(|(name, _)| {
+            passthrough == QueryPassthrough::VisitorWins || !own.iter().any(|(n, _)| n == name)
+        })
+        .collect();
+
+    url.query_pairs_mut()
+        .clear()
+        .extend_pairs(kept)
+        .extend_pairs(added);
+
+    url.to_string()
+}
+
+/// Fills the placeholders of `target` and returns the query parameters it
+/// used. Targets that are not templates come back as they are.
+fn fill<'a>(target: &str, visitor: &'a Visitor) -> (String, Vec<&'a str>) {
+    let Ok(parts) = parse(target) else {
+        return (target.to_owned(), Vec::new());
+    };
+
+    let mut filled = String::with_capacity(target.len());
+    let mut used = Vec::new();
+    for part in parts {
+        match part {
+            Part::Literal(text) => filled.push_str(text),
+            Part::Placeholder(PATH) => {
+                let segments = visitor
+                    .path
+                    .split('/')
+                    .map(|segment| utf8_percent_encode(segment, COMPONENT).to_string());
+                filled.push_str(&segments.collect::<Vec<_>>().join("/"));
+            }
+            Part::Placeholder(name) => {
+                if let Some((name, value)) = visitor.query.iter().find(|(n, _)| n == name) {
+                    filled.extend(utf8_percent_encode(value, COMPONENT));
+                    used.push(name.as_str());
+                }
+            }
+        }
+    }
+
+    (filled, used)
+}
+
+fn parse(target: &str) -> Result<Vec<Part<'_>>, TargetError> {
+    let mut parts = Vec::new();
+    let mut rest = target;
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..].find('}').ok_or(TargetError::Unclosed)? + start;
+        let name = &rest[start + 1..end];
+        if name.is_empty() || !name.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_') {
+            return Err(TargetError::BadPlaceholder(name.to_owned()));
+        }
+        parts.push(Part::Literal(&rest[..start]));
+        parts.push(Part::Placeholder(name));
+        rest = &rest[end + 1..];
+    }
+    parts.push(Part::Literal(rest));
+
+    Ok(parts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn visitor(path: &str, query: &[(&str, &str)]) -> Visitor {
+        Visitor {
+            path: path.to_owned(),
+            query: query
+                .iter()
+                .map(|&(name, value)| (name.to_owned(), value.to_owned()))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn fills_templates_from_path_and_query() {
+        // Given
+        let target = "https://shop.example/{path}?ref={ref}";
+        let visitor = visitor("shoes/red pair", &[("ref", "news&letter"), ("x", "1")]);
+
+        // When
+        let url = build(target, QueryPassthrough::Off, &visitor);
+
+        // Then
+        assert_eq!(
+            url,
+            "https://shop.example/shoes/red%20pair?ref=news%26letter"
+        );
+        assert_eq!(
+            build(target, QueryPassthrough::Off, &Visitor::default()),
+            "https://shop.example/?ref="
+        );
+    }
+
+    #[test]
+    fn passthrough_follows_the_precedence() {
+        // Given
+        let target = "https://example.com/?utm_source=link&a=1";
+        let visitor = visitor("", &[("utm_source", "mail"), ("b", "2")]);
+
+        // Then
+        assert_eq!(build(target, QueryPassthrough::Off, &visitor), target);
+        assert_eq!(
+            build(target, QueryPassthrough::TargetWins, &visitor),
+            "https://example.com/?utm_source=link&a=1&b=2"
+        );
+        assert_eq!(
+            build(target, QueryPassthrough::VisitorWins, &visitor),
+            "https://example.com/?a=1&utm_source=mail&b=2"
+        );
+    }
+
+    #[test]
+    fn values_used_by_the_template_are_not_passed_again() {
+        let visitor = visitor("", &[("ref", "mail"), ("utm_medium", "email")]);
+
+        assert_eq!(
+            build(
+                "https://example.com/?ref={ref}",
+                QueryPassthrough::VisitorWins,
+                &visitor
+            ),
+            "https://example.com/?ref=mail&utm_medium=email"
+        );
+    }
+
+    #[test]
+    fn validation_keeps_templates_and_guards_the_host() {
+        assert_eq!(
+            validate("https://example.com").unwrap(),
+            "https://example.com/"
+        );
+        assert_eq!(
+            validate("https://shop.example/{path}?ref={ref}").unwrap(),
+            "https://shop.example/{path}?ref={ref}"
+        );
+        assert_eq!(
+            validate("https://{path}/"),
+            Err(TargetError::PlaceholderInHost)
+        );
+        assert_eq!(
+            validate("https://example.com{path}"),
+            Err(TargetError::PlaceholderInHost)
+        );
+        assert_eq!(
+            validate("https://example.com/{path"),
+            Err(TargetError::Unclosed)
+        );
+        assert_eq!(
+            validate("https://example.com/{a b}"),
+            Err(TargetError::BadPlaceholder("a b".to_owned()))
+        );
+    }
+
+    #[test]
+    fn only_web_targets_pass() {
+        assert_eq!(
+            validate("http://example.com/a").unwrap(),
+            "http://example.com/a"
+        );
+        assert_eq!(
+            validate("javascript:alert(document.cookie)"),
+            Err(TargetError::Scheme("javascript".to_owned()))
+        );
+        assert_eq!(
+            validate("data:text/html,<script>alert(1)</script>"),
+            Err(TargetError::Scheme("data".to_owned()))
+        );
+        assert_eq!(
+            validate("ftp://example.com/{path}"),
+            Err(TargetError::Scheme("ftp".to_owned()))
+        );
+    }
+}
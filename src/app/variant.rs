@@ -0,0 +1,146 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{app::target, error::AppError};
+
+/// Weights of a link's variants add up to this, so they read as percent.
+pub const TOTAL_WEIGHT: u32 = 100;
+
+/// One arm of an A/B split.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct Variant {
+    /// Reported in analytics and remembered in the visitor's cookie.
+    pub name: String,
+    pub target: String,
+    /// Share of visitors, out of `TOTAL_WEIGHT`.
+    pub weight: u32,
+}
+
+/// The variant `visitor_key` lands on for the link `link_id`. The same key
+/// always lands on the same variant as long as the weights do not change.
+pub fn assign<'a>(
+    variants: &'a [Variant],
+    link_id: &str,
+    visitor_key: &str,
+) -> Option<&'a Variant> {
+    let mut hasher = Sha256::new();
+    hasher.update((link_id.len() as u64).to_be_bytes());
+    hasher.update(link_id);
+    hasher.update(visitor_key);
+    let digest = hasher.finalize();
+    let bucket = u64::from_be_bytes(digest[..8].try_into().expect("digest has 32 bytes"))
+        % u64::from(TOTAL_WEIGHT);
+
+    let mut upper = 0;
+    variants.iter().find(|variant| {
+        upper += u64::from(variant.weight);
+        bucket < upper
+    })
+}
+
+pub fn find<'a>(variants: &'a [Variant], name: &str) -> Option<&'a Variant> {
+    variants.iter().find(|variant| variant.name == name)
+}
+
+/// Checks variants before they are stored and normalizes their targets.
+/// A link has either no variants or at least two.
+pub fn validate(variants: Vec<Variant>) -> Result<Vec<Variant>, AppError> {
+    let invalid = |reason: String| Err(AppError::InvalidVariants { reason });
+
+    if variants.len() == 1 {
+        return invalid("a split needs at least two variants".to_owned());
+    }
+    let total: u64 = variants.iter().map(|v| u64::from(v.weight)).sum();
+    if !variants.is_empty() && total != u64::from(TOTAL_WEIGHT) {
+        return invalid(format!("weights add up to {total}, not {TOTAL_WEIGHT}"));
+    }
+
+    let mut checked: Vec<Variant> = Vec::with_capacity(variants.len());
+    for mut variant in variants {
+        if !is_variant_name(&variant.name) {
+            return invalid(format!(
+                "`{}` is not a variant name: use 1 to 32 letters, digits, `-` or `_`",
+                variant.name
+            ));
+        }
+        if checked.iter().any(|other| other.name == variant.name) {
+            return invalid(format!("`{}` is used twice", variant.name));
+        }
+        if variant.weight == 0 {
+            return invalid(format!("`{}` has no weight", variant.name));
+        }
+        variant.target = match target::validate(&variant.target) {
+            Ok(target) => target,
+            Err(err) => {
+                return invalid(format!(
+                    "`{}` has an invalid target `{}`: {err}",
+                    variant.name, variant.target
+                ))
+            }
+        };
+        checked.push(variant);
+    }
+
+    Ok(checked)
+}
+
+/// Names end up in cookies, so they are kept to token characters.
+fn is_variant_name(name: &str) -> bool {
+    (1..=32).contains(&name.len())
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variant(name: &str, weight: u32) -> Variant {
+        Variant {
+            name: name.to_owned(),
+            target: format!("https://example.com/{name}"),
+            weight,
+        }
+    }
+
+    #[test]
+    fn assignment_follows_the_weights_and_sticks() {
+        // Given
+        let variants = vec![variant("a", 70), variant("b", 30)];
+
+        // When
+        let assigned: Vec<&str> = (0..10_000)
+            .map(|visitor| {
+                assign(&variants, "abc", &visitor.to_string())
+                    .unwrap()
+                    .name
+                    .as_str()
+            })
+            .collect();
+
+        // Then
+        let a = assigned.iter().filter(|&&name| name == "a").count();
+        assert!((6_700..7_300).contains(&a), "{a} of 10000 got a");
+        assert_eq!(assign(&variants, "abc", "42").unwrap().name, assigned[42]);
+    }
+
+    #[test]
+    fn validation_checks_weights_and_names() {
+        assert!(validate(vec![]).unwrap().is_empty());
+        assert_eq!(
+            validate(vec![variant("a", 70), variant("b", 30)])
+                .unwrap()
+                .len(),
+            2
+        );
+        assert!(matches!(
+            validate(vec![variant("a", 70), variant("b", 40)]),
+            Err(AppError::InvalidVariants { reason }) if reason.contains("110")
+        ));
+        assert!(validate(vec![variant("a", 100)]).is_err());
+        assert!(validate(vec![variant("a", 50), variant("a", 50)]).is_err());
+        assert!(validate(vec![variant("a", 100), variant("b", 0)]).is_err());
+        assert!(validate(vec![variant("a b", 50), variant("c", 50)]).is_err());
+    }
+}
@@ -0,0 +1,345 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::{
+    app::event::{DomainEvent, EventKind},
+    clock::{SharedClock, SystemClock},
+    error::AppError,
+};
+
+use super::{
+    sign, WebhookDelivery, WebhookSubscription, EVENT_HEADER, EVENT_ID_HEADER, SIGNATURE_HEADER,
+    TIMESTAMP_HEADER,
+};
+
+#[mockall::automock]
+#[async_trait]
+pub trait WebhookSender {
+    /// Posts `body` to `url` and returns the response status code.
+    async fn send(
+        &self,
+        url: &str,
+        headers: Vec<(String, String)>,
+        body: Vec<u8>,
+    ) -> Result<u16, String>;
+}
+
+#[mockall::automock]
+#[async_trait]
+pub trait WebhookDeliveryRepository {
+    async fn subscriptions_for(
+        &self,
+        kind: EventKind,
+    ) -> Result<Vec<WebhookSubscription>, AppError>;
+    async fn record_delivery(&self, delivery: WebhookDelivery) -> Result<(), AppError>;
+}
+
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl RetryPolicy {
+    /// Delay to wait after the given (1-based) failed attempt.
+    pub fn delay_for(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+
+        self.base_delay.saturating_mul(factor).min(self.max_delay)
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60),
+        }
+    }
+}
+
+pub struct WebhookDispatcher<W, S>
+where
+    W: WebhookDeliveryRepository,
+    S: WebhookSender,
+{
+    repo: W,
+    sender: S,
+    policy: RetryPolicy,
+    clock: SharedClock,
+}
+
+impl<W, S> WebhookDispatcher<W, S>
+where
+    W: WebhookDeliveryRepository + Send + Sync + 'static,
+    S: WebhookSender + Send + Sync + 'static,
+{
+    pub fn new(repo: W, sender: S, policy: RetryPolicy) -> Self {
+        Self {
+            repo,
+            sender,
+            policy,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Where signatures and the delivery log take their timestamps from.
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Consumes events until the bus is closed. Each subscription gets its
+    /// own delivery task so a slow endpoint does not hold back the others.
+    pub async fn run(self, mut events: broadcast::Receiver<DomainEvent>) {
+        let this = Arc::new(self);
+
+        loop {
+            let event = match events.recv().await {
+                Ok(event) => event,
+                Err(RecvError::Lagged(skipped)) => {
+                    tracing::warn!(skipped, "webhook dispatcher lagged behind the event bus");
+                    continue;
+                }
+                Err(RecvError::Closed) => break,
+            };
+
+            let subscriptions = match this.repo.subscriptions_for(event.kind).await {
+                Ok(subscriptions) => subscriptions,
+                Err(err) => {
+                    tracing::error!(%err, "failed to load webhook subscriptions");
+                    continue;
+                }
+            };
+
+            for subscription in subscriptions {
+                let this = this.clone();
+                let event = event.clone();
+                tokio::spawn(async move { this.deliver(&subscription, &event).await });
+            }
+        }
+    }
+
+    /// Delivers one event to one subscription, retrying with exponential
+    /// backoff. Every attempt is written to the delivery log. Returns whether
+    /// the event was eventually accepted.
+    pub async fn deliver(&self, subscription: &WebhookSubscription, event: &DomainEvent) -> bool {
+        let body = match serde_json::to_vec(event) {
+            Ok(body) => body,
+            Err(err) => {
+                tracing::error!(%err, "failed to serialize webhook payload");
+                return false;
+            }
+        };
+
+        for attempt in 1..=self.policy.max_attempts {
+            let timestamp = self.clock.now();
+            let headers = vec![
+                ("Content-Type".to_owned(), "application/json".to_owned()),
+                (
+                    SIGNATURE_HEADER.to_owned(),
+                    format!("sha256={}", sign(&subscription.secret, timestamp, &body)),
+                ),
+                (TIMESTAMP_HEADER.to_owned(), timestamp.to_string()),
+                (EVENT_HEADER.to_owned(), event_name(event.kind)),
+                (EVENT_ID_HEADER.to_owned(), event.id.clone()),
+            ];
+
+            let result = self
+                .sender
+                .send(&subscription.url, headers, body.clone())
+                .await;
+
+            let (status_code, error) = match result {
+                Ok(status) => (Some(status), None),
+                Err(err) => (None, Some(err)),
+            };
+            let succeeded = matches!(status_code, Some(200..=299));
+
+            let delivery = WebhookDelivery {
+                subscription_id: subscription.id.clone(),
+                event_id: event.id.clone(),
+                event_kind: event.kind,
+                attempt,
+                status_code,
+                error,
+                succeeded,
+                attempted_at: timestamp,
+            };
+            if let Err(err) = self.repo.record_delivery(delivery).await {
+                tracing::error!(%err, "failed to record webhook delivery");
+            }
+
+            if succeeded {
+                return true;
+            }
+
+            if attempt < self.policy.max_attempts {
+                tokio::time::sleep(self.policy.delay_for(attempt)).await;
+            }
+        }
+
+        tracing::warn!(
+            subscription = %subscription.id,
+            event = %event.id,
+            "giving up on webhook delivery"
+        );
+
+        false
+    }
+}
+
+fn event_name(kind: EventKind) -> String {
+    serde_json::to_value(kind)
+        .ok()
+        .and_then(|v| v.as_str().map(str::to_owned))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    fn subscription() -> WebhookSubscription {
+        WebhookSubscription {
+            id: "sub-1".to_owned(),
+            url: "https://example.com/hook".to_owned(),
+            secret: "secret".to_owned(),
+            events: vec![],
+            created_at: 0,
+        }
+    }
+
+    fn event() -> DomainEvent {
+        DomainEvent::new(
+            EventKind::LinkCreated,
+            "123".to_owned(),
+            "https://www.google.com/".to_owned(),
+            0,
+        )
+    }
+
+    fn recording_repo(log: Arc<Mutex<Vec<WebhookDelivery>>>) -> MockWebhookDeliveryRepository {
+        let mut repo = MockWebhookDeliveryRepository::new();
+        repo.expect_record_delivery().returning(move |delivery| {
+            log.lock().unwrap().push(delivery);
+            Ok(())
+        });
+        repo
+    }
+
+    #[test]
+    fn backoff_doubles_and_is_capped() {
+        let policy = RetryPolicy {
+            max_attempts: 10,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(10),
+        };
+
+        assert_eq!(policy.delay_for(1), Duration::from_secs(1));
+        assert_eq!(policy.delay_for(2), Duration::from_secs(2));
+        assert_eq!(policy.delay_for(4), Duration::from_secs(8));
+        assert_eq!(policy.delay_for(5), Duration::from_secs(10));
+        assert_eq!(policy.delay_for(40), Duration::from_secs(10));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn retries_until_success_and_logs_every_attempt() {
+        // Given
+        let log = Arc::new(Mutex::new(vec![]));
+        let repo = recording_repo(log.clone());
+
+        let mut sender = MockWebhookSender::new();
+        let mut calls = 0;
+        sender.expect_send().times(3).returning(move |_, _, _| {
+            calls += 1;
+            match calls {
+                1 => Err("connection refused".to_owned()),
+                2 => Ok(500),
+                _ => Ok(204),
+            }
+        });
+
+        let dispatcher = WebhookDispatcher::new(repo, sender, RetryPolicy::default());
+
+        // When
+        let delivered = dispatcher.deliver(&subscription(), &event()).await;
+
+        // Then
+        assert!(delivered);
+        let log = log.lock().unwrap();
+        assert_eq!(log.len(), 3);
+        assert_eq!(log[0].error.as_deref(), Some("connection refused"));
+        assert_eq!(log[1].status_code, Some(500));
+        assert!(!log[1].succeeded);
+        assert_eq!(log[2].attempt, 3);
+        assert!(log[2].succeeded);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn gives_up_after_max_attempts() {
+        // Given
+        let log = Arc::new(Mutex::new(vec![]));
+        let repo = recording_repo(log.clone());
+
+        let mut sender = MockWebhookSender::new();
+        sender.expect_send().times(3).returning(|_, _, _| Ok(503));
+
+        let policy = RetryPolicy {
+            max_attempts: 3,
+            ..RetryPolicy::default()
+        };
+        let dispatcher = WebhookDispatcher::new(repo, sender, policy);
+
+        // When
+        let delivered = dispatcher.deliver(&subscription(), &event()).await;
+
+        // Then
+        assert!(!delivered);
+        assert_eq!(log.lock().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn signs_payload_with_subscription_secret() {
+        // Given
+        let repo = recording_repo(Arc::new(Mutex::new(vec![])));
+
+        let mut sender = MockWebhookSender::new();
+        sender
+            .expect_send()
+            .times(1)
+            .returning(|url, headers, body| {
+                let header = |name: &str| {
+                    headers
+                        .iter()
+                        .find(|(k, _)| k == name)
+                        .map(|(_, v)| v.clone())
+                        .unwrap()
+                };
+                let timestamp: u64 = header(TIMESTAMP_HEADER).parse().unwrap();
+
+                assert_eq!(url, "https://example.com/hook");
+                assert_eq!(header(EVENT_HEADER), "link.created");
+                assert_eq!(
+                    header(SIGNATURE_HEADER),
+                    format!("sha256={}", sign("secret", timestamp, &body))
+                );
+                Ok(200)
+            });
+
+        let dispatcher = WebhookDispatcher::new(repo, sender, RetryPolicy::default());
+
+        // When
+        let delivered = dispatcher.deliver(&subscription(), &event()).await;
+
+        // Then
+        assert!(delivered);
+    }
+}
@@ -0,0 +1,101 @@
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use crate::app::event::EventKind;
+
+pub mod dispatcher;
+
+pub const SIGNATURE_HEADER: &str = "X-Webhook-Signature";
+pub const TIMESTAMP_HEADER: &str = "X-Webhook-Timestamp";
+pub const EVENT_HEADER: &str = "X-Webhook-Event";
+pub const EVENT_ID_HEADER: &str = "X-Webhook-Event-Id";
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WebhookSubscription {
+    pub id: String,
+    pub url: String,
+    pub secret: String,
+    /// Event kinds this subscription receives. Empty means all of them.
+    pub events: Vec<EventKind>,
+    pub created_at: u64,
+}
+
+impl WebhookSubscription {
+    pub fn accepts(&self, kind: EventKind) -> bool {
+        self.events.is_empty() || self.events.contains(&kind)
+    }
+}
+
+/// One delivery attempt of an event to a subscription.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct WebhookDelivery {
+    pub subscription_id: String,
+    pub event_id: String,
+    pub event_kind: EventKind,
+    pub attempt: u32,
+    pub status_code: Option<u16>,
+    pub error: Option<String>,
+    pub succeeded: bool,
+    pub attempted_at: u64,
+}
+
+/// Hex encoded HMAC-SHA256 of `"{timestamp}.{body}"` keyed with the
+/// subscription secret. Receivers recompute it to authenticate deliveries.
+pub fn sign(secret: &str, timestamp: u64, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+
+    hex::encode(mac.finalize().into_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sign_matches_known_vector() {
+        // Given
+        let body = br#"{"hello":"world"}"#;
+
+        // When
+        let signature = sign("secret", 1700000000, body);
+
+        // Then
+        assert_eq!(
+            signature,
+            "654f06c856baf080af3fa272934823257a542d35cf1f88099338f850a60601a4"
+        );
+    }
+
+    #[test]
+    fn empty_event_list_accepts_everything() {
+        let subscription = WebhookSubscription {
+            id: "1".to_owned(),
+            url: "https://example.com/hook".to_owned(),
+            secret: "secret".to_owned(),
+            events: vec![],
+            created_at: 0,
+        };
+
+        assert!(subscription.accepts(EventKind::LinkCreated));
+        assert!(subscription.accepts(EventKind::LinkExpired));
+    }
+
+    #[test]
+    fn filtered_subscription_rejects_other_events() {
+        let subscription = WebhookSubscription {
+            id: "1".to_owned(),
+            url: "https://example.com/hook".to_owned(),
+            secret: "secret".to_owned(),
+            events: vec![EventKind::LinkDeleted],
+            created_at: 0,
+        };
+
+        assert!(subscription.accepts(EventKind::LinkDeleted));
+        assert!(!subscription.accepts(EventKind::LinkCreated));
+    }
+}
@@ -0,0 +1,145 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::AppError;
+
+/// A team owning links jointly. Every member may change every link of the
+/// workspace, whoever created it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct Workspace {
+    pub id: String,
+    pub name: String,
+    /// IDs of the member accounts.
+    #[serde(default)]
+    pub members: Vec<String>,
+    /// Hosts that resolve only this workspace's links.
+    #[serde(default)]
+    pub domains: Vec<String>,
+    #[serde(default)]
+    pub quota: Quota,
+    pub created_at: u64,
+}
+
+/// Limits on what a workspace may use; no limit where unset.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct Quota {
+    /// Live links at a time.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_links: Option<u64>,
+    /// Visits tracked per calendar month (UTC). Once reached, the
+    /// workspace's links keep redirecting but no new ones can be created.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_clicks_per_month: Option<u64>,
+}
+
+impl Quota {
+    /// Fails with `AppError::QuotaExceeded` once `usage` reached a limit.
+    pub fn check(&self, workspace: &str, usage: &Usage) -> Result<(), AppError> {
+        let exceeded = |reason: String| AppError::QuotaExceeded {
+            workspace: workspace.to_owned(),
+            reason,
+        };
+
+        if let Some(max) = self.max_links.filter(|max| usage.links >= *max) {
+            return Err(exceeded(format!("{max} links")));
+        }
+        if let Some(max) = self.max_clicks_per_month.filter(|max| usage.clicks >= *max) {
+            return Err(exceeded(format!("{max} clicks in {}", usage.month)));
+        }
+
+        Ok(())
+    }
+}
+
+/// What a workspace used of its quota.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct Usage {
+    /// The month `clicks` were counted in, as `YYYY-MM`.
+    pub month: String,
+    /// Live links of the workspace.
+    pub links: u64,
+    /// Visits to the workspace's links in `month`.
+    pub clicks: u64,
+}
+
+/// The lowercase host `domain` names, without a trailing dot. Fails with
+/// `AppError::InvalidUrl` for anything but a bare domain name.
+pub fn normalize_domain(domain: &str) -> Result<String, AppError> {
+    let normalized = domain.trim().trim_end_matches('.').to_ascii_lowercase();
+    if normalized.contains([':', '/', '@', '?', '#']) {
+        return Err(AppError::invalid_url(domain, "not a bare domain name"));
+    }
+
+    match url::Host::parse(&normalized) {
+        Ok(url::Host::Domain(host)) if host.contains('.') => Ok(host),
+        _ => Err(AppError::invalid_url(domain, "not a bare domain name")),
+    }
+}
+
+/// The calendar month (UTC) of a unix time as `YYYY-MM`.
+pub fn month_of(unix_time: u64) -> String {
+    // Howard Hinnant's `civil_from_days`, shifted to years starting in March.
+    let days = (unix_time / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!("{year:04}-{month:02}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn months_follow_the_utc_calendar() {
+        assert_eq!(month_of(0), "1970-01");
+        assert_eq!(month_of(951_825_600), "2000-02");
+        assert_eq!(month_of(1_798_761_599), "2026-12");
+        assert_eq!(month_of(1_798_761_600), "2027-01");
+    }
+
+    #[test]
+    fn quotas_stop_at_either_limit() {
+        let quota = Quota {
+            max_links: Some(2),
+            max_clicks_per_month: Some(100),
+        };
+        let usage = |links, clicks| Usage {
+            month: "2026-10".to_owned(),
+            links,
+            clicks,
+        };
+
+        assert!(quota.check("team", &usage(1, 99)).is_ok());
+        assert!(matches!(
+            quota.check("team", &usage(2, 0)),
+            Err(AppError::QuotaExceeded { reason, .. }) if reason == "2 links"
+        ));
+        assert!(matches!(
+            quota.check("team", &usage(0, 100)),
+            Err(AppError::QuotaExceeded { reason, .. }) if reason == "100 clicks in 2026-10"
+        ));
+        assert!(Quota::default().check("team", &usage(u64::MAX, 0)).is_ok());
+    }
+
+    #[test]
+    fn domains_are_bare_lowercase_hosts() {
+        assert_eq!(
+            normalize_domain("Go.Example.COM."),
+            Ok("go.example.com".to_owned())
+        );
+        assert!(normalize_domain("https://go.example.com").is_err());
+        assert!(normalize_domain("go.example.com:8080").is_err());
+        assert!(normalize_domain("127.0.0.1").is_err());
+        assert!(normalize_domain("localhost").is_err());
+    }
+}
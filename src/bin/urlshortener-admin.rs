@@ -0,0 +1,129 @@
+use std::{io, process::ExitCode, sync::Arc};
+
+use clap::Parser;
+
+use urlshortener::{
+    adapters::{
+        inmemory::InMemoryStorage,
+        redis::{
+            account::RedisAccountRepository, webhook::RedisWebhookRepository, RedisRepository,
+        },
+    },
+    app::event::EventBus,
+    clock::{SharedClock, SystemClock},
+    config::Config,
+    di::{self, AccountRepository, CommandRepository, QueryRepository, WebhookRepository},
+    id_provider::{self, IDProvider},
+    ports::cli::{self, Cli, CliError},
+};
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    let config = match Config::from_env() {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("error: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let clock: SharedClock = Arc::new(SystemClock);
+
+    if let Some(url) = &config.redis_url {
+        let repo = match RedisRepository::connect(url).await {
+            Ok(repo) => repo.with_clock(clock.clone()),
+            Err(err) => {
+                eprintln!("error: cannot connect to redis: {err}");
+                return ExitCode::FAILURE;
+            }
+        };
+
+        let idp = match id_provider::from_config(&config, repo.clone()) {
+            Ok(idp) => idp,
+            Err(err) => {
+                eprintln!("error: {err}");
+                return ExitCode::FAILURE;
+            }
+        };
+        let webhooks = RedisWebhookRepository::new(repo.clone());
+        let accounts = RedisAccountRepository::new(repo.clone());
+        return exit_code(run(cli, idp, clock, repo.clone(), repo, webhooks, accounts).await);
+    }
+
+    let (storage, restored) = match InMemoryStorage::open(&config) {
+        Ok((storage, restored)) => (storage.with_clock(clock.clone()), restored),
+        Err(err) => {
+            eprintln!("error: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+    if restored.truncated_bytes > 0 {
+        eprintln!(
+            "warning: dropped {} bytes of a torn write-ahead log record",
+            restored.truncated_bytes
+        );
+    }
+
+    let mutates = cli.command.is_mutating();
+    let idp = match id_provider::from_config(&config, storage.sequence.clone()) {
+        Ok(idp) => idp,
+        Err(err) => {
+            eprintln!("error: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let result = run(
+        cli,
+        idp,
+        clock,
+        storage.repository(),
+        storage.repository(),
+        storage.webhooks(),
+        storage.accounts(),
+    )
+    .await;
+
+    // A partially applied import is still written back, so the snapshot
+    // matches what the report says was stored.
+    if let (true, Some(snapshotter)) = (mutates, &storage.snapshotter) {
+        if let Err(err) = snapshotter.save() {
+            eprintln!("error: {err}");
+            return ExitCode::FAILURE;
+        }
+    }
+
+    exit_code(result)
+}
+
+async fn run<R, Q, W, A>(
+    cli: Cli,
+    idp: Box<dyn IDProvider + Send + Sync>,
+    clock: SharedClock,
+    repo: R,
+    querier: Q,
+    webhooks: W,
+    accounts: A,
+) -> Result<(), CliError>
+where
+    R: CommandRepository,
+    Q: QueryRepository,
+    W: WebhookRepository,
+    A: AccountRepository,
+{
+    let container = di::Container::new(idp, repo, querier, webhooks, accounts, EventBus::new())
+        .with_clock(clock);
+
+    cli::run(cli, &container, &mut io::stdout()).await
+}
+
+fn exit_code(result: Result<(), CliError>) -> ExitCode {
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,257 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use axum::http::HeaderName;
+
+use crate::adapters::inmemory::wal::WalSync;
+use crate::id_provider::{alphabet::Alphabet, IdStrategy};
+
+const DEFAULT_PORT: u16 = 3001;
+const DEFAULT_SNAPSHOT_INTERVAL_SECS: u64 = 60;
+const DEFAULT_EXPIRY_SWEEP_INTERVAL_SECS: u64 = 60;
+const DEFAULT_CACHE_CAPACITY: usize = 10_000;
+const DEFAULT_CACHE_TTL_SECS: u64 = 60;
+const DEFAULT_CACHE_NEGATIVE_TTL_SECS: u64 = 5;
+const DEFAULT_ID_BLOCK_SIZE: u64 = 100;
+const DEFAULT_ID_LENGTH: usize = 7;
+const DEFAULT_ID_EXPECTED_LINKS: u64 = 100_000_000;
+const DEFAULT_IDEMPOTENCY_WINDOW_SECS: u64 = 24 * 60 * 60;
+const DEFAULT_THREAT_LIST_RELOAD_SECS: u64 = 60;
+const DEFAULT_HEALTH_CHECK_CONCURRENCY: usize = 8;
+const DEFAULT_HEALTH_CHECK_HOST_DELAY_MS: u64 = 1_000;
+const DEFAULT_HEALTH_CHECK_TIMEOUT_SECS: u64 = 10;
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+#[error("invalid value `{value}` for {name}")]
+pub struct ConfigError {
+    pub name: &'static str,
+    pub value: String,
+}
+
+/// Settings shared by the server and the admin CLI, read from
+/// `URLSHORTENER_*` environment variables.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Config {
+    pub port: u16,
+    /// Where the in-memory store is persisted. Nothing is persisted when unset.
+    pub snapshot_path: Option<PathBuf>,
+    pub snapshot_interval: Duration,
+    /// Write-ahead log of the in-memory store. Compacted into the snapshot
+    /// on every save when `snapshot_path` is set too.
+    pub wal_path: Option<PathBuf>,
+    pub wal_sync: WalSync,
+    /// Keep links in Redis instead of memory. Snapshot and WAL settings
+    /// are ignored when set.
+    pub redis_url: Option<String>,
+    /// How often links past their expiry are purged from storage.
+    pub expiry_sweep_interval: Duration,
+    /// Resolutions cached in front of Redis; zero disables the cache.
+    pub cache_capacity: usize,
+    pub cache_ttl: Duration,
+    /// How long unknown IDs are remembered as such.
+    pub cache_negative_ttl: Duration,
+    pub id_strategy: IdStrategy,
+    /// Characters IDs are drawn from. Each strategy has its own default.
+    pub id_alphabet: Option<Alphabet>,
+    /// Length of random IDs.
+    pub id_length: usize,
+    /// How many links random IDs must stay collision-safe for; startup
+    /// fails when the alphabet and length are too small for it.
+    pub id_expected_links: u64,
+    /// Extra words, one per line, that generated IDs must not contain.
+    pub id_blocklist_path: Option<PathBuf>,
+    /// Sequential IDs reserved per round trip to the counter.
+    pub id_block_size: u64,
+    /// Scrambles sequential IDs with this key when set.
+    pub id_obfuscation_key: Option<u64>,
+    /// Secret of the hash strategy. Instances must share it, along with the
+    /// alphabet and length, to derive the same IDs.
+    pub id_hash_key: Option<String>,
+    /// Counter file of the in-memory store. Defaults to the snapshot (or
+    /// WAL) path with a `.sequence` suffix.
+    pub sequence_path: Option<PathBuf>,
+    /// How long responses to `POST /api/v1/links` are replayed to retries
+    /// with the same `Idempotency-Key`.
+    pub idempotency_window: Duration,
+    /// Request header a proxy or CDN puts the visitor's country in, e.g.
+    /// `CF-IPCountry`. Redirect rules read it before the GeoIP database.
+    pub country_header: Option<HeaderName>,
+    /// Range file to look up countries of client addresses in; see
+    /// `adapters::geoip`.
+    pub geoip_path: Option<PathBuf>,
+    /// Phishing and malware list that new links are screened against; see
+    /// `adapters::threatlist`. Nothing is screened when unset.
+    pub threat_list_path: Option<PathBuf>,
+    /// How often the threat list file is checked for changes.
+    pub threat_list_reload_interval: Duration,
+    /// Screen targets on every redirect as well, not only at creation.
+    pub screen_on_redirect: bool,
+    /// How often every destination is requested to find dead links.
+    /// Destinations are never checked when unset.
+    pub health_check_interval: Option<Duration>,
+    /// Hosts checked at once.
+    pub health_check_concurrency: usize,
+    /// Pause between two checks on the same host.
+    pub health_check_host_delay: Duration,
+    pub health_check_timeout: Duration,
+    /// API key of the operator, who administers accounts and every link.
+    /// Without it only account keys are accepted, so no account can be
+    /// created on a fresh server.
+    pub admin_api_key: Option<String>,
+}
+
+impl Config {
+    pub fn from_env() -> Result<Self, ConfigError> {
+        Self::from_vars(|name| std::env::var(name).ok())
+    }
+
+    pub fn from_vars(var: impl Fn(&str) -> Option<String>) -> Result<Self, ConfigError> {
+        Ok(Config {
+            port: parse(&var, "URLSHORTENER_PORT")?.unwrap_or(DEFAULT_PORT),
+            snapshot_path: var("URLSHORTENER_SNAPSHOT_PATH").map(PathBuf::from),
+            snapshot_interval: Duration::from_secs(
+                parse(&var, "URLSHORTENER_SNAPSHOT_INTERVAL_SECS")?
+                    .unwrap_or(DEFAULT_SNAPSHOT_INTERVAL_SECS),
+            ),
+            wal_path: var("URLSHORTENER_WAL_PATH").map(PathBuf::from),
+            wal_sync: parse(&var, "URLSHORTENER_WAL_FSYNC")?.unwrap_or_default(),
+            redis_url: var("URLSHORTENER_REDIS_URL"),
+            expiry_sweep_interval: Duration::from_secs(
+                parse(&var, "URLSHORTENER_EXPIRY_SWEEP_INTERVAL_SECS")?
+                    .unwrap_or(DEFAULT_EXPIRY_SWEEP_INTERVAL_SECS),
+            ),
+            cache_capacity: parse(&var, "URLSHORTENER_CACHE_CAPACITY")?
+                .unwrap_or(DEFAULT_CACHE_CAPACITY),
+            cache_ttl: Duration::from_secs(
+                parse(&var, "URLSHORTENER_CACHE_TTL_SECS")?.unwrap_or(DEFAULT_CACHE_TTL_SECS),
+            ),
+            cache_negative_ttl: Duration::from_secs(
+                parse(&var, "URLSHORTENER_CACHE_NEGATIVE_TTL_SECS")?
+                    .unwrap_or(DEFAULT_CACHE_NEGATIVE_TTL_SECS),
+            ),
+            id_strategy: parse(&var, "URLSHORTENER_ID_STRATEGY")?.unwrap_or_default(),
+            id_alphabet: parse(&var, "URLSHORTENER_ID_ALPHABET")?,
+            id_length: parse(&var, "URLSHORTENER_ID_LENGTH")?.unwrap_or(DEFAULT_ID_LENGTH),
+            id_expected_links: parse(&var, "URLSHORTENER_ID_EXPECTED_LINKS")?
+                .unwrap_or(DEFAULT_ID_EXPECTED_LINKS),
+            id_blocklist_path: var("URLSHORTENER_ID_BLOCKLIST_PATH").map(PathBuf::from),
+            id_block_size: parse(&var, "URLSHORTENER_ID_BLOCK_SIZE")?
+                .unwrap_or(DEFAULT_ID_BLOCK_SIZE),
+            id_obfuscation_key: parse(&var, "URLSHORTENER_ID_OBFUSCATION_KEY")?,
+            id_hash_key: var("URLSHORTENER_ID_HASH_KEY"),
+            sequence_path: var("URLSHORTENER_SEQUENCE_PATH").map(PathBuf::from),
+            idempotency_window: Duration::from_secs(
+                parse(&var, "URLSHORTENER_IDEMPOTENCY_WINDOW_SECS")?
+                    .unwrap_or(DEFAULT_IDEMPOTENCY_WINDOW_SECS),
+            ),
+            country_header: parse(&var, "URLSHORTENER_COUNTRY_HEADER")?,
+            geoip_path: var("URLSHORTENER_GEOIP_PATH").map(PathBuf::from),
+            threat_list_path: var("URLSHORTENER_THREAT_LIST_PATH").map(PathBuf::from),
+            threat_list_reload_interval: Duration::from_secs(
+                parse(&var, "URLSHORTENER_THREAT_LIST_RELOAD_SECS")?
+                    .unwrap_or(DEFAULT_THREAT_LIST_RELOAD_SECS),
+            ),
+            screen_on_redirect: parse(&var, "URLSHORTENER_SCREEN_ON_REDIRECT")?.unwrap_or(false),
+            health_check_interval: parse(&var, "URLSHORTENER_HEALTH_CHECK_INTERVAL_SECS")?
+                .map(Duration::from_secs),
+            health_check_concurrency: parse(&var, "URLSHORTENER_HEALTH_CHECK_CONCURRENCY")?
+                .unwrap_or(DEFAULT_HEALTH_CHECK_CONCURRENCY),
+            health_check_host_delay: Duration::from_millis(
+                parse(&var, "URLSHORTENER_HEALTH_CHECK_HOST_DELAY_MS")?
+                    .unwrap_or(DEFAULT_HEALTH_CHECK_HOST_DELAY_MS),
+            ),
+            health_check_timeout: Duration::from_secs(
+                parse(&var, "URLSHORTENER_HEALTH_CHECK_TIMEOUT_SECS")?
+                    .unwrap_or(DEFAULT_HEALTH_CHECK_TIMEOUT_SECS),
+            ),
+            admin_api_key: var("URLSHORTENER_ADMIN_API_KEY").filter(|key| !key.is_empty()),
+        })
+    }
+}
+
+fn parse<T: std::str::FromStr>(
+    var: &impl Fn(&str) -> Option<String>,
+    name: &'static str,
+) -> Result<Option<T>, ConfigError> {
+    var(name)
+        .map(|value| value.parse().map_err(|_| ConfigError { name, value }))
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[test]
+    fn defaults_when_nothing_is_set() {
+        let config = Config::from_vars(|_| None).unwrap();
+
+        assert_eq!(config.port, 3001);
+        assert_eq!(config.snapshot_path, None);
+        assert_eq!(config.snapshot_interval, Duration::from_secs(60));
+        assert_eq!(config.wal_path, None);
+        assert_eq!(config.wal_sync, WalSync::Always);
+        assert_eq!(config.redis_url, None);
+        assert_eq!(config.expiry_sweep_interval, Duration::from_secs(60));
+        assert_eq!(config.cache_capacity, 10_000);
+        assert_eq!(config.cache_negative_ttl, Duration::from_secs(5));
+        assert_eq!(config.id_strategy, IdStrategy::NanoId);
+        assert_eq!(config.id_alphabet, None);
+        assert_eq!(config.id_length, 7);
+        assert_eq!(config.id_obfuscation_key, None);
+        assert_eq!(config.idempotency_window, Duration::from_secs(86_400));
+        assert_eq!(config.threat_list_path, None);
+        assert_eq!(config.threat_list_reload_interval, Duration::from_secs(60));
+        assert!(!config.screen_on_redirect);
+        assert_eq!(config.health_check_interval, None);
+        assert_eq!(config.health_check_concurrency, 8);
+        assert_eq!(config.health_check_host_delay, Duration::from_secs(1));
+        assert_eq!(config.admin_api_key, None);
+    }
+
+    #[test]
+    fn reads_overrides_and_rejects_garbage() {
+        let vars = HashMap::from([
+            ("URLSHORTENER_SNAPSHOT_PATH", "/var/lib/links.snapshot"),
+            ("URLSHORTENER_SNAPSHOT_INTERVAL_SECS", "5"),
+            ("URLSHORTENER_WAL_FSYNC", "never"),
+            ("URLSHORTENER_REDIS_URL", "redis://cache:6379/0"),
+            ("URLSHORTENER_ID_STRATEGY", "sequential"),
+            ("URLSHORTENER_ID_OBFUSCATION_KEY", "1234"),
+            (
+                "URLSHORTENER_ID_ALPHABET",
+                "abcdefghjkmnpqrstuvwxyz23456789",
+            ),
+            ("URLSHORTENER_ID_LENGTH", "9"),
+            ("URLSHORTENER_COUNTRY_HEADER", "CF-IPCountry"),
+        ]);
+        let config = Config::from_vars(|name| vars.get(name).map(|v| v.to_string())).unwrap();
+        assert_eq!(
+            config.snapshot_path,
+            Some(PathBuf::from("/var/lib/links.snapshot"))
+        );
+        assert_eq!(config.snapshot_interval, Duration::from_secs(5));
+        assert_eq!(config.wal_sync, WalSync::Never);
+        assert_eq!(config.redis_url.as_deref(), Some("redis://cache:6379/0"));
+        assert_eq!(config.id_strategy, IdStrategy::Sequential);
+        assert_eq!(config.id_obfuscation_key, Some(1234));
+        assert_eq!(config.id_alphabet.map(|a| a.len()), Some(31));
+        assert_eq!(config.id_length, 9);
+        assert_eq!(
+            config.country_header,
+            Some(HeaderName::from_static("cf-ipcountry"))
+        );
+
+        let result =
+            Config::from_vars(|name| (name == "URLSHORTENER_PORT").then(|| "http".to_owned()));
+        assert_eq!(
+            result,
+            Err(ConfigError {
+                name: "URLSHORTENER_PORT",
+                value: "http".to_owned()
+            })
+        );
+    }
+}
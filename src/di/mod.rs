@@ -1,35 +1,339 @@
+use std::sync::Arc;
+
 use crate::{
     app::{
-        command::create_short_url::{CreateShortUrlCommand, CreateShortUrlRepository},
-        query::get_full_url::{GetFullUrlQuery, GetFullUrlRepository},
+        command::{
+            change_link_status::{ChangeLinkStatusCommand, ChangeLinkStatusRepository},
+            change_membership::{ChangeMembershipCommand, ChangeMembershipRepository},
+            check_link_health::CheckLinkHealthRepository,
+            create_account::{CreateAccountCommand, CreateAccountRepository},
+            create_api_key::{CreateApiKeyCommand, CreateApiKeyRepository},
+            create_short_url::{
+                CreateShortUrlCommand, CreateShortUrlRepository, WorkspaceQuotaRepository,
+            },
+            create_webhook::{CreateWebhookCommand, CreateWebhookRepository},
+            create_workspace::{CreateWorkspaceCommand, CreateWorkspaceRepository},
+            delete_short_url::{DeleteShortUrlCommand, DeleteShortUrlRepository},
+            delete_webhook::{DeleteWebhookCommand, DeleteWebhookRepository},
+            import_short_urls::{ImportShortUrlsCommand, ImportShortUrlsRepository},
+            purge_expired_links::{PurgeExpiredLinksCommand, PurgeExpiredLinksRepository},
+            record_visit::{RecordVisitCommand, RecordVisitRepository},
+            report_link::{ReportLinkCommand, ReportLinkRepository},
+            revoke_api_key::{RevokeApiKeyCommand, RevokeApiKeyRepository},
+            update_short_url::{UpdateShortUrlCommand, UpdateShortUrlRepository},
+            update_workspace::{UpdateWorkspaceCommand, UpdateWorkspaceRepository},
+        },
+        event::EventBus,
+        query::{
+            authenticate::{AuthenticateQuery, AuthenticateRepository},
+            export_short_urls::{ExportShortUrlsQuery, ExportShortUrlsRepository},
+            get_full_url::{GetFullUrlQuery, GetFullUrlRepository},
+            get_link_health::{GetLinkHealthQuery, GetLinkHealthRepository},
+            get_link_stats::{GetLinkStatsQuery, GetLinkStatsRepository},
+            get_stats::{GetStatsQuery, GetStatsRepository},
+            get_workspace::{GetWorkspaceQuery, GetWorkspaceRepository},
+            get_workspace_usage::{GetWorkspaceUsageQuery, GetWorkspaceUsageRepository},
+            list_reports::{ListReportsQuery, ListReportsRepository},
+            list_short_urls::{ListShortUrlsQuery, ListShortUrlsRepository},
+            list_webhook_deliveries::{
+                ListWebhookDeliveriesQuery, ListWebhookDeliveriesRepository,
+            },
+            list_webhooks::{ListWebhooksQuery, ListWebhooksRepository},
+        },
+        screening::SharedScreener,
     },
+    clock::{SharedClock, SystemClock},
     id_provider::IDProvider,
 };
 
-pub struct Container<I, R, Q>
+/// Everything the write side of the container needs from link storage.
+pub trait CommandRepository:
+    CreateShortUrlRepository
+    + UpdateShortUrlRepository
+    + DeleteShortUrlRepository
+    + ImportShortUrlsRepository
+    + PurgeExpiredLinksRepository
+    + RecordVisitRepository
+    + ReportLinkRepository
+    + ChangeLinkStatusRepository
+    + CheckLinkHealthRepository
+    + Clone
+    + Send
+    + Sync
+    + 'static
+{
+}
+
+impl<T> CommandRepository for T where
+    T: CreateShortUrlRepository
+        + UpdateShortUrlRepository
+        + DeleteShortUrlRepository
+        + ImportShortUrlsRepository
+        + PurgeExpiredLinksRepository
+        + RecordVisitRepository
+        + ReportLinkRepository
+        + ChangeLinkStatusRepository
+        + CheckLinkHealthRepository
+        + Clone
+        + Send
+        + Sync
+        + 'static
+{
+}
+
+/// Everything the read side of the container needs from link storage.
+pub trait QueryRepository:
+    GetFullUrlRepository
+    + ListShortUrlsRepository
+    + ExportShortUrlsRepository
+    + GetStatsRepository
+    + GetLinkStatsRepository
+    + GetLinkHealthRepository
+    + GetWorkspaceUsageRepository
+    + ListReportsRepository
+    + Clone
+    + Send
+    + Sync
+    + 'static
+{
+}
+
+impl<T> QueryRepository for T where
+    T: GetFullUrlRepository
+        + ListShortUrlsRepository
+        + ExportShortUrlsRepository
+        + GetStatsRepository
+        + GetLinkStatsRepository
+        + GetLinkHealthRepository
+        + GetWorkspaceUsageRepository
+        + ListReportsRepository
+        + Clone
+        + Send
+        + Sync
+        + 'static
+{
+}
+
+pub trait WebhookRepository:
+    CreateWebhookRepository
+    + DeleteWebhookRepository
+    + ListWebhooksRepository
+    + ListWebhookDeliveriesRepository
+    + Clone
+    + Send
+    + Sync
+    + 'static
+{
+}
+
+impl<T> WebhookRepository for T where
+    T: CreateWebhookRepository
+        + DeleteWebhookRepository
+        + ListWebhooksRepository
+        + ListWebhookDeliveriesRepository
+        + Clone
+        + Send
+        + Sync
+        + 'static
+{
+}
+
+/// Everything the container needs from account storage, which also keeps
+/// workspaces.
+pub trait AccountRepository:
+    CreateAccountRepository
+    + CreateApiKeyRepository
+    + RevokeApiKeyRepository
+    + AuthenticateRepository
+    + CreateWorkspaceRepository
+    + UpdateWorkspaceRepository
+    + ChangeMembershipRepository
+    + WorkspaceQuotaRepository
+    + GetWorkspaceRepository
+    + Clone
+    + Send
+    + Sync
+    + 'static
+{
+}
+
+impl<T> AccountRepository for T where
+    T: CreateAccountRepository
+        + CreateApiKeyRepository
+        + RevokeApiKeyRepository
+        + AuthenticateRepository
+        + CreateWorkspaceRepository
+        + UpdateWorkspaceRepository
+        + ChangeMembershipRepository
+        + WorkspaceQuotaRepository
+        + GetWorkspaceRepository
+        + Clone
+        + Send
+        + Sync
+        + 'static
+{
+}
+
+pub struct Container<I, R, Q, W, A>
 where
     I: IDProvider,
-    R: CreateShortUrlRepository,
-    Q: GetFullUrlRepository,
+    R: CommandRepository,
+    Q: QueryRepository,
+    W: WebhookRepository,
+    A: AccountRepository,
 {
-    pub shorten_command: CreateShortUrlCommand<I, R>,
+    pub shorten_command: CreateShortUrlCommand<I, R, A>,
+    pub update_command: UpdateShortUrlCommand<R>,
+    pub delete_command: DeleteShortUrlCommand<R>,
+    pub import_command: ImportShortUrlsCommand<R>,
+    pub purge_expired_command: PurgeExpiredLinksCommand<R>,
+    pub record_visit_command: RecordVisitCommand<R>,
+    pub report_command: ReportLinkCommand<R>,
+    pub link_status_command: ChangeLinkStatusCommand<R>,
     pub get_full_url_query: GetFullUrlQuery<Q>,
+    pub list_query: ListShortUrlsQuery<Q>,
+    pub export_query: ExportShortUrlsQuery<Q>,
+    pub stats_query: GetStatsQuery<Q>,
+    pub link_stats_query: GetLinkStatsQuery<Q>,
+    pub link_health_query: GetLinkHealthQuery<Q>,
+    pub workspace_usage_query: GetWorkspaceUsageQuery<Q>,
+    pub reports_query: ListReportsQuery<Q>,
+    pub create_webhook_command: CreateWebhookCommand<W>,
+    pub delete_webhook_command: DeleteWebhookCommand<W>,
+    pub list_webhooks_query: ListWebhooksQuery<W>,
+    pub list_webhook_deliveries_query: ListWebhookDeliveriesQuery<W>,
+    pub create_account_command: CreateAccountCommand<A>,
+    pub create_api_key_command: CreateApiKeyCommand<A>,
+    pub revoke_api_key_command: RevokeApiKeyCommand<A>,
+    pub authenticate_query: AuthenticateQuery<A>,
+    pub create_workspace_command: CreateWorkspaceCommand<A>,
+    pub update_workspace_command: UpdateWorkspaceCommand<A>,
+    pub membership_command: ChangeMembershipCommand<A>,
+    pub workspace_query: GetWorkspaceQuery<A>,
+    /// The time everything above goes by; ports read it too.
+    pub clock: SharedClock,
 }
 
-impl<I, R, Q> Container<I, R, Q>
+impl<I, R, Q, W, A> Container<I, R, Q, W, A>
 where
     I: IDProvider,
-    R: CreateShortUrlRepository,
-    Q: GetFullUrlRepository,
+    R: CommandRepository,
+    Q: QueryRepository,
+    W: WebhookRepository,
+    A: AccountRepository,
 {
-    pub fn new(id_provider: I, repository: R, querier: Q) -> Self {
-        let shorten_command = CreateShortUrlCommand::new(id_provider, repository);
-        let get_full_url_query = GetFullUrlQuery::new(querier);
+    pub fn new(
+        id_provider: I,
+        repository: R,
+        querier: Q,
+        webhooks: W,
+        accounts: A,
+        events: EventBus,
+    ) -> Self {
+        let shorten_command = CreateShortUrlCommand::new(
+            id_provider,
+            repository.clone(),
+            accounts.clone(),
+            events.clone(),
+        );
+        let update_command = UpdateShortUrlCommand::new(repository.clone(), events.clone());
+        let delete_command = DeleteShortUrlCommand::new(repository.clone(), events.clone());
+        let import_command = ImportShortUrlsCommand::new(repository.clone());
+        let purge_expired_command =
+            PurgeExpiredLinksCommand::new(repository.clone(), events.clone());
+        let record_visit_command = RecordVisitCommand::new(repository.clone());
+        let report_command = ReportLinkCommand::new(repository.clone());
+        let link_status_command = ChangeLinkStatusCommand::new(repository, events);
+        let get_full_url_query = GetFullUrlQuery::new(querier.clone());
+        let list_query = ListShortUrlsQuery::new(querier.clone());
+        let export_query = ExportShortUrlsQuery::new(querier.clone());
+        let stats_query = GetStatsQuery::new(querier.clone());
+        let link_stats_query = GetLinkStatsQuery::new(querier.clone());
+        let link_health_query = GetLinkHealthQuery::new(querier.clone());
+        let workspace_usage_query = GetWorkspaceUsageQuery::new(querier.clone());
+        let reports_query = ListReportsQuery::new(querier);
+        let create_webhook_command = CreateWebhookCommand::new(webhooks.clone());
+        let delete_webhook_command = DeleteWebhookCommand::new(webhooks.clone());
+        let list_webhooks_query = ListWebhooksQuery::new(webhooks.clone());
+        let list_webhook_deliveries_query = ListWebhookDeliveriesQuery::new(webhooks);
+        let create_account_command = CreateAccountCommand::new(accounts.clone());
+        let create_api_key_command = CreateApiKeyCommand::new(accounts.clone());
+        let revoke_api_key_command = RevokeApiKeyCommand::new(accounts.clone());
+        let authenticate_query = AuthenticateQuery::new(accounts.clone());
+        let create_workspace_command = CreateWorkspaceCommand::new(accounts.clone());
+        let update_workspace_command = UpdateWorkspaceCommand::new(accounts.clone());
+        let membership_command = ChangeMembershipCommand::new(accounts.clone());
+        let workspace_query = GetWorkspaceQuery::new(accounts);
 
         Container {
             shorten_command,
+            update_command,
+            delete_command,
+            import_command,
+            purge_expired_command,
+            record_visit_command,
+            report_command,
+            link_status_command,
             get_full_url_query,
+            list_query,
+            export_query,
+            stats_query,
+            link_stats_query,
+            link_health_query,
+            workspace_usage_query,
+            reports_query,
+            create_webhook_command,
+            delete_webhook_command,
+            list_webhooks_query,
+            list_webhook_deliveries_query,
+            create_account_command,
+            create_api_key_command,
+            revoke_api_key_command,
+            authenticate_query,
+            create_workspace_command,
+            update_workspace_command,
+            membership_command,
+            workspace_query,
+            clock: Arc::new(SystemClock),
         }
     }
-}
 
+    /// Accepts `secret` as the operator's API key; see
+    /// `AuthenticateQuery::with_operator_key`.
+    pub fn with_operator_key(mut self, secret: &str) -> Self {
+        self.authenticate_query = self.authenticate_query.with_operator_key(secret);
+        self
+    }
+
+    /// Quarantines new links to sites `screener` flags. With `on_redirect`
+    /// targets are screened on every visit too, which also catches sites
+    /// listed after their links were made.
+    pub fn with_screener(mut self, screener: SharedScreener, on_redirect: bool) -> Self {
+        if on_redirect {
+            self.get_full_url_query = self.get_full_url_query.with_screener(screener.clone());
+        }
+        self.shorten_command = self.shorten_command.with_screener(screener);
+        self
+    }
+
+    /// Where everything in the container learns the time: when links,
+    /// accounts and reports were created, whether links are live yet or
+    /// expired, and when events happened. The system clock unless set.
+    /// Hand the same clock to the repositories.
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.shorten_command = self.shorten_command.with_clock(clock.clone());
+        self.update_command = self.update_command.with_clock(clock.clone());
+        self.delete_command = self.delete_command.with_clock(clock.clone());
+        self.purge_expired_command = self.purge_expired_command.with_clock(clock.clone());
+        self.report_command = self.report_command.with_clock(clock.clone());
+        self.link_status_command = self.link_status_command.with_clock(clock.clone());
+        self.get_full_url_query = self.get_full_url_query.with_clock(clock.clone());
+        self.workspace_usage_query = self.workspace_usage_query.with_clock(clock.clone());
+        self.create_webhook_command = self.create_webhook_command.with_clock(clock.clone());
+        self.create_account_command = self.create_account_command.with_clock(clock.clone());
+        self.create_api_key_command = self.create_api_key_command.with_clock(clock.clone());
+        self.create_workspace_command = self.create_workspace_command.with_clock(clock.clone());
+        self.clock = clock;
+        self
+    }
+}
@@ -2,15 +2,110 @@ use std::fmt::{self, Display, Formatter};
 
 #[derive(thiserror::Error, Debug, PartialEq)]
 pub enum AppError {
-    NotFound,
-    URLParseError,
+    /// Nothing is stored under the ID.
+    NotFound {
+        id: String,
+    },
+    InvalidUrl {
+        url: String,
+        reason: String,
+    },
+    /// The redirect rule at `index` cannot be stored.
+    InvalidRule {
+        index: usize,
+        reason: String,
+    },
+    /// The A/B split of a link cannot be stored.
+    InvalidVariants {
+        reason: String,
+    },
+    /// The ID is already taken by another link.
+    IdTaken {
+        id: String,
+    },
+    /// Every ID generated for a new link was taken or rejected.
+    IdGenerationFailed,
+    /// No valid API key came with the request.
+    Unauthenticated,
+    /// The caller is known but may not do this.
+    Forbidden {
+        reason: String,
+    },
+    /// The workspace used up its quota.
+    QuotaExceeded {
+        workspace: String,
+        reason: String,
+    },
+    /// Another workspace already serves the domain.
+    DomainTaken {
+        domain: String,
+    },
+    /// The link exists but was taken down.
+    LinkDisabled {
+        id: String,
+    },
+    /// The link is scheduled and shows a coming soon page until it goes
+    /// live. Scheduled links without one are `NotFound`.
+    LinkNotYetActive {
+        id: String,
+        activates_at: u64,
+    },
+    /// A link would expire before it goes live.
+    InvalidSchedule {
+        reason: String,
+    },
+    /// An abuse report cannot be filed as sent.
+    InvalidReport {
+        reason: String,
+    },
+    StorageUnavailable,
+}
+
+impl AppError {
+    pub fn not_found(id: &str) -> Self {
+        AppError::NotFound { id: id.to_owned() }
+    }
+
+    pub fn forbidden(reason: impl Display) -> Self {
+        AppError::Forbidden {
+            reason: reason.to_string(),
+        }
+    }
+
+    pub fn invalid_url(url: &str, reason: impl Display) -> Self {
+        AppError::InvalidUrl {
+            url: url.to_owned(),
+            reason: reason.to_string(),
+        }
+    }
 }
 
 impl Display for AppError {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
-            AppError::NotFound => write!(f, "Not found"),
-            AppError::URLParseError => write!(f, "URL parse error"),
+            AppError::NotFound { id } => write!(f, "Nothing is stored under `{id}`"),
+            AppError::InvalidUrl { url, reason } => write!(f, "Invalid URL `{url}`: {reason}"),
+            AppError::InvalidRule { index, reason } => {
+                write!(f, "Invalid redirect rule #{index}: {reason}")
+            }
+            AppError::InvalidVariants { reason } => write!(f, "Invalid variants: {reason}"),
+            AppError::IdTaken { id } => write!(f, "ID `{id}` is already in use"),
+            AppError::IdGenerationFailed => write!(f, "No free ID could be generated"),
+            AppError::Unauthenticated => write!(f, "A valid API key is required"),
+            AppError::Forbidden { reason } => write!(f, "Not allowed: {reason}"),
+            AppError::QuotaExceeded { workspace, reason } => {
+                write!(f, "Workspace `{workspace}` reached its quota of {reason}")
+            }
+            AppError::DomainTaken { domain } => {
+                write!(f, "Domain `{domain}` already belongs to a workspace")
+            }
+            AppError::LinkDisabled { id } => write!(f, "Link `{id}` has been disabled"),
+            AppError::InvalidReport { reason } => write!(f, "Invalid report: {reason}"),
+            AppError::LinkNotYetActive { id, activates_at } => {
+                write!(f, "Link `{id}` goes live at {activates_at}")
+            }
+            AppError::InvalidSchedule { reason } => write!(f, "Invalid schedule: {reason}"),
+            AppError::StorageUnavailable => write!(f, "Storage unavailable"),
         }
     }
 }
@@ -0,0 +1,91 @@
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+/// Characters IDs are built from. Only URL unreserved characters are
+/// accepted so IDs never need escaping, and each may appear once.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Alphabet(Vec<u8>);
+
+impl Alphabet {
+    /// `a-z`, `A-Z` and `0-9`: the default of sequential IDs.
+    pub fn base62() -> Self {
+        Self(b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789".to_vec())
+    }
+
+    /// The 64 characters nanoid uses by default, `-` and `_` included.
+    pub fn nanoid() -> Self {
+        Self(nanoid::alphabet::SAFE.iter().map(|&c| c as u8).collect())
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn chars(&self) -> Vec<char> {
+        self.0.iter().map(|&b| char::from(b)).collect()
+    }
+}
+
+impl FromStr for Alphabet {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut bytes = Vec::with_capacity(s.len());
+        for c in s.chars() {
+            if !(c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | '_' | '~')) {
+                return Err(format!("`{c}` is not allowed in a URL path unescaped"));
+            }
+            if bytes.contains(&(c as u8)) {
+                return Err(format!("`{c}` appears more than once"));
+            }
+            bytes.push(c as u8);
+        }
+        if bytes.len() < 2 {
+            return Err("an alphabet needs at least two characters".to_owned());
+        }
+
+        Ok(Self(bytes))
+    }
+}
+
+impl Display for Alphabet {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&String::from_utf8_lossy(&self.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_unreserved_characters_only() {
+        assert_eq!(
+            "abcdefghjkmnpqrstuvwxyz23456789"
+                .parse::<Alphabet>()
+                .map(|a| a.len()),
+            Ok(31)
+        );
+        assert!("ab/".parse::<Alphabet>().is_err());
+        assert!("abca".parse::<Alphabet>().is_err());
+        assert!("a".parse::<Alphabet>().is_err());
+    }
+
+    #[test]
+    fn defaults() {
+        assert_eq!(Alphabet::base62().len(), 62);
+        assert_eq!(Alphabet::nanoid().len(), 64);
+        assert_eq!(
+            Alphabet::base62().to_string().parse(),
+            Ok(Alphabet::base62())
+        );
+    }
+}
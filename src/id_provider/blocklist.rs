@@ -0,0 +1,198 @@
+//! Keeps offensive or reserved words out of generated IDs.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use async_trait::async_trait;
+
+use crate::error::AppError;
+
+use super::{IDProvider, IdRequest};
+
+/// How many IDs are drawn before giving up on finding an acceptable one.
+const MAX_ATTEMPTS: usize = 16;
+
+/// Words rejected out of the box. Path segments the HTTP API uses are on it
+/// so a short link can never shadow a route; deployments add their own
+/// words through a blocklist file.
+const DEFAULT_WORDS: &[&str] = &[
+    "admin", "api", "webhooks", "ass", "cock", "cunt", "dick", "fuck", "nazi", "penis", "porn",
+    "rape", "sex", "shit", "slut", "tits", "twat", "whore",
+];
+
+pub trait IdFilter {
+    fn allows(&self, id: &str) -> bool;
+}
+
+/// Rejects IDs containing any listed word, ignoring case and common digit
+/// look-alikes (`5h1t` is caught as `shit`).
+#[derive(Clone, Debug)]
+pub struct Blocklist {
+    words: Vec<String>,
+}
+
+impl Blocklist {
+    pub fn new<S: AsRef<str>>(words: impl IntoIterator<Item = S>) -> Self {
+        Self {
+            words: words
+                .into_iter()
+                .map(|word| normalize(word.as_ref().trim()))
+                .filter(|word| !word.is_empty())
+                .collect(),
+        }
+    }
+
+    /// The built-in words plus those in `path`, one per line. Blank lines
+    /// and lines starting with `#` are ignored.
+    pub fn with_file(path: &Path) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        let extra = text
+            .lines()
+            .filter(|line| !line.trim_start().starts_with('#'));
+
+        Ok(Self::new(DEFAULT_WORDS.iter().copied().chain(extra)))
+    }
+}
+
+impl Default for Blocklist {
+    fn default() -> Self {
+        Self::new(DEFAULT_WORDS)
+    }
+}
+
+impl IdFilter for Blocklist {
+    fn allows(&self, id: &str) -> bool {
+        let id = normalize(id);
+
+        !self.words.iter().any(|word| id.contains(word.as_str()))
+    }
+}
+
+fn normalize(text: &str) -> String {
+    text.chars()
+        .map(|c| match c.to_ascii_lowercase() {
+            '0' => 'o',
+            '1' => 'i',
+            '3' => 'e',
+            '4' => 'a',
+            '5' => 's',
+            '7' => 't',
+            '$' => 's',
+            c => c,
+        })
+        .collect()
+}
+
+/// Draws from `inner` until `filter` accepts the ID.
+pub struct FilteredIDProvider<P, F>
+where
+    P: IDProvider,
+    F: IdFilter,
+{
+    inner: P,
+    filter: F,
+}
+
+impl<P, F> FilteredIDProvider<P, F>
+where
+    P: IDProvider,
+    F: IdFilter,
+{
+    pub fn new(inner: P, filter: F) -> Self {
+        Self { inner, filter }
+    }
+}
+
+#[async_trait]
+impl<P, F> IDProvider for FilteredIDProvider<P, F>
+where
+    P: IDProvider + Send + Sync,
+    F: IdFilter + Send + Sync,
+{
+    async fn provide(&self, request: &IdRequest) -> Result<String, AppError> {
+        let mut request = request.clone();
+        for _ in 0..MAX_ATTEMPTS {
+            let id = self.inner.provide(&request).await?;
+            if self.filter.allows(&id) {
+                return Ok(id);
+            }
+            tracing::debug!("discarded a generated id rejected by the blocklist");
+            // Deterministic providers need to be told to pick another ID.
+            request.attempt += 1;
+        }
+
+        tracing::error!(
+            attempts = MAX_ATTEMPTS,
+            "every generated id was rejected by the blocklist"
+        );
+        Err(AppError::IdGenerationFailed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::id_provider::MockIDProvider;
+
+    use super::*;
+
+    #[test]
+    fn matches_substrings_case_and_look_alikes() {
+        let blocklist = Blocklist::default();
+
+        assert!(!blocklist.allows("xxAdMiNx"));
+        assert!(!blocklist.allows("a5h1tb"));
+        assert!(blocklist.allows("aB3xYz9"));
+    }
+
+    #[test]
+    fn file_words_extend_the_defaults() {
+        // Given
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("blocklist.txt");
+        fs::write(&path, "# brands\nacme\n\n").unwrap();
+
+        // When
+        let blocklist = Blocklist::with_file(&path).unwrap();
+
+        // Then
+        assert!(!blocklist.allows("xACMEx"));
+        assert!(!blocklist.allows("api"));
+        assert!(blocklist.allows("abc"));
+    }
+
+    #[tokio::test]
+    async fn regenerates_rejected_ids() {
+        // Given
+        let mut ids = vec!["fuckit", "adminz", "clean"].into_iter();
+        let mut inner = MockIDProvider::new();
+        inner
+            .expect_provide()
+            .times(3)
+            .returning(move |_| Ok(ids.next().unwrap().to_owned()));
+        let provider = FilteredIDProvider::new(inner, Blocklist::default());
+
+        // When
+        let id = provider.provide(&IdRequest::default()).await;
+
+        // Then
+        assert_eq!(id, Ok("clean".to_owned()));
+    }
+
+    #[tokio::test]
+    async fn gives_up_eventually() {
+        // Given
+        let mut inner = MockIDProvider::new();
+        inner
+            .expect_provide()
+            .times(MAX_ATTEMPTS)
+            .returning(|_| Ok("api".to_owned()));
+        let provider = FilteredIDProvider::new(inner, Blocklist::default());
+
+        // When
+        let id = provider.provide(&IdRequest::default()).await;
+
+        // Then
+        assert_eq!(id, Err(AppError::IdGenerationFailed));
+    }
+}
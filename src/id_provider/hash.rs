@@ -0,0 +1,136 @@
+//! IDs derived from the link itself, so creating the same URL in the same
+//! namespace yields the same ID on every instance sharing the key.
+
+use async_trait::async_trait;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::error::AppError;
+
+use super::{alphabet::Alphabet, IDProvider, IdRequest};
+
+/// HMAC-SHA256 of the namespace and normalized URL, written over the
+/// alphabet. A taken ID is resolved by using one more character of the same
+/// digest stream per attempt, so retries stay deterministic too.
+pub struct HashIDProvider {
+    key: Vec<u8>,
+    alphabet: Alphabet,
+    length: usize,
+}
+
+impl HashIDProvider {
+    pub fn new(key: impl Into<Vec<u8>>, alphabet: Alphabet, length: usize) -> Self {
+        Self {
+            key: key.into(),
+            alphabet,
+            length: length.max(1),
+        }
+    }
+
+    fn id(&self, request: &IdRequest) -> String {
+        let length = self.length + request.attempt;
+        let alphabet = self.alphabet.as_bytes();
+        // Bytes at or above this would favour the first characters.
+        let limit = 256 - 256 % alphabet.len();
+
+        let mut id = String::with_capacity(length);
+        for block in 0u32.. {
+            for byte in self.block(block, request) {
+                if usize::from(byte) < limit {
+                    id.push(char::from(alphabet[usize::from(byte) % alphabet.len()]));
+                    if id.len() == length {
+                        return id;
+                    }
+                }
+            }
+        }
+        unreachable!("the digest stream is unbounded")
+    }
+
+    /// One 32 byte block of the keyed digest stream.
+    fn block(&self, counter: u32, request: &IdRequest) -> [u8; 32] {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&self.key).expect("HMAC accepts keys of any size");
+        mac.update(&counter.to_be_bytes());
+        // The length prefix keeps `ab` + `c` apart from `a` + `bc`.
+        let namespace = request.namespace.as_deref().unwrap_or_default();
+        mac.update(&(namespace.len() as u64).to_be_bytes());
+        mac.update(namespace.as_bytes());
+        mac.update(request.url.as_bytes());
+
+        mac.finalize().into_bytes().into()
+    }
+}
+
+#[async_trait]
+impl IDProvider for HashIDProvider {
+    async fn provide(&self, request: &IdRequest) -> Result<String, AppError> {
+        Ok(self.id(request))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(url: &str, namespace: Option<&str>) -> IdRequest {
+        IdRequest {
+            url: url.to_owned(),
+            namespace: namespace.map(str::to_owned),
+            attempt: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn same_link_same_id() {
+        // Given
+        let one = HashIDProvider::new("secret", Alphabet::base62(), 8);
+        let two = HashIDProvider::new("secret", Alphabet::base62(), 8);
+        let link = request("https://example.com/", Some("ci"));
+
+        // When
+        let (first, second) = (one.provide(&link).await, two.provide(&link).await);
+
+        // Then
+        assert_eq!(first, second);
+        assert_eq!(first.unwrap().len(), 8);
+    }
+
+    #[test]
+    fn namespace_url_and_key_all_matter() {
+        let provider = HashIDProvider::new("secret", Alphabet::base62(), 8);
+        let base = provider.id(&request("https://example.com/", Some("ci")));
+
+        assert_ne!(
+            base,
+            provider.id(&request("https://example.com/", Some("qa")))
+        );
+        assert_ne!(base, provider.id(&request("https://example.com/", None)));
+        assert_ne!(
+            base,
+            provider.id(&request("https://example.org/", Some("ci")))
+        );
+        assert_ne!(
+            base,
+            HashIDProvider::new("other", Alphabet::base62(), 8)
+                .id(&request("https://example.com/", Some("ci")))
+        );
+    }
+
+    #[test]
+    fn retries_extend_the_id() {
+        // Given
+        let provider = HashIDProvider::new("secret", "ab".parse().unwrap(), 4);
+        let mut link = request("https://example.com/", None);
+
+        // When
+        let first = provider.id(&link);
+        link.attempt = 40;
+        let retried = provider.id(&link);
+
+        // Then
+        assert_eq!(retried.len(), 44);
+        assert!(retried.starts_with(&first));
+        assert!(retried.chars().all(|c| c == 'a' || c == 'b'));
+    }
+}
@@ -0,0 +1,302 @@
+pub mod alphabet;
+pub mod blocklist;
+pub mod hash;
+pub mod sequential;
+
+use std::io;
+use std::str::FromStr;
+
+use async_trait::async_trait;
+
+use crate::{config::Config, error::AppError};
+
+use self::{
+    alphabet::Alphabet,
+    blocklist::{Blocklist, FilteredIDProvider},
+    hash::HashIDProvider,
+    sequential::{IdSequenceRepository, SequentialIDProvider},
+};
+
+/// Highest accepted chance that a new random ID hits an existing one once
+/// the expected number of links is stored. Each hit costs a retry.
+const MAX_COLLISION_PROBABILITY: f64 = 1e-3;
+
+/// What a new ID is generated for. Random providers ignore it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct IdRequest {
+    /// The destination URL, normalized by parsing it.
+    pub url: String,
+    pub namespace: Option<String>,
+    /// How many IDs generated for this request turned out to be taken.
+    pub attempt: usize,
+}
+
+#[mockall::automock]
+#[async_trait]
+pub trait IDProvider {
+    async fn provide(&self, request: &IdRequest) -> Result<String, AppError>;
+}
+
+#[async_trait]
+impl<T> IDProvider for Box<T>
+where
+    T: IDProvider + Send + Sync + ?Sized,
+{
+    async fn provide(&self, request: &IdRequest) -> Result<String, AppError> {
+        (**self).provide(request).await
+    }
+}
+
+/// How new short link IDs are generated.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum IdStrategy {
+    /// Random nanoids, 7 characters by default.
+    #[default]
+    NanoId,
+    /// Base62 encoded counter; see [`sequential::SequentialIDProvider`].
+    Sequential,
+    /// Keyed hash of the URL and namespace; see [`hash::HashIDProvider`].
+    Hash,
+}
+
+impl FromStr for IdStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "nanoid" => Ok(IdStrategy::NanoId),
+            "sequential" => Ok(IdStrategy::Sequential),
+            "hash" => Ok(IdStrategy::Hash),
+            other => Err(format!(
+                "unknown id strategy `{other}`, expected nanoid, sequential or hash"
+            )),
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum IdConfigError {
+    #[error(
+        "{length} characters from a {alphabet_size} character alphabet are too few for \
+         {expected_links} links: a new id collides with probability {probability:.2e}"
+    )]
+    CollisionRisk {
+        alphabet_size: usize,
+        length: usize,
+        expected_links: u64,
+        probability: f64,
+    },
+    #[error("the hash id strategy needs URLSHORTENER_ID_HASH_KEY")]
+    MissingHashKey,
+    #[error("cannot read the id blocklist: {0}")]
+    Blocklist(#[from] io::Error),
+}
+
+/// The provider `config` asks for; sequential IDs are drawn from `sequence`.
+/// Generated IDs are checked against the blocklist either way.
+pub fn from_config<S>(
+    config: &Config,
+    sequence: S,
+) -> Result<Box<dyn IDProvider + Send + Sync>, IdConfigError>
+where
+    S: IdSequenceRepository + Send + Sync + 'static,
+{
+    let blocklist = match &config.id_blocklist_path {
+        Some(path) => Blocklist::with_file(path)?,
+        None => Blocklist::default(),
+    };
+
+    let alphabet = config.id_alphabet.clone();
+    let provider: Box<dyn IDProvider + Send + Sync> = match config.id_strategy {
+        IdStrategy::NanoId => {
+            let alphabet = alphabet.unwrap_or_else(Alphabet::nanoid);
+            check_collision_risk(&alphabet, config)?;
+
+            Box::new(NanoIDProvider::new(alphabet, config.id_length))
+        }
+        IdStrategy::Hash => {
+            let key = config
+                .id_hash_key
+                .as_deref()
+                .ok_or(IdConfigError::MissingHashKey)?;
+            let alphabet = alphabet.unwrap_or_else(Alphabet::nanoid);
+            check_collision_risk(&alphabet, config)?;
+
+            Box::new(HashIDProvider::new(key, alphabet, config.id_length))
+        }
+        IdStrategy::Sequential => {
+            let mut provider = SequentialIDProvider::new(sequence, config.id_block_size);
+            if let Some(key) = config.id_obfuscation_key {
+                provider = provider.with_obfuscation(key);
+            }
+            if let Some(alphabet) = alphabet {
+                provider = provider.with_alphabet(alphabet);
+            }
+
+            Box::new(provider)
+        }
+    };
+
+    Ok(Box::new(FilteredIDProvider::new(provider, blocklist)))
+}
+
+/// Random and hashed IDs are only as unique as the space they are drawn
+/// from is large.
+fn check_collision_risk(alphabet: &Alphabet, config: &Config) -> Result<(), IdConfigError> {
+    let probability =
+        collision_probability(alphabet.len(), config.id_length, config.id_expected_links);
+    if probability > MAX_COLLISION_PROBABILITY {
+        return Err(IdConfigError::CollisionRisk {
+            alphabet_size: alphabet.len(),
+            length: config.id_length,
+            expected_links: config.id_expected_links,
+            probability,
+        });
+    }
+
+    Ok(())
+}
+
+/// Chance that one new ID equals any of `existing_links` stored ones.
+fn collision_probability(alphabet_size: usize, length: usize, existing_links: u64) -> f64 {
+    let space = (alphabet_size as f64).powi(length as i32);
+
+    (existing_links as f64 / space).min(1.0)
+}
+
+pub struct NanoIDProvider {
+    alphabet: Vec<char>,
+    length: usize,
+}
+
+impl NanoIDProvider {
+    pub fn new(alphabet: Alphabet, length: usize) -> Self {
+        Self {
+            alphabet: alphabet.chars(),
+            length: length.max(1),
+        }
+    }
+
+    /// Chance that one new ID equals any of `existing_links` stored ones.
+    pub fn collision_probability(&self, existing_links: u64) -> f64 {
+        collision_probability(self.alphabet.len(), self.length, existing_links)
+    }
+}
+
+impl Default for NanoIDProvider {
+    fn default() -> Self {
+        Self::new(Alphabet::nanoid(), 7)
+    }
+}
+
+#[async_trait]
+impl IDProvider for NanoIDProvider {
+    async fn provide(&self, _request: &IdRequest) -> Result<String, AppError> {
+        Ok(nanoid::format(
+            nanoid::rngs::default,
+            &self.alphabet,
+            self.length,
+        ))
+    }
+}
+
+pub struct FakeIDProvider {
+    id: String,
+}
+
+impl FakeIDProvider {
+    pub fn new(id: String) -> Self {
+        Self { id }
+    }
+
+    pub fn set_id(&mut self, id: String) {
+        self.id = id;
+    }
+}
+
+#[async_trait]
+impl IDProvider for FakeIDProvider {
+    async fn provide(&self, _request: &IdRequest) -> Result<String, AppError> {
+        Ok(self.id.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(vars: &[(&str, &str)]) -> Config {
+        Config::from_vars(|name| {
+            vars.iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| value.to_string())
+        })
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn nanoids_use_the_configured_alphabet_and_length() {
+        // Given
+        let provider = NanoIDProvider::new("ab".parse().unwrap(), 12);
+
+        // When
+        let id = provider.provide(&IdRequest::default()).await.unwrap();
+
+        // Then
+        assert_eq!(id.len(), 12);
+        assert!(id.chars().all(|c| c == 'a' || c == 'b'));
+    }
+
+    #[test]
+    fn default_nanoids_are_safe_for_the_expected_links() {
+        let provider = NanoIDProvider::default();
+
+        assert!(provider.collision_probability(100_000_000) < MAX_COLLISION_PROBABILITY);
+        assert_eq!(provider.collision_probability(u64::MAX), 1.0);
+    }
+
+    #[test]
+    fn rejects_an_id_space_too_small_for_the_expected_links() {
+        // Given
+        let config = config(&[
+            (
+                "URLSHORTENER_ID_ALPHABET",
+                "abcdefghjkmnpqrstuvwxyz23456789",
+            ),
+            ("URLSHORTENER_ID_LENGTH", "5"),
+        ]);
+
+        // When
+        let result = from_config(&config, sequential::MockIdSequenceRepository::new());
+
+        // Then
+        assert!(matches!(
+            result,
+            Err(IdConfigError::CollisionRisk {
+                alphabet_size: 31,
+                length: 5,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn hash_ids_need_a_key() {
+        let config = config(&[("URLSHORTENER_ID_STRATEGY", "hash")]);
+
+        assert!(matches!(
+            from_config(&config, sequential::MockIdSequenceRepository::new()),
+            Err(IdConfigError::MissingHashKey)
+        ));
+    }
+
+    #[test]
+    fn sequential_ids_skip_the_collision_check() {
+        let config = config(&[
+            ("URLSHORTENER_ID_STRATEGY", "sequential"),
+            ("URLSHORTENER_ID_LENGTH", "1"),
+        ]);
+
+        assert!(from_config(&config, sequential::MockIdSequenceRepository::new()).is_ok());
+    }
+}
@@ -0,0 +1,318 @@
+//! Short IDs allocated from a counter: `a`, `b`, ..., `9`, `aa`, `ab`, ...
+//!
+//! Counter values are written in bijective numeration over the alphabet
+//! (base62 by default), so every string over the alphabet is used and IDs
+//! only grow a character once all shorter ones are taken. Values are
+//! reserved from an [`IdSequenceRepository`] in blocks, which keeps
+//! instances sharing the repository from ever handing out the same ID while
+//! costing one round trip per block.
+
+use std::ops::Range;
+
+use async_trait::async_trait;
+use tokio::sync::Mutex;
+
+use crate::error::AppError;
+
+use super::{alphabet::Alphabet, IDProvider, IdRequest};
+
+#[mockall::automock]
+#[async_trait]
+pub trait IdSequenceRepository {
+    /// Reserves `count` consecutive counter values for the caller alone and
+    /// returns the first one.
+    async fn reserve(&self, count: u64) -> Result<u64, AppError>;
+}
+
+pub struct SequentialIDProvider<S>
+where
+    S: IdSequenceRepository,
+{
+    sequence: S,
+    block_size: u64,
+    block: Mutex<Range<u64>>,
+    alphabet: Alphabet,
+    obfuscation_key: Option<u64>,
+    obfuscation: Option<Obfuscation>,
+}
+
+impl<S> SequentialIDProvider<S>
+where
+    S: IdSequenceRepository,
+{
+    pub fn new(sequence: S, block_size: u64) -> Self {
+        Self {
+            sequence,
+            block_size: block_size.max(1),
+            block: Mutex::new(0..0),
+            alphabet: Alphabet::base62(),
+            obfuscation_key: None,
+            obfuscation: None,
+        }
+    }
+
+    pub fn with_alphabet(mut self, alphabet: Alphabet) -> Self {
+        self.alphabet = alphabet;
+        self.obfuscation = self
+            .obfuscation_key
+            .map(|key| Obfuscation::new(key, &self.alphabet));
+        self
+    }
+
+    /// Scrambles IDs with a permutation derived from `key` so consecutive
+    /// links do not get neighbouring IDs. IDs keep the length they would
+    /// have had, and the mapping is a bijection, so they stay unique. This
+    /// hides the allocation order from casual enumeration; it is not
+    /// encryption.
+    pub fn with_obfuscation(mut self, key: u64) -> Self {
+        self.obfuscation_key = Some(key);
+        self.obfuscation = Some(Obfuscation::new(key, &self.alphabet));
+        self
+    }
+}
+
+#[async_trait]
+impl<S> IDProvider for SequentialIDProvider<S>
+where
+    S: IdSequenceRepository + Send + Sync,
+{
+    async fn provide(&self, _request: &IdRequest) -> Result<String, AppError> {
+        let value = {
+            let mut block = self.block.lock().await;
+            if block.is_empty() {
+                let start = self.sequence.reserve(self.block_size).await?;
+                *block = start..start.saturating_add(self.block_size);
+            }
+            block.next().ok_or(AppError::StorageUnavailable)?
+        };
+
+        let base = self.alphabet.len() as u128;
+        let (length, index) = split(value, base);
+
+        Ok(match &self.obfuscation {
+            Some(obfuscation) => encode(
+                obfuscation.permute(base, length, index),
+                length,
+                &obfuscation.alphabet,
+            ),
+            None => encode(index, length, self.alphabet.as_bytes()),
+        })
+    }
+}
+
+/// Splits counter `value` into the length of its bijective base `base`
+/// form and its position among the IDs of that length.
+fn split(value: u64, base: u128) -> (u32, u128) {
+    let mut index = u128::from(value);
+    let mut length = 1;
+    let mut size = base;
+    while index >= size {
+        index -= size;
+        length += 1;
+        size *= base;
+    }
+
+    (length, index)
+}
+
+fn encode(mut index: u128, length: u32, alphabet: &[u8]) -> String {
+    let base = alphabet.len() as u128;
+
+    let mut id = vec![0; length as usize];
+    for digit in id.iter_mut().rev() {
+        *digit = alphabet[(index % base) as usize];
+        index /= base;
+    }
+
+    String::from_utf8(id).expect("alphabet is ascii")
+}
+
+/// Keyed permutation: a shuffled alphabet plus an affine map
+/// `i -> (a * i + b) mod base^len` per ID length, with `a` coprime to the
+/// base.
+struct Obfuscation {
+    alphabet: Vec<u8>,
+    multiplier: u128,
+    offset: u128,
+}
+
+impl Obfuscation {
+    fn new(key: u64, alphabet: &Alphabet) -> Self {
+        let mut rng = SplitMix64(key);
+
+        let mut alphabet = alphabet.as_bytes().to_vec();
+        for i in (1..alphabet.len()).rev() {
+            let j = (rng.next_u64() % (i as u64 + 1)) as usize;
+            alphabet.swap(i, j);
+        }
+
+        // Sharing no factor with the base makes it invertible modulo
+        // every power of the base.
+        let base = alphabet.len() as u128;
+        let mut multiplier = u128::from(rng.next_u64());
+        while gcd(multiplier, base) != 1 {
+            multiplier += 1;
+        }
+
+        Self {
+            alphabet,
+            multiplier,
+            offset: u128::from(rng.next_u64()),
+        }
+    }
+
+    fn permute(&self, base: u128, length: u32, index: u128) -> u128 {
+        let size = base.pow(length);
+
+        ((self.multiplier % size) * index + self.offset) % size
+    }
+}
+
+fn gcd(mut a: u128, mut b: u128) -> u128 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    };
+
+    use super::*;
+
+    /// Sequence shared by every clone, like a repository in a real store.
+    #[derive(Clone, Default)]
+    struct SharedSequence(Arc<AtomicU64>);
+
+    #[async_trait]
+    impl IdSequenceRepository for SharedSequence {
+        async fn reserve(&self, count: u64) -> Result<u64, AppError> {
+            Ok(self.0.fetch_add(count, Ordering::SeqCst))
+        }
+    }
+
+    async fn ids(provider: &impl IDProvider, count: usize) -> Vec<String> {
+        let mut ids = Vec::with_capacity(count);
+        for _ in 0..count {
+            ids.push(provider.provide(&IdRequest::default()).await.unwrap());
+        }
+        ids
+    }
+
+    #[tokio::test]
+    async fn counts_in_bijective_base62() {
+        // Given
+        let provider = SequentialIDProvider::new(SharedSequence::default(), 10);
+
+        // When
+        let ids = ids(&provider, 62 + 62 * 62 + 1).await;
+
+        // Then
+        assert_eq!(&ids[..3], ["a", "b", "c"]);
+        assert_eq!(ids[61], "9");
+        assert_eq!(&ids[62..64], ["aa", "ab"]);
+        assert_eq!(ids[62 + 62 * 62 - 1], "99");
+        assert_eq!(ids[62 + 62 * 62], "aaa");
+    }
+
+    #[tokio::test]
+    async fn instances_never_share_ids() {
+        // Given
+        let sequence = SharedSequence::default();
+        let first = SequentialIDProvider::new(sequence.clone(), 7);
+        let second = SequentialIDProvider::new(sequence, 7);
+
+        // When
+        let mut all = ids(&first, 20).await;
+        all.extend(ids(&second, 20).await);
+        all.extend(ids(&first, 20).await);
+
+        // Then
+        let unique: HashSet<_> = all.iter().collect();
+        assert_eq!(unique.len(), all.len());
+    }
+
+    #[tokio::test]
+    async fn reserves_one_block_at_a_time() {
+        // Given
+        let mut sequence = MockIdSequenceRepository::new();
+        let mut next = 0;
+        sequence.expect_reserve().times(2).returning(move |count| {
+            let start = next;
+            next += count;
+            Ok(start)
+        });
+        let provider = SequentialIDProvider::new(sequence, 100);
+
+        // When
+        let ids = ids(&provider, 150).await;
+
+        // Then
+        assert_eq!(ids.len(), 150);
+    }
+
+    #[tokio::test]
+    async fn obfuscation_is_a_length_preserving_bijection() {
+        // Given
+        let provider =
+            SequentialIDProvider::new(SharedSequence::default(), 1000).with_obfuscation(42);
+
+        // When
+        let ids = ids(&provider, 62 + 62 * 62).await;
+
+        // Then
+        assert!(ids[..62].iter().all(|id| id.len() == 1));
+        assert!(ids[62..].iter().all(|id| id.len() == 2));
+        let unique: HashSet<_> = ids.iter().collect();
+        assert_eq!(unique.len(), ids.len());
+        assert_ne!(&ids[62..65], ["aa", "ab", "ac"]);
+    }
+
+    #[tokio::test]
+    async fn custom_alphabet_sets_the_base() {
+        // Given
+        let alphabet: Alphabet = "xyz".parse().unwrap();
+        let provider = SequentialIDProvider::new(SharedSequence::default(), 10)
+            .with_obfuscation(7)
+            .with_alphabet(alphabet);
+
+        // When
+        let ids = ids(&provider, 3 + 9).await;
+
+        // Then
+        assert!(ids[..3].iter().all(|id| id.len() == 1));
+        assert!(ids[3..].iter().all(|id| id.len() == 2));
+        assert!(ids.iter().all(|id| id.chars().all(|c| "xyz".contains(c))));
+        assert_eq!(ids.iter().collect::<HashSet<_>>().len(), ids.len());
+    }
+
+    #[tokio::test]
+    async fn obfuscation_depends_on_the_key() {
+        // Given
+        let one = SequentialIDProvider::new(SharedSequence::default(), 10).with_obfuscation(1);
+        let two = SequentialIDProvider::new(SharedSequence::default(), 10).with_obfuscation(2);
+
+        // When
+        let (one, two) = (ids(&one, 100).await, ids(&two, 100).await);
+
+        // Then
+        assert_ne!(one, two);
+    }
+}
@@ -0,0 +1,8 @@
+pub mod adapters;
+pub mod app;
+pub mod clock;
+pub mod config;
+pub mod di;
+pub mod error;
+pub mod id_provider;
+pub mod ports;
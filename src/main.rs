@@ -1,28 +1,299 @@
-use std::sync::Arc;
+use std::{process::ExitCode, sync::Arc, time::Duration};
 
-use dashmap::DashMap;
+use urlshortener::{
+    adapters::{
+        cache::{CacheConfig, CachedRepository},
+        geoip::GeoIpDatabase,
+        inmemory::InMemoryStorage,
+        prober::HttpLinkProber,
+        redis::{
+            account::RedisAccountRepository, webhook::RedisWebhookRepository, RedisRepository,
+        },
+        threatlist::WatchedThreatList,
+        webhook::HttpWebhookSender,
+    },
+    app::{
+        command::check_link_health::{CheckLinkHealthCommand, HealthCheckPolicy},
+        event::EventBus,
+        screening::SharedScreener,
+        webhook::dispatcher::{RetryPolicy, WebhookDeliveryRepository, WebhookDispatcher},
+    },
+    clock::{SharedClock, SystemClock},
+    config::Config,
+    di::{self, AccountRepository, CommandRepository, QueryRepository, WebhookRepository},
+    id_provider::{self, IDProvider},
+    ports::httpapi::{CountryLookup, Server},
+};
 
-use crate::{adapters::inmemory::InMemoryRepository, ports::httpapi::Server};
-
-pub mod adapters;
-pub mod app;
-pub mod di;
-pub mod error;
-pub mod id_provider;
-pub mod ports;
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 #[tokio::main]
-async fn main() {
+async fn main() -> ExitCode {
     println!("Hello, world!");
 
-    let store = Arc::new(DashMap::new());
-    let repo = InMemoryRepository::new(store.clone());
-    let querier = InMemoryRepository::new(store.clone());
+    // Before anything else, so startup messages follow `RUST_LOG` too.
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| "urlshortener=debug,tower_http=debug".into()),
+        )
+        .with(tracing_subscriber::fmt::layer())
+        .init();
+
+    let config = match Config::from_env() {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("error: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let country = match &config.geoip_path {
+        None => CountryLookup::default(),
+        Some(path) => match GeoIpDatabase::open(path) {
+            Ok(database) => {
+                tracing::info!(ranges = database.len(), "loaded GeoIP database");
+                CountryLookup {
+                    database: Some(Arc::new(database)),
+                    ..CountryLookup::default()
+                }
+            }
+            Err(err) => {
+                eprintln!("error: {err}");
+                return ExitCode::FAILURE;
+            }
+        },
+    };
+    let country = CountryLookup {
+        header: config.country_header.clone(),
+        ..country
+    };
+
+    let screener: Option<SharedScreener> = match &config.threat_list_path {
+        None => None,
+        Some(path) => match WatchedThreatList::open(path) {
+            Ok(list) => {
+                tracing::info!(entries = list.list().len(), "loaded threat list");
+                let list = Arc::new(list);
+                tokio::spawn(
+                    list.clone()
+                        .run_periodic(config.threat_list_reload_interval),
+                );
+                Some(list)
+            }
+            Err(err) => {
+                eprintln!("error: {err}");
+                return ExitCode::FAILURE;
+            }
+        },
+    };
+
+    let clock: SharedClock = Arc::new(SystemClock);
+
+    if let Some(url) = &config.redis_url {
+        let repo = match RedisRepository::connect(url).await {
+            Ok(repo) => repo.with_clock(clock.clone()),
+            Err(err) => {
+                eprintln!("error: cannot connect to redis: {err}");
+                return ExitCode::FAILURE;
+            }
+        };
+        let idp = match id_provider::from_config(&config, repo.clone()) {
+            Ok(idp) => idp,
+            Err(err) => {
+                eprintln!("error: {err}");
+                return ExitCode::FAILURE;
+            }
+        };
+        let webhooks = RedisWebhookRepository::new(repo.clone());
+        let accounts = RedisAccountRepository::new(repo.clone());
+        let cached = CachedRepository::new(
+            repo,
+            CacheConfig {
+                capacity: config.cache_capacity,
+                ttl: config.cache_ttl,
+                negative_ttl: config.cache_negative_ttl,
+            },
+        )
+        .with_clock(clock.clone());
+        serve(
+            &config,
+            country,
+            screener,
+            idp,
+            clock,
+            Backend {
+                repo: cached.clone(),
+                querier: cached.clone(),
+                webhooks,
+                accounts,
+            },
+        )
+        .await;
+
+        let metrics = cached.metrics();
+        tracing::info!(
+            hits = metrics.hits,
+            misses = metrics.misses,
+            evictions = metrics.evictions,
+            "resolution cache"
+        );
+
+        return ExitCode::SUCCESS;
+    }
+
+    // Refuse to start on a damaged snapshot: serving an empty store would
+    // overwrite it on the next save.
+    let (storage, restored) = match InMemoryStorage::open(&config) {
+        Ok((storage, restored)) => (storage.with_clock(clock.clone()), restored),
+        Err(err) => {
+            eprintln!("error: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+    tracing::info!(
+        from_snapshot = restored.from_snapshot,
+        from_wal = restored.from_wal,
+        "restored links"
+    );
+    if restored.truncated_bytes > 0 {
+        tracing::warn!(
+            bytes = restored.truncated_bytes,
+            "dropped a torn write-ahead log record"
+        );
+    }
+    if let Some(snapshotter) = &storage.snapshotter {
+        tokio::spawn(snapshotter.clone().run_periodic(config.snapshot_interval));
+    }
+
+    let idp = match id_provider::from_config(&config, storage.sequence.clone()) {
+        Ok(idp) => idp,
+        Err(err) => {
+            eprintln!("error: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+    serve(
+        &config,
+        country,
+        screener,
+        idp,
+        clock,
+        Backend {
+            repo: storage.repository(),
+            querier: storage.repository(),
+            webhooks: storage.webhooks(),
+            accounts: storage.accounts(),
+        },
+    )
+    .await;
+
+    if let Some(snapshotter) = &storage.snapshotter {
+        if let Err(err) = snapshotter.save() {
+            eprintln!("error: final snapshot failed: {err}");
+            return ExitCode::FAILURE;
+        }
+    }
+
+    ExitCode::SUCCESS
+}
+
+/// The repositories of whichever storage `Config` selects.
+struct Backend<R, Q, W, A> {
+    repo: R,
+    querier: Q,
+    webhooks: W,
+    accounts: A,
+}
+
+/// Runs the HTTP server and its background jobs until shutdown.
+async fn serve<R, Q, W, A>(
+    config: &Config,
+    country: CountryLookup,
+    screener: Option<SharedScreener>,
+    idp: Box<dyn IDProvider + Send + Sync>,
+    clock: SharedClock,
+    backend: Backend<R, Q, W, A>,
+) where
+    R: CommandRepository,
+    Q: QueryRepository,
+    W: WebhookRepository + WebhookDeliveryRepository,
+    A: AccountRepository,
+{
+    let Backend {
+        repo,
+        querier,
+        webhooks,
+        accounts,
+    } = backend;
+    let events = EventBus::new();
+    let dispatcher = WebhookDispatcher::new(
+        webhooks.clone(),
+        HttpWebhookSender::new(Duration::from_secs(10)),
+        RetryPolicy::default(),
+    )
+    .with_clock(clock.clone());
+    tokio::spawn(dispatcher.run(events.subscribe()));
+
+    let mut container = di::Container::new(idp, repo.clone(), querier, webhooks, accounts, events)
+        .with_clock(clock.clone());
+    match &config.admin_api_key {
+        Some(key) => container = container.with_operator_key(key),
+        None => {
+            tracing::warn!("URLSHORTENER_ADMIN_API_KEY is unset; no accounts can be created")
+        }
+    }
+    if let Some(screener) = screener {
+        container = container.with_screener(screener, config.screen_on_redirect);
+    }
+    let container = Arc::new(container);
+
+    let sweeper = container.clone();
+    let interval = config.expiry_sweep_interval;
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            match sweeper.purge_expired_command.execute().await {
+                Ok(0) => {}
+                Ok(purged) => tracing::info!(purged, "purged expired links"),
+                Err(err) => tracing::warn!(%err, "purging expired links failed"),
+            }
+        }
+    });
 
-    let idp = id_provider::NanoIDProvider;
-    let container = Arc::new(di::Container::new(idp, repo, querier));
+    if let Some(interval) = config.health_check_interval {
+        let checker = CheckLinkHealthCommand::new(
+            repo,
+            HttpLinkProber::new(config.health_check_timeout),
+            HealthCheckPolicy {
+                concurrency: config.health_check_concurrency,
+                host_delay: config.health_check_host_delay,
+            },
+        )
+        .with_clock(clock);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            // A run that outlasts the interval is not followed by a burst.
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                match checker.execute().await {
+                    Ok(counts) => tracing::info!(
+                        healthy = counts.healthy,
+                        broken = counts.broken,
+                        unreachable = counts.unreachable,
+                        "checked link destinations"
+                    ),
+                    Err(err) => tracing::warn!(%err, "checking link destinations failed"),
+                }
+            }
+        });
+    }
 
-    let server = Server::new(3001, container);
+    let server = Server::new(config.port, container)
+        .with_idempotency_window(config.idempotency_window)
+        .with_country_lookup(country);
 
     server.run().await;
 }
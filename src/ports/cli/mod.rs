@@ -0,0 +1,479 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use clap::{Parser, Subcommand};
+use futures::StreamExt;
+use serde::Serialize;
+
+use crate::app::account::Principal;
+use crate::app::command::create_short_url::CreateOptions;
+use crate::app::command::import_short_urls::{ConflictPolicy, ImportReport};
+use crate::app::health::HealthCounts;
+use crate::app::query::list_short_urls::ListScope;
+use crate::app::short_url::OpenGraph;
+use crate::di::{
+    AccountRepository, CommandRepository, Container, QueryRepository, WebhookRepository,
+};
+use crate::error::AppError;
+use crate::id_provider::IDProvider;
+use crate::ports::transfer::{self, Format, TransferError};
+
+#[derive(Parser, Debug)]
+#[command(
+    name = "urlshortener-admin",
+    about = "Manage short links from the terminal"
+)]
+pub struct Cli {
+    /// Print machine readable JSON instead of plain text.
+    #[arg(long, global = true)]
+    pub json: bool,
+
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Shorten a URL.
+    Create {
+        url: String,
+        /// Seconds until the link stops resolving.
+        #[arg(long)]
+        ttl: Option<u64>,
+        /// Scope of hash derived IDs: the same URL and namespace always
+        /// get the same ID.
+        #[arg(long)]
+        namespace: Option<String>,
+        /// Show visitors a preview page instead of redirecting them.
+        #[arg(long)]
+        always_preview: bool,
+        /// `og:title` shown when the link is shared.
+        #[arg(long)]
+        og_title: Option<String>,
+        /// `og:description` shown when the link is shared.
+        #[arg(long)]
+        og_description: Option<String>,
+        /// `og:image` shown when the link is shared.
+        #[arg(long)]
+        og_image: Option<String>,
+        /// Unix time the link goes live at; until then it answers 404.
+        #[arg(long)]
+        activates_at: Option<u64>,
+        /// Show a coming soon page until the link goes live.
+        #[arg(long)]
+        coming_soon_page: bool,
+    },
+    /// Print the URL behind a short link.
+    Get { id: String },
+    /// Delete a short link.
+    Delete { id: String },
+    /// List short links ordered by ID.
+    List {
+        #[arg(long, default_value_t = 0)]
+        offset: usize,
+        #[arg(long, default_value_t = 100)]
+        limit: usize,
+    },
+    /// Load links from a file (stdin when omitted), keeping their IDs.
+    Import {
+        file: Option<PathBuf>,
+        /// csv or jsonl.
+        #[arg(long, default_value = "jsonl")]
+        format: Format,
+        /// What to do with IDs that already exist: skip, overwrite or fail.
+        #[arg(long, default_value = "skip")]
+        on_conflict: ConflictPolicy,
+    },
+    /// Write all links to a file (stdout when omitted).
+    Export {
+        file: Option<PathBuf>,
+        /// csv or jsonl.
+        #[arg(long, default_value = "jsonl")]
+        format: Format,
+    },
+    /// Print storage statistics.
+    Stats,
+}
+
+impl Command {
+    /// Whether the command changes stored links.
+    pub fn is_mutating(&self) -> bool {
+        matches!(
+            self,
+            Command::Create { .. } | Command::Delete { .. } | Command::Import { .. }
+        )
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum CliError {
+    #[error("{0}")]
+    App(#[from] AppError),
+    #[error("{0}")]
+    Io(#[from] io::Error),
+    #[error("{0}")]
+    Transfer(#[from] TransferError),
+    #[error("import stopped on conflicting id `{0}`")]
+    ImportConflict(String),
+}
+
+#[derive(Serialize)]
+struct IdOutput<'a> {
+    id: &'a str,
+}
+
+#[derive(Serialize)]
+struct DeleteOutput<'a> {
+    id: &'a str,
+    deleted: bool,
+}
+
+#[derive(Serialize)]
+struct GetOutput<'a> {
+    id: &'a str,
+    url: &'a str,
+}
+
+#[derive(Serialize)]
+struct ExportOutput {
+    exported: usize,
+}
+
+pub async fn run<I, R, Q, W, A>(
+    cli: Cli,
+    container: &Container<I, R, Q, W, A>,
+    out: &mut dyn Write,
+) -> Result<(), CliError>
+where
+    I: IDProvider,
+    R: CommandRepository,
+    Q: QueryRepository,
+    W: WebhookRepository,
+    A: AccountRepository,
+{
+    let json = cli.json;
+
+    match cli.command {
+        Command::Create {
+            url,
+            ttl,
+            namespace,
+            always_preview,
+            og_title,
+            og_description,
+            og_image,
+            activates_at,
+            coming_soon_page,
+        } => {
+            let options = CreateOptions {
+                ttl: ttl.map(Duration::from_secs),
+                namespace,
+                always_preview,
+                open_graph: OpenGraph {
+                    title: og_title,
+                    description: og_description,
+                    image: og_image,
+                },
+                activates_at,
+                coming_soon_page,
+                ..Default::default()
+            };
+            let id = container
+                .shorten_command
+                .execute_with(&Principal::operator(), &url, options)
+                .await?;
+            print(out, json, &IdOutput { id: &id }, &id)?;
+        }
+        Command::Get { id } => {
+            let url = container.get_full_url_query.execute(&id).await?;
+            print(out, json, &GetOutput { id: &id, url: &url }, &url)?;
+        }
+        Command::Delete { id } => {
+            container
+                .delete_command
+                .execute(&Principal::operator(), &id)
+                .await?;
+            let text = format!("deleted {id}");
+            print(
+                out,
+                json,
+                &DeleteOutput {
+                    id: &id,
+                    deleted: true,
+                },
+                &text,
+            )?;
+        }
+        Command::List { offset, limit } => {
+            let links = container
+                .list_query
+                .execute(&Principal::operator(), &ListScope::All, offset, limit)
+                .await?;
+            if json {
+                writeln!(out, "{}", to_json(&links))?;
+            } else {
+                for link in links {
+                    writeln!(out, "{}\t{}", link.id, link.url)?;
+                }
+            }
+        }
+        Command::Import {
+            file,
+            format,
+            on_conflict,
+        } => {
+            let reader: Box<dyn Read + Send> = match file {
+                Some(path) => Box::new(File::open(path)?),
+                None => Box::new(io::stdin()),
+            };
+            let report = transfer::import(
+                container,
+                &Principal::operator(),
+                format,
+                on_conflict,
+                reader,
+            )
+            .await?;
+            print(out, json, &report, &summary(&report))?;
+
+            if let Some(id) = report.conflict {
+                return Err(CliError::ImportConflict(id));
+            }
+        }
+        Command::Export {
+            file: Some(path),
+            format,
+        } => {
+            let mut writer = BufWriter::new(File::create(path)?);
+            let exported = export(container, format, &mut writer).await?;
+            writer.flush()?;
+            let text = format!("exported {exported} links");
+            print(out, json, &ExportOutput { exported }, &text)?;
+        }
+        Command::Export { file: None, format } => {
+            export(container, format, out).await?;
+        }
+        Command::Stats => {
+            let stats = container
+                .stats_query
+                .execute(&Principal::operator())
+                .await?;
+            let mut text = format!("total links: {}", stats.total_links);
+            let health = &stats.link_health;
+            if *health != HealthCounts::default() {
+                text.push_str(&format!(
+                    "\nchecked destinations: {} healthy, {} broken, {} unreachable",
+                    health.healthy, health.broken, health.unreachable
+                ));
+            }
+            print(out, json, &stats, &text)?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn export<I, R, Q, W, A>(
+    container: &Container<I, R, Q, W, A>,
+    format: Format,
+    out: &mut dyn Write,
+) -> Result<usize, CliError>
+where
+    I: IDProvider,
+    R: CommandRepository,
+    Q: QueryRepository,
+    W: WebhookRepository,
+    A: AccountRepository,
+{
+    let mut exported = 0;
+    let mut chunks = std::pin::pin!(transfer::export(container, &Principal::operator(), format)?);
+
+    while let Some(chunk) = chunks.next().await {
+        let (links, bytes) = chunk?;
+        out.write_all(&bytes)?;
+        exported += links;
+    }
+
+    Ok(exported)
+}
+
+fn summary(report: &ImportReport) -> String {
+    let mut text = format!(
+        "created {}, overwritten {}, skipped {}, unchanged {}, invalid {}",
+        report.created, report.overwritten, report.skipped, report.unchanged, report.invalid
+    );
+    for error in &report.errors {
+        text.push_str(&format!("\n  {error}"));
+    }
+    if let Some(id) = &report.conflict {
+        text.push_str(&format!("\nstopped on conflicting id {id}"));
+    }
+
+    text
+}
+
+fn print<T: Serialize>(out: &mut dyn Write, json: bool, value: &T, text: &str) -> io::Result<()> {
+    if json {
+        writeln!(out, "{}", to_json(value))
+    } else {
+        writeln!(out, "{text}")
+    }
+}
+
+fn to_json<T: Serialize>(value: &T) -> String {
+    serde_json::to_string(value).expect("CLI output is always serializable")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use dashmap::DashMap;
+
+    use crate::{
+        adapters::inmemory::{
+            account::InMemoryAccountRepository, webhook::InMemoryWebhookRepository,
+            InMemoryRepository,
+        },
+        app::{event::EventBus, short_url::ShortUrl},
+        id_provider::{FakeIDProvider, NanoIDProvider},
+    };
+
+    use super::*;
+
+    fn container(
+        store: Arc<DashMap<String, ShortUrl>>,
+    ) -> Container<
+        FakeIDProvider,
+        InMemoryRepository,
+        InMemoryRepository,
+        InMemoryWebhookRepository,
+        InMemoryAccountRepository,
+    > {
+        let repo = InMemoryRepository::new(store);
+
+        Container::new(
+            FakeIDProvider::new("test-id".to_owned()),
+            repo.clone(),
+            repo,
+            InMemoryWebhookRepository::new(),
+            InMemoryAccountRepository::new(),
+            EventBus::new(),
+        )
+    }
+
+    fn link(id: &str, url: &str) -> ShortUrl {
+        ShortUrl::new(id.to_owned(), url.to_owned())
+    }
+
+    async fn run_args<I, R, Q, W, A>(container: &Container<I, R, Q, W, A>, args: &[&str]) -> String
+    where
+        I: IDProvider,
+        R: CommandRepository,
+        Q: QueryRepository,
+        W: WebhookRepository,
+        A: AccountRepository,
+    {
+        let cli =
+            Cli::try_parse_from(std::iter::once("urlshortener-admin").chain(args.iter().copied()))
+                .unwrap();
+        let mut out = Vec::new();
+        run(cli, container, &mut out).await.unwrap();
+
+        String::from_utf8(out).unwrap()
+    }
+
+    #[tokio::test]
+    async fn create_and_get_with_json_output() {
+        // Given
+        let container = container(Arc::new(DashMap::new()));
+
+        // When
+        let created = run_args(&container, &["create", "https://example.com", "--json"]).await;
+        let fetched = run_args(&container, &["--json", "get", "test-id"]).await;
+
+        // Then
+        assert_eq!(created.trim(), r#"{"id":"test-id"}"#);
+        assert_eq!(
+            fetched.trim(),
+            r#"{"id":"test-id","url":"https://example.com/"}"#
+        );
+    }
+
+    #[tokio::test]
+    async fn delete_and_stats() {
+        // Given
+        let store = Arc::new(DashMap::new());
+        store.insert("a".to_owned(), link("a", "https://a.com/"));
+        store.insert("b".to_owned(), link("b", "https://b.com/"));
+        let container = container(store);
+
+        // When
+        let deleted = run_args(&container, &["delete", "a"]).await;
+        let stats = run_args(&container, &["stats"]).await;
+
+        // Then
+        assert_eq!(deleted.trim(), "deleted a");
+        assert_eq!(stats.trim(), "total links: 1");
+    }
+
+    #[tokio::test]
+    async fn list_as_text() {
+        // Given
+        let store = Arc::new(DashMap::new());
+        store.insert("b".to_owned(), link("b", "https://b.com/"));
+        store.insert("a".to_owned(), link("a", "https://a.com/"));
+        let container = container(store);
+
+        // When
+        let listed = run_args(&container, &["list"]).await;
+
+        // Then
+        assert_eq!(listed, "a\thttps://a.com/\nb\thttps://b.com/\n");
+    }
+
+    #[tokio::test]
+    async fn export_then_import_into_another_store() {
+        // Given
+        let source = Arc::new(DashMap::new());
+        source.insert("a".to_owned(), link("a", "https://a.com/"));
+        source.insert("b".to_owned(), link("b", "https://b.com/"));
+        let exported = run_args(&container(source), &["export", "--format", "csv"]).await;
+
+        let path = std::env::temp_dir().join(format!("urlshortener-{}.csv", nanoid::nanoid!()));
+        std::fs::write(&path, &exported).unwrap();
+
+        let target = Arc::new(DashMap::new());
+        let repo = InMemoryRepository::new(target.clone());
+        let container = Container::new(
+            NanoIDProvider::default(),
+            repo.clone(),
+            repo,
+            InMemoryWebhookRepository::new(),
+            InMemoryAccountRepository::new(),
+            EventBus::new(),
+        );
+
+        // When
+        let imported = run_args(
+            &container,
+            &[
+                "import",
+                path.to_str().unwrap(),
+                "--format",
+                "csv",
+                "--json",
+            ],
+        )
+        .await;
+        std::fs::remove_file(&path).unwrap();
+
+        // Then
+        let report: ImportReport = serde_json::from_str(&imported).unwrap();
+        assert_eq!(report.created, 2);
+        assert_eq!(target.get("a").unwrap().url, "https://a.com/");
+        assert_eq!(target.get("b").unwrap().url, "https://b.com/");
+    }
+}
@@ -0,0 +1,70 @@
+//! Who calls the management API, told by the `Authorization: Bearer` key.
+
+use std::sync::Arc;
+
+use axum::async_trait;
+use axum::extract::FromRequestParts;
+use axum::http::{header, request::Parts};
+
+use crate::app::account::Principal;
+use crate::di::{
+    AccountRepository, CommandRepository, Container, QueryRepository, WebhookRepository,
+};
+use crate::error::AppError;
+use crate::id_provider::IDProvider;
+
+/// The caller of a handler. Requests without a valid key are rejected with
+/// a 401 before the handler runs.
+pub struct Authenticated(pub Principal);
+
+#[async_trait]
+impl<I, R, Q, W, A> FromRequestParts<Arc<Container<I, R, Q, W, A>>> for Authenticated
+where
+    I: IDProvider + Send + Sync + 'static,
+    R: CommandRepository,
+    Q: QueryRepository,
+    W: WebhookRepository,
+    A: AccountRepository,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        container: &Arc<Container<I, R, Q, W, A>>,
+    ) -> Result<Self, Self::Rejection> {
+        let secret = parts
+            .headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(bearer)
+            .ok_or(AppError::Unauthenticated)?;
+
+        container
+            .authenticate_query
+            .execute(secret)
+            .await
+            .map(Authenticated)
+    }
+}
+
+/// The token of a `Bearer` credential; the scheme is case-insensitive.
+fn bearer(authorization: &str) -> Option<&str> {
+    let (scheme, token) = authorization.split_once(' ')?;
+    let token = token.trim();
+
+    (scheme.eq_ignore_ascii_case("bearer") && !token.is_empty()).then_some(token)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_bearer_tokens_only() {
+        assert_eq!(bearer("Bearer usk_abc"), Some("usk_abc"));
+        assert_eq!(bearer("bearer  usk_abc "), Some("usk_abc"));
+        assert_eq!(bearer("Basic dXNlcjpwdw=="), None);
+        assert_eq!(bearer("Bearer "), None);
+        assert_eq!(bearer("usk_abc"), None);
+    }
+}
@@ -0,0 +1,249 @@
+//! `Idempotency-Key` bookkeeping for `POST /api/v1/links`: the first
+//! response per key is kept for a window and replayed to retries of the same
+//! request.
+//!
+//! Keys live in process memory, so retries are only recognised by the
+//! instance that served the first attempt.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use axum::body::Bytes;
+use axum::http::{HeaderValue, StatusCode};
+use sha2::{Digest, Sha256};
+use tokio::time::Instant;
+
+pub const HEADER: &str = "idempotency-key";
+/// Set on responses replayed from an earlier request.
+pub const REPLAYED_HEADER: &str = "idempotent-replayed";
+const MAX_KEY_LENGTH: usize = 255;
+
+type Fingerprint = [u8; 32];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct StoredResponse {
+    pub status: StatusCode,
+    pub content_type: Option<HeaderValue>,
+    pub body: Bytes,
+}
+
+pub enum Begin {
+    /// First time the key is seen: run the request and hand its response
+    /// to the guard.
+    Proceed(Guard),
+    Replay(StoredResponse),
+    /// The key was used for a different request.
+    Mismatch,
+    /// A request with the key is still running.
+    InProgress,
+}
+
+enum Slot {
+    InFlight(Fingerprint),
+    Done {
+        fingerprint: Fingerprint,
+        response: StoredResponse,
+        expires_at: Instant,
+    },
+}
+
+#[derive(Default)]
+struct Entries {
+    slots: HashMap<String, Slot>,
+    /// Completed keys oldest first; the window is fixed, so this is also
+    /// expiry order.
+    expiry: VecDeque<(Instant, String)>,
+}
+
+impl Entries {
+    fn evict_expired(&mut self, now: Instant) {
+        while let Some((expires_at, _)) = self.expiry.front() {
+            if *expires_at > now {
+                break;
+            }
+            let (expires_at, key) = self.expiry.pop_front().expect("front exists");
+            // The key may have been stored again since.
+            if let Some(Slot::Done { expires_at: at, .. }) = self.slots.get(&key) {
+                if *at == expires_at {
+                    self.slots.remove(&key);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct IdempotencyStore {
+    window: Duration,
+    entries: Arc<Mutex<Entries>>,
+}
+
+impl IdempotencyStore {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            entries: Arc::default(),
+        }
+    }
+
+    /// Whether `key` is acceptable as an idempotency key.
+    pub fn is_valid_key(key: &str) -> bool {
+        !key.is_empty() && key.len() <= MAX_KEY_LENGTH
+    }
+
+    /// Claims `key` for the request described by `request`, unless the key
+    /// already has a response or a request in flight.
+    pub fn begin(&self, key: &str, request: &[u8]) -> Begin {
+        let fingerprint: Fingerprint = Sha256::digest(request).into();
+        let mut entries = self.entries.lock().expect("idempotency lock poisoned");
+        entries.evict_expired(Instant::now());
+
+        match entries.slots.get(key) {
+            Some(Slot::InFlight(stored))
+            | Some(Slot::Done {
+                fingerprint: stored,
+                ..
+            }) if *stored != fingerprint => Begin::Mismatch,
+            Some(Slot::InFlight(_)) => Begin::InProgress,
+            Some(Slot::Done { response, .. }) => Begin::Replay(response.clone()),
+            None => {
+                entries
+                    .slots
+                    .insert(key.to_owned(), Slot::InFlight(fingerprint));
+                Begin::Proceed(Guard {
+                    store: self.clone(),
+                    key: Some(key.to_owned()),
+                    fingerprint,
+                })
+            }
+        }
+    }
+}
+
+/// Holds a claimed key. Dropping it without completing, for example when
+/// the client goes away mid-request, releases the key for retries.
+pub struct Guard {
+    store: IdempotencyStore,
+    key: Option<String>,
+    fingerprint: Fingerprint,
+}
+
+impl Guard {
+    /// Stores `response` for replay. Server errors are not stored, so a
+    /// retry gets another chance instead of the same failure.
+    pub fn complete(mut self, response: StoredResponse) {
+        let key = self.key.take().expect("guard completes once");
+        let mut entries = self
+            .store
+            .entries
+            .lock()
+            .expect("idempotency lock poisoned");
+
+        if response.status.is_server_error() {
+            entries.slots.remove(&key);
+            return;
+        }
+
+        let expires_at = Instant::now() + self.store.window;
+        entries.expiry.push_back((expires_at, key.clone()));
+        entries.slots.insert(
+            key,
+            Slot::Done {
+                fingerprint: self.fingerprint,
+                response,
+                expires_at,
+            },
+        );
+    }
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        if let Some(key) = self.key.take() {
+            if let Ok(mut entries) = self.store.entries.lock() {
+                entries.slots.remove(&key);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn created() -> StoredResponse {
+        StoredResponse {
+            status: StatusCode::OK,
+            content_type: Some(HeaderValue::from_static("application/json")),
+            body: Bytes::from_static(br#"{"id":"abc"}"#),
+        }
+    }
+
+    #[tokio::test]
+    async fn replays_the_first_response() {
+        // Given
+        let store = IdempotencyStore::new(Duration::from_secs(60));
+        let Begin::Proceed(guard) = store.begin("k", b"body") else {
+            panic!("first use must proceed");
+        };
+
+        // When
+        guard.complete(created());
+
+        // Then
+        assert!(matches!(store.begin("k", b"body"), Begin::Replay(r) if r == created()));
+        assert!(matches!(store.begin("k", b"other"), Begin::Mismatch));
+    }
+
+    #[tokio::test]
+    async fn concurrent_use_is_rejected_until_released() {
+        // Given
+        let store = IdempotencyStore::new(Duration::from_secs(60));
+        let first = store.begin("k", b"body");
+
+        // When
+        let second = store.begin("k", b"body");
+        drop(first);
+        let third = store.begin("k", b"body");
+
+        // Then
+        assert!(matches!(second, Begin::InProgress));
+        assert!(matches!(third, Begin::Proceed(_)));
+    }
+
+    #[tokio::test]
+    async fn server_errors_are_not_kept() {
+        // Given
+        let store = IdempotencyStore::new(Duration::from_secs(60));
+        let Begin::Proceed(guard) = store.begin("k", b"body") else {
+            panic!("first use must proceed");
+        };
+
+        // When
+        guard.complete(StoredResponse {
+            status: StatusCode::SERVICE_UNAVAILABLE,
+            content_type: None,
+            body: Bytes::new(),
+        });
+
+        // Then
+        assert!(matches!(store.begin("k", b"body"), Begin::Proceed(_)));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn keys_expire_after_the_window() {
+        // Given
+        let store = IdempotencyStore::new(Duration::from_secs(60));
+        let Begin::Proceed(guard) = store.begin("k", b"body") else {
+            panic!("first use must proceed");
+        };
+        guard.complete(created());
+
+        // When
+        tokio::time::advance(Duration::from_secs(61)).await;
+
+        // Then
+        assert!(matches!(store.begin("k", b"other"), Begin::Proceed(_)));
+    }
+}
@@ -1,81 +1,256 @@
+mod auth;
+mod coming_soon;
+mod idempotency;
+mod openapi;
+mod preview;
+mod problem;
+mod unfurl;
+mod visitor;
+mod warning;
+
+use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
 
-use axum::extract::{MatchedPath, Path, Request, State};
-use axum::response::{IntoResponse, Response};
-use axum::routing::{get, post};
-use axum::{http, Json, Router};
+use axum::body::{Body, Bytes};
+use axum::extract::{ConnectInfo, MatchedPath, Path, Query, RawQuery, Request, State};
+use axum::response::{IntoResponse, Redirect, Response};
+use axum::routing::{delete, get, post, put, MethodRouter};
+use axum::{http, Extension, Json, Router};
+use futures::{stream, StreamExt, TryStreamExt};
 use serde::{Deserialize, Serialize};
+use tokio::sync::{mpsc, oneshot};
+use tokio_util::io::{StreamReader, SyncIoBridge};
 use tower_http::trace::TraceLayer;
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+use utoipa::{IntoParams, OpenApi, ToSchema};
 
-use crate::app::command::create_short_url::CreateShortUrlRepository;
-use crate::app::query::get_full_url::GetFullUrlRepository;
-use crate::di::Container;
+use crate::app::account::{Account, IssuedKey, Principal};
+use crate::app::command::create_short_url::CreateOptions;
+use crate::app::command::import_short_urls::{ConflictPolicy, ImportReport};
+use crate::app::command::update_short_url::LinkChanges;
+use crate::app::command::update_workspace::WorkspaceChanges;
+use crate::app::event::EventKind;
+use crate::app::health::LinkHealth;
+use crate::app::policy::Role;
+use crate::app::query::get_full_url::Resolution;
+use crate::app::query::get_link_stats::LinkStats;
+use crate::app::query::list_short_urls::ListScope;
+use crate::app::redirect_rule::RedirectRule;
+use crate::app::report::AbuseReport;
+use crate::app::short_url::{LinkStatus, OpenGraph, ShortUrl};
+use crate::app::target::QueryPassthrough;
+use crate::app::variant::Variant;
+use crate::app::webhook::{WebhookDelivery, WebhookSubscription};
+use crate::app::workspace::{Quota, Workspace};
+use crate::di::{
+    AccountRepository, CommandRepository, Container, QueryRepository, WebhookRepository,
+};
 use crate::error::AppError;
 use crate::id_provider::IDProvider;
+use crate::ports::transfer::{self, Format, TransferError};
 
-#[derive(Serialize, Deserialize)]
-struct ErrorResponse {
-    message: String,
-}
+use self::auth::Authenticated;
+use self::idempotency::{Begin, IdempotencyStore, StoredResponse};
+use self::problem::{JsonBody, Problem};
+
+pub use self::visitor::CountryLookup;
+
+/// How long a response is replayed to retries carrying the same
+/// `Idempotency-Key`, unless configured otherwise.
+pub const DEFAULT_IDEMPOTENCY_WINDOW: Duration = Duration::from_secs(24 * 60 * 60);
 
-impl IntoResponse for AppError {
+/// Links per page of `GET /api/v1/links`, unless asked for fewer.
+const MAX_PAGE_SIZE: usize = 1000;
+
+impl IntoResponse for TransferError {
     fn into_response(self) -> Response {
-        let (status, message) = match self {
-            AppError::URLParseError => (http::StatusCode::BAD_REQUEST, "Invalid URL".to_owned()),
-            AppError::NotFound => (http::StatusCode::NOT_FOUND, "Not found".to_owned()),
+        let detail = self.to_string();
+        let problem = match self {
+            TransferError::App(err) => Problem::from(err),
+            TransferError::Read(_) => Problem::new(
+                http::StatusCode::BAD_REQUEST,
+                "unreadable-input",
+                "Unreadable input",
+                detail,
+            ),
+            TransferError::Encode(_) => Problem::new(
+                http::StatusCode::INTERNAL_SERVER_ERROR,
+                "export-failed",
+                "Export failed",
+                detail,
+            ),
         };
 
-        (status, Json(ErrorResponse { message })).into_response()
+        problem.into_response()
     }
 }
 
-pub struct Server<I, R, Q>
+pub struct Server<I, R, Q, W, A>
 where
     I: IDProvider + Send + Sync + 'static,
-    R: CreateShortUrlRepository + Send + Sync + 'static,
-    Q: GetFullUrlRepository + Send + Sync + 'static,
+    R: CommandRepository,
+    Q: QueryRepository,
+    W: WebhookRepository,
+    A: AccountRepository,
 {
     port: u16,
-    container: Arc<Container<I, R, Q>>,
+    container: SharedContainer<I, R, Q, W, A>,
+    idempotency_window: Duration,
+    country: CountryLookup,
 }
 
-impl<I, R, Q> Server<I, R, Q>
+impl<I, R, Q, W, A> Server<I, R, Q, W, A>
 where
     I: IDProvider + Send + Sync + 'static,
-    R: CreateShortUrlRepository + Send + Sync + 'static,
-    Q: GetFullUrlRepository + Send + Sync + 'static,
+    R: CommandRepository,
+    Q: QueryRepository,
+    W: WebhookRepository,
+    A: AccountRepository,
 {
-    pub fn new(port: u16, container: Arc<Container<I, R, Q>>) -> Self {
-        Server { port, container }
+    pub fn new(port: u16, container: SharedContainer<I, R, Q, W, A>) -> Self {
+        Server {
+            port,
+            container,
+            idempotency_window: DEFAULT_IDEMPOTENCY_WINDOW,
+            country: CountryLookup::default(),
+        }
     }
 
-    pub async fn run(self) {
-        tracing_subscriber::registry()
-            .with(
-                tracing_subscriber::EnvFilter::try_from_default_env()
-                    .unwrap_or_else(|_| "urlshortner=debug,tower_http=debug".into()),
-            )
-            .with(tracing_subscriber::fmt::layer())
-            .init();
+    pub fn with_idempotency_window(mut self, window: Duration) -> Self {
+        self.idempotency_window = window;
+        self
+    }
 
-        let router = get_router(self.container);
+    /// How redirect rules learn the visitor's country.
+    pub fn with_country_lookup(mut self, country: CountryLookup) -> Self {
+        self.country = country;
+        self
+    }
+
+    pub async fn run(self) {
+        let router = get_router(
+            self.container,
+            IdempotencyStore::new(self.idempotency_window),
+            self.country,
+        );
         let addr = format!("0.0.0.0:{}", self.port);
         let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
 
-        axum::serve(listener, router).await.unwrap();
+        axum::serve(
+            listener,
+            router.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .with_graceful_shutdown(shutdown_signal())
+        .await
+        .unwrap();
+    }
+}
+
+/// Resolves on Ctrl-C or SIGTERM so `run` returns and the caller can flush
+/// state before exiting.
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("failed to install Ctrl-C handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
     }
+
+    tracing::info!("shutting down");
+}
+
+type SharedContainer<I, R, Q, W, A> = Arc<Container<I, R, Q, W, A>>;
+
+type Route<I, R, Q, W, A> = (&'static str, MethodRouter<SharedContainer<I, R, Q, W, A>>);
+
+/// Every route the server answers. Short links resolve at the root; the
+/// management API lives under `/api/v1` so it never shadows an ID. The
+/// OpenAPI document is tested against this table.
+fn routes<I, R, Q, W, A>() -> Vec<Route<I, R, Q, W, A>>
+where
+    I: IDProvider + Send + Sync + 'static,
+    R: CommandRepository,
+    Q: QueryRepository,
+    W: WebhookRepository,
+    A: AccountRepository,
+{
+    vec![
+        ("/:id", get(redirect)),
+        ("/:id/*path", get(redirect_with_path)),
+        (
+            "/:id/report",
+            get(redirect_with_report_path).post(report_link),
+        ),
+        ("/api/v1/links", post(shorten_url).get(list_links)),
+        (
+            "/api/v1/links/:id",
+            get(get_full_url).patch(update_link).delete(delete_link),
+        ),
+        ("/api/v1/links/:id/stats", get(get_link_stats)),
+        ("/api/v1/links/:id/health", get(get_link_health)),
+        ("/api/v1/links/:id/status", put(change_link_status)),
+        ("/api/v1/reports", get(list_reports)),
+        ("/api/v1/webhooks", post(create_webhook).get(list_webhooks)),
+        ("/api/v1/webhooks/:id", delete(delete_webhook)),
+        (
+            "/api/v1/webhooks/:id/deliveries",
+            get(list_webhook_deliveries),
+        ),
+        ("/api/v1/accounts", post(create_account)),
+        ("/api/v1/accounts/:id/keys", post(create_api_key)),
+        ("/api/v1/accounts/:id/keys/:key_id", delete(revoke_api_key)),
+        ("/api/v1/workspaces", post(create_workspace)),
+        (
+            "/api/v1/workspaces/:id",
+            get(get_workspace).patch(update_workspace),
+        ),
+        (
+            "/api/v1/workspaces/:id/members/:account_id",
+            put(add_member).delete(remove_member),
+        ),
+        ("/api/v1/workspaces/:id/usage", get(get_workspace_usage)),
+        ("/api/v1/admin/import", post(import_links)),
+        ("/api/v1/admin/export", get(export_links)),
+        ("/api/v1/metrics", get(metrics)),
+        ("/api/v1/openapi.json", get(openapi_document)),
+    ]
 }
 
-fn get_router<I, R, Q>(container: Arc<Container<I, R, Q>>) -> Router
+fn get_router<I, R, Q, W, A>(
+    container: SharedContainer<I, R, Q, W, A>,
+    idempotency: IdempotencyStore,
+    country: CountryLookup,
+) -> Router
 where
     I: IDProvider + Send + Sync + 'static,
-    R: CreateShortUrlRepository + Send + Sync + 'static,
-    Q: GetFullUrlRepository + Send + Sync + 'static,
+    R: CommandRepository,
+    Q: QueryRepository,
+    W: WebhookRepository,
+    A: AccountRepository,
 {
-    Router::new()
-        .route("/:id", get(get_full_url))
-        .route("/", post(shorten_url))
+    routes()
+        .into_iter()
+        .fold(Router::new(), |router, (path, route)| {
+            router.route(path, route)
+        })
+        .layer(Extension(idempotency))
+        .layer(Extension(country))
+        .layer(axum::middleware::from_fn(problem::add_instance))
         .layer(
             TraceLayer::new_for_http()
                 .make_span_with(|req: &Request| {
@@ -93,33 +268,197 @@ where
         .with_state(container)
 }
 
-#[derive(Deserialize, Serialize)]
+#[derive(Default, Deserialize, Serialize, ToSchema)]
 struct CreateShortURLRequest {
+    /// The destination. Templates such as `https://shop.example/{path}?ref={ref}`
+    /// are filled from the path after the ID and from query values.
     url: String,
+    /// Seconds until the link stops resolving. Links live forever by default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    ttl_seconds: Option<u64>,
+    /// Scope of hash derived IDs; see `CreateOptions::namespace`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    namespace: Option<String>,
+    /// Show visitors a preview page instead of redirecting them.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    always_preview: bool,
+    /// Card shown when the link is shared in chat apps and social sites.
+    #[serde(default, skip_serializing_if = "OpenGraph::is_empty")]
+    open_graph: OpenGraph,
+    /// Checked in order on every visit; the first match overrides `url`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    rules: Vec<RedirectRule>,
+    /// Weighted targets for an A/B split; weights add up to 100.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    variants: Vec<Variant>,
+    /// Pass the query visitors arrive with, such as UTM tags, on to the
+    /// destination.
+    #[serde(default, skip_serializing_if = "QueryPassthrough::is_off")]
+    query_passthrough: QueryPassthrough,
+    /// Workspace to own the link jointly, counted against its quota. The
+    /// caller must be a member.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    workspace: Option<String>,
+    /// Unix time the link goes live at, e.g. for a launch. Until then it
+    /// answers 404.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    activates_at: Option<u64>,
+    /// Show visitors who come before `activates_at` a coming soon page
+    /// instead of the 404.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    coming_soon_page: bool,
 }
 
-#[derive(Deserialize, Serialize)]
+#[derive(Deserialize, Serialize, ToSchema)]
 struct ShortUrlResponse {
     id: String,
 }
 
-async fn shorten_url<I, R, Q>(
-    State(container): State<Arc<Container<I, R, Q>>>,
-    Json(input): Json<CreateShortURLRequest>,
-) -> Result<Json<ShortUrlResponse>, AppError>
+/// Creates a link owned by the caller. With an `Idempotency-Key` header the
+/// first response is replayed to the same caller's retries; reusing the key
+/// for another body is a 422 and using it while the first request still
+/// runs a 409.
+#[utoipa::path(
+    post,
+    path = "/api/v1/links",
+    tag = "links",
+    request_body = CreateShortURLRequest,
+    params(
+        ("Idempotency-Key" = Option<String>, Header,
+            description = "Replays the first response to retries carrying the same key"),
+    ),
+    responses(
+        (status = 200, description = "Link created", body = ShortUrlResponse),
+        (status = 400, description = "Invalid URL, idempotency key or schedule", body = Problem, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or unknown API key", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "Viewers may not create links, the caller is no member of the workspace, or its quota is used up", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "ID taken, or the key is in use", body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "Key reused for another request", body = Problem, content_type = "application/problem+json"),
+        (status = 503, description = "Storage unavailable", body = Problem, content_type = "application/problem+json"),
+    ),
+)]
+async fn shorten_url<I, R, Q, W, A>(
+    State(container): State<SharedContainer<I, R, Q, W, A>>,
+    Authenticated(principal): Authenticated,
+    Extension(idempotency): Extension<IdempotencyStore>,
+    headers: http::HeaderMap,
+    JsonBody(input): JsonBody<CreateShortURLRequest>,
+) -> Response
 where
     I: IDProvider + Send + Sync + 'static,
-    R: CreateShortUrlRepository + Send + Sync + 'static,
-    Q: GetFullUrlRepository + Send + Sync + 'static,
+    R: CommandRepository,
+    Q: QueryRepository,
+    W: WebhookRepository,
+    A: AccountRepository,
+{
+    let Some(key) = headers.get(idempotency::HEADER) else {
+        return create_short_url(&container, &principal, input)
+            .await
+            .into_response();
+    };
+    let key = match key.to_str() {
+        Ok(key) if IdempotencyStore::is_valid_key(key) => key,
+        _ => {
+            return Problem::new(
+                http::StatusCode::BAD_REQUEST,
+                "invalid-idempotency-key",
+                "Invalid Idempotency-Key",
+                "Idempotency keys are 1 to 255 visible ASCII characters",
+            )
+            .into_response();
+        }
+    };
+
+    // Callers choose keys independently, so one caller's key never replays
+    // another's response.
+    let key = format!(
+        "{}\n{key}",
+        principal.account_id.as_deref().unwrap_or_default()
+    );
+    let fingerprint = serde_json::to_vec(&input).expect("request serializes");
+    match idempotency.begin(&key, &fingerprint) {
+        Begin::Proceed(guard) => {
+            let response = create_short_url(&container, &principal, input)
+                .await
+                .into_response();
+            let (parts, body) = response.into_parts();
+            let Ok(body) = axum::body::to_bytes(body, usize::MAX).await else {
+                return http::StatusCode::INTERNAL_SERVER_ERROR.into_response();
+            };
+
+            guard.complete(StoredResponse {
+                status: parts.status,
+                content_type: parts.headers.get(http::header::CONTENT_TYPE).cloned(),
+                body: body.clone(),
+            });
+            Response::from_parts(parts, Body::from(body))
+        }
+        Begin::Replay(stored) => {
+            let mut response = (
+                stored.status,
+                [(idempotency::REPLAYED_HEADER, "true")],
+                stored.body,
+            )
+                .into_response();
+            if let Some(content_type) = stored.content_type {
+                response
+                    .headers_mut()
+                    .insert(http::header::CONTENT_TYPE, content_type);
+            }
+            response
+        }
+        Begin::Mismatch => Problem::new(
+            http::StatusCode::UNPROCESSABLE_ENTITY,
+            "idempotency-key-reused",
+            "Idempotency-Key reused",
+            "The Idempotency-Key was used for a different request",
+        )
+        .into_response(),
+        Begin::InProgress => Problem::new(
+            http::StatusCode::CONFLICT,
+            "idempotency-key-in-use",
+            "Idempotency-Key in use",
+            "A request with this Idempotency-Key is still in progress",
+        )
+        .into_response(),
+    }
+}
+
+async fn create_short_url<I, R, Q, W, A>(
+    container: &Container<I, R, Q, W, A>,
+    principal: &Principal,
+    input: CreateShortURLRequest,
+) -> Result<Json<ShortUrlResponse>, AppError>
+where
+    I: IDProvider,
+    R: CommandRepository,
+    Q: QueryRepository,
+    W: WebhookRepository,
+    A: AccountRepository,
 {
     container
         .shorten_command
-        .execute(&input.url)
+        .execute_with(
+            principal,
+            &input.url,
+            CreateOptions {
+                ttl: input.ttl_seconds.map(Duration::from_secs),
+                namespace: input.namespace,
+                always_preview: input.always_preview,
+                open_graph: input.open_graph,
+                rules: input.rules,
+                variants: input.variants,
+                query_passthrough: input.query_passthrough,
+                workspace: input.workspace,
+                activates_at: input.activates_at,
+                coming_soon_page: input.coming_soon_page,
+            },
+        )
         .await
         .map(|id| Json(ShortUrlResponse { id }))
 }
 
-#[derive(serde::Deserialize, serde::Serialize)]
+#[derive(serde::Deserialize, serde::Serialize, ToSchema)]
 struct FullUrlResponse {
     url: String,
 }
@@ -130,14 +469,228 @@ impl From<String> for FullUrlResponse {
     }
 }
 
-async fn get_full_url<I, Q, R>(
+/// Sends visitors on to the destination of a short link, or shows a
+/// preview of it for `/{id}+` and links that always preview. Unfurl bots
+/// get the link's Open Graph card when it has one. The query is passed on
+/// if the link asks for it. On a workspace's custom domain only that
+/// workspace's links resolve.
+#[utoipa::path(
+    get,
+    path = "/{id}",
+    tag = "redirect",
+    security(()),
+    params(("id" = String, Path,
+        description = "Short link ID, with a trailing `+` for the preview page")),
+    responses(
+        (status = 200, description = "Preview of the destination, an Open Graph card for unfurl bots, or a warning for quarantined links",
+            content_type = "text/html"),
+        (status = 307, description = "Redirect to the destination",
+            headers(("location" = String, description = "The destination URL"))),
+        (status = 404, description = "Unknown or expired link, or one not live yet; scheduled links may show a coming soon page instead", body = Problem, content_type = "application/problem+json"),
+        (status = 451, description = "The link was disabled", body = Problem, content_type = "application/problem+json"),
+    ),
+)]
+async fn redirect<I, Q, R, W, A>(
+    Path(id): Path<String>,
+    State(container): State<SharedContainer<I, R, Q, W, A>>,
+    Extension(country): Extension<CountryLookup>,
+    peer: Option<ConnectInfo<SocketAddr>>,
+    RawQuery(query): RawQuery,
+    headers: http::HeaderMap,
+) -> Result<Response, AppError>
+where
+    I: IDProvider + Send + Sync + 'static,
+    R: CommandRepository,
+    Q: QueryRepository,
+    W: WebhookRepository,
+    A: AccountRepository,
+{
+    let request = visitor::Incoming {
+        path: String::new(),
+        query,
+        peer: peer.map(|ConnectInfo(addr)| addr.ip()),
+        headers,
+    };
+    follow(&container, &id, request, country).await
+}
+
+/// Like `/{id}`, for templated links that take the rest of the path.
+#[utoipa::path(
+    get,
+    path = "/{id}/{path}",
+    tag = "redirect",
+    security(()),
+    params(
+        ("id" = String, Path,
+            description = "Short link ID, with a trailing `+` for the preview page"),
+        ("path" = String, Path, description = "Fills the `{path}` placeholder of the destination"),
+    ),
+    responses(
+        (status = 200, description = "Preview of the destination, an Open Graph card for unfurl bots, or a warning for quarantined links",
+            content_type = "text/html"),
+        (status = 307, description = "Redirect to the destination",
+            headers(("location" = String, description = "The destination URL"))),
+        (status = 404, description = "Unknown or expired link, or one not live yet; scheduled links may show a coming soon page instead", body = Problem, content_type = "application/problem+json"),
+        (status = 451, description = "The link was disabled", body = Problem, content_type = "application/problem+json"),
+    ),
+)]
+async fn redirect_with_path<I, Q, R, W, A>(
+    Path((id, path)): Path<(String, String)>,
+    State(container): State<SharedContainer<I, R, Q, W, A>>,
+    Extension(country): Extension<CountryLookup>,
+    peer: Option<ConnectInfo<SocketAddr>>,
+    RawQuery(query): RawQuery,
+    headers: http::HeaderMap,
+) -> Result<Response, AppError>
+where
+    I: IDProvider + Send + Sync + 'static,
+    R: CommandRepository,
+    Q: QueryRepository,
+    W: WebhookRepository,
+    A: AccountRepository,
+{
+    let request = visitor::Incoming {
+        path,
+        query,
+        peer: peer.map(|ConnectInfo(addr)| addr.ip()),
+        headers,
+    };
+    follow(&container, &id, request, country).await
+}
+
+async fn follow<I, Q, R, W, A>(
+    container: &Container<I, R, Q, W, A>,
+    id: &str,
+    request: visitor::Incoming,
+    country: CountryLookup,
+) -> Result<Response, AppError>
+where
+    I: IDProvider,
+    R: CommandRepository,
+    Q: QueryRepository,
+    W: WebhookRepository,
+    A: AccountRepository,
+{
+    let (id, preview) = match id.strip_suffix(preview::SUFFIX) {
+        Some(id) => (id, true),
+        None => (id, false),
+    };
+    let visitor = visitor::from_request(id, &request, &country, container.clock.now());
+    let headers = request.headers;
+    let Resolution {
+        link,
+        target,
+        variant,
+    } = container.get_full_url_query.resolve(id, &visitor).await?;
+
+    // A workspace's domains serve its links only.
+    let host = headers
+        .get(http::header::HOST)
+        .and_then(|host| host.to_str().ok());
+    if let Some(host) = host {
+        let served = container.workspace_query.for_host(host).await?;
+        if served.is_some() && served != link.workspace {
+            return Err(AppError::not_found(id));
+        }
+    }
+
+    // Quarantined links lead nowhere, not even for bots or previews.
+    if link.status == LinkStatus::Quarantined {
+        let reason = link
+            .status_reason
+            .as_deref()
+            .unwrap_or("reported as harmful");
+        return Ok(warning::render(reason, &target));
+    }
+    match container.get_full_url_query.check_live(&link) {
+        Err(AppError::LinkNotYetActive { activates_at, .. }) => {
+            return Ok(coming_soon::render(activates_at));
+        }
+        live => live?,
+    }
+    if preview {
+        return Ok(preview::render(&link, &target));
+    }
+
+    let has_card = !link.open_graph.is_empty();
+    let mut response = if has_card && unfurl::is_unfurl_bot(&headers) {
+        unfurl::render(&link)
+    } else {
+        // Counting is best effort: a visitor still gets where they are going.
+        if let Err(err) = container
+            .record_visit_command
+            .execute(&link.id, variant.clone())
+            .await
+        {
+            tracing::warn!(id = link.id, %err, "failed to record visit");
+        }
+        if link.always_preview {
+            preview::render(&link, &target)
+        } else {
+            Redirect::temporary(&target).into_response()
+        }
+    };
+
+    if let Some(cookie) = variant
+        .as_deref()
+        .and_then(|variant| visitor::remember_variant(&link.id, variant))
+    {
+        response
+            .headers_mut()
+            .insert(http::header::SET_COOKIE, cookie);
+    }
+
+    // Bots get a different answer than browsers, and rules and splits
+    // pick targets by these headers.
+    let split = !link.variants.is_empty();
+    let mut vary = Vec::new();
+    if has_card || !link.rules.is_empty() || split {
+        vary.push(http::header::USER_AGENT);
+    }
+    if !link.rules.is_empty() || split {
+        vary.push(http::header::ACCEPT_LANGUAGE);
+    }
+    if !link.rules.is_empty() {
+        vary.extend(country.header);
+    }
+    if split {
+        vary.push(http::header::COOKIE);
+    }
+    if !vary.is_empty() {
+        let vary = vary
+            .iter()
+            .map(http::HeaderName::as_str)
+            .collect::<Vec<_>>();
+        if let Ok(value) = http::HeaderValue::from_str(&vary.join(", ")) {
+            response.headers_mut().insert(http::header::VARY, value);
+        }
+    }
+
+    Ok(response)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/links/{id}",
+    tag = "links",
+    params(("id" = String, Path, description = "Short link ID")),
+    responses(
+        (status = 200, description = "The link's destination", body = FullUrlResponse),
+        (status = 401, description = "Missing or unknown API key", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Unknown or expired link", body = Problem, content_type = "application/problem+json"),
+    ),
+)]
+async fn get_full_url<I, Q, R, W, A>(
     Path(id): Path<String>,
-    State(container): State<Arc<Container<I, R, Q>>>,
+    State(container): State<SharedContainer<I, R, Q, W, A>>,
+    Authenticated(_): Authenticated,
 ) -> Result<Json<FullUrlResponse>, AppError>
 where
     I: IDProvider + Send + Sync + 'static,
-    R: CreateShortUrlRepository + Send + Sync + 'static,
-    Q: GetFullUrlRepository + Send + Sync + 'static,
+    R: CommandRepository,
+    Q: QueryRepository,
+    W: WebhookRepository,
+    A: AccountRepository,
 {
     container
         .get_full_url_query
@@ -146,34 +699,2287 @@ where
         .map(|url| Json(FullUrlResponse::from(url)))
 }
 
-#[cfg(test)]
-mod tests {
-    use axum::{body::Body, http};
-    use dashmap::DashMap;
-    use http_body_util::BodyExt;
-    use tower::ServiceExt;
+#[utoipa::path(
+    get,
+    path = "/api/v1/links/{id}/stats",
+    tag = "links",
+    params(("id" = String, Path, description = "Short link ID")),
+    responses(
+        (status = 200, description = "Visits to the link, per A/B variant", body = LinkStats),
+        (status = 401, description = "Missing or unknown API key", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "The link belongs to another account", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Unknown or expired link", body = Problem, content_type = "application/problem+json"),
+    ),
+)]
+async fn get_link_stats<I, Q, R, W, A>(
+    Path(id): Path<String>,
+    State(container): State<SharedContainer<I, R, Q, W, A>>,
+    Authenticated(principal): Authenticated,
+) -> Result<Json<LinkStats>, AppError>
+where
+    I: IDProvider + Send + Sync + 'static,
+    R: CommandRepository,
+    Q: QueryRepository,
+    W: WebhookRepository,
+    A: AccountRepository,
+{
+    container
+        .link_stats_query
+        .execute(&principal, &id)
+        .await
+        .map(Json)
+}
+
+/// What the destination answered when it was last checked: its status,
+/// how long it took and where it redirected.
+#[utoipa::path(
+    get,
+    path = "/api/v1/links/{id}/health",
+    tag = "links",
+    params(("id" = String, Path, description = "Short link ID")),
+    responses(
+        (status = 200, description = "The last check of the destination", body = LinkHealth),
+        (status = 401, description = "Missing or unknown API key", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "The link belongs to another account", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Unknown or expired link, or not checked yet", body = Problem, content_type = "application/problem+json"),
+    ),
+)]
+async fn get_link_health<I, Q, R, W, A>(
+    Path(id): Path<String>,
+    State(container): State<SharedContainer<I, R, Q, W, A>>,
+    Authenticated(principal): Authenticated,
+) -> Result<Json<LinkHealth>, AppError>
+where
+    I: IDProvider + Send + Sync + 'static,
+    R: CommandRepository,
+    Q: QueryRepository,
+    W: WebhookRepository,
+    A: AccountRepository,
+{
+    container
+        .link_health_query
+        .execute(&principal, &id)
+        .await
+        .map(Json)
+}
+
+#[derive(Default, Deserialize, Serialize, ToSchema)]
+struct UpdateLinkRequest {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    always_preview: Option<bool>,
+    /// Replaces all Open Graph values; send `{}` to clear them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    open_graph: Option<OpenGraph>,
+    /// Replaces all redirect rules; send `[]` to remove them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rules: Option<Vec<RedirectRule>>,
+    /// Replaces the A/B split; send `[]` to end it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    variants: Option<Vec<Variant>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    query_passthrough: Option<QueryPassthrough>,
+    /// Reschedules the link; a time that has passed makes it live now.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    activates_at: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    coming_soon_page: Option<bool>,
+}
+
+#[derive(Deserialize, Serialize, ToSchema)]
+struct LinkResponse {
+    id: String,
+    url: String,
+    created_at: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expires_at: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    activates_at: Option<u64>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    coming_soon_page: bool,
+    always_preview: bool,
+    open_graph: OpenGraph,
+    rules: Vec<RedirectRule>,
+    variants: Vec<Variant>,
+    query_passthrough: QueryPassthrough,
+    /// The account the link belongs to; absent for links of the operator.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    owner: Option<String>,
+    /// The workspace owning the link jointly with `owner`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    workspace: Option<String>,
+    status: LinkStatus,
+    /// Why the link is disabled or quarantined.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status_reason: Option<String>,
+}
+
+impl From<ShortUrl> for LinkResponse {
+    fn from(link: ShortUrl) -> Self {
+        LinkResponse {
+            id: link.id,
+            url: link.url,
+            created_at: link.created_at,
+            expires_at: link.expires_at,
+            activates_at: link.activates_at,
+            coming_soon_page: link.coming_soon_page,
+            always_preview: link.always_preview,
+            open_graph: link.open_graph,
+            rules: link.rules,
+            variants: link.variants,
+            query_passthrough: link.query_passthrough,
+            owner: link.owner,
+            workspace: link.workspace,
+            status: link.status,
+            status_reason: link.status_reason,
+        }
+    }
+}
+
+/// Changes the given settings of a link and leaves the others alone. Only
+/// admins, the editor owning it and editors of its workspace may.
+#[utoipa::path(
+    patch,
+    path = "/api/v1/links/{id}",
+    tag = "links",
+    params(("id" = String, Path, description = "Short link ID")),
+    request_body = UpdateLinkRequest,
+    responses(
+        (status = 200, description = "The updated link", body = LinkResponse),
+        (status = 400, description = "Invalid Open Graph image URL, redirect rule or variants", body = Problem, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or unknown API key", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "The link belongs to another account, or the caller is a viewer", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Unknown or expired link", body = Problem, content_type = "application/problem+json"),
+    ),
+)]
+async fn update_link<I, R, Q, W, A>(
+    Path(id): Path<String>,
+    State(container): State<SharedContainer<I, R, Q, W, A>>,
+    Authenticated(principal): Authenticated,
+    JsonBody(input): JsonBody<UpdateLinkRequest>,
+) -> Result<Json<LinkResponse>, AppError>
+where
+    I: IDProvider + Send + Sync + 'static,
+    R: CommandRepository,
+    Q: QueryRepository,
+    W: WebhookRepository,
+    A: AccountRepository,
+{
+    let changes = LinkChanges {
+        always_preview: input.always_preview,
+        open_graph: input.open_graph,
+        rules: input.rules,
+        variants: input.variants,
+        query_passthrough: input.query_passthrough,
+        activates_at: input.activates_at,
+        coming_soon_page: input.coming_soon_page,
+    };
+
+    container
+        .update_command
+        .execute(&principal, &id, changes)
+        .await
+        .map(|link| Json(link.into()))
+}
+
+/// Deletes a link. Only admins, the editor owning it and editors of its
+/// workspace may.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/links/{id}",
+    tag = "links",
+    params(("id" = String, Path, description = "Short link ID")),
+    responses(
+        (status = 204, description = "Link deleted"),
+        (status = 401, description = "Missing or unknown API key", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "The link belongs to another account, or the caller is a viewer", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Unknown or expired link", body = Problem, content_type = "application/problem+json"),
+    ),
+)]
+async fn delete_link<I, R, Q, W, A>(
+    Path(id): Path<String>,
+    State(container): State<SharedContainer<I, R, Q, W, A>>,
+    Authenticated(principal): Authenticated,
+) -> Result<http::StatusCode, AppError>
+where
+    I: IDProvider + Send + Sync + 'static,
+    R: CommandRepository,
+    Q: QueryRepository,
+    W: WebhookRepository,
+    A: AccountRepository,
+{
+    container
+        .delete_command
+        .execute(&principal, &id)
+        .await
+        .map(|_| http::StatusCode::NO_CONTENT)
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct ListLinksParams {
+    /// Links of this account rather than the caller's own; admins only,
+    /// unless it is the caller's.
+    account: Option<String>,
+    /// Links of this workspace, whoever created them; members only.
+    workspace: Option<String>,
+    /// Every link, whoever owns it; admins only.
+    #[serde(default)]
+    all: bool,
+    #[serde(default)]
+    offset: usize,
+    /// At most 1000.
+    #[serde(default = "default_page_size")]
+    limit: usize,
+}
+
+fn default_page_size() -> usize {
+    100
+}
+
+/// Lists the caller's links ordered by ID, or those of another account, of
+/// a workspace or of everyone.
+#[utoipa::path(
+    get,
+    path = "/api/v1/links",
+    tag = "links",
+    params(ListLinksParams),
+    responses(
+        (status = 200, description = "A page of links", body = [LinkResponse]),
+        (status = 401, description = "Missing or unknown API key", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "Listing another account's links without being an admin, or a workspace the caller is no member of", body = Problem, content_type = "application/problem+json"),
+    ),
+)]
+async fn list_links<I, R, Q, W, A>(
+    State(container): State<SharedContainer<I, R, Q, W, A>>,
+    Authenticated(principal): Authenticated,
+    Query(params): Query<ListLinksParams>,
+) -> Result<Json<Vec<LinkResponse>>, AppError>
+where
+    I: IDProvider + Send + Sync + 'static,
+    R: CommandRepository,
+    Q: QueryRepository,
+    W: WebhookRepository,
+    A: AccountRepository,
+{
+    let scope = match (params.all, params.account, params.workspace) {
+        (true, _, _) => ListScope::All,
+        (false, Some(account_id), _) => ListScope::Account(account_id),
+        (false, None, Some(workspace)) => ListScope::Workspace(workspace),
+        (false, None, None) => ListScope::Mine,
+    };
+
+    container
+        .list_query
+        .execute(
+            &principal,
+            &scope,
+            params.offset,
+            params.limit.min(MAX_PAGE_SIZE),
+        )
+        .await
+        .map(|links| Json(links.into_iter().map(Into::into).collect()))
+}
+
+#[derive(Deserialize, Serialize, ToSchema)]
+struct CreateWebhookRequest {
+    url: String,
+    #[serde(default)]
+    events: Vec<EventKind>,
+    secret: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, ToSchema)]
+struct WebhookResponse {
+    id: String,
+    url: String,
+    events: Vec<EventKind>,
+    created_at: u64,
+    /// Only returned once, when the subscription is created.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    secret: Option<String>,
+}
+
+impl From<WebhookSubscription> for WebhookResponse {
+    fn from(subscription: WebhookSubscription) -> Self {
+        WebhookResponse {
+            id: subscription.id,
+            url: subscription.url,
+            events: subscription.events,
+            created_at: subscription.created_at,
+            secret: None,
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/webhooks",
+    tag = "webhooks",
+    request_body = CreateWebhookRequest,
+    responses(
+        (status = 201, description = "Subscription created; the secret is only shown here",
+            body = WebhookResponse),
+        (status = 400, description = "Invalid URL", body = Problem, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or unknown API key", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "Admins only", body = Problem, content_type = "application/problem+json"),
+    ),
+)]
+async fn create_webhook<I, R, Q, W, A>(
+    State(container): State<SharedContainer<I, R, Q, W, A>>,
+    Authenticated(principal): Authenticated,
+    JsonBody(input): JsonBody<CreateWebhookRequest>,
+) -> Result<(http::StatusCode, Json<WebhookResponse>), AppError>
+where
+    I: IDProvider + Send + Sync + 'static,
+    R: CommandRepository,
+    Q: QueryRepository,
+    W: WebhookRepository,
+    A: AccountRepository,
+{
+    let subscription = container
+        .create_webhook_command
+        .execute(&principal, &input.url, input.events, input.secret)
+        .await?;
+
+    let secret = subscription.secret.clone();
+    let mut response = WebhookResponse::from(subscription);
+    response.secret = Some(secret);
+
+    Ok((http::StatusCode::CREATED, Json(response)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/webhooks",
+    tag = "webhooks",
+    responses(
+        (status = 200, description = "All subscriptions", body = [WebhookResponse]),
+        (status = 401, description = "Missing or unknown API key", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "Admins only", body = Problem, content_type = "application/problem+json"),
+    ),
+)]
+async fn list_webhooks<I, R, Q, W, A>(
+    State(container): State<SharedContainer<I, R, Q, W, A>>,
+    Authenticated(principal): Authenticated,
+) -> Result<Json<Vec<WebhookResponse>>, AppError>
+where
+    I: IDProvider + Send + Sync + 'static,
+    R: CommandRepository,
+    Q: QueryRepository,
+    W: WebhookRepository,
+    A: AccountRepository,
+{
+    container
+        .list_webhooks_query
+        .execute(&principal)
+        .await
+        .map(|subscriptions| Json(subscriptions.into_iter().map(Into::into).collect()))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/webhooks/{id}",
+    tag = "webhooks",
+    params(("id" = String, Path, description = "Subscription ID")),
+    responses(
+        (status = 204, description = "Subscription deleted"),
+        (status = 401, description = "Missing or unknown API key", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "Admins only", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Unknown subscription", body = Problem, content_type = "application/problem+json"),
+    ),
+)]
+async fn delete_webhook<I, R, Q, W, A>(
+    Path(id): Path<String>,
+    State(container): State<SharedContainer<I, R, Q, W, A>>,
+    Authenticated(principal): Authenticated,
+) -> Result<http::StatusCode, AppError>
+where
+    I: IDProvider + Send + Sync + 'static,
+    R: CommandRepository,
+    Q: QueryRepository,
+    W: WebhookRepository,
+    A: AccountRepository,
+{
+    container
+        .delete_webhook_command
+        .execute(&principal, &id)
+        .await
+        .map(|_| http::StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/webhooks/{id}/deliveries",
+    tag = "webhooks",
+    params(("id" = String, Path, description = "Subscription ID")),
+    responses(
+        (status = 200, description = "Delivery attempts, oldest first", body = [WebhookDelivery]),
+        (status = 401, description = "Missing or unknown API key", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "Admins only", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Unknown subscription", body = Problem, content_type = "application/problem+json"),
+    ),
+)]
+async fn list_webhook_deliveries<I, R, Q, W, A>(
+    Path(id): Path<String>,
+    State(container): State<SharedContainer<I, R, Q, W, A>>,
+    Authenticated(principal): Authenticated,
+) -> Result<Json<Vec<WebhookDelivery>>, AppError>
+where
+    I: IDProvider + Send + Sync + 'static,
+    R: CommandRepository,
+    Q: QueryRepository,
+    W: WebhookRepository,
+    A: AccountRepository,
+{
+    container
+        .list_webhook_deliveries_query
+        .execute(&principal, &id)
+        .await
+        .map(Json)
+}
+
+#[derive(Deserialize, Serialize, ToSchema)]
+struct CreateAccountRequest {
+    name: String,
+    /// `editor` unless given.
+    #[serde(default)]
+    role: Role,
+}
+
+#[derive(Deserialize, Serialize, ToSchema)]
+struct CreatedAccountResponse {
+    account: Account,
+    /// The account's first key; its secret is only shown here.
+    key: IssuedKey,
+}
+
+/// Creates an account along with its first API key.
+#[utoipa::path(
+    post,
+    path = "/api/v1/accounts",
+    tag = "accounts",
+    request_body = CreateAccountRequest,
+    responses(
+        (status = 201, description = "Account created", body = CreatedAccountResponse),
+        (status = 401, description = "Missing or unknown API key", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "Admins only", body = Problem, content_type = "application/problem+json"),
+    ),
+)]
+async fn create_account<I, R, Q, W, A>(
+    State(container): State<SharedContainer<I, R, Q, W, A>>,
+    Authenticated(principal): Authenticated,
+    JsonBody(input): JsonBody<CreateAccountRequest>,
+) -> Result<(http::StatusCode, Json<CreatedAccountResponse>), AppError>
+where
+    I: IDProvider + Send + Sync + 'static,
+    R: CommandRepository,
+    Q: QueryRepository,
+    W: WebhookRepository,
+    A: AccountRepository,
+{
+    let (account, key) = container
+        .create_account_command
+        .execute(&principal, &input.name, input.role)
+        .await?;
+
+    Ok((
+        http::StatusCode::CREATED,
+        Json(CreatedAccountResponse { account, key }),
+    ))
+}
+
+/// Issues another key for an account, e.g. to rotate the current one.
+#[utoipa::path(
+    post,
+    path = "/api/v1/accounts/{id}/keys",
+    tag = "accounts",
+    params(("id" = String, Path, description = "Account ID")),
+    responses(
+        (status = 201, description = "Key created; the secret is only shown here", body = IssuedKey),
+        (status = 401, description = "Missing or unknown API key", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "Another account, and the caller is no admin", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Unknown account", body = Problem, content_type = "application/problem+json"),
+    ),
+)]
+async fn create_api_key<I, R, Q, W, A>(
+    Path(id): Path<String>,
+    State(container): State<SharedContainer<I, R, Q, W, A>>,
+    Authenticated(principal): Authenticated,
+) -> Result<(http::StatusCode, Json<IssuedKey>), AppError>
+where
+    I: IDProvider + Send + Sync + 'static,
+    R: CommandRepository,
+    Q: QueryRepository,
+    W: WebhookRepository,
+    A: AccountRepository,
+{
+    container
+        .create_api_key_command
+        .execute(&principal, &id)
+        .await
+        .map(|key| (http::StatusCode::CREATED, Json(key)))
+}
+
+/// Revokes a key; requests made with it are rejected from then on.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/accounts/{id}/keys/{key_id}",
+    tag = "accounts",
+    params(
+        ("id" = String, Path, description = "Account ID"),
+        ("key_id" = String, Path, description = "Key ID"),
+    ),
+    responses(
+        (status = 204, description = "Key revoked"),
+        (status = 401, description = "Missing or unknown API key", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "Another account, and the caller is no admin", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Unknown account or key", body = Problem, content_type = "application/problem+json"),
+    ),
+)]
+async fn revoke_api_key<I, R, Q, W, A>(
+    Path((id, key_id)): Path<(String, String)>,
+    State(container): State<SharedContainer<I, R, Q, W, A>>,
+    Authenticated(principal): Authenticated,
+) -> Result<http::StatusCode, AppError>
+where
+    I: IDProvider + Send + Sync + 'static,
+    R: CommandRepository,
+    Q: QueryRepository,
+    W: WebhookRepository,
+    A: AccountRepository,
+{
+    container
+        .revoke_api_key_command
+        .execute(&principal, &id, &key_id)
+        .await
+        .map(|_| http::StatusCode::NO_CONTENT)
+}
+
+#[derive(Deserialize, Serialize, ToSchema)]
+struct CreateWorkspaceRequest {
+    name: String,
+    /// Hosts such as `go.example.com` that resolve only this workspace's
+    /// links; DNS must point them at this server.
+    #[serde(default)]
+    domains: Vec<String>,
+    #[serde(default)]
+    quota: Quota,
+}
+
+/// Opens a workspace. Members are added one by one afterwards.
+#[utoipa::path(
+    post,
+    path = "/api/v1/workspaces",
+    tag = "workspaces",
+    request_body = CreateWorkspaceRequest,
+    responses(
+        (status = 201, description = "Workspace created", body = Workspace),
+        (status = 400, description = "Invalid domain", body = Problem, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or unknown API key", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "Admins only", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "A domain belongs to another workspace", body = Problem, content_type = "application/problem+json"),
+    ),
+)]
+async fn create_workspace<I, R, Q, W, A>(
+    State(container): State<SharedContainer<I, R, Q, W, A>>,
+    Authenticated(principal): Authenticated,
+    JsonBody(input): JsonBody<CreateWorkspaceRequest>,
+) -> Result<(http::StatusCode, Json<Workspace>), AppError>
+where
+    I: IDProvider + Send + Sync + 'static,
+    R: CommandRepository,
+    Q: QueryRepository,
+    W: WebhookRepository,
+    A: AccountRepository,
+{
+    container
+        .create_workspace_command
+        .execute(&principal, &input.name, input.domains, input.quota)
+        .await
+        .map(|workspace| (http::StatusCode::CREATED, Json(workspace)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/workspaces/{id}",
+    tag = "workspaces",
+    params(("id" = String, Path, description = "Workspace ID")),
+    responses(
+        (status = 200, description = "The workspace", body = Workspace),
+        (status = 401, description = "Missing or unknown API key", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "Members and admins only", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Unknown workspace", body = Problem, content_type = "application/problem+json"),
+    ),
+)]
+async fn get_workspace<I, R, Q, W, A>(
+    Path(id): Path<String>,
+    State(container): State<SharedContainer<I, R, Q, W, A>>,
+    Authenticated(principal): Authenticated,
+) -> Result<Json<Workspace>, AppError>
+where
+    I: IDProvider + Send + Sync + 'static,
+    R: CommandRepository,
+    Q: QueryRepository,
+    W: WebhookRepository,
+    A: AccountRepository,
+{
+    container
+        .workspace_query
+        .execute(&principal, &id)
+        .await
+        .map(Json)
+}
+
+#[derive(Default, Deserialize, Serialize, ToSchema)]
+struct UpdateWorkspaceRequest {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    /// Replaces all domains; send `[]` to remove them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    domains: Option<Vec<String>>,
+    /// Replaces both limits; send `{}` to lift them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    quota: Option<Quota>,
+}
+
+/// Changes the given settings of a workspace and leaves the others alone.
+#[utoipa::path(
+    patch,
+    path = "/api/v1/workspaces/{id}",
+    tag = "workspaces",
+    params(("id" = String, Path, description = "Workspace ID")),
+    request_body = UpdateWorkspaceRequest,
+    responses(
+        (status = 200, description = "The updated workspace", body = Workspace),
+        (status = 400, description = "Invalid domain", body = Problem, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or unknown API key", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "Admins only", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Unknown workspace", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "A domain belongs to another workspace", body = Problem, content_type = "application/problem+json"),
+    ),
+)]
+async fn update_workspace<I, R, Q, W, A>(
+    Path(id): Path<String>,
+    State(container): State<SharedContainer<I, R, Q, W, A>>,
+    Authenticated(principal): Authenticated,
+    JsonBody(input): JsonBody<UpdateWorkspaceRequest>,
+) -> Result<Json<Workspace>, AppError>
+where
+    I: IDProvider + Send + Sync + 'static,
+    R: CommandRepository,
+    Q: QueryRepository,
+    W: WebhookRepository,
+    A: AccountRepository,
+{
+    let changes = WorkspaceChanges {
+        name: input.name,
+        domains: input.domains,
+        quota: input.quota,
+    };
+
+    container
+        .update_workspace_command
+        .execute(&principal, &id, changes)
+        .await
+        .map(Json)
+}
+
+/// Makes an account a member, who may then change every link of the
+/// workspace.
+#[utoipa::path(
+    put,
+    path = "/api/v1/workspaces/{id}/members/{account_id}",
+    tag = "workspaces",
+    params(
+        ("id" = String, Path, description = "Workspace ID"),
+        ("account_id" = String, Path, description = "Account ID"),
+    ),
+    responses(
+        (status = 200, description = "The workspace with its members", body = Workspace),
+        (status = 401, description = "Missing or unknown API key", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "Admins only", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Unknown workspace or account", body = Problem, content_type = "application/problem+json"),
+    ),
+)]
+async fn add_member<I, R, Q, W, A>(
+    Path((id, account_id)): Path<(String, String)>,
+    State(container): State<SharedContainer<I, R, Q, W, A>>,
+    Authenticated(principal): Authenticated,
+) -> Result<Json<Workspace>, AppError>
+where
+    I: IDProvider + Send + Sync + 'static,
+    R: CommandRepository,
+    Q: QueryRepository,
+    W: WebhookRepository,
+    A: AccountRepository,
+{
+    container
+        .membership_command
+        .add(&principal, &id, &account_id)
+        .await
+        .map(Json)
+}
+
+/// Removes a member; the links they created stay with the workspace.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/workspaces/{id}/members/{account_id}",
+    tag = "workspaces",
+    params(
+        ("id" = String, Path, description = "Workspace ID"),
+        ("account_id" = String, Path, description = "Account ID"),
+    ),
+    responses(
+        (status = 200, description = "The workspace with its members", body = Workspace),
+        (status = 401, description = "Missing or unknown API key", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "Admins only", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Unknown workspace", body = Problem, content_type = "application/problem+json"),
+    ),
+)]
+async fn remove_member<I, R, Q, W, A>(
+    Path((id, account_id)): Path<(String, String)>,
+    State(container): State<SharedContainer<I, R, Q, W, A>>,
+    Authenticated(principal): Authenticated,
+) -> Result<Json<Workspace>, AppError>
+where
+    I: IDProvider + Send + Sync + 'static,
+    R: CommandRepository,
+    Q: QueryRepository,
+    W: WebhookRepository,
+    A: AccountRepository,
+{
+    container
+        .membership_command
+        .remove(&principal, &id, &account_id)
+        .await
+        .map(Json)
+}
+
+#[derive(Deserialize, Serialize, ToSchema)]
+struct WorkspaceUsageResponse {
+    /// The month `clicks` are counted in, as `YYYY-MM` (UTC).
+    month: String,
+    links: u64,
+    clicks: u64,
+    quota: Quota,
+}
+
+/// How much of its quota a workspace used this month.
+#[utoipa::path(
+    get,
+    path = "/api/v1/workspaces/{id}/usage",
+    tag = "workspaces",
+    params(("id" = String, Path, description = "Workspace ID")),
+    responses(
+        (status = 200, description = "Usage counters next to the quota", body = WorkspaceUsageResponse),
+        (status = 401, description = "Missing or unknown API key", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "Members and admins only", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Unknown workspace", body = Problem, content_type = "application/problem+json"),
+    ),
+)]
+async fn get_workspace_usage<I, R, Q, W, A>(
+    Path(id): Path<String>,
+    State(container): State<SharedContainer<I, R, Q, W, A>>,
+    Authenticated(principal): Authenticated,
+) -> Result<Json<WorkspaceUsageResponse>, AppError>
+where
+    I: IDProvider + Send + Sync + 'static,
+    R: CommandRepository,
+    Q: QueryRepository,
+    W: WebhookRepository,
+    A: AccountRepository,
+{
+    let workspace = container.workspace_query.execute(&principal, &id).await?;
+    let usage = container
+        .workspace_usage_query
+        .execute(&principal, &id)
+        .await?;
+
+    Ok(Json(WorkspaceUsageResponse {
+        month: usage.month,
+        links: usage.links,
+        clicks: usage.clicks,
+        quota: workspace.quota,
+    }))
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct ImportParams {
+    #[serde(default)]
+    format: Format,
+    #[serde(default)]
+    on_conflict: ConflictPolicy,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct ExportParams {
+    #[serde(default)]
+    format: Format,
+}
+
+/// Streams the request body through the importer; the body is never
+/// buffered as a whole.
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/import",
+    tag = "admin",
+    params(ImportParams),
+    request_body(
+        description = "Links as CSV or JSON lines, per `format`",
+        content((String = "text/csv"), (String = "application/x-ndjson")),
+    ),
+    responses(
+        (status = 200, description = "Import finished", body = ImportReport),
+        (status = 400, description = "Unreadable input", body = Problem, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or unknown API key", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "Admins only", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "Stopped by the `fail` conflict policy", body = ImportReport),
+    ),
+)]
+async fn import_links<I, R, Q, W, A>(
+    State(container): State<SharedContainer<I, R, Q, W, A>>,
+    Authenticated(principal): Authenticated,
+    Query(params): Query<ImportParams>,
+    body: Body,
+) -> Result<(http::StatusCode, Json<ImportReport>), TransferError>
+where
+    I: IDProvider + Send + Sync + 'static,
+    R: CommandRepository,
+    Q: QueryRepository,
+    W: WebhookRepository,
+    A: AccountRepository,
+{
+    let reader = StreamReader::new(body.into_data_stream().map_err(std::io::Error::other));
+    let reader = SyncIoBridge::new(reader);
+
+    let report = transfer::import(
+        &container,
+        &principal,
+        params.format,
+        params.on_conflict,
+        reader,
+    )
+    .await?;
+    let status = if report.is_aborted() {
+        http::StatusCode::CONFLICT
+    } else {
+        http::StatusCode::OK
+    };
+
+    Ok((status, Json(report)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/export",
+    tag = "admin",
+    params(ExportParams),
+    responses(
+        (status = 200, description = "Every live link, streamed",
+            content((String = "text/csv"), (String = "application/x-ndjson"))),
+        (status = 401, description = "Missing or unknown API key", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "Admins only", body = Problem, content_type = "application/problem+json"),
+    ),
+)]
+async fn export_links<I, R, Q, W, A>(
+    State(container): State<SharedContainer<I, R, Q, W, A>>,
+    Authenticated(principal): Authenticated,
+    Query(params): Query<ExportParams>,
+) -> Response
+where
+    I: IDProvider + Send + Sync + 'static,
+    R: CommandRepository,
+    Q: QueryRepository,
+    W: WebhookRepository,
+    A: AccountRepository,
+{
+    let (tx, rx) = mpsc::channel::<Result<Bytes, TransferError>>(4);
+    // The export borrows the container, so it starts on the task; whether it
+    // may start at all comes back before the response does.
+    let (started_tx, started_rx) = oneshot::channel();
+
+    tokio::spawn(async move {
+        let chunks = match transfer::export(&container, &principal, params.format) {
+            Ok(chunks) => {
+                let _ = started_tx.send(Ok(()));
+                chunks
+            }
+            Err(err) => {
+                let _ = started_tx.send(Err(err));
+                return;
+            }
+        };
+        let mut chunks = std::pin::pin!(chunks);
+        while let Some(chunk) = chunks.next().await {
+            let chunk = chunk.map(|(_, bytes)| Bytes::from(bytes));
+            let failed = chunk.is_err();
+            if tx.send(chunk).await.is_err() || failed {
+                break;
+            }
+        }
+    });
+
+    match started_rx.await {
+        Ok(Ok(())) => {}
+        Ok(Err(err)) => return err.into_response(),
+        Err(_) => return http::StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    }
+    let body = stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|chunk| (chunk, rx))
+    });
+    let extension = match params.format {
+        Format::Csv => "csv",
+        Format::JsonLines => "jsonl",
+    };
+
+    (
+        [
+            (
+                http::header::CONTENT_TYPE,
+                params.format.content_type().to_owned(),
+            ),
+            (
+                http::header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"links.{extension}\""),
+            ),
+        ],
+        Body::from_stream(body),
+    )
+        .into_response()
+}
+
+/// Like `/{id}/{path}` with the path `report`, which the route for abuse
+/// reports would shadow otherwise.
+#[utoipa::path(
+    get,
+    path = "/{id}/report",
+    tag = "redirect",
+    security(()),
+    params(("id" = String, Path, description = "Short link ID")),
+    responses(
+        (status = 200, description = "Preview of the destination, an Open Graph card for unfurl bots, or a warning for quarantined links",
+            content_type = "text/html"),
+        (status = 307, description = "Redirect to the destination",
+            headers(("location" = String, description = "The destination URL"))),
+        (status = 404, description = "Unknown or expired link, or one not live yet; scheduled links may show a coming soon page instead", body = Problem, content_type = "application/problem+json"),
+        (status = 451, description = "The link was disabled", body = Problem, content_type = "application/problem+json"),
+    ),
+)]
+async fn redirect_with_report_path<I, Q, R, W, A>(
+    Path(id): Path<String>,
+    State(container): State<SharedContainer<I, R, Q, W, A>>,
+    Extension(country): Extension<CountryLookup>,
+    peer: Option<ConnectInfo<SocketAddr>>,
+    RawQuery(query): RawQuery,
+    headers: http::HeaderMap,
+) -> Result<Response, AppError>
+where
+    I: IDProvider + Send + Sync + 'static,
+    R: CommandRepository,
+    Q: QueryRepository,
+    W: WebhookRepository,
+    A: AccountRepository,
+{
+    let request = visitor::Incoming {
+        path: "report".to_owned(),
+        query,
+        peer: peer.map(|ConnectInfo(addr)| addr.ip()),
+        headers,
+    };
+    follow(&container, &id, request, country).await
+}
+
+#[derive(Deserialize, Serialize, ToSchema)]
+struct ReportRequest {
+    /// What is wrong with the link, in at most 1000 characters.
+    reason: String,
+}
+
+#[derive(Deserialize, Serialize, ToSchema)]
+struct ReportResponse {
+    /// The report's ID, to refer to it later.
+    id: String,
+}
+
+/// Reports a link as abusive, e.g. for phishing or malware. Anyone may;
+/// the link keeps working until an admin reviews the report.
+#[utoipa::path(
+    post,
+    path = "/{id}/report",
+    tag = "moderation",
+    security(()),
+    params(("id" = String, Path, description = "Short link ID")),
+    request_body = ReportRequest,
+    responses(
+        (status = 202, description = "Report filed for review", body = ReportResponse),
+        (status = 400, description = "Missing or overlong reason", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Unknown or expired link", body = Problem, content_type = "application/problem+json"),
+    ),
+)]
+async fn report_link<I, Q, R, W, A>(
+    Path(id): Path<String>,
+    State(container): State<SharedContainer<I, R, Q, W, A>>,
+    JsonBody(input): JsonBody<ReportRequest>,
+) -> Result<(http::StatusCode, Json<ReportResponse>), AppError>
+where
+    I: IDProvider + Send + Sync + 'static,
+    R: CommandRepository,
+    Q: QueryRepository,
+    W: WebhookRepository,
+    A: AccountRepository,
+{
+    let report = container.report_command.execute(&id, &input.reason).await?;
+
+    Ok((
+        http::StatusCode::ACCEPTED,
+        Json(ReportResponse { id: report.id }),
+    ))
+}
+
+#[derive(Deserialize, Serialize, ToSchema)]
+struct LinkStatusRequest {
+    status: LinkStatus,
+    /// Shown on the warning page of quarantined links. Dropped when the
+    /// link is made active again.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    reason: Option<String>,
+}
+
+/// Disables, quarantines or restores a link, and closes its open reports
+/// with that outcome. Admins only.
+#[utoipa::path(
+    put,
+    path = "/api/v1/links/{id}/status",
+    tag = "moderation",
+    params(("id" = String, Path, description = "Short link ID")),
+    request_body = LinkStatusRequest,
+    responses(
+        (status = 200, description = "The link with its new status", body = LinkResponse),
+        (status = 401, description = "Missing or unknown API key", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "The caller is no admin", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Unknown or expired link", body = Problem, content_type = "application/problem+json"),
+    ),
+)]
+async fn change_link_status<I, Q, R, W, A>(
+    Path(id): Path<String>,
+    State(container): State<SharedContainer<I, R, Q, W, A>>,
+    Authenticated(principal): Authenticated,
+    JsonBody(input): JsonBody<LinkStatusRequest>,
+) -> Result<Json<LinkResponse>, AppError>
+where
+    I: IDProvider + Send + Sync + 'static,
+    R: CommandRepository,
+    Q: QueryRepository,
+    W: WebhookRepository,
+    A: AccountRepository,
+{
+    container
+        .link_status_command
+        .execute(&principal, &id, input.status, input.reason)
+        .await
+        .map(|link| Json(link.into()))
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct ListReportsParams {
+    /// Reviewed reports too, not only the open ones.
+    #[serde(default)]
+    all: bool,
+    #[serde(default)]
+    offset: usize,
+    /// At most 1000.
+    #[serde(default = "default_page_size")]
+    limit: usize,
+}
+
+/// The abuse report queue, oldest first. Admins only.
+#[utoipa::path(
+    get,
+    path = "/api/v1/reports",
+    tag = "moderation",
+    params(ListReportsParams),
+    responses(
+        (status = 200, description = "A page of reports", body = [AbuseReport]),
+        (status = 401, description = "Missing or unknown API key", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "The caller is no admin", body = Problem, content_type = "application/problem+json"),
+    ),
+)]
+async fn list_reports<I, Q, R, W, A>(
+    State(container): State<SharedContainer<I, R, Q, W, A>>,
+    Authenticated(principal): Authenticated,
+    Query(params): Query<ListReportsParams>,
+) -> Result<Json<Vec<AbuseReport>>, AppError>
+where
+    I: IDProvider + Send + Sync + 'static,
+    R: CommandRepository,
+    Q: QueryRepository,
+    W: WebhookRepository,
+    A: AccountRepository,
+{
+    container
+        .reports_query
+        .execute(
+            &principal,
+            !params.all,
+            params.offset,
+            params.limit.min(MAX_PAGE_SIZE),
+        )
+        .await
+        .map(Json)
+}
+
+/// Link counts in the Prometheus text format, for scraping with an API key.
+#[utoipa::path(
+    get,
+    path = "/api/v1/metrics",
+    tag = "metrics",
+    responses(
+        (status = 200, description = "Live links, and checked links by the state of their destination",
+            body = String, content_type = "text/plain; version=0.0.4"),
+        (status = 401, description = "Missing or unknown API key", body = Problem, content_type = "application/problem+json"),
+    ),
+)]
+async fn metrics<I, Q, R, W, A>(
+    State(container): State<SharedContainer<I, R, Q, W, A>>,
+    Authenticated(principal): Authenticated,
+) -> Result<Response, AppError>
+where
+    I: IDProvider + Send + Sync + 'static,
+    R: CommandRepository,
+    Q: QueryRepository,
+    W: WebhookRepository,
+    A: AccountRepository,
+{
+    let stats = container.stats_query.execute(&principal).await?;
+    let health = stats.link_health;
+    let body = format!(
+        "# HELP urlshortener_links Live links.\n\
+         # TYPE urlshortener_links gauge\n\
+         urlshortener_links {}\n\
+         # HELP urlshortener_link_health Checked links by what their destination last answered.\n\
+         # TYPE urlshortener_link_health gauge\n\
+         urlshortener_link_health{{state=\"healthy\"}} {}\n\
+         urlshortener_link_health{{state=\"broken\"}} {}\n\
+         urlshortener_link_health{{state=\"unreachable\"}} {}\n",
+        stats.total_links, health.healthy, health.broken, health.unreachable
+    );
+
+    Ok((
+        [(
+            http::header::CONTENT_TYPE,
+            "text/plain; version=0.0.4; charset=utf-8",
+        )],
+        body,
+    )
+        .into_response())
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/openapi.json",
+    tag = "meta",
+    security(()),
+    responses((status = 200, description = "This document", content_type = "application/json")),
+)]
+async fn openapi_document() -> Json<utoipa::openapi::OpenApi> {
+    Json(openapi::ApiDoc::openapi())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use axum::{body::Body, http};
+    use dashmap::DashMap;
+    use http_body_util::BodyExt;
+    use tower::ServiceExt;
+
+    use crate::{
+        adapters::inmemory::{
+            account::InMemoryAccountRepository, webhook::InMemoryWebhookRepository,
+            InMemoryRepository,
+        },
+        app::{
+            command::check_link_health::CheckLinkHealthRepository, event::EventBus, health::Probe,
+            short_url::ShortUrl,
+        },
+        clock::FakeClock,
+        id_provider::FakeIDProvider,
+    };
+
+    use super::*;
+
+    /// The operator's key in every test container.
+    const ADMIN_KEY: &str = "test-admin-key";
+    const ADMIN_AUTH: &str = "Bearer test-admin-key";
+
+    fn idempotency() -> IdempotencyStore {
+        IdempotencyStore::new(DEFAULT_IDEMPOTENCY_WINDOW)
+    }
+
+    fn create_request(url: &str, idempotency_key: &str) -> http::Request<Body> {
+        let input = CreateShortURLRequest {
+            url: url.to_owned(),
+            ..Default::default()
+        };
+
+        http::Request::builder()
+            .method(http::Method::POST)
+            .uri("/api/v1/links")
+            .header(http::header::AUTHORIZATION, ADMIN_AUTH)
+            .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+            .header(idempotency::HEADER, idempotency_key)
+            .body(Body::from(serde_json::to_string(&input).unwrap()))
+            .unwrap()
+    }
+
+    fn get_router_with_mock_container() -> Router {
+        let store = Arc::new(DashMap::new());
+        store.insert(
+            "test-id".to_owned(),
+            ShortUrl::new("test-id".to_owned(), "test-url".to_owned()),
+        );
+        store.insert(
+            "test-id-2".to_owned(),
+            ShortUrl::new("test-id-2".to_owned(), "test-url-2".to_owned()),
+        );
+        let repo = InMemoryRepository::new(store);
+
+        let container = Container::new(
+            FakeIDProvider::new("new-id".to_owned()),
+            repo.clone(),
+            repo,
+            InMemoryWebhookRepository::new(),
+            InMemoryAccountRepository::new(),
+            EventBus::new(),
+        )
+        .with_operator_key(ADMIN_KEY);
+
+        get_router(Arc::new(container), idempotency(), CountryLookup::default())
+    }
+
+    #[tokio::test]
+    async fn test_get_full_url() {
+        // Given
+        let router = get_router_with_mock_container();
+
+        // When
+        let response = router
+            .oneshot(
+                http::Request::builder()
+                    .uri("/api/v1/links/test-id")
+                    .header(http::header::AUTHORIZATION, ADMIN_AUTH)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        // Then
+        assert_eq!(response.status(), http::StatusCode::OK);
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: FullUrlResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body.url, "test-url");
+    }
+
+    #[tokio::test]
+    async fn short_link_redirects() {
+        // Given
+        let router = get_router_with_mock_container();
+
+        // When
+        let response = router
+            .oneshot(
+                http::Request::builder()
+                    .uri("/test-id")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        // Then
+        assert_eq!(response.status(), http::StatusCode::TEMPORARY_REDIRECT);
+        assert_eq!(response.headers()[http::header::LOCATION], "test-url");
+    }
+
+    #[tokio::test]
+    async fn scheduled_links_wait_for_their_time() {
+        // Given
+        let store = Arc::new(DashMap::new());
+        let mut launch = ShortUrl::new("launch".to_owned(), "https://example.com/".to_owned());
+        launch.activates_at = Some(1_000);
+        let mut teaser = launch.clone();
+        teaser.id = "teaser".to_owned();
+        teaser.coming_soon_page = true;
+        store.insert(launch.id.clone(), launch);
+        store.insert(teaser.id.clone(), teaser);
+        let repo = InMemoryRepository::new(store);
+        let clock = FakeClock::new(999);
+        let container = Container::new(
+            FakeIDProvider::new("new-id".to_owned()),
+            repo.clone(),
+            repo,
+            InMemoryWebhookRepository::new(),
+            InMemoryAccountRepository::new(),
+            EventBus::new(),
+        )
+        .with_operator_key(ADMIN_KEY)
+        .with_clock(Arc::new(clock.clone()));
+        let router = get_router(Arc::new(container), idempotency(), CountryLookup::default());
+        let visit = |id: &str| {
+            http::Request::builder()
+                .uri(format!("/{id}"))
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        // When
+        let early = router.clone().oneshot(visit("launch")).await.unwrap();
+        let soon = router.clone().oneshot(visit("teaser")).await.unwrap();
+        clock.advance(1);
+        let live = router.clone().oneshot(visit("launch")).await.unwrap();
+        let expiring_first = router
+            .oneshot(
+                http::Request::builder()
+                    .method(http::Method::POST)
+                    .uri("/api/v1/links")
+                    .header(http::header::AUTHORIZATION, ADMIN_AUTH)
+                    .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                    .body(Body::from(
+                        r#"{"url":"https://example.com/","ttl_seconds":60,"activates_at":99999999999}"#,
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        // Then
+        assert_eq!(early.status(), http::StatusCode::NOT_FOUND);
+        let body = early.into_body().collect().await.unwrap().to_bytes();
+        let problem: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(problem["type"], "urn:urlshortener:problem:not-found");
+
+        assert_eq!(soon.status(), http::StatusCode::NOT_FOUND);
+        assert_eq!(soon.headers()[http::header::CACHE_CONTROL], "no-store");
+        let body = soon.into_body().collect().await.unwrap().to_bytes();
+        let page = String::from_utf8(body.to_vec()).unwrap();
+        assert!(page.contains("Coming soon"));
+        assert!(page.contains("1970-01-01T00:16:40Z"));
+        assert!(!page.contains("example.com"));
+
+        assert_eq!(live.status(), http::StatusCode::TEMPORARY_REDIRECT);
+        assert_eq!(
+            live.headers()[http::header::LOCATION],
+            "https://example.com/"
+        );
+
+        assert_eq!(expiring_first.status(), http::StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn rule_windows_and_expiry_follow_the_injected_clock() {
+        use crate::app::redirect_rule::RedirectRule;
+
+        // Given
+        let clock = FakeClock::new(1_000);
+        let store = Arc::new(DashMap::new());
+        let mut sale = ShortUrl::new("sale".to_owned(), "https://example.com/".to_owned());
+        sale.created_at = 1_000;
+        sale.expires_at = Some(1_200);
+        sale.rules = vec![RedirectRule {
+            target: "https://example.com/sale".to_owned(),
+            starts_at: Some(1_100),
+            ..Default::default()
+        }];
+        store.insert(sale.id.clone(), sale);
+        let repo = InMemoryRepository::new(store).with_clock(Arc::new(clock.clone()));
+        let container = Container::new(
+            FakeIDProvider::new("new-id".to_owned()),
+            repo.clone(),
+            repo,
+            InMemoryWebhookRepository::new(),
+            InMemoryAccountRepository::new(),
+            EventBus::new(),
+        )
+        .with_clock(Arc::new(clock.clone()));
+        let router = get_router(Arc::new(container), idempotency(), CountryLookup::default());
+        let visit = || {
+            http::Request::builder()
+                .uri("/sale")
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        // When
+        let before = router.clone().oneshot(visit()).await.unwrap();
+        clock.set(1_100);
+        let during = router.clone().oneshot(visit()).await.unwrap();
+        clock.set(1_200);
+        let expired = router.oneshot(visit()).await.unwrap();
+
+        // Then
+        assert_eq!(
+            before.headers()[http::header::LOCATION],
+            "https://example.com/"
+        );
+        assert_eq!(
+            during.headers()[http::header::LOCATION],
+            "https://example.com/sale"
+        );
+        assert_eq!(expired.status(), http::StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn teasers_hide_behind_other_domains_and_statuses() {
+        use crate::app::{
+            command::create_workspace::CreateWorkspaceRepository,
+            short_url::LinkStatus,
+            workspace::{Quota, Workspace},
+        };
+
+        // Given
+        let store = Arc::new(DashMap::new());
+        let mut teaser = ShortUrl::new("teaser".to_owned(), "https://example.com/".to_owned());
+        teaser.activates_at = Some(1_000);
+        teaser.coming_soon_page = true;
+        let mut disabled = teaser.clone();
+        disabled.id = "disabled".to_owned();
+        disabled.status = LinkStatus::Disabled;
+        store.insert(teaser.id.clone(), teaser);
+        store.insert(disabled.id.clone(), disabled);
+        let repo = InMemoryRepository::new(store);
+        let accounts = InMemoryAccountRepository::new();
+        accounts
+            .save_workspace(Workspace {
+                id: "red".to_owned(),
+                name: "red".to_owned(),
+                members: Vec::new(),
+                domains: vec!["red.example.com".to_owned()],
+                quota: Quota::default(),
+                created_at: 0,
+            })
+            .await
+            .unwrap();
+        let container = Container::new(
+            FakeIDProvider::new("new-id".to_owned()),
+            repo.clone(),
+            repo,
+            InMemoryWebhookRepository::new(),
+            accounts,
+            EventBus::new(),
+        )
+        .with_clock(Arc::new(FakeClock::new(999)));
+        let router = get_router(Arc::new(container), idempotency(), CountryLookup::default());
+        let visit = |id: &str, host: &str| {
+            http::Request::builder()
+                .uri(format!("/{id}"))
+                .header(http::header::HOST, host)
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        // When
+        let teased = router
+            .clone()
+            .oneshot(visit("teaser", "sho.rt"))
+            .await
+            .unwrap();
+        let elsewhere = router
+            .clone()
+            .oneshot(visit("teaser", "red.example.com"))
+            .await
+            .unwrap();
+        let disabled = router.oneshot(visit("disabled", "sho.rt")).await.unwrap();
+
+        // Then
+        assert_eq!(teased.status(), http::StatusCode::NOT_FOUND);
+        assert!(teased.headers()[http::header::CONTENT_TYPE]
+            .to_str()
+            .unwrap()
+            .starts_with("text/html"));
+        assert_eq!(elsewhere.status(), http::StatusCode::NOT_FOUND);
+        let body = elsewhere.into_body().collect().await.unwrap().to_bytes();
+        let problem: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(problem["type"], "urn:urlshortener:problem:not-found");
+        assert_eq!(
+            disabled.status(),
+            http::StatusCode::UNAVAILABLE_FOR_LEGAL_REASONS
+        );
+    }
+
+    #[tokio::test]
+    async fn plus_suffix_shows_an_escaped_preview() {
+        // Given
+        let store = Arc::new(DashMap::new());
+        store.insert(
+            "abc".to_owned(),
+            ShortUrl::new(
+                "abc".to_owned(),
+                "https://example.com/?q=\"><script>alert(1)</script>".to_owned(),
+            ),
+        );
+        let repo = InMemoryRepository::new(store);
+        let container = Container::new(
+            FakeIDProvider::new("new-id".to_owned()),
+            repo.clone(),
+            repo,
+            InMemoryWebhookRepository::new(),
+            InMemoryAccountRepository::new(),
+            EventBus::new(),
+        )
+        .with_operator_key(ADMIN_KEY);
+        let router = get_router(Arc::new(container), idempotency(), CountryLookup::default());
+
+        // When
+        let response = router
+            .oneshot(
+                http::Request::builder()
+                    .uri("/abc+")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        // Then
+        assert_eq!(response.status(), http::StatusCode::OK);
+        assert!(response.headers()[http::header::CONTENT_TYPE]
+            .to_str()
+            .unwrap()
+            .starts_with("text/html"));
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let page = String::from_utf8(body.to_vec()).unwrap();
+        assert!(page.contains("This link goes to example.com"));
+        assert!(page.contains(
+            "href=\"https://example.com/?q=&quot;&gt;&lt;script&gt;alert(1)&lt;/script&gt;\""
+        ));
+        assert!(!page.contains("<script>"));
+    }
+
+    #[tokio::test]
+    async fn always_preview_links_do_not_redirect() {
+        // Given
+        let router = get_router_with_mock_container();
+        let input = CreateShortURLRequest {
+            url: "https://example.com/".to_owned(),
+            always_preview: true,
+            ..Default::default()
+        };
+        let created = router
+            .clone()
+            .oneshot(
+                http::Request::builder()
+                    .method(http::Method::POST)
+                    .uri("/api/v1/links")
+                    .header(http::header::AUTHORIZATION, ADMIN_AUTH)
+                    .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                    .body(Body::from(serde_json::to_string(&input).unwrap()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(created.status(), http::StatusCode::OK);
+
+        // When
+        let response = router
+            .oneshot(
+                http::Request::builder()
+                    .uri("/new-id")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        // Then
+        assert_eq!(response.status(), http::StatusCode::OK);
+        assert!(response.headers().get(http::header::LOCATION).is_none());
+    }
+
+    fn visit(id: &str, user_agent: &str) -> http::Request<Body> {
+        http::Request::builder()
+            .uri(format!("/{id}"))
+            .header(http::header::USER_AGENT, user_agent)
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    fn patch(id: &str, body: &str) -> http::Request<Body> {
+        http::Request::builder()
+            .method(http::Method::PATCH)
+            .uri(format!("/api/v1/links/{id}"))
+            .header(http::header::AUTHORIZATION, ADMIN_AUTH)
+            .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+            .body(Body::from(body.to_owned()))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn links_to_listed_sites_show_a_warning() {
+        // Given
+        let store = Arc::new(DashMap::new());
+        store.insert(
+            "old".to_owned(),
+            ShortUrl::new("old".to_owned(), "https://evil.example/".to_owned()),
+        );
+        let repo = InMemoryRepository::new(store);
+        let list = crate::adapters::threatlist::ThreatList::from_reader(
+            "0.0.0.0 evil.example\n".as_bytes(),
+        )
+        .unwrap();
+        let container = Container::new(
+            FakeIDProvider::new("new-id".to_owned()),
+            repo.clone(),
+            repo,
+            InMemoryWebhookRepository::new(),
+            InMemoryAccountRepository::new(),
+            EventBus::new(),
+        )
+        .with_operator_key(ADMIN_KEY)
+        .with_screener(Arc::new(list), false);
+        let router = get_router(Arc::new(container), idempotency(), CountryLookup::default());
+        let visit = |id: &str| {
+            http::Request::builder()
+                .uri(format!("/{id}"))
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        // When
+        let created = router
+            .clone()
+            .oneshot(create_request("https://login.evil.example/", "k1"))
+            .await
+            .unwrap();
+        let warned = router.clone().oneshot(visit("new-id")).await.unwrap();
+        let old = router.clone().oneshot(visit("old")).await.unwrap();
+        let listed = router
+            .oneshot(
+                http::Request::builder()
+                    .uri("/api/v1/links")
+                    .header(http::header::AUTHORIZATION, ADMIN_AUTH)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        // Then
+        assert_eq!(created.status(), http::StatusCode::OK);
+        let body = listed.into_body().collect().await.unwrap().to_bytes();
+        let links: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let quarantined: Vec<_> = links
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|link| link["status"] == "quarantined")
+            .filter_map(|link| link["status_reason"].as_str())
+            .collect();
+        assert_eq!(quarantined, ["`evil.example` is on the threat list"]);
+
+        assert_eq!(warned.status(), http::StatusCode::OK);
+        assert!(warned.headers().get(http::header::LOCATION).is_none());
+        let body = warned.into_body().collect().await.unwrap().to_bytes();
+        let page = String::from_utf8(body.to_vec()).unwrap();
+        assert!(page.contains("This link may be harmful"));
+        assert!(!page.contains("href="));
+
+        // Only new links are screened unless redirects are too.
+        assert_eq!(old.status(), http::StatusCode::TEMPORARY_REDIRECT);
+    }
+
+    #[tokio::test]
+    async fn reported_links_can_be_disabled_and_restored() {
+        // Given
+        let router = get_router_with_mock_container();
+        let report = http::Request::builder()
+            .method(http::Method::POST)
+            .uri("/test-id/report")
+            .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+            .body(Body::from(r#"{"reason":"Phishing for bank logins"}"#))
+            .unwrap();
+        let queue = |uri: &str| as_holder_of(ADMIN_KEY, http::Method::GET, uri, "");
+        let set_status = |body: &str| {
+            as_holder_of(
+                ADMIN_KEY,
+                http::Method::PUT,
+                "/api/v1/links/test-id/status",
+                body,
+            )
+        };
+        let visit = || {
+            http::Request::builder()
+                .uri("/test-id")
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        // When
+        let reported = router.clone().oneshot(report).await.unwrap();
+        let anonymous = router
+            .clone()
+            .oneshot(
+                http::Request::builder()
+                    .uri("/api/v1/reports")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let open: Vec<AbuseReport> = json_body(
+            router
+                .clone()
+                .oneshot(queue("/api/v1/reports"))
+                .await
+                .unwrap(),
+        )
+        .await;
+        let disabled = router
+            .clone()
+            .oneshot(set_status(r#"{"status":"disabled","reason":"phishing"}"#))
+            .await
+            .unwrap();
+        let blocked = router.clone().oneshot(visit()).await.unwrap();
+        let still_open: Vec<AbuseReport> = json_body(
+            router
+                .clone()
+                .oneshot(queue("/api/v1/reports"))
+                .await
+                .unwrap(),
+        )
+        .await;
+        let all: Vec<AbuseReport> = json_body(
+            router
+                .clone()
+                .oneshot(queue("/api/v1/reports?all=true"))
+                .await
+                .unwrap(),
+        )
+        .await;
+        let restored = router
+            .clone()
+            .oneshot(set_status(r#"{"status":"active"}"#))
+            .await
+            .unwrap();
+        let followed = router.oneshot(visit()).await.unwrap();
+
+        // Then
+        assert_eq!(reported.status(), http::StatusCode::ACCEPTED);
+        assert_eq!(anonymous.status(), http::StatusCode::UNAUTHORIZED);
+        assert_eq!(open.len(), 1);
+        assert_eq!(open[0].link_id, "test-id");
+        assert_eq!(open[0].reason, "Phishing for bank logins");
+
+        assert_eq!(disabled.status(), http::StatusCode::OK);
+        let link: serde_json::Value = json_body(disabled).await;
+        assert_eq!(link["status"], "disabled");
+        assert_eq!(link["status_reason"], "phishing");
+        assert_eq!(
+            blocked.status(),
+            http::StatusCode::UNAVAILABLE_FOR_LEGAL_REASONS
+        );
+        assert!(still_open.is_empty());
+        assert_eq!(all.len(), 1);
+        assert_eq!(all[0].outcome, Some(LinkStatus::Disabled));
+
+        let link: serde_json::Value = json_body(restored).await;
+        assert_eq!(link["status"], "active");
+        assert!(link.get("status_reason").is_none());
+        assert_eq!(followed.status(), http::StatusCode::TEMPORARY_REDIRECT);
+    }
+
+    #[tokio::test]
+    async fn reports_need_a_reason_and_a_live_link() {
+        // Given
+        let router = get_router_with_mock_container();
+        let report = |id: &str, body: &str| {
+            http::Request::builder()
+                .method(http::Method::POST)
+                .uri(format!("/{id}/report"))
+                .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                .body(Body::from(body.to_owned()))
+                .unwrap()
+        };
+
+        // When
+        let blank = router
+            .clone()
+            .oneshot(report("test-id", r#"{"reason":"  "}"#))
+            .await
+            .unwrap();
+        let unknown = router
+            .clone()
+            .oneshot(report("nope", r#"{"reason":"spam"}"#))
+            .await
+            .unwrap();
+        let followed = router
+            .oneshot(
+                http::Request::builder()
+                    .uri("/test-id/report")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        // Then
+        assert_eq!(blank.status(), http::StatusCode::BAD_REQUEST);
+        assert_eq!(unknown.status(), http::StatusCode::NOT_FOUND);
+        // Following the link with the path `report` still redirects.
+        assert_eq!(followed.status(), http::StatusCode::TEMPORARY_REDIRECT);
+    }
+
+    #[tokio::test]
+    async fn destination_checks_show_in_the_api_and_metrics() {
+        // Given
+        let store = Arc::new(DashMap::new());
+        for id in ["up", "down"] {
+            store.insert(
+                id.to_owned(),
+                ShortUrl::new(id.to_owned(), format!("https://{id}.example/")),
+            );
+        }
+        let repo = InMemoryRepository::new(store);
+        let checked = |status| {
+            LinkHealth::new(
+                "https://down.example/".to_owned(),
+                Probe {
+                    status: Some(status),
+                    redirects: vec!["https://down.example/moved".to_owned()],
+                    ..Default::default()
+                },
+                1_700_000_000,
+            )
+        };
+        repo.record_health("up", checked(200)).await.unwrap();
+        repo.record_health("down", checked(503)).await.unwrap();
+        let container = Container::new(
+            FakeIDProvider::new("new-id".to_owned()),
+            repo.clone(),
+            repo,
+            InMemoryWebhookRepository::new(),
+            InMemoryAccountRepository::new(),
+            EventBus::new(),
+        )
+        .with_operator_key(ADMIN_KEY);
+        let router = get_router(Arc::new(container), idempotency(), CountryLookup::default());
+        let get = |uri: &str| as_holder_of(ADMIN_KEY, http::Method::GET, uri, "");
+
+        // When
+        let health = router
+            .clone()
+            .oneshot(get("/api/v1/links/down/health"))
+            .await
+            .unwrap();
+        let unchecked = router
+            .clone()
+            .oneshot(get("/api/v1/links/nope/health"))
+            .await
+            .unwrap();
+        let metrics = router.oneshot(get("/api/v1/metrics")).await.unwrap();
+
+        // Then
+        assert_eq!(health.status(), http::StatusCode::OK);
+        let health: serde_json::Value = json_body(health).await;
+        assert_eq!(health["state"], "broken");
+        assert_eq!(health["status"], 503);
+        assert_eq!(health["redirects"][0], "https://down.example/moved");
+        assert_eq!(unchecked.status(), http::StatusCode::NOT_FOUND);
+
+        assert_eq!(metrics.status(), http::StatusCode::OK);
+        let body = metrics.into_body().collect().await.unwrap().to_bytes();
+        let text = String::from_utf8(body.to_vec()).unwrap();
+        assert!(text.contains("urlshortener_links 2\n"));
+        assert!(text.contains("urlshortener_link_health{state=\"healthy\"} 1\n"));
+        assert!(text.contains("urlshortener_link_health{state=\"broken\"} 1\n"));
+    }
+
+    #[tokio::test]
+    async fn unfurl_bots_get_the_open_graph_card() {
+        // Given
+        let router = get_router_with_mock_container();
+        let updated = router
+            .clone()
+            .oneshot(patch(
+                "test-id",
+                r#"{"open_graph":{"title":"Spring <sale>","image":"https://cdn.example.com/card.png"}}"#,
+            ))
+            .await
+            .unwrap();
+        assert_eq!(updated.status(), http::StatusCode::OK);
+
+        // When
+        let bot = router
+            .clone()
+            .oneshot(visit("test-id", "Slackbot-LinkExpanding 1.0"))
+            .await
+            .unwrap();
+        let browser = router
+            .oneshot(visit("test-id", "Mozilla/5.0 Firefox/128.0"))
+            .await
+            .unwrap();
+
+        // Then
+        assert_eq!(bot.status(), http::StatusCode::OK);
+        assert_eq!(bot.headers()[http::header::VARY], "user-agent");
+        assert!(bot.headers()[http::header::CONTENT_SECURITY_POLICY]
+            .to_str()
+            .unwrap()
+            .starts_with("default-src 'none'"));
+        let body = bot.into_body().collect().await.unwrap().to_bytes();
+        let page = String::from_utf8(body.to_vec()).unwrap();
+        assert!(page.contains(r#"<meta property="og:title" content="Spring &lt;sale&gt;">"#));
+        assert!(page
+            .contains(r#"<meta property="og:image" content="https://cdn.example.com/card.png">"#));
+
+        assert_eq!(browser.status(), http::StatusCode::TEMPORARY_REDIRECT);
+        assert_eq!(browser.headers()[http::header::VARY], "user-agent");
+    }
+
+    #[tokio::test]
+    async fn rules_pick_the_target() {
+        // Given
+        let router = get_router_with_mock_container();
+        let updated = router
+            .clone()
+            .oneshot(patch(
+                "test-id",
+                r#"{"rules":[
+                    {"target":"https://apps.apple.com/app/id1","platforms":["ios"]},
+                    {"target":"https://example.com/de","languages":["de"]}
+                ]}"#,
+            ))
+            .await
+            .unwrap();
+        assert_eq!(updated.status(), http::StatusCode::OK);
+        let german = http::Request::builder()
+            .uri("/test-id")
+            .header(http::header::ACCEPT_LANGUAGE, "de-CH, en;q=0.5")
+            .body(Body::empty())
+            .unwrap();
+
+        // When
+        let iphone = router
+            .clone()
+            .oneshot(visit(
+                "test-id",
+                "Mozilla/5.0 (iPhone; CPU iPhone OS 17_5 like Mac OS X)",
+            ))
+            .await
+            .unwrap();
+        let german = router.clone().oneshot(german).await.unwrap();
+        let other = router
+            .oneshot(visit("test-id", "Mozilla/5.0 (X11; Linux x86_64)"))
+            .await
+            .unwrap();
+
+        // Then
+        assert_eq!(
+            iphone.headers()[http::header::LOCATION],
+            "https://apps.apple.com/app/id1"
+        );
+        assert_eq!(
+            german.headers()[http::header::LOCATION],
+            "https://example.com/de"
+        );
+        assert_eq!(other.headers()[http::header::LOCATION], "test-url");
+        assert_eq!(
+            other.headers()[http::header::VARY],
+            "user-agent, accept-language"
+        );
+    }
+
+    #[tokio::test]
+    async fn invalid_rules_are_rejected() {
+        // Given
+        let router = get_router_with_mock_container();
+
+        // When
+        let response = router
+            .oneshot(patch(
+                "test-id",
+                r#"{"rules":[{"target":"https://example.com","countries":["USA"]}]}"#,
+            ))
+            .await
+            .unwrap();
+
+        // Then
+        assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Problem = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body.code, "invalid-rule");
+    }
+
+    #[tokio::test]
+    async fn variants_split_traffic_stickily_and_are_counted() {
+        // Given
+        let router = get_router_with_mock_container();
+        let updated = router
+            .clone()
+            .oneshot(patch(
+                "test-id",
+                r#"{"variants":[
+                    {"name":"a","target":"https://example.com/a","weight":50},
+                    {"name":"b","target":"https://example.com/b","weight":50}
+                ]}"#,
+            ))
+            .await
+            .unwrap();
+        assert_eq!(updated.status(), http::StatusCode::OK);
+        let returning = http::Request::builder()
+            .uri("/test-id")
+            .header(http::header::COOKIE, "variant_test-id=b")
+            .body(Body::empty())
+            .unwrap();
+
+        // When
+        let first = router
+            .clone()
+            .oneshot(visit("test-id", "Mozilla/5.0 (X11; Linux x86_64)"))
+            .await
+            .unwrap();
+        let returning = router.clone().oneshot(returning).await.unwrap();
+        let stats = router
+            .oneshot(
+                http::Request::builder()
+                    .uri("/api/v1/links/test-id/stats")
+                    .header(http::header::AUTHORIZATION, ADMIN_AUTH)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        // Then
+        let cookie = first.headers()[http::header::SET_COOKIE].to_str().unwrap();
+        let name = &cookie["variant_test-id=".len()..][..1];
+        assert_eq!(
+            first.headers()[http::header::LOCATION],
+            format!("https://example.com/{name}").as_str()
+        );
+        assert_eq!(
+            returning.headers()[http::header::LOCATION],
+            "https://example.com/b"
+        );
+        assert_eq!(
+            returning.headers()[http::header::VARY],
+            "user-agent, accept-language, cookie"
+        );
+        assert_eq!(stats.status(), http::StatusCode::OK);
+        let body = stats.into_body().collect().await.unwrap().to_bytes();
+        let stats: LinkStats = serde_json::from_slice(&body).unwrap();
+        assert_eq!(stats.visits, 2);
+        assert_eq!(stats.variants.values().sum::<u64>(), 2);
+        assert!(stats.variants["b"] >= 1);
+    }
+
+    #[tokio::test]
+    async fn templated_links_take_the_path_and_query() {
+        // Given
+        let router = get_router_with_mock_container();
+        let created = router
+            .clone()
+            .oneshot(
+                http::Request::builder()
+                    .method("POST")
+                    .uri("/api/v1/links")
+                    .header(http::header::AUTHORIZATION, ADMIN_AUTH)
+                    .header(http::header::CONTENT_TYPE, "application/json")
+                    .body(Body::from(
+                        r#"{"url":"https://shop.example/{path}?ref={ref}","query_passthrough":"target_wins"}"#,
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(created.status(), http::StatusCode::OK);
+
+        // When
+        let response = router
+            .oneshot(visit(
+                "new-id/shoes/red?ref=mail&utm_source=news",
+                "curl/8.5.0",
+            ))
+            .await
+            .unwrap();
+
+        // Then
+        assert_eq!(response.status(), http::StatusCode::TEMPORARY_REDIRECT);
+        assert_eq!(
+            response.headers()[http::header::LOCATION],
+            "https://shop.example/shoes/red?ref=mail&utm_source=news"
+        );
+    }
+
+    #[tokio::test]
+    async fn query_passes_through_with_the_chosen_precedence() {
+        // Given
+        let router = get_router_with_mock_container();
+        let updated = router
+            .clone()
+            .oneshot(patch(
+                "test-id",
+                r#"{
+                    "rules":[{"target":"https://example.com/?utm_source=link&a=1"}],
+                    "query_passthrough":"visitor_wins"
+                }"#,
+            ))
+            .await
+            .unwrap();
+        assert_eq!(updated.status(), http::StatusCode::OK);
+
+        // When
+        let response = router
+            .oneshot(visit("test-id?utm_source=news", "curl/8.5.0"))
+            .await
+            .unwrap();
+
+        // Then
+        assert_eq!(
+            response.headers()[http::header::LOCATION],
+            "https://example.com/?a=1&utm_source=news"
+        );
+    }
+
+    #[tokio::test]
+    async fn templates_may_not_pick_the_host() {
+        // Given
+        let router = get_router_with_mock_container();
+
+        // When
+        let response = router
+            .oneshot(patch(
+                "test-id",
+                r#"{"rules":[{"target":"https://{path}/"}]}"#,
+            ))
+            .await
+            .unwrap();
+
+        // Then
+        assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn variants_must_add_up_to_100() {
+        // Given
+        let router = get_router_with_mock_container();
+
+        // When
+        let response = router
+            .oneshot(patch(
+                "test-id",
+                r#"{"variants":[
+                    {"name":"a","target":"https://example.com/a","weight":50},
+                    {"name":"b","target":"https://example.com/b","weight":40}
+                ]}"#,
+            ))
+            .await
+            .unwrap();
+
+        // Then
+        assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Problem = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body.code, "invalid-variants");
+    }
+
+    #[tokio::test]
+    async fn update_returns_the_link() {
+        // Given
+        let router = get_router_with_mock_container();
+
+        // When
+        let response = router
+            .oneshot(patch("test-id", r#"{"always_preview":true}"#))
+            .await
+            .unwrap();
+
+        // Then
+        assert_eq!(response.status(), http::StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let link: LinkResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(link.id, "test-id");
+        assert!(link.always_preview);
+        assert!(link.open_graph.is_empty());
+    }
+
+    #[tokio::test]
+    async fn update_of_unknown_link_is_not_found() {
+        // Given
+        let router = get_router_with_mock_container();
 
-    use crate::{adapters::inmemory::InMemoryRepository, id_provider::FakeIDProvider};
+        // When
+        let response = router
+            .oneshot(patch("nope", r#"{"always_preview":true}"#))
+            .await
+            .unwrap();
 
-    use super::*;
+        // Then
+        assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+    }
 
-    fn get_router_with_mock_container() -> Router {
-        let store = Arc::new(DashMap::new());
-        store.insert("test-id".to_owned(), "test-url".to_owned());
-        store.insert("test-id-2".to_owned(), "test-url-2".to_owned());
-        let repo = InMemoryRepository::new(store);
+    #[tokio::test]
+    async fn openapi_document_matches_the_routes() {
+        // Given
+        let spec = openapi::ApiDoc::openapi();
+        let routed: BTreeSet<String> = routes::<
+            FakeIDProvider,
+            InMemoryRepository,
+            InMemoryRepository,
+            InMemoryWebhookRepository,
+            InMemoryAccountRepository,
+        >()
+        .into_iter()
+        .map(|(path, _)| {
+            path.split('/')
+                .map(|segment| match segment.strip_prefix([':', '*']) {
+                    Some(param) => format!("{{{param}}}"),
+                    None => segment.to_owned(),
+                })
+                .collect::<Vec<_>>()
+                .join("/")
+        })
+        .collect();
 
-        let container = Container::new(
-            FakeIDProvider::new("test-id".to_owned()),
-            repo.clone(),
-            repo,
-        );
+        // Then every route is documented, and nothing else
+        let documented: BTreeSet<String> = spec.paths.paths.keys().cloned().collect();
+        assert_eq!(documented, routed);
+
+        // And each path answers exactly the documented methods
+        for (path, item) in &spec.paths.paths {
+            let uri = path.replace("{id}", "probe");
+            let operations = [
+                (http::Method::GET, item.get.is_some()),
+                (http::Method::POST, item.post.is_some()),
+                (http::Method::PUT, item.put.is_some()),
+                (http::Method::PATCH, item.patch.is_some()),
+                (http::Method::DELETE, item.delete.is_some()),
+            ];
+            for (method, is_documented) in operations {
+                let response = get_router_with_mock_container()
+                    .oneshot(
+                        http::Request::builder()
+                            .method(method.clone())
+                            .uri(&uri)
+                            .body(Body::empty())
+                            .unwrap(),
+                    )
+                    .await
+                    .unwrap();
 
-        get_router(Arc::new(container))
+                let is_routed = response.status() != http::StatusCode::METHOD_NOT_ALLOWED;
+                assert_eq!(is_routed, is_documented, "{method} {path}");
+            }
+        }
     }
 
     #[tokio::test]
-    async fn test_get_full_url() {
+    async fn serves_the_openapi_document() {
         // Given
         let router = get_router_with_mock_container();
 
@@ -181,7 +2987,7 @@ mod tests {
         let response = router
             .oneshot(
                 http::Request::builder()
-                    .uri("/test-id")
+                    .uri("/api/v1/openapi.json")
                     .body(Body::empty())
                     .unwrap(),
             )
@@ -190,10 +2996,11 @@ mod tests {
 
         // Then
         assert_eq!(response.status(), http::StatusCode::OK);
-
         let body = response.into_body().collect().await.unwrap().to_bytes();
-        let body: FullUrlResponse = serde_json::from_slice(&body).unwrap();
-        assert_eq!(body.url, "test-url");
+        let spec: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert!(spec["openapi"].as_str().unwrap().starts_with("3."));
+        assert!(spec["components"]["schemas"]["CreateShortURLRequest"].is_object());
+        assert!(spec["components"]["schemas"]["Problem"].is_object());
     }
 
     #[tokio::test]
@@ -205,7 +3012,8 @@ mod tests {
         let response = router
             .oneshot(
                 http::Request::builder()
-                    .uri("/not-found")
+                    .uri("/api/v1/links/not-found")
+                    .header(http::header::AUTHORIZATION, ADMIN_AUTH)
                     .body(Body::empty())
                     .unwrap(),
             )
@@ -214,10 +3022,16 @@ mod tests {
 
         // Then
         assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+        assert_eq!(
+            response.headers()[http::header::CONTENT_TYPE],
+            problem::CONTENT_TYPE
+        );
 
         let body = response.into_body().collect().await.unwrap().to_bytes();
-        let body: ErrorResponse = serde_json::from_slice(&body).unwrap();
-        assert_eq!(body.message, "Not found");
+        let body: Problem = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body.code, "not-found");
+        assert_eq!(body.id.as_deref(), Some("not-found"));
+        assert_eq!(body.instance.as_deref(), Some("/api/v1/links/not-found"));
     }
 
     #[tokio::test]
@@ -229,7 +3043,8 @@ mod tests {
         let response = router
             .oneshot(
                 http::Request::builder()
-                    .uri("/test-id-2")
+                    .uri("/api/v1/links/test-id-2")
+                    .header(http::header::AUTHORIZATION, ADMIN_AUTH)
                     .body(Body::empty())
                     .unwrap(),
             )
@@ -250,6 +3065,7 @@ mod tests {
 
         let create_short_url_request = CreateShortURLRequest {
             url: "https://example.com".to_owned(),
+            ..Default::default()
         };
 
         // When
@@ -257,7 +3073,8 @@ mod tests {
             .oneshot(
                 http::Request::builder()
                     .method(http::Method::POST)
-                    .uri("/")
+                    .uri("/api/v1/links")
+                    .header(http::header::AUTHORIZATION, ADMIN_AUTH)
                     .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
                     .body(Body::from(
                         serde_json::to_string(&create_short_url_request).unwrap(),
@@ -272,7 +3089,53 @@ mod tests {
 
         let body = response.into_body().collect().await.unwrap().to_bytes();
         let body: ShortUrlResponse = serde_json::from_slice(&body).unwrap();
-        assert_eq!(body.id, "test-id");
+        assert_eq!(body.id, "new-id");
+    }
+
+    #[tokio::test]
+    async fn idempotency_key_replays_the_first_response() {
+        // Given
+        let router = get_router_with_mock_container();
+        let first = router
+            .clone()
+            .oneshot(create_request("https://example.com", "retry-1"))
+            .await
+            .unwrap();
+        let first_status = first.status();
+        let first_body = first.into_body().collect().await.unwrap().to_bytes();
+
+        // When
+        let retry = router
+            .oneshot(create_request("https://example.com", "retry-1"))
+            .await
+            .unwrap();
+
+        // Then
+        assert_eq!(first_status, http::StatusCode::OK);
+        assert_eq!(retry.status(), http::StatusCode::OK);
+        assert_eq!(retry.headers()[idempotency::REPLAYED_HEADER], "true");
+        let retry_body = retry.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(retry_body, first_body);
+    }
+
+    #[tokio::test]
+    async fn idempotency_key_reused_for_another_body_is_rejected() {
+        // Given
+        let router = get_router_with_mock_container();
+        router
+            .clone()
+            .oneshot(create_request("https://example.com", "retry-1"))
+            .await
+            .unwrap();
+
+        // When
+        let response = router
+            .oneshot(create_request("https://example.org", "retry-1"))
+            .await
+            .unwrap();
+
+        // Then
+        assert_eq!(response.status(), http::StatusCode::UNPROCESSABLE_ENTITY);
     }
 
     #[tokio::test]
@@ -282,17 +3145,24 @@ mod tests {
         let repo = InMemoryRepository::new(store.clone());
         let repo2 = InMemoryRepository::new(store);
 
-        let container = Arc::new(Container::new(
-            FakeIDProvider::new("test-id".to_owned()),
-            repo,
-            repo2,
-        ));
+        let container = Arc::new(
+            Container::new(
+                FakeIDProvider::new("test-id".to_owned()),
+                repo,
+                repo2,
+                InMemoryWebhookRepository::new(),
+                InMemoryAccountRepository::new(),
+                EventBus::new(),
+            )
+            .with_operator_key(ADMIN_KEY),
+        );
 
-        let router1 = get_router(container.clone());
-        let router2 = get_router(container.clone());
+        let router1 = get_router(container.clone(), idempotency(), CountryLookup::default());
+        let router2 = get_router(container.clone(), idempotency(), CountryLookup::default());
 
         let create_short_url_request = CreateShortURLRequest {
             url: "https://example.com/".to_owned(),
+            ..Default::default()
         };
 
         // When
@@ -300,7 +3170,8 @@ mod tests {
             .oneshot(
                 http::Request::builder()
                     .method(http::Method::POST)
-                    .uri("/")
+                    .uri("/api/v1/links")
+                    .header(http::header::AUTHORIZATION, ADMIN_AUTH)
                     .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
                     .body(Body::from(
                         serde_json::to_string(&create_short_url_request).unwrap(),
@@ -313,7 +3184,8 @@ mod tests {
         let resp2 = router2
             .oneshot(
                 http::Request::builder()
-                    .uri("/test-id")
+                    .uri("/api/v1/links/test-id")
+                    .header(http::header::AUTHORIZATION, ADMIN_AUTH)
                     .body(Body::empty())
                     .unwrap(),
             )
@@ -338,6 +3210,7 @@ mod tests {
         let router = get_router_with_mock_container();
         let create_short_url_request = CreateShortURLRequest {
             url: "invalid-url".to_owned(),
+            ..Default::default()
         };
 
         // When
@@ -345,7 +3218,8 @@ mod tests {
             .oneshot(
                 http::Request::builder()
                     .method(http::Method::POST)
-                    .uri("/")
+                    .uri("/api/v1/links")
+                    .header(http::header::AUTHORIZATION, ADMIN_AUTH)
                     .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
                     .body(Body::from(
                         serde_json::to_string(&create_short_url_request).unwrap(),
@@ -359,7 +3233,431 @@ mod tests {
         assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
 
         let body = response.into_body().collect().await.unwrap().to_bytes();
-        let body: ErrorResponse = serde_json::from_slice(&body).unwrap();
-        assert_eq!(body.message, "Invalid URL");
+        let body: Problem = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body.code, "invalid-url");
+        assert_eq!(body.url.as_deref(), Some("invalid-url"));
+        assert_eq!(body.instance.as_deref(), Some("/api/v1/links"));
+    }
+
+    #[tokio::test]
+    async fn malformed_json_is_a_problem() {
+        // Given
+        let router = get_router_with_mock_container();
+
+        // When
+        let response = router
+            .oneshot(
+                http::Request::builder()
+                    .method(http::Method::POST)
+                    .uri("/api/v1/links")
+                    .header(http::header::AUTHORIZATION, ADMIN_AUTH)
+                    .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                    .body(Body::from("{\"url\":"))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        // Then
+        assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+        assert_eq!(
+            response.headers()[http::header::CONTENT_TYPE],
+            problem::CONTENT_TYPE
+        );
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Problem = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body.code, "malformed-body");
+        assert_eq!(body.status, http::StatusCode::BAD_REQUEST);
+        assert_eq!(body.instance.as_deref(), Some("/api/v1/links"));
+    }
+
+    #[tokio::test]
+    async fn create_and_list_webhooks() {
+        // Given
+        let router = get_router_with_mock_container();
+        let request = CreateWebhookRequest {
+            url: "https://example.com/hook".to_owned(),
+            events: vec![EventKind::LinkCreated],
+            secret: Some("s3cret".to_owned()),
+        };
+
+        // When
+        let created = router
+            .clone()
+            .oneshot(
+                http::Request::builder()
+                    .method(http::Method::POST)
+                    .uri("/api/v1/webhooks")
+                    .header(http::header::AUTHORIZATION, ADMIN_AUTH)
+                    .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                    .body(Body::from(serde_json::to_string(&request).unwrap()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        let listed = router
+            .oneshot(
+                http::Request::builder()
+                    .uri("/api/v1/webhooks")
+                    .header(http::header::AUTHORIZATION, ADMIN_AUTH)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        // Then
+        assert_eq!(created.status(), http::StatusCode::CREATED);
+        let body = created.into_body().collect().await.unwrap().to_bytes();
+        let created: WebhookResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(created.secret.as_deref(), Some("s3cret"));
+
+        assert_eq!(listed.status(), http::StatusCode::OK);
+        let body = listed.into_body().collect().await.unwrap().to_bytes();
+        let listed: Vec<WebhookResponse> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].id, created.id);
+        assert_eq!(listed[0].secret, None);
+    }
+
+    #[tokio::test]
+    async fn deliveries_of_unknown_webhook() {
+        // Given
+        let router = get_router_with_mock_container();
+
+        // When
+        let response = router
+            .oneshot(
+                http::Request::builder()
+                    .uri("/api/v1/webhooks/missing/deliveries")
+                    .header(http::header::AUTHORIZATION, ADMIN_AUTH)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        // Then
+        assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn import_csv_then_export_jsonl() {
+        // Given
+        let router = get_router_with_mock_container();
+        let csv = "id,url,created_at,metadata\n\
+                   imported,https://example.com,7,\"{\"\"team\"\":\"\"growth\"\"}\"\n\
+                   test-id,https://other.com,,\n";
+
+        // When
+        let imported = router
+            .clone()
+            .oneshot(
+                http::Request::builder()
+                    .method(http::Method::POST)
+                    .uri("/api/v1/admin/import?format=csv&on_conflict=skip")
+                    .header(http::header::AUTHORIZATION, ADMIN_AUTH)
+                    .header(http::header::CONTENT_TYPE, "text/csv")
+                    .body(Body::from(csv))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        let exported = router
+            .oneshot(
+                http::Request::builder()
+                    .uri("/api/v1/admin/export?format=jsonl")
+                    .header(http::header::AUTHORIZATION, ADMIN_AUTH)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        // Then
+        assert_eq!(imported.status(), http::StatusCode::OK);
+        let body = imported.into_body().collect().await.unwrap().to_bytes();
+        let report: ImportReport = serde_json::from_slice(&body).unwrap();
+        assert_eq!((report.created, report.skipped), (1, 1));
+
+        assert_eq!(exported.status(), http::StatusCode::OK);
+        assert_eq!(
+            exported.headers()[http::header::CONTENT_TYPE],
+            "application/x-ndjson"
+        );
+        let body = exported.into_body().collect().await.unwrap().to_bytes();
+        let links: Vec<ShortUrl> = body
+            .split(|b| *b == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| serde_json::from_slice(line).unwrap())
+            .collect();
+        assert_eq!(links.len(), 3);
+        assert_eq!(links[0].id, "imported");
+        assert_eq!(links[0].created_at, 7);
+        assert_eq!(links[0].metadata["team"], "growth");
+    }
+
+    #[tokio::test]
+    async fn import_with_fail_policy_reports_conflict() {
+        // Given
+        let router = get_router_with_mock_container();
+        let jsonl = "{\"id\":\"test-id\",\"url\":\"https://other.com\"}\n";
+
+        // When
+        let response = router
+            .oneshot(
+                http::Request::builder()
+                    .method(http::Method::POST)
+                    .uri("/api/v1/admin/import?on_conflict=fail")
+                    .header(http::header::AUTHORIZATION, ADMIN_AUTH)
+                    .body(Body::from(jsonl))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        // Then
+        assert_eq!(response.status(), http::StatusCode::CONFLICT);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let report: ImportReport = serde_json::from_slice(&body).unwrap();
+        assert_eq!(report.conflict.as_deref(), Some("test-id"));
+    }
+
+    fn as_holder_of(key: &str, method: http::Method, uri: &str, body: &str) -> http::Request<Body> {
+        http::Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(http::header::AUTHORIZATION, format!("Bearer {key}"))
+            .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+            .body(Body::from(body.to_owned()))
+            .unwrap()
+    }
+
+    async fn json_body<T: serde::de::DeserializeOwned>(response: Response) -> T {
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn api_requires_a_known_key() {
+        // Given
+        let router = get_router_with_mock_container();
+
+        // When
+        let missing = router
+            .clone()
+            .oneshot(
+                http::Request::builder()
+                    .uri("/api/v1/links/test-id")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let unknown = router
+            .clone()
+            .oneshot(as_holder_of(
+                "usk_guess",
+                http::Method::GET,
+                "/api/v1/links/test-id",
+                "",
+            ))
+            .await
+            .unwrap();
+        let redirect = router
+            .oneshot(visit("test-id", "curl/8.5.0"))
+            .await
+            .unwrap();
+
+        // Then
+        for response in [missing, unknown] {
+            assert_eq!(response.status(), http::StatusCode::UNAUTHORIZED);
+            assert_eq!(response.headers()[http::header::WWW_AUTHENTICATE], "Bearer");
+            let problem: Problem = json_body(response).await;
+            assert_eq!(problem.code, "unauthenticated");
+        }
+        assert_eq!(redirect.status(), http::StatusCode::TEMPORARY_REDIRECT);
+    }
+
+    #[tokio::test]
+    async fn roles_decide_who_changes_and_reads_links() {
+        // Given
+        let router = get_router_with_mock_container();
+        let mut keys = Vec::new();
+        for (name, role) in [("alice", "editor"), ("bob", "editor"), ("vic", "viewer")] {
+            let created = router
+                .clone()
+                .oneshot(as_holder_of(
+                    ADMIN_KEY,
+                    http::Method::POST,
+                    "/api/v1/accounts",
+                    &format!("{{\"name\":\"{name}\",\"role\":\"{role}\"}}"),
+                ))
+                .await
+                .unwrap();
+            assert_eq!(created.status(), http::StatusCode::CREATED);
+            let created: CreatedAccountResponse = json_body(created).await;
+            keys.push(created.key.secret);
+        }
+        let (alice, bob, vic) = (&keys[0], &keys[1], &keys[2]);
+        let send = |key: &str, method, uri: &str, body: &str| {
+            router.clone().oneshot(as_holder_of(key, method, uri, body))
+        };
+        let link = "{\"url\":\"https://example.com/\"}";
+        let created = send(alice, http::Method::POST, "/api/v1/links", link)
+            .await
+            .unwrap();
+        assert_eq!(created.status(), http::StatusCode::OK);
+
+        // When
+        let listed = send(alice, http::Method::GET, "/api/v1/links", "")
+            .await
+            .unwrap();
+        let patched = send(bob, http::Method::PATCH, "/api/v1/links/new-id", "{}")
+            .await
+            .unwrap();
+        let viewer_created = send(vic, http::Method::POST, "/api/v1/links", link)
+            .await
+            .unwrap();
+        let viewer_deleted = send(vic, http::Method::DELETE, "/api/v1/links/new-id", "")
+            .await
+            .unwrap();
+        let webhooks = send(bob, http::Method::GET, "/api/v1/webhooks", "")
+            .await
+            .unwrap();
+        let stats = send(bob, http::Method::GET, "/api/v1/links/new-id/stats", "")
+            .await
+            .unwrap();
+        let own_stats = send(alice, http::Method::GET, "/api/v1/links/new-id/stats", "")
+            .await
+            .unwrap();
+        let listed_all = send(vic, http::Method::GET, "/api/v1/links?all=true", "")
+            .await
+            .unwrap();
+        let deleted = send(alice, http::Method::DELETE, "/api/v1/links/new-id", "")
+            .await
+            .unwrap();
+
+        // Then
+        let listed: Vec<LinkResponse> = json_body(listed).await;
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].id, "new-id");
+        assert!(listed[0].owner.is_some());
+        for response in [
+            patched,
+            viewer_created,
+            viewer_deleted,
+            webhooks,
+            stats,
+            listed_all,
+        ] {
+            assert_eq!(response.status(), http::StatusCode::FORBIDDEN);
+        }
+        assert_eq!(own_stats.status(), http::StatusCode::OK);
+        assert_eq!(deleted.status(), http::StatusCode::NO_CONTENT);
+    }
+
+    #[tokio::test]
+    async fn workspaces_share_links_domains_and_quotas() {
+        // Given
+        let router = get_router_with_mock_container();
+        let send = |key: &str, method, uri: &str, body: &str| {
+            router.clone().oneshot(as_holder_of(key, method, uri, body))
+        };
+        let mut members = Vec::new();
+        for name in ["alice", "bob"] {
+            let created = send(
+                ADMIN_KEY,
+                http::Method::POST,
+                "/api/v1/accounts",
+                &format!("{{\"name\":\"{name}\"}}"),
+            )
+            .await
+            .unwrap();
+            let created: CreatedAccountResponse = json_body(created).await;
+            members.push((created.account.id, created.key.secret));
+        }
+        let workspace = send(
+            ADMIN_KEY,
+            http::Method::POST,
+            "/api/v1/workspaces",
+            r#"{"name":"Team","domains":["Go.Example.com"],"quota":{"max_links":1}}"#,
+        )
+        .await
+        .unwrap();
+        assert_eq!(workspace.status(), http::StatusCode::CREATED);
+        let workspace: Workspace = json_body(workspace).await;
+        assert_eq!(workspace.domains, ["go.example.com"]);
+        for (account_id, _) in &members {
+            let uri = format!("/api/v1/workspaces/{}/members/{account_id}", workspace.id);
+            let added = send(ADMIN_KEY, http::Method::PUT, &uri, "").await.unwrap();
+            assert_eq!(added.status(), http::StatusCode::OK);
+        }
+        let (alice, bob) = (&members[0].1, &members[1].1);
+        let in_team = format!(
+            r#"{{"url":"https://example.com/","workspace":"{}"}}"#,
+            workspace.id
+        );
+        let visit = |id: &str| {
+            router.clone().oneshot(
+                http::Request::builder()
+                    .uri(format!("/{id}"))
+                    .header(http::header::HOST, "go.example.com:443")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+        };
+
+        // When
+        let created = send(alice, http::Method::POST, "/api/v1/links", &in_team)
+            .await
+            .unwrap();
+        let patched_by_bob = send(
+            bob,
+            http::Method::PATCH,
+            "/api/v1/links/new-id",
+            r#"{"always_preview":false}"#,
+        )
+        .await
+        .unwrap();
+        let over_quota = send(alice, http::Method::POST, "/api/v1/links", &in_team)
+            .await
+            .unwrap();
+        let own_domain = visit("new-id").await.unwrap();
+        let other_link = visit("test-id").await.unwrap();
+        let domain_taken = send(
+            ADMIN_KEY,
+            http::Method::POST,
+            "/api/v1/workspaces",
+            r#"{"name":"Other","domains":["go.example.com"]}"#,
+        )
+        .await
+        .unwrap();
+        let usage = send(
+            bob,
+            http::Method::GET,
+            &format!("/api/v1/workspaces/{}/usage", workspace.id),
+            "",
+        )
+        .await
+        .unwrap();
+
+        // Then
+        assert_eq!(created.status(), http::StatusCode::OK);
+        assert_eq!(patched_by_bob.status(), http::StatusCode::OK);
+        let patched: LinkResponse = json_body(patched_by_bob).await;
+        assert_eq!(patched.workspace.as_deref(), Some(workspace.id.as_str()));
+        assert_eq!(over_quota.status(), http::StatusCode::FORBIDDEN);
+        let problem: Problem = json_body(over_quota).await;
+        assert_eq!(problem.code, "quota-exceeded");
+        assert_eq!(own_domain.status(), http::StatusCode::TEMPORARY_REDIRECT);
+        assert_eq!(other_link.status(), http::StatusCode::NOT_FOUND);
+        assert_eq!(domain_taken.status(), http::StatusCode::CONFLICT);
+        let usage: WorkspaceUsageResponse = json_body(usage).await;
+        assert_eq!((usage.links, usage.clicks), (1, 1));
+        assert_eq!(usage.quota.max_links, Some(1));
     }
 }
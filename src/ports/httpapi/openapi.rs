@@ -0,0 +1,72 @@
+//! The OpenAPI 3 document of the HTTP API, served at `/api/v1/openapi.json`.
+
+use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "urlshortener",
+        description = "Short links resolve at `/{id}`; everything else lives under `/api/v1`."
+    ),
+    modifiers(&BearerAuth),
+    security(("api_key" = [])),
+    paths(
+        super::redirect,
+        super::redirect_with_path,
+        super::redirect_with_report_path,
+        super::report_link,
+        super::shorten_url,
+        super::get_full_url,
+        super::update_link,
+        super::delete_link,
+        super::list_links,
+        super::get_link_stats,
+        super::get_link_health,
+        super::change_link_status,
+        super::list_reports,
+        super::create_webhook,
+        super::list_webhooks,
+        super::delete_webhook,
+        super::list_webhook_deliveries,
+        super::create_account,
+        super::create_api_key,
+        super::revoke_api_key,
+        super::create_workspace,
+        super::get_workspace,
+        super::update_workspace,
+        super::add_member,
+        super::remove_member,
+        super::get_workspace_usage,
+        super::import_links,
+        super::export_links,
+        super::metrics,
+        super::openapi_document,
+    ),
+    tags(
+        (name = "redirect", description = "Following short links"),
+        (name = "links", description = "Creating, inspecting and updating links"),
+        (name = "webhooks", description = "Event subscriptions"),
+        (name = "accounts", description = "Accounts and their API keys"),
+        (name = "workspaces", description = "Teams sharing links, domains and quotas"),
+        (name = "moderation", description = "Abuse reports and disabled or quarantined links"),
+        (name = "admin", description = "Bulk import and export"),
+        (name = "metrics", description = "Counts for monitoring"),
+        (name = "meta", description = "This document"),
+    )
+)]
+pub struct ApiDoc;
+
+/// API keys go in `Authorization: Bearer <key>`. Routes that take none
+/// opt out with `security(())`.
+struct BearerAuth;
+
+impl Modify for BearerAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "api_key",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+        );
+    }
+}
@@ -0,0 +1,97 @@
+//! The page shown instead of a redirect when a link is opened as `/abc+`
+//! or has `always_preview` set, so visitors see where a link goes first.
+
+use axum::http::header;
+use axum::response::{Html, IntoResponse, Response};
+
+use crate::app::short_url::ShortUrl;
+
+/// Appended to an ID to ask for the preview instead of the redirect. IDs
+/// never contain it, as no alphabet may.
+pub const SUFFIX: char = '+';
+
+const TEMPLATE: &str = include_str!("preview.html");
+
+/// Styles are inline and the page runs no scripts, which also keeps a
+/// `javascript:` destination from running on our origin.
+pub(super) const CONTENT_SECURITY_POLICY: &str = "default-src 'none'; style-src 'unsafe-inline'";
+
+/// Previews `link`, which leads this visitor to `target`.
+pub fn render(link: &ShortUrl, target: &str) -> Response {
+    let domain = url::Url::parse(target)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_owned))
+        .unwrap_or_else(|| target.to_owned());
+
+    let page = TEMPLATE
+        .replace("{{domain}}", &escape(&domain))
+        .replace("{{url}}", &escape(target))
+        .replace("{{created}}", &escape(&format_date(link.created_at)));
+
+    (
+        [(header::CONTENT_SECURITY_POLICY, CONTENT_SECURITY_POLICY)],
+        Html(page),
+    )
+        .into_response()
+}
+
+/// Escapes text for use in element content and quoted attributes.
+pub(super) fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+/// `YYYY-MM-DD HH:MM UTC` for a Unix timestamp.
+fn format_date(timestamp: u64) -> String {
+    let days = timestamp / 86_400;
+    let minutes = timestamp % 86_400 / 60;
+
+    // Days to civil date, after Howard Hinnant's `civil_from_days`.
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let day_of_era = z % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02} UTC",
+        minutes / 60,
+        minutes % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_markup() {
+        assert_eq!(
+            escape(r#"<a href="x">'&'</a>"#),
+            "&lt;a href=&quot;x&quot;&gt;&#39;&amp;&#39;&lt;/a&gt;"
+        );
+    }
+
+    #[test]
+    fn formats_dates() {
+        assert_eq!(format_date(0), "1970-01-01 00:00 UTC");
+        assert_eq!(format_date(951_782_400), "2000-02-29 00:00 UTC");
+        assert_eq!(format_date(1_714_566_896), "2024-05-01 12:34 UTC");
+    }
+}
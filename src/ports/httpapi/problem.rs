@@ -0,0 +1,287 @@
+//! RFC 7807 `application/problem+json` error bodies.
+
+use axum::async_trait;
+use axum::body::Body;
+use axum::extract::rejection::JsonRejection;
+use axum::extract::{FromRequest, Request};
+use axum::http::{header, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::error::AppError;
+
+pub const CONTENT_TYPE: &str = "application/problem+json";
+
+/// A machine-readable error. `code` is stable and also names the `type`;
+/// `detail` is meant for humans and may change.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Problem {
+    #[serde(rename = "type")]
+    pub problem_type: String,
+    pub title: String,
+    #[schema(value_type = u16)]
+    #[serde(with = "status_code")]
+    pub status: StatusCode,
+    pub detail: String,
+    /// The request path the problem occurred on.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instance: Option<String>,
+    pub code: String,
+    /// The link, subscription or workspace ID the problem is about.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    /// The URL that failed to parse.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+impl Problem {
+    pub fn new(status: StatusCode, code: &str, title: &str, detail: impl Into<String>) -> Self {
+        Problem {
+            problem_type: format!("urn:urlshortener:problem:{code}"),
+            title: title.to_owned(),
+            status,
+            detail: detail.into(),
+            instance: None,
+            code: code.to_owned(),
+            id: None,
+            url: None,
+        }
+    }
+}
+
+impl From<AppError> for Problem {
+    fn from(err: AppError) -> Self {
+        let detail = err.to_string();
+        match err {
+            AppError::NotFound { id } => Problem {
+                id: Some(id),
+                ..Problem::new(StatusCode::NOT_FOUND, "not-found", "Not found", detail)
+            },
+            AppError::InvalidUrl { url, .. } => Problem {
+                url: Some(url),
+                ..Problem::new(
+                    StatusCode::BAD_REQUEST,
+                    "invalid-url",
+                    "Invalid URL",
+                    detail,
+                )
+            },
+            AppError::InvalidRule { .. } => Problem::new(
+                StatusCode::BAD_REQUEST,
+                "invalid-rule",
+                "Invalid redirect rule",
+                detail,
+            ),
+            AppError::InvalidVariants { .. } => Problem::new(
+                StatusCode::BAD_REQUEST,
+                "invalid-variants",
+                "Invalid variants",
+                detail,
+            ),
+            AppError::IdTaken { id } => Problem {
+                id: Some(id),
+                ..Problem::new(
+                    StatusCode::CONFLICT,
+                    "id-taken",
+                    "ID already in use",
+                    detail,
+                )
+            },
+            AppError::IdGenerationFailed => Problem::new(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "id-generation-failed",
+                "ID generation failed",
+                detail,
+            ),
+            AppError::Unauthenticated => Problem::new(
+                StatusCode::UNAUTHORIZED,
+                "unauthenticated",
+                "Unauthenticated",
+                detail,
+            ),
+            AppError::Forbidden { .. } => {
+                Problem::new(StatusCode::FORBIDDEN, "forbidden", "Forbidden", detail)
+            }
+            AppError::QuotaExceeded { workspace, .. } => Problem {
+                id: Some(workspace),
+                ..Problem::new(
+                    StatusCode::FORBIDDEN,
+                    "quota-exceeded",
+                    "Quota exceeded",
+                    detail,
+                )
+            },
+            AppError::DomainTaken { .. } => Problem::new(
+                StatusCode::CONFLICT,
+                "domain-taken",
+                "Domain already in use",
+                detail,
+            ),
+            AppError::LinkDisabled { id } => Problem {
+                id: Some(id),
+                ..Problem::new(
+                    StatusCode::UNAVAILABLE_FOR_LEGAL_REASONS,
+                    "link-disabled",
+                    "Link disabled",
+                    detail,
+                )
+            },
+            AppError::InvalidReport { .. } => Problem::new(
+                StatusCode::BAD_REQUEST,
+                "invalid-report",
+                "Invalid report",
+                detail,
+            ),
+            AppError::LinkNotYetActive { id, .. } => Problem {
+                id: Some(id),
+                ..Problem::new(
+                    StatusCode::NOT_FOUND,
+                    "not-yet-active",
+                    "Not live yet",
+                    detail,
+                )
+            },
+            AppError::InvalidSchedule { .. } => Problem::new(
+                StatusCode::BAD_REQUEST,
+                "invalid-schedule",
+                "Invalid schedule",
+                detail,
+            ),
+            AppError::StorageUnavailable => Problem::new(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "storage-unavailable",
+                "Storage unavailable",
+                detail,
+            ),
+        }
+    }
+}
+
+impl From<JsonRejection> for Problem {
+    fn from(rejection: JsonRejection) -> Self {
+        let code = match rejection {
+            JsonRejection::MissingJsonContentType(_) => "unsupported-media-type",
+            JsonRejection::JsonSyntaxError(_) => "malformed-body",
+            _ => "invalid-body",
+        };
+
+        Problem::new(
+            rejection.status(),
+            code,
+            "Invalid request body",
+            rejection.body_text(),
+        )
+    }
+}
+
+impl IntoResponse for Problem {
+    fn into_response(self) -> Response {
+        let body = serde_json::to_vec(&self).expect("problems serialize");
+        let mut response =
+            (self.status, [(header::CONTENT_TYPE, CONTENT_TYPE)], body).into_response();
+        if self.status == StatusCode::UNAUTHORIZED {
+            response.headers_mut().insert(
+                header::WWW_AUTHENTICATE,
+                header::HeaderValue::from_static("Bearer"),
+            );
+        }
+        // Kept so `add_instance` can render it again with the request path.
+        response.extensions_mut().insert(self);
+
+        response
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        Problem::from(self).into_response()
+    }
+}
+
+/// Middleware filling in `instance` on problems, which handlers and
+/// extractors cannot do as they do not see the request path.
+pub async fn add_instance(request: Request, next: Next) -> Response {
+    let path = request.uri().path().to_owned();
+    let response = next.run(request).await;
+
+    match response.extensions().get::<Problem>() {
+        Some(problem) if problem.instance.is_none() => {
+            let problem = Problem {
+                instance: Some(path),
+                ..problem.clone()
+            };
+            let body = serde_json::to_vec(&problem).expect("problems serialize");
+            let (parts, _) = response.into_parts();
+
+            Response::from_parts(parts, Body::from(body))
+        }
+        _ => response,
+    }
+}
+
+/// `Json` whose rejections are problems rather than plain text.
+pub struct JsonBody<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequest<S> for JsonBody<T>
+where
+    axum::Json<T>: FromRequest<S, Rejection = JsonRejection>,
+    S: Send + Sync,
+{
+    type Rejection = Problem;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let axum::Json(value) = axum::Json::<T>::from_request(request, state).await?;
+
+        Ok(JsonBody(value))
+    }
+}
+
+mod status_code {
+    use axum::http::StatusCode;
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(status: &StatusCode, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u16(status.as_u16())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<StatusCode, D::Error> {
+        StatusCode::from_u16(u16::deserialize(deserializer)?).map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn app_errors_carry_their_context() {
+        let problem = Problem::from(AppError::invalid_url("nope", "relative URL without a base"));
+
+        assert_eq!(problem.status, StatusCode::BAD_REQUEST);
+        assert_eq!(problem.code, "invalid-url");
+        assert_eq!(problem.problem_type, "urn:urlshortener:problem:invalid-url");
+        assert_eq!(problem.url.as_deref(), Some("nope"));
+        assert_eq!(
+            problem.detail,
+            "Invalid URL `nope`: relative URL without a base"
+        );
+    }
+
+    #[test]
+    fn serializes_rfc_7807_members() {
+        let problem = Problem::from(AppError::IdTaken {
+            id: "abc".to_owned(),
+        });
+
+        let json = serde_json::to_value(&problem).unwrap();
+
+        assert_eq!(json["type"], "urn:urlshortener:problem:id-taken");
+        assert_eq!(json["status"], 409);
+        assert_eq!(json["id"], "abc");
+        assert!(json.get("instance").is_none());
+    }
+}
@@ -0,0 +1,123 @@
+//! Cards for chat apps and social sites. Their crawlers follow redirects
+//! and read the destination's tags, so links with `og:` values of their own
+//! get a page carrying those instead.
+
+use axum::http::{header, HeaderMap};
+use axum::response::{Html, IntoResponse, Response};
+
+use crate::app::short_url::ShortUrl;
+
+use super::preview::{escape, CONTENT_SECURITY_POLICY};
+
+const TEMPLATE: &str = include_str!("unfurl.html");
+
+/// User agent fragments of link unfurling crawlers, lowercase.
+const BOTS: &[&str] = &[
+    "facebookexternalhit",
+    "facebot",
+    "twitterbot",
+    "slackbot-linkexpanding",
+    "discordbot",
+    "telegrambot",
+    "whatsapp",
+    "linkedinbot",
+    "skypeuripreview",
+    "mattermost",
+    "redditbot",
+    "pinterestbot",
+    "embedly",
+    "iframely",
+    "vkshare",
+];
+
+pub fn is_unfurl_bot(headers: &HeaderMap) -> bool {
+    let Some(user_agent) = headers
+        .get(header::USER_AGENT)
+        .and_then(|value| value.to_str().ok())
+    else {
+        return false;
+    };
+    let user_agent = user_agent.to_ascii_lowercase();
+
+    BOTS.iter().any(|bot| user_agent.contains(bot))
+}
+
+pub fn render(link: &ShortUrl) -> Response {
+    let og = &link.open_graph;
+    let card = if og.image.is_some() {
+        "summary_large_image"
+    } else {
+        "summary"
+    };
+
+    let mut meta = vec![
+        property("og:type", "website"),
+        property("og:url", &link.url),
+        name("twitter:card", card),
+    ];
+    if let Some(title) = &og.title {
+        meta.push(property("og:title", title));
+    }
+    if let Some(description) = &og.description {
+        meta.push(property("og:description", description));
+        meta.push(name("description", description));
+    }
+    if let Some(image) = &og.image {
+        meta.push(property("og:image", image));
+    }
+
+    let page = TEMPLATE
+        .replace(
+            "{{title}}",
+            &escape(og.title.as_deref().unwrap_or(&link.url)),
+        )
+        .replace("{{meta}}", &meta.join("\n"))
+        .replace("{{url}}", &escape(&link.url));
+
+    (
+        [(header::CONTENT_SECURITY_POLICY, CONTENT_SECURITY_POLICY)],
+        Html(page),
+    )
+        .into_response()
+}
+
+fn property(property: &str, content: &str) -> String {
+    format!(
+        r#"<meta property="{property}" content="{}">"#,
+        escape(content)
+    )
+}
+
+fn name(name: &str, content: &str) -> String {
+    format!(r#"<meta name="{name}" content="{}">"#, escape(content))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderValue;
+
+    use super::*;
+
+    fn user_agent(value: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::USER_AGENT, HeaderValue::from_static(value));
+        headers
+    }
+
+    #[test]
+    fn detects_unfurl_bots() {
+        assert!(is_unfurl_bot(&user_agent(
+            "Slackbot-LinkExpanding 1.0 (+https://api.slack.com/robots)"
+        )));
+        assert!(is_unfurl_bot(&user_agent(
+            "facebookexternalhit/1.1 (+http://www.facebook.com/externalhit_uatext.php)"
+        )));
+        assert!(is_unfurl_bot(&user_agent(
+            "Mozilla/5.0 (compatible; Discordbot/2.0; +https://discordapp.com)"
+        )));
+        assert!(!is_unfurl_bot(&user_agent(
+            "Mozilla/5.0 (X11; Linux x86_64; rv:128.0) Gecko/20100101 Firefox/128.0"
+        )));
+        assert!(!is_unfurl_bot(&HeaderMap::new()));
+    }
+}
@@ -0,0 +1,186 @@
+//! What redirect rules get to know about a visitor, read from the request.
+
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use axum::http::{header, HeaderMap, HeaderName, HeaderValue};
+
+use crate::adapters::geoip::GeoIpDatabase;
+use crate::app::redirect_rule::{Platform, Visitor};
+
+/// How long a visitor keeps the A/B variant they were served.
+const VARIANT_COOKIE_MAX_AGE_SECS: u64 = 30 * 24 * 60 * 60;
+
+/// Where a visitor's country comes from: a header set by a proxy in front
+/// of us, else the GeoIP database. Without either, country conditions
+/// never match.
+#[derive(Clone, Default)]
+pub struct CountryLookup {
+    pub header: Option<HeaderName>,
+    pub database: Option<Arc<GeoIpDatabase>>,
+}
+
+impl CountryLookup {
+    fn country(&self, headers: &HeaderMap, peer: Option<IpAddr>) -> Option<String> {
+        let from_header = self.header.as_ref().and_then(|name| {
+            let value = headers.get(name)?.to_str().ok()?.trim();
+            (value.len() == 2 && value.bytes().all(|b| b.is_ascii_alphabetic()))
+                .then(|| value.to_ascii_uppercase())
+        });
+
+        from_header.or_else(|| self.database.as_ref()?.country(peer?))
+    }
+}
+
+/// The parts of a request to a short link that say who follows it.
+pub struct Incoming {
+    /// Path segments after the link ID, percent-decoded.
+    pub path: String,
+    pub query: Option<String>,
+    pub peer: Option<IpAddr>,
+    pub headers: HeaderMap,
+}
+
+/// The visitor following link `id` at `now`.
+pub fn from_request(id: &str, incoming: &Incoming, country: &CountryLookup, now: u64) -> Visitor {
+    let Incoming {
+        path,
+        query,
+        peer,
+        headers,
+    } = incoming;
+    let header = |name| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+    };
+
+    let fingerprint = format!(
+        "{}\n{}\n{}",
+        peer.map(|ip| ip.to_string()).unwrap_or_default(),
+        header(header::USER_AGENT),
+        header(header::ACCEPT_LANGUAGE),
+    );
+
+    Visitor {
+        platform: Platform::from_user_agent(header(header::USER_AGENT)),
+        language: preferred_language(header(header::ACCEPT_LANGUAGE)),
+        country: country.country(headers, *peer),
+        now,
+        fingerprint,
+        variant: cookie(headers, &variant_cookie(id)),
+        path: path.clone(),
+        query: url::form_urlencoded::parse(query.as_deref().unwrap_or_default().as_bytes())
+            .into_owned()
+            .collect(),
+    }
+}
+
+/// `Set-Cookie` value that keeps the visitor on `variant` of link `id`.
+pub fn remember_variant(id: &str, variant: &str) -> Option<HeaderValue> {
+    HeaderValue::from_str(&format!(
+        "{}={variant}; Path=/{id}; Max-Age={VARIANT_COOKIE_MAX_AGE_SECS}; HttpOnly; SameSite=Lax",
+        variant_cookie(id)
+    ))
+    .ok()
+}
+
+/// One cookie per link, scoped to its path, so splits stay independent.
+fn variant_cookie(id: &str) -> String {
+    format!("variant_{id}")
+}
+
+fn cookie(headers: &HeaderMap, name: &str) -> Option<String> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .find_map(|pair| {
+            let (key, value) = pair.trim().split_once('=')?;
+            (key == name).then(|| value.to_owned())
+        })
+}
+
+/// The highest weighted tag of an `Accept-Language` value; the first one
+/// on ties.
+fn preferred_language(accept_language: &str) -> Option<String> {
+    let mut best: Option<(&str, f32)> = None;
+    for entry in accept_language.split(',') {
+        let mut parts = entry.split(';');
+        let tag = parts.next().unwrap_or_default().trim();
+        let weight = parts
+            .find_map(|param| param.trim().strip_prefix("q="))
+            .map_or(Some(1.0), |q| q.trim().parse::<f32>().ok())
+            .unwrap_or(0.0);
+
+        if tag.is_empty() || tag == "*" || weight <= 0.0 {
+            continue;
+        }
+        if best.is_none_or(|(_, best)| weight > best) {
+            best = Some((tag, weight));
+        }
+    }
+
+    best.map(|(tag, _)| tag.to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn picks_the_preferred_language() {
+        assert_eq!(
+            preferred_language("en;q=0.8, de-AT, de;q=0.9"),
+            Some("de-AT".to_owned())
+        );
+        assert_eq!(preferred_language("*, fr;q=0.5"), Some("fr".to_owned()));
+        assert_eq!(preferred_language("en;q=0"), None);
+        assert_eq!(preferred_language(""), None);
+    }
+
+    #[test]
+    fn reads_the_variant_cookie_of_the_link() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::COOKIE,
+            HeaderValue::from_static("variant_xyz=b; variant_abc=a"),
+        );
+        let incoming = Incoming {
+            path: String::new(),
+            query: None,
+            peer: None,
+            headers,
+        };
+
+        let visitor = from_request("abc", &incoming, &CountryLookup::default(), 0);
+
+        assert_eq!(visitor.variant.as_deref(), Some("a"));
+        assert_eq!(
+            remember_variant("abc", "a").unwrap(),
+            "variant_abc=a; Path=/abc; Max-Age=2592000; HttpOnly; SameSite=Lax"
+        );
+    }
+
+    #[test]
+    fn header_country_wins_over_the_database() {
+        // Given
+        let database =
+            GeoIpDatabase::from_reader("10.0.0.0,10.255.255.255,NL\n".as_bytes()).unwrap();
+        let lookup = CountryLookup {
+            header: Some(HeaderName::from_static("cf-ipcountry")),
+            database: Some(Arc::new(database)),
+        };
+        let peer = Some("10.1.2.3".parse().unwrap());
+        let mut headers = HeaderMap::new();
+
+        // Then
+        assert_eq!(lookup.country(&headers, peer), Some("NL".to_owned()));
+        headers.insert("cf-ipcountry", HeaderValue::from_static("us"));
+        assert_eq!(lookup.country(&headers, peer), Some("US".to_owned()));
+        headers.insert("cf-ipcountry", HeaderValue::from_static("XX1"));
+        assert_eq!(lookup.country(&headers, peer), Some("NL".to_owned()));
+    }
+}
@@ -0,0 +1,29 @@
+//! The page shown instead of a redirect when a link is quarantined because
+//! its destination is on a threat list.
+
+use axum::http::header;
+use axum::response::{Html, IntoResponse, Response};
+
+use super::preview::escape;
+
+const TEMPLATE: &str = include_str!("warning.html");
+
+/// The page links nowhere, so neither scripts nor anything else may load.
+const CONTENT_SECURITY_POLICY: &str = "default-src 'none'; style-src 'unsafe-inline'";
+
+/// Warns about `target`, quarantined for `reason`. Not cached, so the
+/// redirect works again as soon as the quarantine is lifted.
+pub fn render(reason: &str, target: &str) -> Response {
+    let page = TEMPLATE
+        .replace("{{url}}", &escape(target))
+        .replace("{{reason}}", &escape(reason));
+
+    (
+        [
+            (header::CONTENT_SECURITY_POLICY, CONTENT_SECURITY_POLICY),
+            (header::CACHE_CONTROL, "no-store"),
+        ],
+        Html(page),
+    )
+        .into_response()
+}
@@ -1 +1,3 @@
+pub mod cli;
 pub mod httpapi;
+pub mod transfer;
@@ -0,0 +1,384 @@
+//! CSV and JSON Lines codecs for bulk import and export, shared by the CLI
+//! and the admin HTTP endpoints.
+
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Read};
+use std::str::FromStr;
+
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+
+use crate::app::account::Principal;
+use crate::app::command::import_short_urls::{ConflictPolicy, ImportReport};
+use crate::app::short_url::ShortUrl;
+use crate::di::{
+    AccountRepository, CommandRepository, Container, QueryRepository, WebhookRepository,
+};
+use crate::error::AppError;
+use crate::id_provider::IDProvider;
+
+/// Links handed to the import command or fetched for export at once.
+pub const BATCH_SIZE: usize = 1000;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, utoipa::ToSchema)]
+pub enum Format {
+    #[serde(rename = "csv")]
+    Csv,
+    #[default]
+    #[serde(rename = "jsonl")]
+    JsonLines,
+}
+
+impl Format {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Format::Csv => "text/csv",
+            Format::JsonLines => "application/x-ndjson",
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => Ok(Format::Csv),
+            "jsonl" | "ndjson" => Ok(Format::JsonLines),
+            other => Err(format!("unknown format `{other}`, expected csv or jsonl")),
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum TransferError {
+    #[error("{0}")]
+    App(#[from] AppError),
+    #[error("failed to read input: {0}")]
+    Read(String),
+    #[error("failed to encode output: {0}")]
+    Encode(String),
+}
+
+enum Decoded {
+    Link(Box<ShortUrl>),
+    /// A malformed record; reported and skipped.
+    Invalid(String),
+    /// The input itself broke; the import stops.
+    Failed(String),
+}
+
+/// CSV row layout. `metadata` holds a JSON object so rows keep a fixed set
+/// of columns regardless of which metadata keys links have.
+#[derive(Serialize, Deserialize)]
+struct CsvRecord {
+    id: String,
+    url: String,
+    created_at: Option<u64>,
+    metadata: Option<String>,
+    expires_at: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct JsonRecord {
+    id: String,
+    url: String,
+    created_at: Option<u64>,
+    #[serde(default)]
+    metadata: BTreeMap<String, String>,
+    expires_at: Option<u64>,
+}
+
+fn to_link(
+    id: String,
+    url: String,
+    created_at: Option<u64>,
+    metadata: BTreeMap<String, String>,
+    expires_at: Option<u64>,
+) -> ShortUrl {
+    let mut link = ShortUrl::new(id, url);
+    if let Some(created_at) = created_at {
+        link.created_at = created_at;
+    }
+    link.metadata = metadata;
+    link.expires_at = expires_at;
+
+    link
+}
+
+fn decode(format: Format, reader: impl Read + 'static) -> Box<dyn Iterator<Item = Decoded>> {
+    match format {
+        Format::Csv => Box::new(
+            csv::ReaderBuilder::new()
+                .from_reader(reader)
+                .into_deserialize::<CsvRecord>()
+                .map(|record| match record {
+                    Ok(record) => {
+                        let metadata = match record.metadata.as_deref() {
+                            None | Some("") => Ok(BTreeMap::new()),
+                            Some(raw) => serde_json::from_str(raw),
+                        };
+                        match metadata {
+                            Ok(metadata) => Decoded::Link(Box::new(to_link(
+                                record.id,
+                                record.url,
+                                record.created_at,
+                                metadata,
+                                record.expires_at,
+                            ))),
+                            Err(err) => {
+                                Decoded::Invalid(format!("{}: invalid metadata: {err}", record.id))
+                            }
+                        }
+                    }
+                    Err(err) if err.is_io_error() => Decoded::Failed(err.to_string()),
+                    Err(err) => {
+                        let line = err.position().map(|p| p.line()).unwrap_or_default();
+                        Decoded::Invalid(format!("line {line}: {err}"))
+                    }
+                }),
+        ),
+        Format::JsonLines => Box::new(
+            BufReader::new(reader)
+                .lines()
+                .enumerate()
+                .filter(|(_, line)| !matches!(line, Ok(line) if line.trim().is_empty()))
+                .map(|(index, line)| match line {
+                    Ok(line) => match serde_json::from_str::<JsonRecord>(&line) {
+                        Ok(record) => Decoded::Link(Box::new(to_link(
+                            record.id,
+                            record.url,
+                            record.created_at,
+                            record.metadata,
+                            record.expires_at,
+                        ))),
+                        Err(err) => Decoded::Invalid(format!("line {}: {err}", index + 1)),
+                    },
+                    Err(err) => Decoded::Failed(err.to_string()),
+                }),
+        ),
+    }
+}
+
+/// Streaming encoder; CSV gets its header with the first batch.
+pub struct Encoder {
+    format: Format,
+    wrote_header: bool,
+}
+
+impl Encoder {
+    pub fn new(format: Format) -> Self {
+        Self {
+            format,
+            wrote_header: false,
+        }
+    }
+
+    pub fn encode(&mut self, links: &[ShortUrl]) -> Result<Vec<u8>, TransferError> {
+        let encode_err = |err: &dyn std::fmt::Display| TransferError::Encode(err.to_string());
+
+        match self.format {
+            Format::Csv => {
+                let mut writer = csv::WriterBuilder::new()
+                    .has_headers(!self.wrote_header)
+                    .from_writer(vec![]);
+                for link in links {
+                    let metadata = if link.metadata.is_empty() {
+                        None
+                    } else {
+                        Some(serde_json::to_string(&link.metadata).map_err(|e| encode_err(&e))?)
+                    };
+                    writer
+                        .serialize(CsvRecord {
+                            id: link.id.clone(),
+                            url: link.url.clone(),
+                            created_at: Some(link.created_at),
+                            metadata,
+                            expires_at: link.expires_at,
+                        })
+                        .map_err(|e| encode_err(&e))?;
+                    self.wrote_header = true;
+                }
+
+                writer.into_inner().map_err(|e| encode_err(&e))
+            }
+            Format::JsonLines => {
+                let mut out = Vec::new();
+                for link in links {
+                    serde_json::to_writer(&mut out, link).map_err(|e| encode_err(&e))?;
+                    out.push(b'\n');
+                }
+
+                Ok(out)
+            }
+        }
+    }
+}
+
+/// Decodes `reader` on a blocking thread and feeds the records to the
+/// import command in batches, so inputs of any size run in bounded memory.
+pub async fn import<I, R, Q, W, A>(
+    container: &Container<I, R, Q, W, A>,
+    principal: &Principal,
+    format: Format,
+    policy: ConflictPolicy,
+    reader: impl Read + Send + 'static,
+) -> Result<ImportReport, TransferError>
+where
+    I: IDProvider,
+    R: CommandRepository,
+    Q: QueryRepository,
+    W: WebhookRepository,
+    A: AccountRepository,
+{
+    let (tx, mut rx) = mpsc::channel(BATCH_SIZE);
+    let decoder = tokio::task::spawn_blocking(move || {
+        for record in decode(format, reader) {
+            if tx.blocking_send(record).is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut report = ImportReport::default();
+    let mut batch = Vec::with_capacity(BATCH_SIZE);
+    let mut failure = None;
+
+    while let Some(record) = rx.recv().await {
+        match record {
+            Decoded::Link(link) => batch.push(*link),
+            Decoded::Invalid(err) => report.record_invalid(err),
+            Decoded::Failed(err) => {
+                failure = Some(err);
+                break;
+            }
+        }
+
+        if batch.len() == BATCH_SIZE {
+            container
+                .import_command
+                .execute(principal, std::mem::take(&mut batch), policy, &mut report)
+                .await?;
+            if report.is_aborted() {
+                break;
+            }
+        }
+    }
+    drop(rx);
+    let _ = decoder.await;
+
+    if let Some(err) = failure {
+        return Err(TransferError::Read(err));
+    }
+    if !report.is_aborted() {
+        container
+            .import_command
+            .execute(principal, batch, policy, &mut report)
+            .await?;
+    }
+
+    Ok(report)
+}
+
+/// Encoded chunks of the whole link database, with the number of links in
+/// each chunk. Fails up front when `principal` may not export.
+pub fn export<'a, I, R, Q, W, A>(
+    container: &'a Container<I, R, Q, W, A>,
+    principal: &Principal,
+    format: Format,
+) -> Result<impl Stream<Item = Result<(usize, Vec<u8>), TransferError>> + Send + 'a, TransferError>
+where
+    I: IDProvider,
+    R: CommandRepository,
+    Q: QueryRepository,
+    W: WebhookRepository,
+    A: AccountRepository,
+{
+    let mut encoder = Encoder::new(format);
+
+    let batches = container.export_query.execute(principal, BATCH_SIZE)?;
+
+    Ok(batches.map(move |batch| {
+        let batch = batch?;
+        encoder.encode(&batch).map(|bytes| (batch.len(), bytes))
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    fn decode_all(format: Format, input: &str) -> Vec<Decoded> {
+        decode(format, Cursor::new(input.as_bytes().to_vec())).collect()
+    }
+
+    fn link(id: &str, url: &str) -> ShortUrl {
+        let mut link = ShortUrl::new(id.to_owned(), url.to_owned());
+        link.created_at = 42;
+        link.expires_at = Some(4_102_444_800);
+        link.metadata
+            .insert("campaign".to_owned(), "spring, \"25\"".to_owned());
+        link
+    }
+
+    #[test]
+    fn csv_round_trip_keeps_metadata() {
+        // Given
+        let links = vec![link("a", "https://a.com/"), link("b", "https://b.com/")];
+        let mut encoder = Encoder::new(Format::Csv);
+
+        // When
+        let mut bytes = encoder.encode(&links[..1]).unwrap();
+        bytes.extend(encoder.encode(&links[1..]).unwrap());
+        let decoded = decode_all(Format::Csv, std::str::from_utf8(&bytes).unwrap());
+
+        // Then
+        assert!(bytes.starts_with(b"id,url,created_at,metadata,expires_at\n"));
+        let decoded: Vec<ShortUrl> = decoded
+            .into_iter()
+            .map(|d| match d {
+                Decoded::Link(link) => *link,
+                _ => panic!("unexpected record"),
+            })
+            .collect();
+        assert_eq!(decoded, links);
+    }
+
+    #[test]
+    fn jsonl_round_trip() {
+        // Given
+        let links = vec![link("a", "https://a.com/")];
+
+        // When
+        let bytes = Encoder::new(Format::JsonLines).encode(&links).unwrap();
+        let decoded = decode_all(Format::JsonLines, std::str::from_utf8(&bytes).unwrap());
+
+        // Then
+        assert!(matches!(&decoded[..], [Decoded::Link(l)] if **l == links[0]));
+    }
+
+    #[test]
+    fn malformed_records_are_invalid_with_line_numbers() {
+        // Given
+        let input = "{\"id\":\"a\",\"url\":\"https://a.com\"}\n\nnot json\n";
+
+        // When
+        let decoded = decode_all(Format::JsonLines, input);
+
+        // Then
+        assert_eq!(decoded.len(), 2);
+        assert!(matches!(&decoded[1], Decoded::Invalid(err) if err.starts_with("line 3:")));
+    }
+
+    #[test]
+    fn csv_without_optional_columns() {
+        let decoded = decode_all(Format::Csv, "id,url\nabc,https://example.com\n");
+
+        assert!(
+            matches!(&decoded[..], [Decoded::Link(l)] if l.id == "abc" && l.metadata.is_empty())
+        );
+    }
+}